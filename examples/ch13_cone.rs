@@ -47,7 +47,7 @@ fn main() {
         .translate(0.0, 0.0, 15.0)
         .transform();
 
-    let cone_x = Object::new_cone(-1.0, 1.0, true)
+    let cone_x = Object::new_cone(Some(-1.0), Some(1.0), true)
         .with_material(
             Material::new()
                 .with_color(Color::red())
@@ -59,7 +59,7 @@ fn main() {
         .translate(0.0, 2.0, 2.0)
         .transform();
 
-    let cone_y = Object::new_cone(-1.0, 1.0, true)
+    let cone_y = Object::new_cone(Some(-1.0), Some(1.0), true)
         .with_material(
             Material::new()
                 .with_color(Color::blue())
@@ -70,7 +70,7 @@ fn main() {
         .translate(-3.0, 2.0, 0.0)
         .transform();
 
-    let cone_z = Object::new_cone(-1.0, 1.0, false)
+    let cone_z = Object::new_cone(Some(-1.0), Some(1.0), false)
         .with_material(
             Material::new()
                 .with_color(Color::green())
@@ -102,6 +102,8 @@ fn main() {
         .with_fov(fov)
         .with_transformation(&view_transform(&from, &to, &up));
 
-    let canvas = camera.render(&world, ParallelRendering::True);
+    let canvas = camera
+        .render(&world, ParallelRendering::True, None)
+        .unwrap();
     canvas.export("ch13_cone.png").unwrap();
 }
@@ -38,7 +38,7 @@ fn main() {
         .translate(0.0, 0.0, 15.0)
         .transform();
 
-    let cylinder_x = Object::new_cylinder(f64::NEG_INFINITY, f64::INFINITY, true)
+    let cylinder_x = Object::new_cylinder(None, None, true)
         .with_material(
             Material::new()
                 .with_color(Color::red())
@@ -50,7 +50,7 @@ fn main() {
         .translate(0.0, 0.0, 0.0)
         .transform();
 
-    let cylinder_y = Object::new_cylinder(f64::NEG_INFINITY, f64::INFINITY, true)
+    let cylinder_y = Object::new_cylinder(None, None, true)
         .with_material(
             Material::new()
                 .with_color(Color::blue())
@@ -61,7 +61,7 @@ fn main() {
         .translate(0.0, 0.0, 0.0)
         .transform();
 
-    let cylinder_z = Object::new_cylinder(f64::NEG_INFINITY, f64::INFINITY, true)
+    let cylinder_z = Object::new_cylinder(None, None, true)
         .with_material(
             Material::new()
                 .with_color(Color::green())
@@ -73,7 +73,7 @@ fn main() {
         .translate(0.0, 0.0, 0.0)
         .transform();
 
-    let shallow_cylinder = Object::new_cylinder(-2.0, 2.0, false)
+    let shallow_cylinder = Object::new_cylinder(Some(-2.0), Some(2.0), false)
         .with_material(
             Material::new()
                 .with_pattern(Pattern::new_checker(Color::white(), Color::red()))
@@ -85,7 +85,7 @@ fn main() {
         .translate(-3.0, 3.0, -4.0)
         .transform();
 
-    let cylinder = Object::new_cylinder(-2.0, 2.0, true)
+    let cylinder = Object::new_cylinder(Some(-2.0), Some(2.0), true)
         .with_material(
             Material::new()
                 .with_pattern(Pattern::new_checker(Color::white(), Color::red()))
@@ -97,7 +97,7 @@ fn main() {
         .translate(-3.0, 6.0, -4.0)
         .transform();
 
-    let refractive_cylinder = Object::new_cylinder(-2.0, 2.0, true)
+    let refractive_cylinder = Object::new_cylinder(Some(-2.0), Some(2.0), true)
         .with_material(
             Material::new()
                 .with_color(Color::new(0.1, 0.1, 0.1))
@@ -140,6 +140,8 @@ fn main() {
         .with_fov(fov)
         .with_transformation(&view_transform(&from, &to, &up));
 
-    let canvas = camera.render(&world, ParallelRendering::True);
+    let canvas = camera
+        .render(&world, ParallelRendering::True, None)
+        .unwrap();
     canvas.export("ch13_cylinder.png").unwrap();
 }
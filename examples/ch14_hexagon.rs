@@ -12,7 +12,7 @@ fn hexagon_corner() -> Object {
 }
 
 fn hexagon_edge() -> Object {
-    Object::new_cylinder(0.0, 1.0, false)
+    Object::new_cylinder(Some(0.0), Some(1.0), false)
         .scale(0.25, 1.0, 0.25)
         .rotate_z(-PI / 2.0)
         .rotate_y(-PI / 6.0)
@@ -63,6 +63,8 @@ fn main() {
         .with_fov(fov)
         .with_transformation(&view_transform(&from, &to, &up));
 
-    let canvas = camera.render(&world, ParallelRendering::True);
+    let canvas = camera
+        .render(&world, ParallelRendering::True, None)
+        .unwrap();
     canvas.export("ch14_hexagon.png").unwrap();
 }
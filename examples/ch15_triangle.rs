@@ -83,6 +83,8 @@ fn main() {
         .with_fov(fov)
         .with_transformation(&view_transform(&from, &to, &up));
 
-    let canvas = camera.render(&world, ParallelRendering::True);
+    let canvas = camera
+        .render(&world, ParallelRendering::True, None)
+        .unwrap();
     canvas.export("ch15_triangle.png").unwrap();
 }
@@ -18,7 +18,6 @@ use ray_tracer::{
 use sha3::{Digest, Sha3_256};
 use std::{
     f64::consts::PI,
-    fs::File,
     io::{Read, Write},
     time::Instant,
 };
@@ -45,6 +44,22 @@ fn output_path(path: &std::path::Path) -> Result<String, Box<dyn std::error::Err
 
 /* ---------------------------------------------------------------------------------------------- */
 
+// Bump when the serialized representation of objects changes without a new crate version.
+const CACHE_FORMAT_VERSION: u32 = 1;
+
+// A cache which can't be read, e.g. because it was written by another version, is ignored and
+// will be overwritten.
+fn read_cached_object(cache_path: &str) -> Option<Object> {
+    let compressed = std::fs::read(cache_path).ok()?;
+    let mut gz = GzDecoder::new(&compressed[..]);
+    let mut serialized = vec![];
+    gz.read_to_end(&mut serialized).ok()?;
+
+    bincode::deserialize(&serialized).ok()
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
 #[derive(PartialEq)]
 enum FileType {
     Yaml,
@@ -114,12 +129,26 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .help("Deactivate parallel rendering")
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("threads")
+                .short("j")
+                .long("threads")
+                .value_name("INT")
+                .help("Number of rendering threads")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("soft-shadows")
                 .long("soft-shadows")
                 .help("Use soft shadows (takes much more time)")
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("linear-colors")
+                .long("linear-colors")
+                .help("Don't convert colors of YAML materials from sRGB to linear")
+                .takes_value(false),
+        )
         .arg(
             Arg::with_name("INPUT")
                 .help("Sets the input YAML or OBJ file to use")
@@ -148,8 +177,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let rotate_x = clap::value_t!(matches.value_of("rotate-x"), f64).unwrap_or(0.0);
     let rotate_y = clap::value_t!(matches.value_of("rotate-y"), f64).unwrap_or(0.0);
     let rotate_z = clap::value_t!(matches.value_of("rotate-z"), f64).unwrap_or(0.0);
-    let parallel: ParallelRendering = matches.is_present("sequential").into();
+    let parallel: ParallelRendering = match clap::value_t!(matches.value_of("threads"), usize) {
+        Ok(threads) if !matches.is_present("sequential") => ParallelRendering::Threads(threads),
+        _ => matches.is_present("sequential").into(),
+    };
     let soft_shadows = matches.is_present("soft-shadows");
+    let yaml_options = yaml::ParseOptions {
+        srgb_colors: !matches.is_present("linear-colors"),
+    };
 
     println!("Input file: {}", path_str);
     println!("Factor: {}", factor);
@@ -160,7 +195,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let construction_start = Instant::now();
     let (world, camera) = match ext {
         FileType::Yaml => {
-            let (objects, lights, camera) = yaml::parse(path);
+            let (objects, lights, camera) = yaml::parse_with_options(path, &yaml_options);
 
             let objects = if bvh_threshold == 0 {
                 objects
@@ -175,6 +210,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
         FileType::Obj => {
             let hash = Sha3_256::new()
+                .chain(env!("CARGO_PKG_VERSION"))
+                .chain(CACHE_FORMAT_VERSION.to_le_bytes())
                 .chain(path_str)
                 .chain(rotate_x.to_le_bytes())
                 .chain(rotate_y.to_le_bytes())
@@ -184,42 +221,39 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
             let cache_path = format!(".rtc_{:x}.gz", hash);
 
-            let group = if File::open(&cache_path).is_err() {
-                let object = obj::parse_file(path)?
-                    .rotate_x(rotate_x)
-                    .rotate_y(rotate_y)
-                    .rotate_z(rotate_z)
-                    .transform();
-
-                let bbox = object.bounding_box();
-                // Translate the object to touch the floor at 0.0.
-                let object = object.translate(0.0, -bbox.min().y(), 0.0).transform();
-
-                let object = if bvh_threshold == 0 {
+            let group = match read_cached_object(&cache_path) {
+                Some(object) => {
+                    println!("Using cached object");
                     object
-                } else {
-                    object.divide(bvh_threshold)
-                };
-
-                println!("Writing cached object");
+                }
+                None => {
+                    let object = obj::parse_file(path)?
+                        .rotate_x(rotate_x)
+                        .rotate_y(rotate_y)
+                        .rotate_z(rotate_z)
+                        .transform();
+
+                    let object = if bvh_threshold == 0 {
+                        object
+                    } else {
+                        object.divide(bvh_threshold)
+                    };
+
+                    println!("Writing cached object");
+
+                    let serialized = bincode::serialize(&object)?;
+                    let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+                    gz.write_all(&serialized)?;
+                    let compressed = gz.finish()?;
+                    std::fs::write(&cache_path, &compressed)?;
 
-                let serialized = bincode::serialize(&object)?;
-                let mut gz = GzEncoder::new(Vec::new(), Compression::default());
-                gz.write_all(&serialized)?;
-                let compressed = gz.finish()?;
-                std::fs::write(&cache_path, &compressed)?;
-
-                object
-            } else {
-                println!("Using cached object");
-
-                let compressed = std::fs::read(&cache_path)?;
-                let mut gz = GzDecoder::new(&compressed[..]);
-                let mut serialized = vec![];
-                gz.read_to_end(&mut serialized)?;
-                bincode::deserialize(&serialized)?
+                    object
+                }
             };
 
+            // Whether it comes from the cache or not, the object is put on the floor the same way.
+            let group = group.grounded();
+
             let floor = Object::new_plane().with_material(
                 Material::new()
                     .with_pattern(Pattern::new_checker(
@@ -297,7 +331,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Time elapsed in construction: {:?}", construction_duration);
 
     let rendering_start = Instant::now();
-    let canvas = camera.with_anti_aliasing(aa_level).render(&world, parallel);
+    let canvas = camera
+        .with_anti_aliasing(aa_level)
+        .render(&world, parallel, None)?;
     let rendering_duration = rendering_start.elapsed();
     println!("Time elapsed in rendering: {:?}", rendering_duration);
 
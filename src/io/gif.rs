@@ -0,0 +1,59 @@
+/* ---------------------------------------------------------------------------------------------- */
+
+use crate::rtc::Canvas;
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    Delay, Frame, ImageResult,
+};
+use std::{fs::File, io::BufWriter};
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Encodes the frames as an animated GIF, looping forever.
+pub fn write(frames: &[Canvas], path: &str, fps: u32) -> ImageResult<()> {
+    let mut encoder = GifEncoder::new(BufWriter::new(File::create(path)?));
+    encoder.set_repeat(Repeat::Infinite)?;
+
+    let delay = Delay::from_numer_denom_ms(1000, fps.max(1));
+    encoder.encode_frames(
+        frames
+            .iter()
+            .map(|canvas| Frame::from_parts(canvas.to_rgba_image(), 0, 0, delay)),
+    )
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtc::Color;
+    use image::{codecs::gif::GifDecoder, AnimationDecoder};
+
+    #[test]
+    fn writing_an_animated_gif() {
+        let frames = vec![
+            Canvas::new_with_color(4, 3, Color::white()),
+            Canvas::new_with_color(4, 3, Color::black()),
+            Canvas::new_with_color(4, 3, Color::new(1.0, 0.0, 0.0)),
+        ];
+        let path = std::env::temp_dir().join("ray_tracer_writing_an_animated_gif.gif");
+        let path = path.to_str().unwrap();
+
+        write(&frames, path, 10).unwrap();
+
+        let decoded = GifDecoder::new(File::open(path).unwrap())
+            .unwrap()
+            .into_frames()
+            .collect_frames()
+            .unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(decoded.len(), 3);
+        assert_eq!(decoded[0].buffer().dimensions(), (4, 3));
+        assert_eq!(decoded[0].delay().numer_denom_ms(), (100, 1));
+        assert_eq!(decoded[2].buffer().get_pixel(0, 0).0, [255, 0, 0, 255]);
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
@@ -2,14 +2,14 @@
 
 use crate::{
     primitive::{Point, Tuple, Vector},
-    rtc::Object,
+    rtc::{BoundingBox, Object},
 };
 use std::{
     collections::HashMap,
     error::Error,
-    f64::{INFINITY, NEG_INFINITY},
     fmt,
     io::{prelude::*, BufReader},
+    iter,
 };
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -22,7 +22,7 @@ pub enum ObjParserError {
 
 impl fmt::Display for ObjParserError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match &*self {
+        match self {
             ObjParserError::ParseError(err) => write!(f, "{}", err),
             ObjParserError::IoError(err) => write!(f, "{}", err),
         }
@@ -76,7 +76,7 @@ struct Face {
 
 impl Face {
     fn has_normals(&self) -> bool {
-        self.vertices[0].normal_index != None
+        self.vertices[0].normal_index.is_some()
     }
 }
 
@@ -96,7 +96,8 @@ impl Data {
     }
 
     pub fn normalize(mut self) -> Self {
-        let (bbox_min, bbox_max) = self.bounding_box();
+        let bbox = self.bounding_box();
+        let (bbox_min, bbox_max) = (bbox.min(), bbox.max());
 
         let sx = bbox_max.x() - bbox_min.x();
         let sy = bbox_max.y() - bbox_min.y();
@@ -115,28 +116,10 @@ impl Data {
         self
     }
 
-    fn bounding_box(&self) -> (Point, Point) {
-        let mut x_min = INFINITY;
-        let mut y_min = INFINITY;
-        let mut z_min = INFINITY;
-        let mut x_max = NEG_INFINITY;
-        let mut y_max = NEG_INFINITY;
-        let mut z_max = NEG_INFINITY;
-
-        for vertex in &self.vertices {
-            x_min = x_min.min(vertex.x());
-            y_min = y_min.min(vertex.y());
-            z_min = z_min.min(vertex.z());
-
-            x_max = x_max.max(vertex.x());
-            y_max = y_max.max(vertex.y());
-            z_max = z_max.max(vertex.z());
-        }
-
-        (
-            Point::new(x_min, y_min, z_min),
-            Point::new(x_max, y_max, z_max),
-        )
+    fn bounding_box(&self) -> BoundingBox {
+        self.vertices
+            .iter()
+            .fold(BoundingBox::new(), |bbox, vertex| bbox.add_point(*vertex))
     }
 }
 
@@ -170,7 +153,7 @@ fn parse_vertex(line_vec: &[&str], line: &str, line_number: usize, mut data: Dat
     let err_msg = format!("Invalid vertex `{}` at line {}", line.trim(), line_number);
     let err_fn = |_| ParseError(err_msg.clone());
 
-    if line_vec.len() != 4 {
+    if line_vec.len() != 4 && line_vec.len() != 5 {
         return Err(ParseError(err_msg).into());
     }
 
@@ -178,7 +161,16 @@ fn parse_vertex(line_vec: &[&str], line: &str, line_number: usize, mut data: Dat
     let y = line_vec[2].parse::<f64>().map_err(err_fn)?;
     let z = line_vec[3].parse::<f64>().map_err(err_fn)?;
 
-    data.vertices.push(Point::new(x, y, z));
+    // Optional weight of rational vertices.
+    let w = match line_vec.get(4) {
+        Some(w) => w.parse::<f64>().map_err(err_fn)?,
+        None => 1.0,
+    };
+    if w == 0.0 {
+        return Err(ParseError(err_msg).into());
+    }
+
+    data.vertices.push(Point::new(x / w, y / w, z / w));
 
     Ok(data)
 }
@@ -254,10 +246,9 @@ fn parse_face(
 fn parse_data(s: &str) -> Result<Data> {
     let buf = BufReader::new(s.as_bytes());
     let mut data = Data::new();
-    let mut line_number = 1;
     let mut current_group = None;
 
-    for line in buf.lines() {
+    for (line_number, line) in (1..).zip(buf.lines()) {
         if let Ok(line) = line {
             let vec = line.split_whitespace().collect::<Vec<&str>>();
             if vec.is_empty() {
@@ -274,7 +265,6 @@ fn parse_data(s: &str) -> Result<Data> {
                 data.ignored += 1;
             }
         }
-        line_number += 1;
     }
 
     Ok(data)
@@ -335,16 +325,18 @@ pub fn parse_str(s: &str) -> Result<Object> {
     if named.is_empty() {
         Ok(anonymous_group)
     } else {
-        let mut groups = Vec::with_capacity(named.len());
-        groups.push(anonymous_group);
-        for (_, triangles) in named {
+        let capacity = named.len() + 1;
+        let groups = named.into_values().map(|triangles| {
             if triangles.is_empty() {
                 panic!();
             }
-            groups.push(Object::new_group(triangles));
-        }
+            Object::new_group(triangles)
+        });
 
-        Ok(Object::new_group(groups))
+        Ok(Object::new_group_with_capacity(
+            iter::once(anonymous_group).chain(groups),
+            capacity,
+        ))
     }
 }
 
@@ -357,9 +349,132 @@ pub fn parse_file(path: &std::path::Path) -> Result<Object> {
 
 /* ---------------------------------------------------------------------------------------------- */
 
+#[derive(Debug, Default)]
+struct Exporter {
+    vertices: Vec<Point>,
+    vertices_indices: HashMap<[u64; 3], usize>,
+    normals: Vec<Vector>,
+    normals_indices: HashMap<[u64; 3], usize>,
+    faces: Vec<[(usize, Option<usize>); 3]>,
+}
+
+impl Exporter {
+    fn add_vertex(&mut self, point: Point) -> usize {
+        let key = [
+            point.x().to_bits(),
+            point.y().to_bits(),
+            point.z().to_bits(),
+        ];
+        let vertices = &mut self.vertices;
+
+        *self.vertices_indices.entry(key).or_insert_with(|| {
+            vertices.push(point);
+            vertices.len()
+        })
+    }
+
+    fn add_normal(&mut self, normal: Vector) -> usize {
+        let key = [
+            normal.x().to_bits(),
+            normal.y().to_bits(),
+            normal.z().to_bits(),
+        ];
+        let normals = &mut self.normals;
+
+        *self.normals_indices.entry(key).or_insert_with(|| {
+            normals.push(normal);
+            normals.len()
+        })
+    }
+
+    // Returns false when the shape of the object can't be exported.
+    fn add_object(&mut self, object: &Object) -> bool {
+        let transformation = object.transformation();
+        let normal_transformation = object.transformation_inverse().transpose();
+
+        if let Some(t) = object.shape().as_triangle() {
+            let face =
+                [t.p1(), t.p2(), t.p3()].map(|p| (self.add_vertex(*transformation * p), None));
+            self.faces.push(face);
+        } else if let Some(t) = object.shape().as_smooth_triangle() {
+            let face = [(t.p1(), t.n1()), (t.p2(), t.n2()), (t.p3(), t.n3())].map(|(p, n)| {
+                (
+                    self.add_vertex(*transformation * p),
+                    Some(self.add_normal((normal_transformation * n).normalize())),
+                )
+            });
+            self.faces.push(face);
+        } else if let Some(m) = object.shape().as_mesh() {
+            let vertices = m
+                .vertices()
+                .iter()
+                .map(|p| self.add_vertex(*transformation * *p))
+                .collect::<Vec<_>>();
+            let normals = m
+                .normals()
+                .iter()
+                .map(|n| self.add_normal((normal_transformation * *n).normalize()))
+                .collect::<Vec<_>>();
+
+            for face in m.faces() {
+                self.faces
+                    .push(face.map(|index| (vertices[index], normals.get(index).copied())));
+            }
+        } else {
+            return false;
+        }
+
+        true
+    }
+
+    fn write(&self, mut writer: impl Write) -> std::io::Result<()> {
+        for vertex in &self.vertices {
+            writeln!(writer, "v {} {} {}", vertex.x(), vertex.y(), vertex.z())?;
+        }
+
+        for normal in &self.normals {
+            writeln!(writer, "vn {} {} {}", normal.x(), normal.y(), normal.z())?;
+        }
+
+        for face in &self.faces {
+            write!(writer, "f")?;
+            for (vertex_index, normal_index) in face {
+                match normal_index {
+                    None => write!(writer, " {}", vertex_index)?,
+                    Some(normal_index) => write!(writer, " {}//{}", vertex_index, normal_index)?,
+                }
+            }
+            writeln!(writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Only triangles and meshes are exported, other shapes are skipped. Returns the number of skipped
+// shapes.
+pub fn write(objects: &[Object], writer: impl Write) -> Result<usize> {
+    let mut exporter = Exporter::default();
+
+    let skipped = objects
+        .iter()
+        .flat_map(Object::flatten)
+        .filter(|object| !exporter.add_object(object))
+        .count();
+
+    exporter.write(writer)?;
+
+    Ok(skipped)
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::rtc::Transform;
 
     #[test]
     fn ignoring_unrecognized_lines() {
@@ -370,7 +485,7 @@ mod tests {
         dqsqds
         "#;
 
-        let data = parse_data(&txt).unwrap();
+        let data = parse_data(txt).unwrap();
         assert_eq!(data.ignored, 6);
     }
 
@@ -384,7 +499,7 @@ mod tests {
         dqsqds
         "#;
 
-        let data = parse_data(&txt).unwrap();
+        let data = parse_data(txt).unwrap();
         assert_eq!(data.ignored, 3);
         assert_eq!(data.vertices.len(), 5);
         assert_eq!(data.vertices[1], Point::new(-1.0, 1.0, 0.0));
@@ -393,6 +508,25 @@ mod tests {
         assert_eq!(data.vertices[4], Point::new(1.0, 1.0, 0.0));
     }
 
+    #[test]
+    fn weighted_vertex_records() {
+        let txt = r#"
+        v 2 4 6 2
+        v 1 2 3 1
+        "#;
+
+        let data = parse_data(txt).unwrap();
+        assert_eq!(data.vertices.len(), 3);
+        assert_eq!(data.vertices[1], Point::new(1.0, 2.0, 3.0));
+        assert_eq!(data.vertices[2], Point::new(1.0, 2.0, 3.0));
+
+        let data = parse_data("v 1 2 3 0");
+        assert_eq!(
+            format!("{}", data.unwrap_err()),
+            "Invalid vertex `v 1 2 3 0` at line 1"
+        );
+    }
+
     #[test]
     fn vertex_normal_records() {
         let txt = r#"
@@ -401,7 +535,7 @@ mod tests {
         vn 1 2 3
         "#;
 
-        let data = parse_data(&txt).unwrap();
+        let data = parse_data(txt).unwrap();
         assert_eq!(data.normals.len(), 4);
         assert_eq!(data.normals[1], Vector::new(0.0, 0.0, 1.0));
         assert_eq!(data.normals[2], Vector::new(0.707, 0.0, -0.707));
@@ -419,7 +553,7 @@ mod tests {
                 v 1 1 0
                 "#;
 
-            let data = parse_data(&txt);
+            let data = parse_data(txt);
             assert!(data.is_err());
             let err = data.unwrap_err();
             assert_eq!(format!("{}", err), "Invalid vertex `v 3` at line 4");
@@ -429,7 +563,7 @@ mod tests {
                 v -1 a 0
                 "#;
 
-            let data = parse_data(&txt);
+            let data = parse_data(txt);
             assert!(data.is_err());
             let err = data.unwrap_err();
             assert_eq!(format!("{}", err), "Invalid vertex `v -1 a 0` at line 2");
@@ -449,7 +583,7 @@ mod tests {
                 f 1 3 4
                 "#;
 
-            let data = parse_data(&txt).unwrap();
+            let data = parse_data(txt).unwrap();
 
             assert_eq!(data.ignored, 3);
             assert_eq!(data.vertices.len(), 5);
@@ -514,7 +648,7 @@ mod tests {
                 f 2 3 4
                 "#;
 
-            let data = parse_data(&txt).unwrap();
+            let data = parse_data(txt).unwrap();
 
             assert_eq!(data.ignored, 3);
             assert_eq!(data.vertices.len(), 5);
@@ -600,7 +734,7 @@ mod tests {
                 f 1 2 3 4 5
                 "#;
 
-            let data = parse_data(&txt).unwrap();
+            let data = parse_data(txt).unwrap();
 
             let face = &data.faces[0];
             let triangles = mk_triangles(face, &data.vertices, &data.normals);
@@ -639,7 +773,7 @@ mod tests {
         f 1/0/3 2/102/1 3/14/2
         "#;
 
-        let data = parse_data(&txt).unwrap();
+        let data = parse_data(txt).unwrap();
 
         let face0 = &data.faces[0];
         let face0_triangles = mk_triangles(face0, &data.vertices, &data.normals);
@@ -667,6 +801,130 @@ mod tests {
         assert_eq!(t1.n2(), data.normals[1]);
         assert_eq!(t1.n3(), data.normals[2]);
     }
+
+    #[test]
+    fn exporting_triangles_then_parsing_them_back() {
+        let group = Object::new_group(vec![
+            Object::new_triangle(
+                Point::new(-1.0, 1.0, 0.0),
+                Point::new(-1.0, -1.0, 0.0),
+                Point::new(1.0, -1.0, 0.0),
+            ),
+            Object::new_triangle(
+                Point::new(-1.0, 1.0, 0.0),
+                Point::new(1.0, -1.0, 0.0),
+                Point::new(1.0, 1.0, 0.0),
+            ),
+            Object::new_sphere(),
+        ]);
+
+        let mut buffer = vec![];
+        let skipped = write(std::slice::from_ref(&group), &mut buffer).unwrap();
+        let txt = String::from_utf8(buffer).unwrap();
+
+        // The sphere can't be exported.
+        assert_eq!(skipped, 1);
+
+        // Vertices are shared between faces.
+        assert_eq!(txt.lines().filter(|line| line.starts_with("v ")).count(), 4);
+        assert_eq!(txt.lines().filter(|line| line.starts_with("f ")).count(), 2);
+
+        let triangles = |object: &Object| {
+            object
+                .flatten()
+                .iter()
+                .filter_map(|o| o.shape().as_triangle().map(|t| [t.p1(), t.p2(), t.p3()]))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(triangles(&parse_str(&txt).unwrap()), triangles(&group));
+    }
+
+    #[test]
+    fn exporting_smooth_triangles_writes_world_space_normals() {
+        let triangle = Object::new_smooth_triangle(
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+            Vector::new(-1.0, 0.0, 0.0),
+            Vector::new(1.0, 0.0, 0.0),
+        );
+        let group = Object::new_group(vec![triangle])
+            .translate(0.0, 0.0, 5.0)
+            .transform();
+
+        let mut buffer = vec![];
+        assert_eq!(write(&[group], &mut buffer).unwrap(), 0);
+        let data = parse_data(&String::from_utf8(buffer).unwrap()).unwrap();
+
+        assert_eq!(data.vertices[1], Point::new(0.0, 1.0, 5.0));
+        assert_eq!(data.normals[1], Vector::new(0.0, 1.0, 0.0));
+        assert_eq!(data.faces.len(), 1);
+        assert!(data.faces[0].has_normals());
+    }
+
+    #[test]
+    fn exporting_meshes_writes_their_indexed_faces_and_normals() {
+        let vertices = vec![
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(0.0, -1.0, 0.0),
+        ];
+        let normals = vec![
+            Vector::new(0.0, 1.0, 0.0),
+            Vector::new(-1.0, 0.0, 0.0),
+            Vector::new(1.0, 0.0, 0.0),
+            Vector::new(0.0, -1.0, 0.0),
+        ];
+        let mesh = Object::new_mesh(vertices, normals, vec![[0, 1, 2], [1, 3, 2]])
+            .translate(0.0, 0.0, 5.0)
+            .transform();
+
+        let mut buffer = vec![];
+        assert_eq!(write(&[mesh], &mut buffer).unwrap(), 0);
+        let txt = String::from_utf8(buffer).unwrap();
+        let data = parse_data(&txt).unwrap();
+
+        assert_eq!(txt.lines().filter(|line| line.starts_with("v ")).count(), 4);
+        assert_eq!(data.vertices[4], Point::new(0.0, -1.0, 5.0));
+        assert_eq!(data.normals[4], Vector::new(0.0, -1.0, 0.0));
+        assert_eq!(data.faces.len(), 2);
+        assert!(data.faces.iter().all(|face| face.has_normals()));
+        assert!(txt.contains("f 2//2 4//4 3//3"));
+    }
+
+    #[test]
+    fn a_cached_object_and_a_parsed_one_have_the_same_bounding_box() {
+        let txt = r#"
+        v -3 2 0
+        v 1 -4 1
+        v 1 0 5
+        v 2 1 -2
+        f 1 2 3
+        f 1 3 4
+        f 2 3 4
+        "#;
+
+        let object = parse_str(txt)
+            .unwrap()
+            .rotate_x(std::f64::consts::PI / 3.0)
+            .transform()
+            .divide(1);
+
+        let parsed = object.clone().grounded();
+        let cached: Object = bincode::deserialize(&bincode::serialize(&object).unwrap()).unwrap();
+        let cached = cached.grounded();
+
+        assert_eq!(parsed.bounding_box(), cached.bounding_box());
+        assert!(parsed.bounding_box().min().y().abs() < 1e-6);
+
+        let normalized = parse_data(txt).unwrap().normalize().bounding_box();
+        // The largest extent is along z.
+        assert_eq!(normalized.min(), Point::new(-5.0 / 7.0, -6.0 / 7.0, -1.0));
+        assert_eq!(normalized.max(), Point::new(5.0 / 7.0, 6.0 / 7.0, 1.0));
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -0,0 +1,399 @@
+/* ---------------------------------------------------------------------------------------------- */
+
+use crate::{
+    primitive::{Point, Tuple, Vector},
+    rtc::Object,
+};
+use std::{
+    convert::TryInto,
+    error::Error,
+    fmt,
+    io::{prelude::*, BufReader},
+};
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[derive(Debug)]
+pub enum StlParserError {
+    ParseError(ParseError),
+    IoError(std::io::Error),
+}
+
+impl fmt::Display for StlParserError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StlParserError::ParseError(err) => write!(f, "{}", err),
+            StlParserError::IoError(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for StlParserError {}
+
+impl From<ParseError> for StlParserError {
+    fn from(err: ParseError) -> StlParserError {
+        StlParserError::ParseError(err)
+    }
+}
+
+impl From<std::io::Error> for StlParserError {
+    fn from(err: std::io::Error) -> StlParserError {
+        StlParserError::IoError(err)
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+type Result<T> = std::result::Result<T, StlParserError>;
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[derive(Debug)]
+pub struct ParseError(String);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for ParseError {}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+const BINARY_HEADER_SIZE: usize = 80;
+const BINARY_TRIANGLE_SIZE: usize = 50;
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// An ASCII STL file starts with "solid". As a binary STL file can also start with "solid", we
+// then rely on the size announced in its header. Some exporters pad binary files, hence the
+// file can be larger than announced.
+fn is_binary(bytes: &[u8]) -> bool {
+    if bytes.len() < BINARY_HEADER_SIZE + 4 {
+        return false;
+    }
+
+    if !bytes.trim_ascii_start().starts_with(b"solid") {
+        return true;
+    }
+
+    bytes.len() >= BINARY_HEADER_SIZE + 4 + binary_count(bytes) * BINARY_TRIANGLE_SIZE
+}
+
+fn binary_count(bytes: &[u8]) -> usize {
+    u32::from_le_bytes(
+        bytes[BINARY_HEADER_SIZE..BINARY_HEADER_SIZE + 4]
+            .try_into()
+            .unwrap(),
+    ) as usize
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Triangles compute their own normal from the winding of their vertices, which is reversed when it
+// disagrees with the facet normal given by the file (a null normal means none was given).
+fn new_facet(normal: Vector, p1: Point, p2: Point, p3: Point) -> Object {
+    if normal.dot((p3 - p1) * (p2 - p1)) < 0.0 {
+        Object::new_triangle(p1, p3, p2)
+    } else {
+        Object::new_triangle(p1, p2, p3)
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+fn read_f32s(bytes: &[u8]) -> (f64, f64, f64) {
+    let read_f32 =
+        |offset: usize| f32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap()) as f64;
+
+    (read_f32(0), read_f32(4), read_f32(8))
+}
+
+fn read_point(bytes: &[u8]) -> Point {
+    let (x, y, z) = read_f32s(bytes);
+
+    Point::new(x, y, z)
+}
+
+fn read_vector(bytes: &[u8]) -> Vector {
+    let (x, y, z) = read_f32s(bytes);
+
+    Vector::new(x, y, z)
+}
+
+// Only the announced triangles are read, as padding could otherwise be read as triangles.
+fn parse_binary(bytes: &[u8]) -> Result<Vec<Object>> {
+    let count = binary_count(bytes);
+    let records = &bytes[BINARY_HEADER_SIZE + 4..];
+
+    if records.len() < count * BINARY_TRIANGLE_SIZE {
+        return Err(ParseError(format!(
+            "Expected {} triangles, found {}",
+            count,
+            records.len() / BINARY_TRIANGLE_SIZE
+        ))
+        .into());
+    }
+
+    Ok(records
+        .chunks_exact(BINARY_TRIANGLE_SIZE)
+        .take(count)
+        .map(|record| {
+            new_facet(
+                read_vector(record),
+                read_point(&record[12..]),
+                read_point(&record[24..]),
+                read_point(&record[36..]),
+            )
+        })
+        .collect())
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+fn parse_ascii(bytes: &[u8]) -> Result<Vec<Object>> {
+    let buf = BufReader::new(bytes);
+    let mut triangles = vec![];
+    let mut vertices = Vec::with_capacity(3);
+    let mut normal = Vector::zero();
+
+    for (line_number, line) in (1..).zip(buf.lines()) {
+        let line = line?;
+        let vec = line.split_whitespace().collect::<Vec<&str>>();
+
+        if vec.is_empty() {
+            continue;
+        }
+
+        match vec[0] {
+            "facet" => {
+                let err_msg = format!("Invalid facet `{}` at line {}", line.trim(), line_number);
+                let err_fn = |_| ParseError(err_msg.clone());
+
+                if vec.len() != 5 || vec[1] != "normal" {
+                    return Err(ParseError(err_msg).into());
+                }
+
+                let x = vec[2].parse::<f64>().map_err(err_fn)?;
+                let y = vec[3].parse::<f64>().map_err(err_fn)?;
+                let z = vec[4].parse::<f64>().map_err(err_fn)?;
+
+                normal = Vector::new(x, y, z);
+            }
+            "vertex" => {
+                let err_msg = format!("Invalid vertex `{}` at line {}", line.trim(), line_number);
+                let err_fn = |_| ParseError(err_msg.clone());
+
+                if vec.len() != 4 || vertices.len() == 3 {
+                    return Err(ParseError(err_msg).into());
+                }
+
+                let x = vec[1].parse::<f64>().map_err(err_fn)?;
+                let y = vec[2].parse::<f64>().map_err(err_fn)?;
+                let z = vec[3].parse::<f64>().map_err(err_fn)?;
+
+                vertices.push(Point::new(x, y, z));
+            }
+            "endfacet" => {
+                if vertices.len() != 3 {
+                    let err_msg = format!("Invalid facet ending at line {}", line_number);
+                    return Err(ParseError(err_msg).into());
+                }
+
+                triangles.push(new_facet(normal, vertices[0], vertices[1], vertices[2]));
+                vertices.clear();
+                normal = Vector::zero();
+            }
+            _ => {}
+        }
+    }
+
+    if triangles.is_empty() && !bytes.trim_ascii().is_empty() {
+        return Err(ParseError("No facet found".to_string()).into());
+    }
+
+    Ok(triangles)
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+pub fn parse_reader(mut reader: impl Read) -> Result<Object> {
+    let mut bytes = vec![];
+    reader.read_to_end(&mut bytes)?;
+
+    let triangles = if is_binary(&bytes) {
+        parse_binary(&bytes)?
+    } else {
+        parse_ascii(&bytes)?
+    };
+
+    Ok(Object::new_group(triangles))
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+pub fn parse_file(path: &std::path::Path) -> Result<Object> {
+    parse_reader(std::fs::File::open(path)?)
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two triangles per face of a unit cube.
+    const CUBE_TRIANGLES: [[[f32; 3]; 3]; 12] = [
+        [[0.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0, 1.0, 0.0]],
+        [[0.0, 0.0, 0.0], [1.0, 1.0, 0.0], [1.0, 0.0, 0.0]],
+        [[0.0, 0.0, 1.0], [1.0, 0.0, 1.0], [1.0, 1.0, 1.0]],
+        [[0.0, 0.0, 1.0], [1.0, 1.0, 1.0], [0.0, 1.0, 1.0]],
+        [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 0.0, 1.0]],
+        [[0.0, 0.0, 0.0], [1.0, 0.0, 1.0], [0.0, 0.0, 1.0]],
+        [[0.0, 1.0, 0.0], [0.0, 1.0, 1.0], [1.0, 1.0, 1.0]],
+        [[0.0, 1.0, 0.0], [1.0, 1.0, 1.0], [1.0, 1.0, 0.0]],
+        [[0.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 1.0]],
+        [[0.0, 0.0, 0.0], [0.0, 1.0, 1.0], [0.0, 1.0, 0.0]],
+        [[1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [1.0, 1.0, 1.0]],
+        [[1.0, 0.0, 0.0], [1.0, 1.0, 1.0], [1.0, 0.0, 1.0]],
+    ];
+
+    fn ascii_cube() -> String {
+        let mut txt = String::from("solid cube\n");
+        for triangle in CUBE_TRIANGLES.iter() {
+            txt += "  facet normal 0 0 0\n    outer loop\n";
+            for [x, y, z] in triangle.iter() {
+                txt += &format!("      vertex {} {} {}\n", x, y, z);
+            }
+            txt += "    endloop\n  endfacet\n";
+        }
+        txt += "endsolid cube\n";
+
+        txt
+    }
+
+    fn binary_cube() -> Vec<u8> {
+        // Start the header with "solid" to make sure it's not mistaken for an ASCII file.
+        let mut bytes = b"solid cube".to_vec();
+        bytes.resize(BINARY_HEADER_SIZE, 0);
+        bytes.extend_from_slice(&(CUBE_TRIANGLES.len() as u32).to_le_bytes());
+        for triangle in CUBE_TRIANGLES.iter() {
+            bytes.extend_from_slice(&[0; 12]);
+            for coordinate in triangle.iter().flatten() {
+                bytes.extend_from_slice(&coordinate.to_le_bytes());
+            }
+            bytes.extend_from_slice(&[0; 2]);
+        }
+
+        bytes
+    }
+
+    fn triangles(object: &Object) -> Vec<[Point; 3]> {
+        object
+            .flatten()
+            .iter()
+            .map(|o| {
+                let t = o.shape().as_triangle().unwrap();
+                [t.p1(), t.p2(), t.p3()]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parsing_an_ascii_stl_cube() {
+        let cube = parse_reader(ascii_cube().as_bytes()).unwrap();
+
+        assert_eq!(triangles(&cube).len(), 12);
+        assert_eq!(cube.bounding_box().min(), Point::new(0.0, 0.0, 0.0));
+        assert_eq!(cube.bounding_box().max(), Point::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn parsing_a_binary_stl_cube() {
+        let bytes = binary_cube();
+        assert!(is_binary(&bytes));
+
+        let cube = parse_reader(&bytes[..]).unwrap();
+        let ascii_cube = parse_reader(ascii_cube().as_bytes()).unwrap();
+
+        assert_eq!(triangles(&cube).len(), 12);
+        assert_eq!(triangles(&cube), triangles(&ascii_cube));
+    }
+
+    #[test]
+    fn detecting_binary_stl_files_by_their_header_or_size() {
+        let mut bytes = binary_cube();
+        let ascii_cube = parse_reader(ascii_cube().as_bytes()).unwrap();
+
+        // Padding after the announced triangles.
+        bytes.extend_from_slice(&[0; 10]);
+        assert!(is_binary(&bytes));
+        assert_eq!(
+            triangles(&parse_reader(&bytes[..]).unwrap()),
+            triangles(&ascii_cube)
+        );
+
+        // A header which doesn't start with "solid" with fewer triangles than announced.
+        bytes[..5].copy_from_slice(b"cubes");
+        bytes.truncate(bytes.len() - 10 - BINARY_TRIANGLE_SIZE);
+        assert!(is_binary(&bytes));
+        let err = parse_reader(&bytes[..]).unwrap_err();
+        assert_eq!(format!("{}", err), "Expected 12 triangles, found 11");
+    }
+
+    #[test]
+    fn padding_of_binary_stl_files_is_not_read_as_triangles() {
+        let mut bytes = binary_cube();
+        bytes.extend_from_slice(&[0; 3 * BINARY_TRIANGLE_SIZE + 7]);
+
+        let cube = parse_reader(&bytes[..]).unwrap();
+        let ascii_cube = parse_reader(ascii_cube().as_bytes()).unwrap();
+
+        assert_eq!(triangles(&cube), triangles(&ascii_cube));
+    }
+
+    #[test]
+    fn facet_normals_orient_triangles() {
+        let facet = |normal: &str| {
+            format!(
+                "solid foo\nfacet normal {}\nouter loop\n{}endloop\nendfacet\nendsolid foo\n",
+                normal, "vertex 0 0 0\nvertex 1 0 0\nvertex 0 1 0\n"
+            )
+        };
+        let normal = |txt: String| {
+            let group = parse_reader(txt.as_bytes()).unwrap();
+            group.flatten()[0]
+                .shape()
+                .as_triangle()
+                .unwrap()
+                .normal_at(&Point::zero())
+        };
+
+        assert_eq!(normal(facet("0 0 1")), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(normal(facet("0 0 -1")), Vector::new(0.0, 0.0, -1.0));
+        // No normal given, the triangle keeps the winding of its vertices.
+        assert_eq!(normal(facet("0 0 0")), Vector::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn parsing_an_ascii_stl_without_facets() {
+        let err = parse_reader("not an stl file\n".as_bytes()).unwrap_err();
+        assert_eq!(format!("{}", err), "No facet found");
+
+        assert!(triangles(&parse_reader("".as_bytes()).unwrap()).is_empty());
+    }
+
+    #[test]
+    fn parsing_an_invalid_ascii_stl() {
+        let txt = "solid foo\nfacet normal 0 0 0\nouter loop\nvertex 0 a 0\n";
+
+        let err = parse_reader(txt.as_bytes()).unwrap_err();
+        assert_eq!(
+            format!("{}", err),
+            "Invalid vertex `vertex 0 a 0` at line 4"
+        );
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
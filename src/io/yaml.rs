@@ -4,7 +4,7 @@ use crate::{
     primitive::{Point, Tuple, Vector},
     rtc::{
         rotation_x, rotation_y, rotation_z, scaling, shearing, translation, view_transform, Camera,
-        Color, Light, Material, Object, Pattern, Transform,
+        Color, DiffuseModel, Light, Material, Object, Pattern, Transform,
     },
 };
 use std::collections::HashMap;
@@ -16,7 +16,22 @@ type Definitions<'a> = HashMap<&'a Yaml, Yaml>;
 
 /* ---------------------------------------------------------------------------------------------- */
 
-fn get_definitions(yaml: &Yaml) -> Definitions {
+#[derive(Clone, Copy, Debug)]
+pub struct ParseOptions {
+    // Colors of materials are given in sRGB space and converted to the linear space of the
+    // lighting computations. Light intensities are always linear.
+    pub srgb_colors: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions { srgb_colors: true }
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+fn get_definitions(yaml: &Yaml) -> Definitions<'_> {
     let mut definitions = HashMap::new();
 
     for elem in yaml.as_vec().unwrap().iter() {
@@ -30,7 +45,7 @@ fn get_definitions(yaml: &Yaml) -> Definitions {
                 Some(parent) => {
                     if let Some(definition_value_hash) = definition_value.as_hash() {
                         let mut parent_hash = get_hash(&definitions, parent).clone();
-                        parent_hash.extend(definition_value_hash.clone().into_iter());
+                        parent_hash.extend(definition_value_hash.clone());
 
                         Yaml::Hash(parent_hash)
                     } else {
@@ -106,6 +121,19 @@ fn mk_usize_from_key(hash: &yaml::Hash, key: &str) -> Option<usize> {
 
 /* ---------------------------------------------------------------------------------------------- */
 
+// Indices of lights, in the order of their declaration.
+fn mk_light_mask_from_key(defs: &Definitions, hash: &yaml::Hash, key: &str) -> Option<u64> {
+    hash.get(&Yaml::from_str(key)).map(|yaml| {
+        get_array(defs, yaml)
+            .iter()
+            .map(mk_usize)
+            .filter(|&index| index < u64::BITS as usize)
+            .fold(0, |mask, index| mask | 1 << index)
+    })
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
 fn mk_f64(yaml: &Yaml) -> f64 {
     match yaml.as_f64() {
         None => match yaml.as_i64() {
@@ -139,6 +167,18 @@ fn mk_color_from_key(hash: &yaml::Hash, key: &str) -> Option<Color> {
 
 /* ---------------------------------------------------------------------------------------------- */
 
+fn mk_material_color(options: &ParseOptions, yaml: &Yaml) -> Color {
+    let color = mk_color(yaml);
+
+    if options.srgb_colors {
+        Color::from_srgb(color.r, color.g, color.b)
+    } else {
+        color
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
 fn mk_point(yaml: &Yaml) -> Point {
     let xyz = yaml.as_vec().unwrap();
     assert_eq!(xyz.len(), 3);
@@ -169,7 +209,9 @@ fn mk_vector_from_key(hash: &yaml::Hash, key: &str) -> Option<Vector> {
 
 /* ---------------------------------------------------------------------------------------------- */
 
-fn mk_pattern(defs: &Definitions, hash: &yaml::Hash) -> Option<Pattern> {
+fn mk_pattern(defs: &Definitions, options: &ParseOptions, hash: &yaml::Hash) -> Option<Pattern> {
+    let mk_color = |yaml: &Yaml| mk_material_color(options, yaml);
+
     if let Some(color) = hash.get(&Yaml::from_str("color")) {
         Some(Pattern::new_plain(mk_color(color)))
     } else if let Some(pattern) = hash.get(&Yaml::from_str("pattern")) {
@@ -188,7 +230,17 @@ fn mk_pattern(defs: &Definitions, hash: &yaml::Hash) -> Option<Pattern> {
                     .as_vec()
                     .unwrap();
 
-                Pattern::new_checker(mk_color(&colors[0]), mk_color(&colors[1]))
+                match mk_f64_from_key(pattern_hash, "border-width") {
+                    Some(width) => Pattern::new_checker_with_border(
+                        mk_color(&colors[0]),
+                        mk_color(&colors[1]),
+                        width,
+                        pattern_hash
+                            .get(&Yaml::from_str("border-color"))
+                            .map_or(Color::black(), mk_color),
+                    ),
+                    None => Pattern::new_checker(mk_color(&colors[0]), mk_color(&colors[1])),
+                }
             }
 
             "gradient" => {
@@ -235,14 +287,14 @@ fn mk_pattern(defs: &Definitions, hash: &yaml::Hash) -> Option<Pattern> {
 
 /* ---------------------------------------------------------------------------------------------- */
 
-fn mk_material(defs: &Definitions, hash: &yaml::Hash) -> Material {
+fn mk_material(defs: &Definitions, options: &ParseOptions, hash: &yaml::Hash) -> Material {
     let default = Material::new();
 
     match hash.get(&Yaml::from_str("material")) {
         Some(material_yaml) => {
             let material_hash = get_hash(defs, material_yaml);
 
-            Material::new()
+            let material = Material::new()
                 .with_ambient(mk_f64_from_key(material_hash, "ambient").unwrap_or(default.ambient))
                 .with_diffuse(mk_f64_from_key(material_hash, "diffuse").unwrap_or(default.diffuse))
                 .with_reflective(
@@ -258,10 +310,39 @@ fn mk_material(defs: &Definitions, hash: &yaml::Hash) -> Material {
                 .with_specular(
                     mk_f64_from_key(material_hash, "specular").unwrap_or(default.specular),
                 )
+                .with_subsurface(
+                    mk_f64_from_key(material_hash, "subsurface").unwrap_or(default.subsurface),
+                )
                 .with_transparency(
                     mk_f64_from_key(material_hash, "transparency").unwrap_or(default.transparency),
                 )
-                .with_pattern(mk_pattern(defs, material_hash).unwrap_or(default.pattern))
+                .with_pattern(mk_pattern(defs, options, material_hash).unwrap_or(default.pattern));
+
+            let material = match mk_f64_from_key(material_hash, "diffuse-roughness") {
+                Some(roughness) => {
+                    material.with_diffuse_model(DiffuseModel::OrenNayar { roughness })
+                }
+                None => material,
+            };
+
+            let material = match material_hash.get(&Yaml::from_str("emissive")) {
+                Some(color) => material.with_emissive(mk_material_color(options, color)),
+                None => material,
+            };
+
+            let material = match mk_f64_from_key(material_hash, "clearcoat") {
+                Some(strength) => material.with_clearcoat(
+                    strength,
+                    mk_f64_from_key(material_hash, "clearcoat-roughness")
+                        .unwrap_or(default.clearcoat_roughness),
+                ),
+                None => material,
+            };
+
+            match mk_f64_from_key(material_hash, "thin-film") {
+                Some(thickness) => material.with_thin_film(thickness),
+                None => material,
+            }
         }
         None => default,
     }
@@ -274,11 +355,7 @@ where
     T: Transform,
 {
     // Recursively find transformations that are referenced via a "define" statement.
-    fn get_transformations(
-        defs: &Definitions,
-        array: &[Yaml],
-        transformations: &mut Vec<Yaml>,
-    ) {
+    fn get_transformations(defs: &Definitions, array: &[Yaml], transformations: &mut Vec<Yaml>) {
         for transform in array {
             match transform[0].as_str() {
                 Some(_) => transformations.push(transform.clone()),
@@ -334,15 +411,16 @@ where
 
 /* ---------------------------------------------------------------------------------------------- */
 
-fn mk_object(defs: &Definitions, hash: &yaml::Hash, ty: &str) -> Object {
+fn mk_object(defs: &Definitions, options: &ParseOptions, hash: &yaml::Hash, ty: &str) -> Object {
     let object = match ty {
         "cube" => Object::new_cube(),
         "plane" => Object::new_plane(),
         "sphere" => Object::new_sphere(),
         _ => panic!("Unexpected object type: {:?}", ty),
     }
-    .with_material(mk_material(defs, hash))
-    .with_shadow(mk_bool_from_key(hash, "shadow").unwrap_or(true));
+    .with_material(mk_material(defs, options, hash))
+    .with_shadow(mk_bool_from_key(hash, "shadow").unwrap_or(true))
+    .with_light_mask(mk_light_mask_from_key(defs, hash, "lights").unwrap_or(u64::MAX));
 
     transform(defs, object, hash)
 }
@@ -399,10 +477,20 @@ fn mk_light(hash: &yaml::Hash) -> Light {
 
 /* ---------------------------------------------------------------------------------------------- */
 
-// TODO: don't unwrap() everywhere...
 pub fn parse(path: &std::path::Path) -> (Vec<Object>, Vec<Light>, Camera) {
-    let yaml = std::fs::read_to_string(path).unwrap();
-    let docs = YamlLoader::load_from_str(&yaml).unwrap();
+    parse_with_options(path, &ParseOptions::default())
+}
+
+pub fn parse_with_options(
+    path: &std::path::Path,
+    options: &ParseOptions,
+) -> (Vec<Object>, Vec<Light>, Camera) {
+    parse_str(&std::fs::read_to_string(path).unwrap(), options)
+}
+
+// TODO: don't unwrap() everywhere...
+fn parse_str(yaml: &str, options: &ParseOptions) -> (Vec<Object>, Vec<Light>, Camera) {
+    let docs = YamlLoader::load_from_str(yaml).unwrap();
     let doc = &docs[0];
 
     let mut objects = vec![];
@@ -426,7 +514,7 @@ pub fn parse(path: &std::path::Path) -> (Vec<Object>, Vec<Light>, Camera) {
                     lights.push(mk_light(hash));
                 }
                 "cube" | "plane" | "sphere" => {
-                    objects.push(mk_object(&definitions, hash, ty));
+                    objects.push(mk_object(&definitions, options, hash, ty));
                 }
                 _ => unimplemented!(),
             }
@@ -437,3 +525,57 @@ pub fn parse(path: &std::path::Path) -> (Vec<Object>, Vec<Light>, Camera) {
 }
 
 /* ---------------------------------------------------------------------------------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENE: &str = r#"
+- add: camera
+  width: 10
+  height: 10
+  field-of-view: 1
+  from: [0, 0, -5]
+  to: [0, 0, 0]
+  up: [0, 1, 0]
+- add: light
+  at: [0, 0, -10]
+  intensity: [0.5, 0.5, 0.5]
+- add: sphere
+  material:
+    color: [0.5, 0.5, 0.5]
+"#;
+
+    #[test]
+    fn colors_of_materials_are_srgb_by_default() {
+        let color = |options: &ParseOptions| {
+            let (objects, lights, _) = parse_str(SCENE, options);
+            assert_eq!(lights[0].intensity(), Color::new(0.5, 0.5, 0.5));
+
+            objects[0]
+                .material()
+                .pattern
+                .pattern_at_object(&objects[0], &Point::zero())
+        };
+
+        assert_eq!(
+            color(&ParseOptions::default()),
+            Color::from_srgb(0.5, 0.5, 0.5)
+        );
+        assert_eq!(
+            color(&ParseOptions { srgb_colors: false }),
+            Color::new(0.5, 0.5, 0.5)
+        );
+    }
+
+    #[test]
+    fn objects_are_linked_to_lights_by_their_indices() {
+        let scene = format!("{}  lights: [1, 3]\n- add: cube\n", SCENE);
+        let (objects, _, _) = parse_str(&scene, &ParseOptions::default());
+
+        assert_eq!(objects[0].light_mask(), 0b1010);
+        assert_eq!(objects[1].light_mask(), u64::MAX);
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
@@ -21,23 +21,40 @@ pub mod primitive {
 }
 
 pub mod io {
+    pub mod gif;
     pub mod obj;
+    pub mod stl;
     pub mod yaml;
 }
 
 pub mod rtc {
-    use bounds::BoundingBox;
+    pub(crate) use bounds::BoundingBox;
+    pub use camera::BokehShape;
     pub use camera::Camera;
     pub use camera::ParallelRendering;
+    pub use camera::Projection;
     pub use canvas::Canvas;
+    pub use canvas::CanvasError;
+    pub use canvas::DiffStats;
+    use color::without_nan_checks;
     pub use color::Color;
-    use intersection::{Intersection, IntersectionPusher, IntersectionState, Intersections};
+    pub use color::ColorError;
+    pub use cube_map::{CubeFace, CubeMap};
+    pub use environment::Environment;
+    pub use intersection::IntersectionState;
+    use intersection::{coplanar_runs, Intersection, IntersectionPusher, Intersections};
     pub use light::Light;
+    pub use lights::Falloff;
+    pub use material::DiffuseModel;
     pub use material::Material;
     pub use object::Object;
     pub use pattern::Pattern;
     use ray::Ray;
+    pub use render_options::RenderOptions;
+    pub use scene::{SceneBuilder, SceneError};
+    pub use shader::Shader;
     use shape::Shape;
+    pub use shapes::CsgOperation;
     pub use transformation::*;
     pub use world::World;
 
@@ -45,18 +62,31 @@ pub mod rtc {
     mod camera;
     mod canvas;
     mod color;
+    mod cube_map;
+    mod denoise;
+    mod environment;
     mod intersection;
     mod light;
     mod material;
+    mod noise;
     mod object;
     mod pattern;
     mod ray;
+    mod render_options;
+    mod shader;
     mod shape;
+    mod subdivision;
     pub mod transformation;
     pub mod world;
 
+    mod scene {
+        pub use scene_builder::{SceneBuilder, SceneError};
+
+        mod scene_builder;
+    }
+
     mod lights {
-        pub use area_light::AreaLight;
+        pub use area_light::{AreaLight, Falloff};
         pub use point_light::PointLight;
 
         mod area_light;
@@ -64,25 +94,43 @@ pub mod rtc {
     }
 
     mod shapes {
+        pub use capsule::Capsule;
+        pub use clip::Clip;
         pub use cone::Cone;
+        pub use csg::{Csg, CsgOperation};
         pub use cube::Cube;
         pub use cylinder::Cylinder;
+        pub use disk::Disk;
         pub use group::Group;
         pub use group::GroupBuilder;
+        pub use height_field::HeightField;
+        pub use lod::Lod;
+        pub use mesh::Mesh;
+        pub use metaball::MetaBall;
         pub use plane::Plane;
         pub use smooth_triangle::SmoothTriangle;
         pub use sphere::Sphere;
         pub use test_shape::TestShape;
+        pub use torus::Torus;
         pub use triangle::Triangle;
 
+        mod capsule;
+        mod clip;
         mod cone;
+        mod csg;
         mod cube;
         mod cylinder;
+        mod disk;
         mod group;
+        mod height_field;
+        mod lod;
+        mod mesh;
+        mod metaball;
         mod plane;
         mod smooth_triangle;
         mod sphere;
         mod test_shape;
+        mod torus;
         mod triangle;
     }
 }
@@ -32,6 +32,11 @@ impl Matrix {
         }
     }
 
+    // Row-major order.
+    pub fn to_array(&self) -> [f64; MATRIX_SIZE * MATRIX_SIZE] {
+        self.data
+    }
+
     pub fn transpose(&self) -> Matrix {
         let mut res = Matrix::new();
         for i in 0..MATRIX_SIZE {
@@ -97,7 +102,7 @@ impl Matrix {
 
     fn cofactor(&self, row: usize, col: usize) -> f64 {
         let minor = self.minor(row, col);
-        if (row + col) % 2 == 0 {
+        if (row + col).is_multiple_of(2) {
             minor
         } else {
             -minor
@@ -115,6 +120,15 @@ impl Default for Matrix {
 
 /* ---------------------------------------------------------------------------------------------- */
 
+// Row-major order.
+impl From<[f64; MATRIX_SIZE * MATRIX_SIZE]> for Matrix {
+    fn from(data: [f64; MATRIX_SIZE * MATRIX_SIZE]) -> Self {
+        Matrix { data }
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
 impl PartialEq for Matrix {
     fn eq(&self, other: &Matrix) -> bool {
         for i in 0..MATRIX_SIZE {
@@ -151,10 +165,13 @@ impl std::ops::IndexMut<(usize, usize)> for Matrix {
 
 /* ---------------------------------------------------------------------------------------------- */
 
-impl std::ops::Mul for Matrix {
-    type Output = Matrix;
-
-    fn mul(self, rhs: Matrix) -> Self::Output {
+// The scalar versions are kept to check the SIMD ones.
+#[cfg_attr(
+    all(feature = "simd", target_arch = "x86_64", not(test)),
+    allow(dead_code)
+)]
+impl Matrix {
+    fn mul_scalar(&self, rhs: &Matrix) -> Matrix {
         let mut res = Matrix::new();
 
         for row in 0..MATRIX_SIZE {
@@ -168,18 +185,9 @@ impl std::ops::Mul for Matrix {
 
         res
     }
-}
-
-/* ---------------------------------------------------------------------------------------------- */
 
-impl<T> std::ops::Mul<T> for Matrix
-where
-    T: Tuple,
-{
-    type Output = T;
-
-    fn mul(self, rhs: T) -> Self::Output {
-        Self::Output::new(
+    fn mul_tuple_scalar<T: Tuple>(&self, rhs: T) -> T {
+        T::new(
             self[(0, 0)] * rhs.x()
                 + self[(0, 1)] * rhs.y()
                 + self[(0, 2)] * rhs.z()
@@ -198,11 +206,124 @@ where
 
 /* ---------------------------------------------------------------------------------------------- */
 
+// SSE2 is always available on x86_64. Products are summed in the same order as in the scalar
+// versions and without fused multiply-add, so the results are bit-identical.
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+mod simd {
+    use super::{Matrix, MATRIX_SIZE};
+    use crate::primitive::tuple::Tuple;
+    use std::arch::x86_64::*;
+
+    pub fn mul(lhs: &Matrix, rhs: &Matrix) -> Matrix {
+        let mut res = Matrix::new();
+
+        // Each row of the result is a combination of the rows of rhs, two columns at a time.
+        unsafe {
+            for row in 0..MATRIX_SIZE {
+                for col in [0, 2] {
+                    let rhs_at = |k: usize| _mm_loadu_pd(&rhs.data[k * MATRIX_SIZE + col]);
+                    let lhs_at = |k: usize| _mm_set1_pd(lhs.data[row * MATRIX_SIZE + k]);
+
+                    let mut acc = _mm_mul_pd(lhs_at(0), rhs_at(0));
+                    acc = _mm_add_pd(acc, _mm_mul_pd(lhs_at(1), rhs_at(1)));
+                    acc = _mm_add_pd(acc, _mm_mul_pd(lhs_at(2), rhs_at(2)));
+                    acc = _mm_add_pd(acc, _mm_mul_pd(lhs_at(3), rhs_at(3)));
+
+                    _mm_storeu_pd(&mut res.data[row * MATRIX_SIZE + col], acc);
+                }
+            }
+        }
+
+        res
+    }
+
+    pub fn mul_tuple<T: Tuple>(lhs: &Matrix, rhs: T) -> T {
+        let mut res = [0.0; 4];
+        let coordinates = [rhs.x(), rhs.y(), rhs.z(), rhs.w()];
+
+        // Two rows at a time, the last one being ignored.
+        unsafe {
+            for row in [0, 2] {
+                let lhs_at = |k: usize| {
+                    _mm_set_pd(
+                        lhs.data[(row + 1) * MATRIX_SIZE + k],
+                        lhs.data[row * MATRIX_SIZE + k],
+                    )
+                };
+                let rhs_at = |k: usize| _mm_set1_pd(coordinates[k]);
+
+                let mut acc = _mm_mul_pd(lhs_at(0), rhs_at(0));
+                acc = _mm_add_pd(acc, _mm_mul_pd(lhs_at(1), rhs_at(1)));
+                acc = _mm_add_pd(acc, _mm_mul_pd(lhs_at(2), rhs_at(2)));
+                acc = _mm_add_pd(acc, _mm_mul_pd(lhs_at(3), rhs_at(3)));
+
+                _mm_storeu_pd(&mut res[row], acc);
+            }
+        }
+
+        T::new(res[0], res[1], res[2])
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+impl std::ops::Mul for Matrix {
+    type Output = Matrix;
+
+    fn mul(self, rhs: Matrix) -> Self::Output {
+        #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+        {
+            simd::mul(&self, &rhs)
+        }
+        #[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+        {
+            self.mul_scalar(&rhs)
+        }
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+impl<T> std::ops::Mul<T> for Matrix
+where
+    T: Tuple,
+{
+    type Output = T;
+
+    fn mul(self, rhs: T) -> Self::Output {
+        #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+        {
+            simd::mul_tuple(&self, rhs)
+        }
+        #[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+        {
+            self.mul_tuple_scalar(rhs)
+        }
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
 #[cfg(test)]
 mod tests {
     use crate::primitive::{point::Point, vector::Vector};
 
     use super::*;
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+    #[test]
+    fn converting_a_matrix_to_and_from_an_array() {
+        let data = [
+            1.0, 2.0, 3.0, 4.0, 5.5, 6.5, 7.5, 8.5, 9.0, 10.0, 11.0, 12.0, 13.5, 14.5, 15.5, 16.5,
+        ];
+        let m = Matrix::from(data);
+
+        assert_eq!(m[(0, 3)], 4.0);
+        assert_eq!(m[(1, 0)], 5.5);
+        assert_eq!(m[(3, 2)], 15.5);
+        assert_eq!(Matrix::from(m.to_array()), m);
+        assert_eq!(m.to_array(), data);
+    }
 
     #[test]
     fn create() {
@@ -405,7 +526,7 @@ mod tests {
             m[(3, 2)] = 0.0;
             m[(3, 3)] = 1.0;
 
-            let clone = m.clone();
+            let clone = m;
             assert_eq!(m * Matrix::id(), clone);
         }
         {
@@ -662,6 +783,34 @@ mod tests {
             assert_eq!(c * b.invert(), a);
         }
     }
+
+    #[test]
+    fn multiplications_match_the_scalar_ones() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut random_matrix = || {
+            let mut data = [0.0; MATRIX_SIZE * MATRIX_SIZE];
+            data.iter_mut()
+                .for_each(|x| *x = rng.gen_range(-100.0..100.0));
+            Matrix::from(data)
+        };
+
+        for _ in 0..1000 {
+            let (a, b) = (random_matrix(), random_matrix());
+            let (p, v) = (
+                Point::new(b[(0, 0)], b[(1, 1)], b[(2, 2)]),
+                Vector::new(b[(0, 1)], b[(1, 2)], b[(2, 3)]),
+            );
+
+            let bits = |m: Matrix| m.to_array().map(f64::to_bits);
+            assert_eq!(bits(a * b), bits(a.mul_scalar(&b)));
+
+            let p_bits = |p: Point| [p.x(), p.y(), p.z()].map(f64::to_bits);
+            assert_eq!(p_bits(a * p), p_bits(a.mul_tuple_scalar(p)));
+
+            let v_bits = |v: Vector| [v.x(), v.y(), v.z()].map(f64::to_bits);
+            assert_eq!(v_bits(a * v), v_bits(a.mul_tuple_scalar(v)));
+        }
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -87,7 +87,7 @@ mod tests {
             m0[1][0] = 1.0;
             m0[1][1] = -2.0;
 
-            let m1 = m0.clone();
+            let m1 = m0;
 
             assert_eq!(m0, m1);
         }
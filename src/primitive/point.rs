@@ -17,6 +17,21 @@ pub struct Point {
 
 /* ---------------------------------------------------------------------------------------------- */
 
+impl Point {
+    pub fn into_array(self) -> [f64; 3] {
+        [self.x, self.y, self.z]
+    }
+
+    // Unlike ==, the tolerance is given.
+    pub fn approx_eq(&self, other: &Point, epsilon: f64) -> bool {
+        self.x.approx_eq_epsilon(other.x, epsilon)
+            && self.y.approx_eq_epsilon(other.y, epsilon)
+            && self.z.approx_eq_epsilon(other.z, epsilon)
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
 impl Tuple for Point {
     fn new(x: f64, y: f64, z: f64) -> Self {
         Point { x, y, z }
@@ -49,6 +64,14 @@ impl Tuple for Point {
 
 /* ---------------------------------------------------------------------------------------------- */
 
+impl From<[f64; 3]> for Point {
+    fn from([x, y, z]: [f64; 3]) -> Self {
+        Point { x, y, z }
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
 impl PartialEq for Point {
     fn eq(&self, other: &Point) -> bool {
         self.x.approx_eq(other.x) && self.y.approx_eq(other.y) && self.z.approx_eq(other.z)
@@ -141,6 +164,14 @@ impl std::ops::Add<f64> for Point {
 mod tests {
     use super::*;
 
+    #[test]
+    fn converting_a_point_to_and_from_an_array() {
+        let p = Point::from([1.0, -2.0, 3.5]);
+
+        assert_eq!(p, Point::new(1.0, -2.0, 3.5));
+        assert_eq!(p.into_array(), [1.0, -2.0, 3.5]);
+    }
+
     #[test]
     fn add() {
         let p = Point {
@@ -176,6 +207,17 @@ mod tests {
         };
         assert_eq!(res, expected);
     }
+
+    #[test]
+    fn comparing_points_with_a_given_precision() {
+        let p = Point::new(1.0, -2.0, 3.0);
+        let q = Point::new(1.0005, -2.0, 3.0);
+
+        assert!(p.approx_eq(&q, 1e-3));
+        assert!(!p.approx_eq(&q, 1e-4));
+        assert!(!q.approx_eq(&p, 1e-4));
+        assert!(p.approx_eq(&p, 0.0));
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -1,7 +1,9 @@
 /* ---------------------------------------------------------------------------------------------- */
 
 use crate::{float::ApproxEq, primitive::tuple::Tuple};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
 
 /* ---------------------------------------------------------------------------------------------- */
 
@@ -15,6 +17,13 @@ pub struct Vector {
 /* ---------------------------------------------------------------------------------------------- */
 
 impl Vector {
+    // Unlike ==, the tolerance is given.
+    pub fn approx_eq(&self, other: &Vector, epsilon: f64) -> bool {
+        self.x.approx_eq_epsilon(other.x, epsilon)
+            && self.y.approx_eq_epsilon(other.y, epsilon)
+            && self.z.approx_eq_epsilon(other.z, epsilon)
+    }
+
     pub fn magnitude(&self) -> f64 {
         f64::sqrt(self.x * self.x + self.y * self.y + self.z * self.z)
     }
@@ -24,7 +33,65 @@ impl Vector {
     }
 
     pub fn reflect(&self, normal: &Vector) -> Vector {
-        *self - (*normal * 2.0) * (*self ^ *normal)
+        *self - (*normal * 2.0) * self.dot(*normal)
+    }
+
+    // Two vectors orthogonal to self and to each other (Duff et al., "Building an Orthonormal
+    // Basis, Revisited"). self must be normalized.
+    pub fn orthonormal_basis(&self) -> (Vector, Vector) {
+        let sign = 1.0_f64.copysign(self.z);
+        let a = -1.0 / (sign + self.z);
+        let b = self.x * self.y * a;
+
+        (
+            Vector::new(1.0 + sign * self.x * self.x * a, sign * b, -sign * self.x),
+            Vector::new(b, sign + self.y * self.y * a, -self.y),
+        )
+    }
+
+    // Uniformly distributed direction on the side of the surface the normal points to.
+    pub fn random_in_hemisphere(normal: &Vector, rng: &mut impl Rng) -> Vector {
+        let cos_theta = rng.gen::<f64>();
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+        let phi = 2.0 * PI * rng.gen::<f64>();
+
+        normal.local_to_world(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+    }
+
+    // Direction distributed proportionally to the cosine of its angle with the normal, as
+    // needed by diffuse surfaces.
+    pub fn random_cosine_weighted(normal: &Vector, rng: &mut impl Rng) -> Vector {
+        let r = rng.gen::<f64>().sqrt();
+        let phi = 2.0 * PI * rng.gen::<f64>();
+        let z = (1.0 - r * r).max(0.0).sqrt();
+
+        normal.local_to_world(r * phi.cos(), r * phi.sin(), z)
+    }
+
+    // Converts coordinates expressed in a basis whose z axis is self.
+    fn local_to_world(&self, x: f64, y: f64, z: f64) -> Vector {
+        let normal = self.normalize();
+        let (tangent, bitangent) = normal.orthonormal_basis();
+
+        tangent * x + bitangent * y + normal * z
+    }
+
+    // Same as the ^ operator.
+    pub fn dot(&self, other: Vector) -> f64 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    // Same as the * operator.
+    pub fn cross(&self, other: Vector) -> Vector {
+        Self {
+            x: self.y * other.z - self.z * other.y,
+            y: self.z * other.x - self.x * other.z,
+            z: self.x * other.y - self.y * other.x,
+        }
+    }
+
+    pub fn into_array(self) -> [f64; 3] {
+        [self.x, self.y, self.z]
     }
 }
 
@@ -62,6 +129,14 @@ impl Tuple for Vector {
 
 /* ---------------------------------------------------------------------------------------------- */
 
+impl From<[f64; 3]> for Vector {
+    fn from([x, y, z]: [f64; 3]) -> Self {
+        Vector { x, y, z }
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
 impl PartialEq for Vector {
     fn eq(&self, other: &Vector) -> bool {
         self.x.approx_eq_low_precision(other.x)
@@ -143,11 +218,7 @@ impl std::ops::Mul for Vector {
     type Output = Vector;
 
     fn mul(self, rhs: Vector) -> Self::Output {
-        Self {
-            x: self.y * rhs.z - self.z * rhs.y,
-            y: self.z * rhs.x - self.x * rhs.z,
-            z: self.x * rhs.y - self.y * rhs.x,
-        }
+        self.cross(rhs)
     }
 }
 
@@ -158,7 +229,7 @@ impl std::ops::BitXor for Vector {
     type Output = f64;
 
     fn bitxor(self, rhs: Vector) -> Self::Output {
-        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+        self.dot(rhs)
     }
 }
 
@@ -181,6 +252,15 @@ impl std::ops::Div<f64> for Vector {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    #[test]
+    fn converting_a_vector_to_and_from_an_array() {
+        let v = Vector::new(0.5, 2.0, -1.0);
+
+        assert_eq!(Vector::from(v.into_array()), v);
+        assert_eq!(v.into_array(), [0.5, 2.0, -1.0]);
+    }
 
     #[test]
     fn add() {
@@ -449,4 +529,79 @@ mod tests {
 
         assert_eq!(v.reflect(&n), Vector::new(1.0, 0.0, 0.0));
     }
+
+    #[test]
+    fn building_an_orthonormal_basis() {
+        let normals = [
+            Vector::new(0.0, 0.0, 1.0),
+            Vector::new(0.0, 0.0, -1.0),
+            Vector::new(1.0, 0.0, 0.0),
+            Vector::new(1.0, -2.0, 3.0).normalize(),
+            Vector::new(-0.3, 0.1, -0.9).normalize(),
+        ];
+
+        for n in normals {
+            let (t, b) = n.orthonormal_basis();
+
+            assert!(t.magnitude().approx_eq(1.0));
+            assert!(b.magnitude().approx_eq(1.0));
+            assert!(t.dot(b).approx_eq(0.0));
+            assert!(t.dot(n).approx_eq(0.0));
+            assert!(b.dot(n).approx_eq(0.0));
+        }
+    }
+
+    #[test]
+    fn sampling_directions_in_a_hemisphere() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let normal = Vector::new(1.0, 2.0, -2.0).normalize();
+        const SAMPLES: usize = 10_000;
+
+        let mut uniform_mean = Vector::zero();
+        let mut cosine_mean = Vector::zero();
+
+        for _ in 0..SAMPLES {
+            let uniform = Vector::random_in_hemisphere(&normal, &mut rng);
+            assert!(uniform.magnitude().approx_eq(1.0));
+            assert!(uniform.dot(normal) >= 0.0);
+            uniform_mean = uniform_mean + uniform / SAMPLES as f64;
+
+            let cosine = Vector::random_cosine_weighted(&normal, &mut rng);
+            assert!(cosine.magnitude().approx_eq(1.0));
+            assert!(cosine.dot(normal) >= 0.0);
+            cosine_mean = cosine_mean + cosine / SAMPLES as f64;
+        }
+
+        // The mean directions are aligned with the normal. Their lengths are the mean cosines
+        // with the normal: 1/2 for a uniform distribution and 2/3 for a cosine-weighted one.
+        assert!(uniform_mean.normalize().dot(normal) > 0.99);
+        assert!((uniform_mean.magnitude() - 0.5).abs() < 0.02);
+        assert!(cosine_mean.normalize().dot(normal) > 0.99);
+        assert!((cosine_mean.magnitude() - 2.0 / 3.0).abs() < 0.02);
+    }
+
+    #[test]
+    fn dot_and_cross_methods_match_the_operators() {
+        let a = Vector::new(1.0, 2.0, 3.0);
+        let b = Vector::new(2.0, 3.0, 4.0);
+
+        assert_eq!(a.dot(b), a ^ b);
+        assert_eq!(a.dot(b), 20.0);
+        assert_eq!(a.cross(b), a * b);
+        assert_eq!(a.cross(b), Vector::new(-1.0, 2.0, -1.0));
+        assert_eq!(b.cross(a), b * a);
+    }
+
+    #[test]
+    fn comparing_vectors_with_a_given_precision() {
+        let v = Vector::new(0.0, 1.0, 0.0);
+        let w = Vector::new(0.0, 1.0, 0.02);
+
+        assert!(v.approx_eq(&w, 0.05));
+        assert!(!v.approx_eq(&w, 0.01));
+        // Tighter than ==.
+        let w = Vector::new(0.0, 1.0, 1e-5);
+        assert_eq!(v, w);
+        assert!(!v.approx_eq(&w, 1e-6));
+    }
 }
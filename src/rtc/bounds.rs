@@ -9,6 +9,11 @@ use serde::{Deserialize, Serialize};
 
 /* ---------------------------------------------------------------------------------------------- */
 
+// Relative margin used to pad bounding boxes.
+const PADDING: f64 = 1.0e-9;
+
+/* ---------------------------------------------------------------------------------------------- */
+
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
 pub struct BoundingBox {
     min: Point,
@@ -60,6 +65,33 @@ impl BoundingBox {
         self
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.min.x() > self.max.x() || self.min.y() > self.max.y() || self.min.z() > self.max.z()
+    }
+
+    // Inflates the box by a tiny relative margin, so rays that graze it are not rejected
+    // because of floating-point errors.
+    pub fn padded(self) -> Self {
+        if self.is_empty() {
+            return self;
+        }
+
+        let pad = |x: f64| PADDING * x.abs().max(1.0);
+
+        Self {
+            min: Point::new(
+                self.min.x() - pad(self.min.x()),
+                self.min.y() - pad(self.min.y()),
+                self.min.z() - pad(self.min.z()),
+            ),
+            max: Point::new(
+                self.max.x() + pad(self.max.x()),
+                self.max.y() + pad(self.max.y()),
+                self.max.z() + pad(self.max.z()),
+            ),
+        }
+    }
+
     pub fn contains_point(&self, point: &Point) -> bool {
         self.min.x() <= point.x()
             && point.x() <= self.max.x()
@@ -73,6 +105,20 @@ impl BoundingBox {
         self.contains_point(&other.min) && self.contains_point(&other.max)
     }
 
+    pub fn distance_to(&self, point: &Point) -> f64 {
+        let dx = (self.min.x() - point.x())
+            .max(point.x() - self.max.x())
+            .max(0.0);
+        let dy = (self.min.y() - point.y())
+            .max(point.y() - self.max.y())
+            .max(0.0);
+        let dz = (self.min.z() - point.z())
+            .max(point.z() - self.max.z())
+            .max(0.0);
+
+        (dx * dx + dy * dy + dz * dz).sqrt()
+    }
+
     pub fn is_intersected(&self, ray: &Ray) -> bool {
         let (xtmin, xtmax) = BoundingBox::check_axis(
             ray.origin.x(),
@@ -278,6 +324,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn transforming_a_bounding_box() {
         let bbox1 = BoundingBox::new()
             .with_min(Point::new(-1.0, -1.0, -1.0))
@@ -354,6 +401,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn the_distance_from_a_point_to_a_bounding_box() {
+        let bbox = BoundingBox::new()
+            .with_min(Point::new(-1.0, -1.0, -1.0))
+            .with_max(Point::new(1.0, 1.0, 1.0));
+
+        assert_eq!(bbox.distance_to(&Point::new(0.5, 0.0, -0.5)), 0.0);
+        assert_eq!(bbox.distance_to(&Point::new(0.0, 0.0, -5.0)), 4.0);
+        assert_eq!(bbox.distance_to(&Point::new(4.0, 5.0, 0.0)), 5.0);
+    }
+
+    #[test]
+    fn padding_a_bounding_box() {
+        let bbox = BoundingBox::new()
+            .with_min(Point::new(-1.0, f64::NEG_INFINITY, 0.0))
+            .with_max(Point::new(1000.0, 2.0, f64::INFINITY))
+            .padded();
+
+        assert!(bbox.min().x() < -1.0);
+        assert_eq!(bbox.min().y(), f64::NEG_INFINITY);
+        assert!(bbox.min().z() < 0.0);
+        assert!(bbox.max().x() > 1000.0);
+        assert!(bbox.max().y() > 2.0);
+        assert_eq!(bbox.max().z(), f64::INFINITY);
+
+        assert!(BoundingBox::new().padded().is_empty());
+    }
+
     #[test]
     fn splitting_a_perfect_cube() {
         let bbox = BoundingBox::new()
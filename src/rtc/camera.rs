@@ -1,11 +1,16 @@
 /* ---------------------------------------------------------------------------------------------- */
 
 use crate::{
-    primitive::{Matrix, Point, Tuple},
-    rtc::{Canvas, Color, Ray, Transform, World},
+    primitive::{Matrix, Point, Tuple, Vector},
+    rtc::{denoise::denoise, rotation_y, Canvas, Color, Object, Ray, Transform, World},
 };
-use rayon::prelude::*;
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use rayon::{prelude::*, ThreadPoolBuildError};
 use serde::{Deserialize, Serialize};
+use std::{
+    f64::consts::PI,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
 /* ---------------------------------------------------------------------------------------------- */
 
@@ -16,18 +21,70 @@ pub struct Camera {
     fov: f64,
     transformation: Matrix,
     transformation_inverse: Matrix,
+    // Pixels are not square when the aspect ratio is overridden.
     pixel_size: f64,
+    pixel_height: f64,
+    aspect: Option<f64>,
     half_width: f64,
     half_height: f64,
     anti_aliasing_offsets: Vec<f64>,
+    adaptive_threshold: Option<f64>,
+    projection: Projection,
+    aperture: f64,
+    focal_distance: f64,
+    bokeh: BokehShape,
+    primary_rays_only: bool,
+    distortion: (f64, f64),
+    lens_shift: (f64, f64),
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Projection {
+    Perspective,
+    // Equidistant fisheye: the distance of a pixel to the center of the image is proportional
+    // to the angle of its ray with the optical axis.
+    Fisheye { fov: f64 },
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Shape of the lens aperture, which gives its shape to out of focus highlights.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum BokehShape {
+    Circle,
+    Polygon { sides: usize },
 }
 
 /* ---------------------------------------------------------------------------------------------- */
 
+// Diameter, in pixels, of the circle of confusion from which a pixel is considered fully blurry
+// in a focus map.
+const FOCUS_MAP_MAX_BLUR: f64 = 4.0;
+
+// Log-average luminance of auto-exposed images, the reflectance of a photographer's gray card.
+const AUTO_EXPOSURE_MIDDLE_GRAY: f64 = 0.18;
+
+/* ---------------------------------------------------------------------------------------------- */
+
 #[derive(Debug)]
 pub enum ParallelRendering {
     True,
     False,
+    // Render on a dedicated pool of the given number of threads.
+    Threads(usize),
+}
+
+impl ParallelRendering {
+    // Number of threads a render would use.
+    pub fn threads(&self) -> usize {
+        match self {
+            ParallelRendering::True => rayon::current_num_threads(),
+            ParallelRendering::False => 1,
+            ParallelRendering::Threads(n) => (*n).max(1),
+        }
+    }
 }
 
 impl std::fmt::Display for ParallelRendering {
@@ -35,6 +92,7 @@ impl std::fmt::Display for ParallelRendering {
         match self {
             ParallelRendering::True => write!(f, "true"),
             ParallelRendering::False => write!(f, "false"),
+            ParallelRendering::Threads(_) => write!(f, "{} threads", self.threads()),
         }
     }
 }
@@ -57,30 +115,31 @@ impl Camera {
     }
 
     pub fn with_size(mut self, h_size: usize, v_size: usize) -> Self {
-        let (pixel_size, half_width, half_height) = Camera::pixel_size(h_size, v_size, self.fov);
         self.h_size = h_size;
         self.v_size = v_size;
-        self.pixel_size = pixel_size;
-        self.half_width = half_width;
-        self.half_height = half_height;
 
-        self
+        self.update_view()
     }
 
     pub fn with_fov(mut self, fov: f64) -> Self {
-        let (pixel_size, half_width, half_height) =
-            Camera::pixel_size(self.h_size, self.v_size, fov);
         self.fov = fov;
-        self.pixel_size = pixel_size;
-        self.half_width = half_width;
-        self.half_height = half_height;
 
-        self
+        self.update_view()
     }
 
-    fn pixel_size(h_size: usize, v_size: usize, fov: f64) -> (f64, f64, f64) {
-        let half_view = (fov / 2.0).tan();
-        let aspect = h_size as f64 / v_size as f64;
+    // Ratio of the width to the height of the field of view, instead of the one of the canvas.
+    // The field of view still spans the larger of both dimensions.
+    pub fn with_aspect(mut self, aspect: f64) -> Self {
+        self.aspect = Some(aspect);
+
+        self.update_view()
+    }
+
+    fn update_view(mut self) -> Self {
+        let half_view = (self.fov / 2.0).tan();
+        let aspect = self
+            .aspect
+            .unwrap_or(self.h_size as f64 / self.v_size as f64);
 
         let (half_width, half_height) = if aspect >= 1.0 {
             (half_view, half_view / aspect)
@@ -88,9 +147,12 @@ impl Camera {
             (half_view * aspect, half_view)
         };
 
-        let pixel_size = (half_width * 2.0) / h_size as f64;
+        self.pixel_size = (half_width * 2.0) / self.h_size as f64;
+        self.pixel_height = (half_height * 2.0) / self.v_size as f64;
+        self.half_width = half_width;
+        self.half_height = half_height;
 
-        (pixel_size, half_width, half_height)
+        self
     }
 
     pub fn with_transformation(mut self, transformation: &Matrix) -> Self {
@@ -111,46 +173,325 @@ impl Camera {
         self
     }
 
-    fn ray_for_pixel(&self, px: usize, py: usize, x_offset: f64, y_offset: f64) -> Ray {
+    // Pixels start with the sub-samples at the corners of the anti-aliasing grid. Only when
+    // they differ by more than the threshold, on any channel, are the other sub-samples traced.
+    pub fn with_adaptive_anti_aliasing(mut self, threshold: f64) -> Self {
+        self.adaptive_threshold = Some(threshold.max(0.0));
+
+        self
+    }
+
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+
+        self
+    }
+
+    // An aperture of 0 is a pinhole camera, where everything is in focus.
+    pub fn with_depth_of_field(mut self, aperture: f64, focal_distance: f64) -> Self {
+        self.aperture = aperture.max(0.0);
+        self.focal_distance = focal_distance;
+
+        self
+    }
+
+    pub fn with_bokeh(mut self, bokeh: BokehShape) -> Self {
+        self.bokeh = match bokeh {
+            BokehShape::Polygon { sides } if sides < 3 => BokehShape::Circle,
+            _ => bokeh,
+        };
+
+        self
+    }
+
+    // Radial lens distortion of the perspective projection, with the polynomial model
+    // 1 + k1 * r^2 + k2 * r^4, where r is 1 at the middle of the longest side of the image.
+    // A negative k1 gives a barrel distortion, a positive one a pincushion distortion.
+    pub fn with_distortion(mut self, k1: f64, k2: f64) -> Self {
+        self.distortion = (k1, k2);
+
+        self
+    }
+
+    // Moves the image within the field of view of the perspective projection, in fractions of the
+    // width and of the height of the image, toward the right and the top. Looking up at a building
+    // with a shift rather than by tilting the camera keeps its vertical lines parallel.
+    pub fn with_lens_shift(mut self, dx: f64, dy: f64) -> Self {
+        self.lens_shift = (dx, dy);
+
+        self
+    }
+
+    // Image coordinates are seen as distorted: rays go through their undistorted position, using
+    // the first-order inverse of the model. Returns None when the model folds over itself.
+    fn undistort(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let (k1, k2) = self.distortion;
+        if k1 == 0.0 && k2 == 0.0 {
+            return Some((x, y));
+        }
+
+        let half_size = self.half_width.max(self.half_height);
+        let r2 = (x * x + y * y) / (half_size * half_size);
+        let factor = 1.0 + k1 * r2 + k2 * r2 * r2;
+
+        if factor <= 0.0 {
+            None
+        } else {
+            Some((x / factor, y / factor))
+        }
+    }
+
+    // Maps a sample of the unit square to a point of the lens aperture, in camera space.
+    fn lens_sample(&self, u: f64, v: f64) -> (f64, f64) {
+        match self.bokeh {
+            BokehShape::Circle => {
+                let radius = self.aperture * u.sqrt();
+                let theta = 2.0 * PI * v;
+
+                (radius * theta.cos(), radius * theta.sin())
+            }
+            BokehShape::Polygon { sides } => {
+                // Pick one of the triangles going from the center to each side of the polygon,
+                // then a point uniformly distributed within this triangle.
+                let sector = ((v * sides as f64) as usize).min(sides - 1);
+                let t = v * sides as f64 - sector as f64;
+
+                let angle = |k: usize| 2.0 * PI * k as f64 / sides as f64;
+                let (a0, a1) = (angle(sector), angle(sector + 1));
+
+                let r = self.aperture * u.sqrt();
+                (
+                    r * ((1.0 - t) * a0.cos() + t * a1.cos()),
+                    r * ((1.0 - t) * a0.sin() + t * a1.sin()),
+                )
+            }
+        }
+    }
+
+    // Returns None when the pixel doesn't map to any ray, like the pixels outside the circular
+    // image of a fisheye lens.
+    fn ray_for_pixel(&self, px: usize, py: usize, x_offset: f64, y_offset: f64) -> Option<Ray> {
+        match self.projection {
+            Projection::Perspective => {
+                let lens = if self.aperture > 0.0 {
+                    // Seeded by the pixel and its sub-sample to render the same image each time.
+                    let seed = (py * self.h_size + px) as u64
+                        ^ x_offset.to_bits().rotate_left(21)
+                        ^ y_offset.to_bits().rotate_left(42);
+                    let mut rng = SmallRng::seed_from_u64(seed);
+                    self.lens_sample(rng.gen(), rng.gen())
+                } else {
+                    (0.0, 0.0)
+                };
+
+                self.perspective_ray_for_pixel(px, py, x_offset, y_offset, lens)
+            }
+            Projection::Fisheye { fov } => {
+                self.fisheye_ray_for_pixel(px, py, x_offset, y_offset, fov)
+            }
+        }
+    }
+
+    fn perspective_ray_for_pixel(
+        &self,
+        px: usize,
+        py: usize,
+        x_offset: f64,
+        y_offset: f64,
+        (lens_x, lens_y): (f64, f64),
+    ) -> Option<Ray> {
         let x_offset = (px as f64 + x_offset) * self.pixel_size;
-        let y_offset = (py as f64 + y_offset) * self.pixel_size;
+        let y_offset = (py as f64 + y_offset) * self.pixel_height;
 
-        let world_x = self.half_width - x_offset;
-        let world_y = self.half_height - y_offset;
+        // The x axis of the camera goes toward the left of the image.
+        let (dx, dy) = self.lens_shift;
+        let (world_x, world_y) = self.undistort(
+            self.half_width - x_offset - dx * 2.0 * self.half_width,
+            self.half_height - y_offset + dy * 2.0 * self.half_height,
+        )?;
+
+        // With a pinhole camera, the lens is reduced to the origin and the focal distance has
+        // no effect.
+        let focal_distance = if self.aperture > 0.0 {
+            self.focal_distance
+        } else {
+            1.0
+        };
 
         let transformation_inv = self.transformation_inverse;
-        let pixel = transformation_inv * Point::new(world_x, world_y, -1.0);
+        let pixel = transformation_inv
+            * Point::new(
+                world_x * focal_distance,
+                world_y * focal_distance,
+                -focal_distance,
+            );
 
-        let origin = transformation_inv * Point::zero();
+        let origin = transformation_inv * Point::new(lens_x, lens_y, 0.0);
         let direction = (pixel - origin).normalize();
 
-        Ray { origin, direction }
+        Some(Ray { origin, direction })
+    }
+
+    fn fisheye_ray_for_pixel(
+        &self,
+        px: usize,
+        py: usize,
+        x_offset: f64,
+        y_offset: f64,
+        fov: f64,
+    ) -> Option<Ray> {
+        let dx = (px as f64 + x_offset) - self.h_size as f64 / 2.0;
+        let dy = (py as f64 + y_offset) - self.v_size as f64 / 2.0;
+
+        let radius = self.h_size.min(self.v_size) as f64 / 2.0;
+        let norm = (dx * dx + dy * dy).sqrt();
+        let distance = norm / radius;
+
+        if distance > 1.0 {
+            return None;
+        }
+
+        let theta = distance * fov / 2.0;
+        let (sin_phi, cos_phi) = if norm > 0.0 {
+            (-dy / norm, -dx / norm)
+        } else {
+            (0.0, 0.0)
+        };
+
+        // Same orientation as the perspective projection: x grows to the left and y grows upward.
+        let local_direction =
+            Vector::new(theta.sin() * cos_phi, theta.sin() * sin_phi, -theta.cos());
+
+        let transformation_inv = self.transformation_inverse;
+        let origin = transformation_inv * Point::zero();
+        let direction = (transformation_inv * local_direction).normalize();
+
+        Some(Ray { origin, direction })
+    }
+
+    fn sample_color_at(
+        &self,
+        world: &World,
+        col: usize,
+        row: usize,
+        x_offset: f64,
+        y_offset: f64,
+    ) -> Color {
+        match self.ray_for_pixel(col, row, x_offset, y_offset) {
+            Some(ray) if self.primary_rays_only => world.primary_color_at(&ray),
+            Some(ray) => world.color_at(&ray),
+            // Pixels without a ray keep the background color.
+            None => Color::black(),
+        }
     }
 
     fn color_at(&self, world: &World, col: usize, row: usize) -> Color {
-        let mut color = Color::black();
+        self.color_and_nb_samples_at(world, col, row).0
+    }
+
+    fn color_and_nb_samples_at(&self, world: &World, col: usize, row: usize) -> (Color, usize) {
+        let offsets = self.anti_aliasing_samples();
+        let sample = |(x_offset, y_offset): (f64, f64)| {
+            self.sample_color_at(world, col, row, x_offset, y_offset)
+        };
+
+        // Corners of the grid, in the order of the sub-samples.
+        let side = self.anti_aliasing_offsets.len();
+        let corners = [0, side - 1, side * (side - 1), side * side - 1];
 
-        for x_offset in &self.anti_aliasing_offsets {
-            for y_offset in &self.anti_aliasing_offsets {
-                let ray = self.ray_for_pixel(col, row, *x_offset, *y_offset);
-                color = color + world.color_at(&ray);
+        let corner_samples = match self.adaptive_threshold {
+            Some(threshold) if side > 1 => {
+                let colors = corners.map(|i| sample(offsets[i]));
+
+                if spread(&colors) <= threshold {
+                    return (compensated_sum(colors) / colors.len() as f64, colors.len());
+                }
+
+                Some(colors)
             }
-        }
+            _ => None,
+        };
+
+        let sum = compensated_sum(offsets.iter().enumerate().map(|(i, offset)| {
+            match (
+                &corner_samples,
+                corners.iter().position(|&corner| corner == i),
+            ) {
+                (Some(colors), Some(corner)) => colors[corner],
+                _ => sample(*offset),
+            }
+        }));
 
-        color / (self.anti_aliasing_offsets.len() * self.anti_aliasing_offsets.len()) as f64
+        (sum / offsets.len() as f64, offsets.len())
     }
 
-    pub fn render(&self, world: &World, parallel: ParallelRendering) -> Canvas {
+    // Setting the cancel flag stops the rendering after the rows in progress: the remaining
+    // pixels keep the background color. Fails when the threads for the rendering can't be created.
+    pub fn render(
+        &self,
+        world: &World,
+        parallel: ParallelRendering,
+        cancel: Option<&AtomicBool>,
+    ) -> Result<Canvas, ThreadPoolBuildError> {
         match parallel {
-            ParallelRendering::True => self.parallel_render(world),
-            ParallelRendering::False => self.sequential_render(world),
+            ParallelRendering::True => Ok(self.parallel_render(world, cancel)),
+            ParallelRendering::False => Ok(self.sequential_render(world, cancel)),
+            ParallelRendering::Threads(_) => Ok(rayon::ThreadPoolBuilder::new()
+                .num_threads(parallel.threads())
+                .build()?
+                .install(|| self.parallel_render(world, cancel))),
+        }
+    }
+
+    // The exposure scales the rendered image so its log-average luminance is middle gray, then
+    // Reinhard's operator compresses its highlights into [0, 1]. Also returns the exposure.
+    pub fn render_auto_exposed(&self, world: &World) -> (Canvas, f64) {
+        let mut image = self.parallel_render(world, None);
+
+        let log_average = image.log_average_luminance();
+        let exposure = if log_average > 0.0 {
+            AUTO_EXPOSURE_MIDDLE_GRAY / log_average
+        } else {
+            1.0
+        };
+
+        let tone_map = |c: f64| c / (1.0 + c);
+        for pixel in image.pixels().iter_mut() {
+            let exposed = *pixel * exposure;
+            *pixel = Color::new(
+                tone_map(exposed.r),
+                tone_map(exposed.g),
+                tone_map(exposed.b),
+            );
+        }
+
+        (image, exposure)
+    }
+
+    fn is_cancelled(cancel: Option<&AtomicBool>) -> bool {
+        cancel.is_some_and(|flag| flag.load(Ordering::Relaxed))
+    }
+
+    // Fast but ugly: half the resolution, no anti-aliasing and neither reflections nor
+    // refractions. Meant for interactive loops, not for final images.
+    pub fn render_preview(&self, world: &World) -> Canvas {
+        let camera = Camera {
+            primary_rays_only: true,
+            ..self.clone()
         }
+        .with_size((self.h_size / 2).max(1), (self.v_size / 2).max(1))
+        .with_anti_aliasing(1);
+
+        camera.parallel_render(world, None)
     }
 
-    pub fn sequential_render(&self, world: &World) -> Canvas {
+    pub fn sequential_render(&self, world: &World, cancel: Option<&AtomicBool>) -> Canvas {
         let mut image = Canvas::new(self.h_size, self.v_size);
 
         for row in 0..self.v_size {
+            if Camera::is_cancelled(cancel) {
+                break;
+            }
             for col in 0..self.h_size {
                 image[row][col] = self.color_at(world, col, row);
             }
@@ -159,7 +500,7 @@ impl Camera {
         image
     }
 
-    pub fn parallel_render(&self, world: &World) -> Canvas {
+    pub fn parallel_render(&self, world: &World, cancel: Option<&AtomicBool>) -> Canvas {
         const BAND_SIZE: usize = 10;
         let mut image = Canvas::new(self.h_size, self.v_size);
 
@@ -168,10 +509,140 @@ impl Camera {
             .par_chunks_mut(self.h_size * BAND_SIZE)
             .enumerate()
             .for_each(|(i, band)| {
-                for row in 0..BAND_SIZE {
-                    for col in 0..self.h_size {
-                        band[row * self.h_size + col] =
-                            self.color_at(world, col, row + i * BAND_SIZE);
+                // The last band is shorter when the height is not a multiple of BAND_SIZE.
+                for (row, line) in band.chunks_mut(self.h_size).enumerate() {
+                    if Camera::is_cancelled(cancel) {
+                        return;
+                    }
+                    for (col, pixel) in line.iter_mut().enumerate() {
+                        *pixel = self.color_at(world, col, row + i * BAND_SIZE);
+                    }
+                }
+            });
+
+        image
+    }
+
+    // Renders the rows from start_row (included) to end_row (excluded) only, to split a rendering
+    // between several processes. The resulting canvas has the full width of the image.
+    pub fn render_scanlines(&self, world: &World, start_row: usize, end_row: usize) -> Canvas {
+        let end_row = end_row.min(self.v_size);
+        let start_row = start_row.min(end_row);
+        let mut image = Canvas::new(self.h_size, end_row - start_row);
+
+        image
+            .pixels()
+            .par_chunks_mut(self.h_size.max(1))
+            .enumerate()
+            .for_each(|(row, line)| {
+                for (col, pixel) in line.iter_mut().enumerate() {
+                    *pixel = self.color_at(world, col, start_row + row);
+                }
+            });
+
+        image
+    }
+
+    // The camera orbits around the y axis of the world, by a full turn over all frames.
+    pub fn render_turntable(&self, world: &World, frames: usize) -> Vec<Canvas> {
+        (0..frames)
+            .map(|frame| {
+                let angle = 2.0 * PI * frame as f64 / frames as f64;
+                // Moving the camera by a rotation is the same as moving the world by its inverse.
+                let transformation = self.transformation * rotation_y(-angle);
+
+                self.clone()
+                    .with_transformation(&transformation)
+                    .parallel_render(world, None)
+            })
+            .collect()
+    }
+
+    // Helps to set the depth of field: sharp pixels are green and blurry ones are red, depending
+    // on the size of their circle of confusion. Pixels that hit nothing stay black.
+    pub fn render_focus_map(&self, world: &World) -> Canvas {
+        self.render_pass(|col, row| {
+            self.blur_at(world, col, row).map(|blur| {
+                let blur = (blur / FOCUS_MAP_MAX_BLUR).min(1.0);
+                Color::new(blur, 1.0 - blur, 0.0)
+            })
+        })
+    }
+
+    // White where the lights fully reach the visible surface, black where they are all
+    // occluded, whether the world renders shadows or not. The background is black.
+    pub fn render_shadow_pass(&self, world: &World) -> Canvas {
+        self.render_pass(|col, row| {
+            self.center_ray_for_pixel(col, row)
+                .and_then(|ray| world.light_fraction_at(&ray))
+                .map(|fraction| Color::new(fraction, fraction, fraction))
+        })
+    }
+
+    // Fresnel reflectance of the surface seen through the center of each pixel, whether it is
+    // reflective or not. The background is black.
+    pub fn render_fresnel(&self, world: &World) -> Canvas {
+        self.render_pass(|col, row| {
+            self.center_ray_for_pixel(col, row)
+                .and_then(|ray| world.reflectance_at(&ray))
+                .map(|reflectance| Color::new(reflectance, reflectance, reflectance))
+        })
+    }
+
+    // Filters the noise of soft shadows, glossy surfaces and such, guided by the albedo and the
+    // normal seen through the center of each pixel.
+    pub fn render_denoised(&self, world: &World) -> Canvas {
+        let beauty = self.parallel_render(world, None);
+        let (albedo, normal) = self.render_guides(world);
+
+        denoise(&beauty, &albedo, &normal)
+    }
+
+    // Albedo and normal of the surface seen through the center of each pixel, found with a single
+    // ray. Pixels without a surface are black.
+    fn render_guides(&self, world: &World) -> (Canvas, Canvas) {
+        let mut albedo = Canvas::new(self.h_size, self.v_size);
+        let mut normal = Canvas::new(self.h_size, self.v_size);
+
+        albedo
+            .pixels()
+            .par_chunks_mut(self.h_size.max(1))
+            .zip(normal.pixels().par_chunks_mut(self.h_size.max(1)))
+            .enumerate()
+            .for_each(|(row, (albedo_line, normal_line))| {
+                for (col, (albedo, normal)) in albedo_line
+                    .iter_mut()
+                    .zip(normal_line.iter_mut())
+                    .enumerate()
+                {
+                    if let Some((a, n)) = self
+                        .center_ray_for_pixel(col, row)
+                        .and_then(|ray| world.albedo_and_normal_at(&ray))
+                    {
+                        *albedo = a;
+                        *normal = Color::new(n.x(), n.y(), n.z());
+                    }
+                }
+            });
+
+        (albedo, normal)
+    }
+
+    // Diagnostic passes compute a single value per pixel. Pixels without one are black.
+    fn render_pass<F>(&self, pixel_color: F) -> Canvas
+    where
+        F: Fn(usize, usize) -> Option<Color> + Sync,
+    {
+        let mut image = Canvas::new(self.h_size, self.v_size);
+
+        image
+            .pixels()
+            .par_chunks_mut(self.h_size.max(1))
+            .enumerate()
+            .for_each(|(row, line)| {
+                for (col, pixel) in line.iter_mut().enumerate() {
+                    if let Some(color) = pixel_color(col, row) {
+                        *pixel = color;
                     }
                 }
             });
@@ -179,6 +650,227 @@ impl Camera {
         image
     }
 
+    // Diameter, in pixels, of the circle of confusion of the surface seen through the center of
+    // the pixel.
+    fn blur_at(&self, world: &World, col: usize, row: usize) -> Option<f64> {
+        let ray = self.center_ray_for_pixel(col, row)?;
+        let distance = world.distance_at(&ray)?;
+
+        // Only the perspective projection simulates a lens.
+        if self.aperture <= 0.0 || self.projection != Projection::Perspective {
+            return Some(0.0);
+        }
+
+        // A point is spread over a disk on the focal plane, whose size depends on how far from
+        // this plane it is.
+        let depth = -(self.transformation * ray.position(distance)).z();
+        let diameter = 2.0 * self.aperture * (depth - self.focal_distance).abs() / depth;
+
+        Some(diameter / (self.pixel_size * self.focal_distance))
+    }
+
+    // Also returns the number of sub-samples traced for each pixel, to see where adaptive
+    // anti-aliasing spends its effort.
+    pub fn render_with_sample_map(&self, world: &World) -> (Canvas, Vec<u16>) {
+        let mut image = Canvas::new(self.h_size, self.v_size);
+        let mut sample_map = vec![0; self.h_size * self.v_size];
+
+        image
+            .pixels()
+            .par_chunks_mut(self.h_size.max(1))
+            .zip(sample_map.par_chunks_mut(self.h_size.max(1)))
+            .enumerate()
+            .for_each(|(row, (line, nb_samples_line))| {
+                for (col, (pixel, nb_samples)) in
+                    line.iter_mut().zip(nb_samples_line.iter_mut()).enumerate()
+                {
+                    let (color, nb) = self.color_and_nb_samples_at(world, col, row);
+                    *pixel = color;
+                    *nb_samples = nb as u16;
+                }
+            });
+
+        (image, sample_map)
+    }
+
+    // For compositing: each pixel has the id of the object seen through its center, or 0 for the
+    // background. Ids are stable as long as the world is built the same way.
+    pub fn render_object_ids(&self, world: &World) -> Vec<u32> {
+        let ids = world.object_ids();
+        let mut image = vec![0; self.h_size * self.v_size];
+
+        image
+            .par_chunks_mut(self.h_size.max(1))
+            .enumerate()
+            .for_each(|(row, line)| {
+                for (col, pixel) in line.iter_mut().enumerate() {
+                    *pixel = self
+                        .center_ray_for_pixel(col, row)
+                        .and_then(|ray| world.object_at(&ray))
+                        .and_then(|object| ids.get(&(object as *const Object as usize)))
+                        .copied()
+                        .unwrap_or(0);
+                }
+            });
+
+        image
+    }
+
+    // Through the center of the pixel and of the lens.
+    fn center_ray_for_pixel(&self, col: usize, row: usize) -> Option<Ray> {
+        match self.projection {
+            Projection::Perspective => {
+                self.perspective_ray_for_pixel(col, row, 0.5, 0.5, (0.0, 0.0))
+            }
+            Projection::Fisheye { fov } => self.fisheye_ray_for_pixel(col, row, 0.5, 0.5, fov),
+        }
+    }
+
+    fn anti_aliasing_samples(&self) -> Vec<(f64, f64)> {
+        self.anti_aliasing_offsets
+            .iter()
+            .flat_map(|x| self.anti_aliasing_offsets.iter().map(move |y| (*x, *y)))
+            .collect()
+    }
+
+    // Each pass renders a share of the anti-aliasing sub-samples and the callback is given the
+    // average of all the sub-samples rendered so far. The last image is the same as the one of a
+    // regular rendering. With adaptive anti-aliasing, the first pass renders the corners of the
+    // pixels, and only the pixels whose corners differ get the other sub-samples.
+    pub fn render_progressive<F>(&self, world: &World, passes: usize, mut callback: F) -> Canvas
+    where
+        F: FnMut(usize, &Canvas),
+    {
+        // Interleave the sub-samples so that each pass covers the whole pixel.
+        let interleave = |samples: &[(f64, f64)], passes: usize| {
+            (0..passes)
+                .map(|pass| {
+                    samples
+                        .iter()
+                        .skip(pass)
+                        .step_by(passes)
+                        .copied()
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let samples = self.anti_aliasing_samples();
+        let side = self.anti_aliasing_offsets.len();
+        let corners = [0, side - 1, side * (side - 1), side * side - 1];
+
+        let (threshold, schedule) = match self.adaptive_threshold {
+            Some(threshold) if side > 1 => {
+                let others = samples
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| !corners.contains(i))
+                    .map(|(_, sample)| *sample)
+                    .collect::<Vec<_>>();
+                let corners = corners.map(|i| samples[i]).to_vec();
+
+                let schedule = match passes.clamp(1, others.len() + 1) {
+                    1 => vec![[corners, others].concat()],
+                    passes => std::iter::once(corners)
+                        .chain(interleave(&others, passes - 1))
+                        .collect(),
+                };
+
+                (Some(threshold), schedule)
+            }
+            _ => (None, interleave(&samples, passes.clamp(1, samples.len()))),
+        };
+
+        let mut sum = Canvas::new(self.h_size, self.v_size);
+        let mut image = Canvas::new(self.h_size, self.v_size);
+        let mut nb_samples = vec![0; self.h_size * self.v_size];
+        let mut converged = vec![false; self.h_size * self.v_size];
+
+        for (pass, pass_samples) in schedule.iter().enumerate() {
+            sum.pixels()
+                .par_chunks_mut(self.h_size.max(1))
+                .zip(nb_samples.par_chunks_mut(self.h_size.max(1)))
+                .zip(converged.par_chunks_mut(self.h_size.max(1)))
+                .enumerate()
+                .for_each(|(row, ((line, nb_samples_line), converged_line))| {
+                    for (col, ((pixel, nb), converged)) in line
+                        .iter_mut()
+                        .zip(nb_samples_line.iter_mut())
+                        .zip(converged_line.iter_mut())
+                        .enumerate()
+                    {
+                        if *converged {
+                            continue;
+                        }
+
+                        let sample = |(x_offset, y_offset): &(f64, f64)| {
+                            self.sample_color_at(world, col, row, *x_offset, *y_offset)
+                        };
+
+                        let pass_samples = match threshold {
+                            Some(threshold) if pass == 0 => {
+                                let colors = [0, 1, 2, 3].map(|i| sample(&pass_samples[i]));
+                                *pixel = compensated_sum(colors);
+                                *nb = colors.len();
+
+                                if spread(&colors) <= threshold {
+                                    *converged = true;
+                                    continue;
+                                }
+
+                                &pass_samples[colors.len()..]
+                            }
+                            _ => &pass_samples[..],
+                        };
+
+                        for offset in pass_samples {
+                            *pixel = *pixel + sample(offset);
+                        }
+                        *nb += pass_samples.len();
+                    }
+                });
+
+            for row in 0..self.v_size {
+                for col in 0..self.h_size {
+                    image[row][col] = sum[row][col] / nb_samples[row * self.h_size + col] as f64;
+                }
+            }
+
+            callback(pass, &image);
+        }
+
+        image
+    }
+
+    // Renders one image per anti-aliasing sub-sample, alongside their average. Mostly useful to
+    // see what anti-aliasing actually does.
+    pub fn render_samples(&self, world: &World) -> (Canvas, Vec<Canvas>) {
+        let samples = self
+            .anti_aliasing_samples()
+            .par_iter()
+            .map(|(x_offset, y_offset)| {
+                let mut image = Canvas::new(self.h_size, self.v_size);
+                for row in 0..self.v_size {
+                    for col in 0..self.h_size {
+                        image[row][col] =
+                            self.sample_color_at(world, col, row, *x_offset, *y_offset);
+                    }
+                }
+                image
+            })
+            .collect::<Vec<_>>();
+
+        let mut averaged = Canvas::new(self.h_size, self.v_size);
+        for row in 0..self.v_size {
+            for col in 0..self.h_size {
+                averaged[row][col] = compensated_sum(samples.iter().map(|sample| sample[row][col]))
+                    / samples.len() as f64;
+            }
+        }
+
+        (averaged, samples)
+    }
+
     pub fn h_size(&self) -> usize {
         self.h_size
     }
@@ -190,29 +882,78 @@ impl Camera {
     pub fn fov(&self) -> f64 {
         self.fov
     }
+
+    pub fn projection(&self) -> Projection {
+        self.projection
+    }
+
+    // Center of the lens, in world space.
+    pub fn position(&self) -> Point {
+        self.transformation_inverse * Point::zero()
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
 
-impl Default for Camera {
-    fn default() -> Self {
-        let h_size = 100;
-        let v_size = 100;
-        let fov = std::f64::consts::PI / 2.0;
+// Largest difference of a component between two colors.
+fn spread(colors: &[Color]) -> f64 {
+    colors
+        .iter()
+        .flat_map(|a| colors.iter().map(move |b| *a - *b))
+        .map(|d| d.r.abs().max(d.g.abs()).max(d.b.abs()))
+        .fold(0.0, f64::max)
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Floating-point additions are not associative: sub-samples are always summed in the same order,
+// so pixels don't depend on how the rendering is scheduled. Kahan summation also accumulates less
+// rounding errors than a naive one, without having to store the samples.
+fn compensated_sum(colors: impl IntoIterator<Item = Color>) -> Color {
+    let (sum, _) = colors.into_iter().fold(
+        (Color::black(), Color::black()),
+        |(sum, compensation), color| {
+            let y = color - compensation;
+            let t = sum + y;
+
+            // Infinite samples would turn the compensation into NaNs.
+            if t.is_finite() {
+                (t, (t - sum) - y)
+            } else {
+                (t, Color::black())
+            }
+        },
+    );
+
+    sum
+}
 
-        let (pixel_size, half_width, half_height) = Camera::pixel_size(h_size, v_size, fov);
+/* ---------------------------------------------------------------------------------------------- */
 
+impl Default for Camera {
+    fn default() -> Self {
         Camera {
-            h_size,
-            v_size,
-            fov,
+            h_size: 100,
+            v_size: 100,
+            fov: std::f64::consts::PI / 2.0,
             transformation: Matrix::id(),
             transformation_inverse: Matrix::id(),
-            pixel_size,
-            half_width,
-            half_height,
+            pixel_size: 0.0,
+            pixel_height: 0.0,
+            aspect: None,
+            half_width: 0.0,
+            half_height: 0.0,
             anti_aliasing_offsets: vec![0.5],
+            adaptive_threshold: None,
+            projection: Projection::Perspective,
+            aperture: 0.0,
+            focal_distance: 1.0,
+            bokeh: BokehShape::Circle,
+            primary_rays_only: false,
+            distortion: (0.0, 0.0),
+            lens_shift: (0.0, 0.0),
         }
+        .update_view()
     }
 }
 
@@ -237,9 +978,9 @@ mod tests {
 
     use super::*;
     use crate::{
-        float::ApproxEq,
+        float::{ApproxEq, EPSILON},
         primitive::{Point, Tuple, Vector},
-        rtc::{view_transform, Color},
+        rtc::{view_transform, Color, Light, Material, Object, Pattern, RenderOptions},
     };
 
     #[test]
@@ -257,7 +998,7 @@ mod tests {
     #[test]
     fn constructing_a_ray_through_the_center_of_the_canvas() {
         let c = Camera::new().with_size(201, 101).with_fov(PI / 2.0);
-        let r = c.ray_for_pixel(100, 50, 0.5, 0.5);
+        let r = c.ray_for_pixel(100, 50, 0.5, 0.5).unwrap();
 
         assert_eq!(r.origin, Point::new(0.0, 0.0, 0.0));
         assert_eq!(r.direction, Vector::new(0.0, 0.0, -1.0));
@@ -266,27 +1007,100 @@ mod tests {
     #[test]
     fn constructing_a_ray_through_a_corner_of_the_canvas() {
         let c = Camera::new().with_size(201, 101).with_fov(PI / 2.0);
-        let r = c.ray_for_pixel(0, 0, 0.5, 0.5);
+        let r = c.ray_for_pixel(0, 0, 0.5, 0.5).unwrap();
 
         assert_eq!(r.origin, Point::new(0.0, 0.0, 0.0));
         assert_eq!(r.direction, Vector::new(0.66519, 0.33259, -0.66851));
     }
 
     #[test]
-    fn constructing_a_ray_when_the_camera_is_transformed() {
-        let c = Camera::new()
-            .with_size(201, 101)
-            .with_fov(PI / 2.0)
-            .translate(0.0, -2.0, 5.0)
+    fn shifting_the_lens_keeps_vertical_lines_parallel() {
+        // Where the rays of a column reach a wall in front of the camera.
+        let wall_x = |c: &Camera, row: usize| {
+            let r = c.ray_for_pixel(80, row, 0.5, 0.5).unwrap();
+            r.origin.x() + r.direction.x() * (-5.0 - r.origin.z()) / r.direction.z()
+        };
+        let camera = Camera::new().with_size(101, 101).with_fov(PI / 2.0);
+
+        let shifted = camera.clone().with_lens_shift(0.0, 0.3);
+        let tilted = camera.with_transformation(&view_transform(
+            &Point::zero(),
+            &Point::new(0.0, 0.6, -1.0),
+            &Vector::new(0.0, 1.0, 0.0),
+        ));
+
+        // Both look up.
+        assert!(
+            shifted
+                .ray_for_pixel(50, 50, 0.5, 0.5)
+                .unwrap()
+                .direction
+                .y()
+                > 0.5
+        );
+        assert!(
+            tilted
+                .ray_for_pixel(50, 50, 0.5, 0.5)
+                .unwrap()
+                .direction
+                .y()
+                > 0.5
+        );
+
+        assert!(wall_x(&shifted, 0).approx_eq(wall_x(&shifted, 100)));
+        assert!((wall_x(&tilted, 0) - wall_x(&tilted, 100)).abs() > 0.5);
+    }
+
+    #[test]
+    fn overriding_the_aspect_ratio_of_a_camera() {
+        // Spread of the rays through the top left corner of the canvas.
+        let corner_spread = |c: &Camera| {
+            let r = c.ray_for_pixel(0, 0, 0.0, 0.0).unwrap();
+            (
+                r.direction.x() / -r.direction.z(),
+                r.direction.y() / -r.direction.z(),
+            )
+        };
+
+        let c = Camera::new().with_size(100, 100).with_fov(PI / 2.0);
+        let (x, y) = corner_spread(&c);
+        assert!(x.approx_eq(1.0));
+        assert!(y.approx_eq(1.0));
+
+        let c = c.with_aspect(2.0);
+        let (x, y) = corner_spread(&c);
+        assert!(x.approx_eq(1.0));
+        assert!(y.approx_eq(0.5));
+
+        // The pixel grid doesn't change the field of view.
+        let c = Camera::new()
+            .with_aspect(0.5)
+            .with_size(300, 100)
+            .with_fov(PI / 2.0);
+        let (x, y) = corner_spread(&c);
+        assert!(x.approx_eq(0.5));
+        assert!(y.approx_eq(1.0));
+
+        let r = c.ray_for_pixel(150, 50, 0.0, 0.0).unwrap();
+        assert_eq!(r.direction, Vector::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn constructing_a_ray_when_the_camera_is_transformed() {
+        let c = Camera::new()
+            .with_size(201, 101)
+            .with_fov(PI / 2.0)
+            .translate(0.0, -2.0, 5.0)
             .rotate_y(PI / 4.0)
             .transform();
-        let r = c.ray_for_pixel(100, 50, 0.5, 0.5);
+        let r = c.ray_for_pixel(100, 50, 0.5, 0.5).unwrap();
 
         assert_eq!(r.origin, Point::new(0.0, 2.0, -5.0));
         assert_eq!(
             r.direction,
             Vector::new(f64::sqrt(2.0) / 2.0, 0.0, -f64::sqrt(2.0) / 2.0)
         );
+        assert_eq!(c.position(), r.origin);
     }
 
     #[test]
@@ -300,11 +1114,40 @@ mod tests {
             .with_fov(PI / 2.0)
             .with_transformation(&view_transform(&from, &to, &up));
 
-        let image = c.sequential_render(&w);
+        let image = c.sequential_render(&w, None);
 
         assert_eq!(image[5][5], Color::new(0.38066, 0.47583, 0.2855));
     }
 
+    #[test]
+    fn rendering_a_denoised_world_with_a_camera() {
+        let w = crate::rtc::world::tests::default_world();
+        let from = Point::new(0.0, 0.0, -5.0);
+        let to = Point::new(0.0, 0.0, 0.0);
+        let up = Vector::new(0.0, 1.0, 0.0);
+        let c = Camera::new()
+            .with_size(11, 11)
+            .with_fov(PI / 2.0)
+            .with_transformation(&view_transform(&from, &to, &up));
+
+        // Without noise, the smooth shading of the sphere is barely changed and the background
+        // doesn't bleed on it.
+        let image = c.render_denoised(&w);
+        let expected = Color::new(0.38066, 0.47583, 0.2855);
+        let error = image[5][5] - expected;
+        assert!(error.r.abs().max(error.g.abs()).max(error.b.abs()) < 0.05);
+        assert_eq!(image[0][0], Color::black());
+
+        // Both guides are found with a single ray per pixel.
+        let w = w.with_cost_profiling();
+        let (albedo, normal) = c.render_guides(&w);
+        let stats: serde_json::Value = serde_json::from_str(&w.stats_json()).unwrap();
+        assert_eq!(stats["rays"].as_u64().unwrap(), 11 * 11);
+        assert_eq!(albedo[5][5], Color::new(0.8, 1.0, 0.6));
+        assert_eq!(normal[5][5], Color::new(0.0, 0.0, -1.0));
+        assert_eq!(normal[0][0], Color::black());
+    }
+
     #[test]
     fn parallel_rendering_a_world_with_a_camera() {
         let w = crate::rtc::world::tests::default_world();
@@ -316,11 +1159,709 @@ mod tests {
             .with_fov(PI / 2.0)
             .with_transformation(&view_transform(&from, &to, &up));
 
-        let image = c.sequential_render(&w);
-        let par_image = c.parallel_render(&w);
+        let image = c.sequential_render(&w, None);
+        let par_image = c.parallel_render(&w, None);
 
         assert_eq!(image, par_image);
     }
+
+    #[test]
+    fn rendering_with_a_given_number_of_threads() {
+        let w = crate::rtc::world::tests::default_world();
+        let from = Point::new(0.0, 0.0, -5.0);
+        let to = Point::new(0.0, 0.0, 0.0);
+        let up = Vector::new(0.0, 1.0, 0.0);
+        let c = Camera::new()
+            .with_size(50, 50)
+            .with_fov(PI / 2.0)
+            .with_transformation(&view_transform(&from, &to, &up));
+
+        let image = c.render(&w, ParallelRendering::False, None).unwrap();
+
+        assert_eq!(
+            image,
+            c.render(&w, ParallelRendering::Threads(1), None).unwrap()
+        );
+        for threads in [2, 3, 8] {
+            assert_eq!(
+                image,
+                c.render(&w, ParallelRendering::Threads(threads), None)
+                    .unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn the_effective_number_of_rendering_threads() {
+        assert_eq!(ParallelRendering::False.threads(), 1);
+        assert_eq!(ParallelRendering::Threads(0).threads(), 1);
+        assert_eq!(ParallelRendering::Threads(4).threads(), 4);
+        assert_eq!(
+            ParallelRendering::True.threads(),
+            rayon::current_num_threads()
+        );
+    }
+
+    #[test]
+    fn cancelling_a_rendering() {
+        let w = crate::rtc::world::tests::default_world();
+        let from = Point::new(0.0, 0.0, -5.0);
+        let to = Point::new(0.0, 0.0, 0.0);
+        let up = Vector::new(0.0, 1.0, 0.0);
+        let c = Camera::new()
+            .with_size(25, 25)
+            .with_fov(PI / 2.0)
+            .with_transformation(&view_transform(&from, &to, &up));
+
+        let cancel = AtomicBool::new(true);
+        for parallel in [ParallelRendering::True, ParallelRendering::False] {
+            let image = c.render(&w, parallel, Some(&cancel)).unwrap();
+            assert_eq!(image, Canvas::new(25, 25));
+        }
+
+        let cancel = AtomicBool::new(false);
+        let image = c
+            .render(&w, ParallelRendering::True, Some(&cancel))
+            .unwrap();
+        assert_eq!(image, c.render(&w, ParallelRendering::False, None).unwrap());
+        assert_eq!(image[12][12], Color::new(0.38066, 0.47583, 0.2855));
+    }
+
+    #[test]
+    fn rendering_the_anti_aliasing_samples() {
+        let w = crate::rtc::world::tests::default_world();
+        let from = Point::new(0.0, 0.0, -5.0);
+        let to = Point::new(0.0, 0.0, 0.0);
+        let up = Vector::new(0.0, 1.0, 0.0);
+        let c = Camera::new()
+            .with_size(11, 11)
+            .with_fov(PI / 2.0)
+            .with_transformation(&view_transform(&from, &to, &up))
+            .with_anti_aliasing(3);
+
+        let (averaged, samples) = c.render_samples(&w);
+        assert_eq!(samples.len(), 9);
+        assert_eq!(averaged, c.sequential_render(&w, None));
+
+        for row in 0..11 {
+            for col in 0..11 {
+                let sum = samples
+                    .iter()
+                    .fold(Color::black(), |acc, sample| acc + sample[row][col]);
+                assert_eq!(sum / 9.0, averaged[row][col]);
+            }
+        }
+    }
+
+    #[test]
+    fn rendering_progressively() {
+        let w = crate::rtc::world::tests::default_world();
+        let from = Point::new(0.0, 0.0, -5.0);
+        let to = Point::new(0.0, 0.0, 0.0);
+        let up = Vector::new(0.0, 1.0, 0.0);
+        let c = Camera::new()
+            .with_size(11, 11)
+            .with_fov(PI / 2.0)
+            .with_transformation(&view_transform(&from, &to, &up))
+            .with_anti_aliasing(4);
+
+        let mut passes = vec![];
+        let image = c.render_progressive(&w, 3, |pass, _| passes.push(pass));
+
+        assert_eq!(passes, vec![0, 1, 2]);
+        assert_eq!(image, c.sequential_render(&w, None));
+
+        // There can't be more passes than sub-samples.
+        let mut nb_passes = 0;
+        c.with_anti_aliasing(1)
+            .render_progressive(&w, 3, |_, _| nb_passes += 1);
+        assert_eq!(nb_passes, 1);
+    }
+
+    #[test]
+    fn rendering_scanlines() {
+        let w = crate::rtc::world::tests::default_world();
+        let from = Point::new(0.0, 0.0, -5.0);
+        let to = Point::new(0.0, 0.0, 0.0);
+        let up = Vector::new(0.0, 1.0, 0.0);
+        let c = Camera::new()
+            .with_size(11, 11)
+            .with_fov(PI / 2.0)
+            .with_transformation(&view_transform(&from, &to, &up));
+
+        let top = c.render_scanlines(&w, 0, 6);
+        let bottom = c.render_scanlines(&w, 6, 11);
+        assert_eq!((top.width(), top.height()), (11, 6));
+        assert_eq!((bottom.width(), bottom.height()), (11, 5));
+
+        let mut image = Canvas::new(11, 11);
+        image.blit(&top, 0, 0).unwrap();
+        image.blit(&bottom, 6, 0).unwrap();
+        assert_eq!(image, c.sequential_render(&w, None));
+
+        assert_eq!(c.render_scanlines(&w, 8, 20).height(), 3);
+        assert_eq!(c.render_scanlines(&w, 5, 2).height(), 0);
+    }
+
+    #[test]
+    fn rendering_a_turntable() {
+        let w = crate::rtc::world::tests::default_world();
+        let to = Point::new(0.0, 0.0, 0.0);
+        let up = Vector::new(0.0, 1.0, 0.0);
+        let c = Camera::new()
+            .with_size(11, 11)
+            .with_fov(PI / 2.0)
+            .with_transformation(&view_transform(&Point::new(0.0, 0.0, -5.0), &to, &up));
+
+        let frames = c.render_turntable(&w, 4);
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[0], c.sequential_render(&w, None));
+
+        // After three quarters of a turn, the camera looks at the world from the right.
+        let last = Camera::new()
+            .with_size(11, 11)
+            .with_fov(PI / 2.0)
+            .with_transformation(&view_transform(&Point::new(5.0, 0.0, 0.0), &to, &up));
+        assert_eq!(frames[3], last.sequential_render(&w, None));
+        assert_ne!(frames[3], frames[0]);
+
+        assert!(c.render_turntable(&w, 0).is_empty());
+    }
+
+    fn lens_samples(c: &Camera) -> Vec<(f64, f64)> {
+        const STEPS: usize = 20;
+
+        (0..=STEPS)
+            .flat_map(|i| {
+                (0..=STEPS).map(move |j| (i as f64 / STEPS as f64, j as f64 / STEPS as f64))
+            })
+            .map(|(u, v)| c.lens_sample(u, v))
+            .collect()
+    }
+
+    #[test]
+    fn circular_bokeh_samples_fall_within_the_aperture_disk() {
+        let c = Camera::new()
+            .with_depth_of_field(0.5, 4.0)
+            .with_bokeh(BokehShape::Circle);
+
+        for (x, y) in lens_samples(&c) {
+            assert!((x * x + y * y).sqrt() <= 0.5 + EPSILON);
+        }
+    }
+
+    #[test]
+    fn polygonal_bokeh_samples_fall_within_the_polygon() {
+        let sides = 6;
+        let c = Camera::new()
+            .with_depth_of_field(0.5, 4.0)
+            .with_bokeh(BokehShape::Polygon { sides });
+
+        let vertices = (0..sides)
+            .map(|k| {
+                let angle = 2.0 * PI * k as f64 / sides as f64;
+                (0.5 * angle.cos(), 0.5 * angle.sin())
+            })
+            .collect::<Vec<_>>();
+
+        // The polygon is convex and its vertices are counter-clockwise, thus all samples must
+        // be on the left of each edge.
+        for (x, y) in lens_samples(&c) {
+            for k in 0..sides {
+                let (x0, y0) = vertices[k];
+                let (x1, y1) = vertices[(k + 1) % sides];
+                assert!((x1 - x0) * (y - y0) - (y1 - y0) * (x - x0) >= -EPSILON);
+            }
+        }
+
+        // A hexagon doesn't reach the disk between its vertices.
+        let (x, y) = c.lens_sample(1.0, 0.5 / sides as f64);
+        assert!((x * x + y * y).sqrt() < 0.45);
+    }
+
+    #[test]
+    fn rays_through_the_lens_converge_on_the_focal_plane() {
+        let c = Camera::new()
+            .with_size(201, 101)
+            .with_fov(PI / 2.0)
+            .with_depth_of_field(0.5, 4.0);
+
+        let focal_point = Point::new(0.0, 0.0, -4.0);
+
+        for lens in [(0.0, 0.0), (0.3, 0.0), (-0.2, 0.4)].iter() {
+            let r = c
+                .perspective_ray_for_pixel(100, 50, 0.5, 0.5, *lens)
+                .unwrap();
+            assert_eq!(r.origin, Point::new(lens.0, lens.1, 0.0));
+
+            let t = (focal_point - r.origin).magnitude();
+            assert_eq!(r.position(t), focal_point);
+        }
+    }
+
+    #[test]
+    fn rendering_with_depth_of_field_is_reproducible() {
+        let w = crate::rtc::world::tests::default_world();
+        let c = Camera::new()
+            .with_size(11, 11)
+            .with_fov(PI / 2.0)
+            .with_transformation(&view_transform(
+                &Point::new(0.0, 0.0, -5.0),
+                &Point::new(0.0, 0.0, 0.0),
+                &Vector::new(0.0, 1.0, 0.0),
+            ))
+            .with_depth_of_field(0.5, 3.0);
+
+        let image = c.render(&w, ParallelRendering::True, None).unwrap();
+        let other_image = c.render(&w, ParallelRendering::True, None).unwrap();
+
+        for row in 0..11 {
+            for col in 0..11 {
+                assert_eq!(
+                    image[row][col].r.to_bits(),
+                    other_image[row][col].r.to_bits()
+                );
+            }
+        }
+        // Sub-samples go through different points of the lens.
+        assert_ne!(
+            c.ray_for_pixel(5, 5, 0.25, 0.25).unwrap().origin,
+            c.ray_for_pixel(5, 5, 0.75, 0.75).unwrap().origin
+        );
+    }
+
+    #[test]
+    fn rendering_a_focus_map() {
+        let wall_at = |z: f64| {
+            World::new().with_objects(vec![Object::new_plane()
+                .rotate_x(PI / 2.0)
+                .translate(0.0, 0.0, z)
+                .transform()])
+        };
+        let c = Camera::new()
+            .with_size(21, 11)
+            .with_fov(PI / 2.0)
+            .with_depth_of_field(1.0, 4.0);
+
+        let sharp = Color::new(0.0, 1.0, 0.0);
+        let blurry = Color::new(1.0, 0.0, 0.0);
+
+        let in_focus = c.render_focus_map(&wall_at(-4.0));
+        assert_eq!(in_focus[5][10], sharp);
+        assert_eq!(in_focus[0][0], sharp);
+
+        let out_of_focus = c.render_focus_map(&wall_at(-40.0));
+        assert_eq!(out_of_focus[5][10], blurry);
+
+        // Slightly off the focal plane is somewhere in between.
+        let close = c.render_focus_map(&wall_at(-4.5))[5][10];
+        assert!(close.r > 0.0 && close.g > close.r);
+
+        assert_eq!(c.render_focus_map(&World::new()), Canvas::new(21, 11));
+        assert_eq!(
+            c.with_depth_of_field(0.0, 4.0)
+                .render_focus_map(&wall_at(-40.0))[5][10],
+            sharp
+        );
+    }
+
+    #[test]
+    fn rendering_a_shadow_pass() {
+        let floor = Object::new_plane();
+        let blocker = Object::new_cube()
+            .scale(0.5, 0.5, 0.5)
+            .translate(0.0, 5.0, 0.0)
+            .transform();
+        let light = Light::new_point_light(Color::white(), Point::new(0.0, 10.0, 0.0));
+        let world = |render_options: RenderOptions| {
+            World::new()
+                .with_objects(vec![floor.clone(), blocker.clone()])
+                .with_lights(vec![light.clone()])
+                .with_render_options(render_options)
+        };
+
+        // Looking down at the floor from beside the blocker.
+        let c = Camera::new()
+            .with_size(21, 21)
+            .with_fov(PI / 2.0)
+            .with_transformation(&view_transform(
+                &Point::new(0.0, 3.0, -0.01),
+                &Point::new(0.0, 0.0, 0.0),
+                &Vector::new(0.0, 0.0, 1.0),
+            ));
+
+        for render_options in [
+            RenderOptions::new(),
+            RenderOptions::new().with_shadows(false),
+        ] {
+            let pass = c.render_shadow_pass(&world(render_options));
+
+            assert_eq!(pass[10][10], Color::black());
+            assert_eq!(pass[0][0], Color::white());
+        }
+
+        // Disabling shadows lights the floor under the blocker.
+        let shadowed = c
+            .render(&world(RenderOptions::new()), ParallelRendering::True, None)
+            .unwrap();
+        let unshadowed = c
+            .render(
+                &world(RenderOptions::new().with_shadows(false)),
+                ParallelRendering::True,
+                None,
+            )
+            .unwrap();
+        assert_eq!(shadowed[10][10], Color::new(0.1, 0.1, 0.1));
+        assert!(unshadowed[10][10].r > 0.5);
+        assert_eq!(shadowed[0][0], unshadowed[0][0]);
+    }
+
+    #[test]
+    fn rendering_the_fresnel_reflectance() {
+        let glass = Object::new_plane().with_material(
+            Material::new()
+                .with_transparency(1.0)
+                .with_refractive_index(1.5),
+        );
+        let world = World::new().with_objects(vec![glass]);
+
+        let head_on = Camera::new()
+            .with_size(11, 11)
+            .with_fov(PI / 4.0)
+            .with_transformation(&view_transform(
+                &Point::new(0.0, 5.0, 0.0),
+                &Point::new(0.0, 0.0, 0.0),
+                &Vector::new(0.0, 0.0, 1.0),
+            ))
+            .render_fresnel(&world);
+        assert!((head_on[5][5].r - 0.04).abs() < 0.001);
+
+        let grazing = Camera::new()
+            .with_size(11, 11)
+            .with_fov(PI / 100.0)
+            .with_transformation(&view_transform(
+                &Point::new(0.0, 0.2, 0.0),
+                &Point::new(0.0, 0.0, 20.0),
+                &Vector::new(0.0, 1.0, 0.0),
+            ))
+            .render_fresnel(&world);
+        assert!(grazing[5][5].r > 0.9);
+
+        // Above the horizon, rays miss the plane.
+        let sky = Camera::new()
+            .with_size(11, 11)
+            .with_transformation(&view_transform(
+                &Point::new(0.0, 1.0, 0.0),
+                &Point::new(0.0, 2.0, 10.0),
+                &Vector::new(0.0, 1.0, 0.0),
+            ))
+            .render_fresnel(&world);
+        assert_eq!(sky[0][5], Color::black());
+    }
+
+    #[test]
+    fn adaptive_anti_aliasing_spends_samples_on_details() {
+        let lit = |pattern: Pattern| {
+            Material::new()
+                .with_pattern(pattern)
+                .with_ambient(1.0)
+                .with_diffuse(0.0)
+                .with_specular(0.0)
+        };
+        // A fine checker on the right half of the image, a plain wall on the left half.
+        let detailed = Object::new_plane()
+            .rotate_x(PI / 2.0)
+            .translate(0.0, 0.0, 1.0)
+            .transform()
+            .with_material(lit(Pattern::new_checker(Color::white(), Color::black())
+                .scale(0.03, 0.03, 0.03)
+                .transform()));
+        let flat = Object::new_cube()
+            .scale(10.0, 10.0, 0.1)
+            .translate(-10.0, 0.0, 0.0)
+            .transform()
+            .with_material(lit(Pattern::new_plain(Color::new(0.2, 0.4, 0.6))));
+        let world = World::new()
+            .with_objects(vec![detailed, flat])
+            .with_lights(vec![Light::new_point_light(
+                Color::white(),
+                Point::new(0.0, 0.0, -10.0),
+            )]);
+
+        let c = Camera::new()
+            .with_size(20, 10)
+            .with_fov(PI / 2.0)
+            .with_transformation(&view_transform(
+                &Point::new(0.0, 0.0, -5.0),
+                &Point::new(0.0, 0.0, 0.0),
+                &Vector::new(0.0, 1.0, 0.0),
+            ))
+            .with_anti_aliasing(5);
+
+        let (image, sample_map) = c.render_with_sample_map(&world);
+        assert_eq!(
+            image,
+            c.render(&world, ParallelRendering::True, None).unwrap()
+        );
+        assert!(sample_map.iter().all(|&nb| nb == 25));
+
+        let c = c.with_adaptive_anti_aliasing(0.05);
+        let (image, sample_map) = c.render_with_sample_map(&world);
+        assert_eq!(
+            image,
+            c.render(&world, ParallelRendering::False, None).unwrap()
+        );
+
+        // Progressive renderings skip the same sub-samples.
+        for passes in [1, 2, 5] {
+            let mut images = vec![];
+            let progressive =
+                c.render_progressive(&world, passes, |_, image| images.push(image.clone()));
+            assert_eq!(progressive, image);
+
+            // The plain wall is done after the first pass.
+            assert_eq!(images[0][5][2], image[5][2]);
+        }
+
+        let nb_samples = |cols: std::ops::Range<usize>| {
+            (0..10)
+                .flat_map(|row| cols.clone().map(move |col| row * 20 + col))
+                .map(|i| sample_map[i] as usize)
+                .sum::<usize>()
+        };
+        assert_eq!(nb_samples(0..8), 4 * 80);
+        assert!(nb_samples(12..20) > 2 * nb_samples(0..8));
+        assert!(sample_map.iter().all(|&nb| nb == 4 || nb == 25));
+    }
+
+    #[test]
+    fn rendering_object_ids() {
+        let world = || {
+            World::new().with_objects(vec![
+                Object::new_group(vec![
+                    Object::new_sphere().translate(-2.0, 0.0, 0.0).transform(),
+                    Object::new_sphere().translate(2.0, 0.0, 0.0).transform(),
+                ]),
+                Object::new_cube().translate(0.0, 0.0, 20.0).transform(),
+            ])
+        };
+        let c = Camera::new()
+            .with_size(21, 11)
+            .with_fov(PI / 2.0)
+            .with_transformation(&view_transform(
+                &Point::new(0.0, 0.0, -5.0),
+                &Point::new(0.0, 0.0, 0.0),
+                &Vector::new(0.0, 1.0, 0.0),
+            ));
+
+        let ids = c.render_object_ids(&world());
+        assert_eq!(ids.len(), 21 * 11);
+
+        // Leaves are numbered in the order of the world.
+        let left_sphere = ids[5 * 21 + 5];
+        let right_sphere = ids[5 * 21 + 15];
+        assert_eq!((left_sphere, right_sphere), (1, 2));
+        assert_eq!(ids[5 * 21 + 6], left_sphere);
+        assert_eq!(ids[5 * 21 + 10], 3);
+        assert_eq!(ids[0], 0);
+
+        // Ids don't depend on where the world lives in memory.
+        assert_eq!(c.render_object_ids(&world()), ids);
+    }
+
+    #[test]
+    fn rendering_a_preview() {
+        let w = crate::rtc::world::tests::default_world();
+        let from = Point::new(0.0, 0.0, -5.0);
+        let to = Point::new(0.0, 0.0, 0.0);
+        let up = Vector::new(0.0, 1.0, 0.0);
+        let c = Camera::new()
+            .with_size(22, 11)
+            .with_fov(PI / 2.0)
+            .with_anti_aliasing(4)
+            .with_transformation(&view_transform(&from, &to, &up));
+
+        let image = c.render_preview(&w);
+
+        assert_eq!(image.width(), 11);
+        assert_eq!(image.height(), 5);
+        assert_eq!(image[2][5], Color::new(0.38066, 0.47583, 0.2855));
+    }
+
+    #[test]
+    fn the_center_ray_of_a_fisheye_camera_is_axial() {
+        let c = Camera::new()
+            .with_size(101, 101)
+            .with_projection(Projection::Fisheye { fov: PI });
+        let r = c.ray_for_pixel(50, 50, 0.5, 0.5).unwrap();
+
+        assert_eq!(r.origin, Point::new(0.0, 0.0, 0.0));
+        assert_eq!(r.direction, Vector::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn a_ray_at_the_edge_of_a_fisheye_image_is_at_half_the_fov() {
+        let fov = PI / 2.0;
+        let c = Camera::new()
+            .with_size(101, 101)
+            .with_projection(Projection::Fisheye { fov });
+        let axis = Vector::new(0.0, 0.0, -1.0);
+
+        let left = c.ray_for_pixel(0, 50, 0.0, 0.5).unwrap();
+        assert!(f64::acos(left.direction ^ axis).approx_eq(fov / 2.0));
+        assert_eq!(
+            left.direction,
+            Vector::new(f64::sqrt(2.0) / 2.0, 0.0, -f64::sqrt(2.0) / 2.0)
+        );
+
+        let top = c.ray_for_pixel(50, 0, 0.5, 0.0).unwrap();
+        assert!(f64::acos(top.direction ^ axis).approx_eq(fov / 2.0));
+        assert_eq!(
+            top.direction,
+            Vector::new(0.0, f64::sqrt(2.0) / 2.0, -f64::sqrt(2.0) / 2.0)
+        );
+    }
+
+    #[test]
+    fn fisheye_pixels_outside_the_image_circle_are_background() {
+        let w = World::new()
+            .with_objects(vec![Object::new_sphere()
+                .with_material(Material::new().with_ambient(1.0))
+                .scale(10.0, 10.0, 10.0)
+                .transform()])
+            .with_lights(vec![Light::new_point_light(Color::white(), Point::zero())]);
+        let c = Camera::new()
+            .with_size(11, 11)
+            .with_projection(Projection::Fisheye { fov: PI });
+
+        assert!(c.ray_for_pixel(0, 0, 0.5, 0.5).is_none());
+
+        let image = c.sequential_render(&w, None);
+
+        assert_eq!(image[0][0], Color::black());
+        assert_eq!(image[10][10], Color::black());
+        assert_ne!(image[5][5], Color::black());
+        assert_ne!(image[5][0], Color::black());
+    }
+
+    #[test]
+    fn lines_near_the_border_bow_with_lens_distortion() {
+        // Where the rays of the leftmost pixels meet the z = -1 plane, at the top, the middle and
+        // the bottom of the image. A straight vertical line of the scene is seen through pixels
+        // that are the closest to it; bowed pixels thus mean a bowed line.
+        let left_edge = |c: Camera| {
+            [0, 50, 100].map(|row| {
+                let r = c.ray_for_pixel(0, row, 0.5, 0.5).unwrap();
+                r.direction.x() / -r.direction.z()
+            })
+        };
+        let camera = Camera::new().with_size(101, 101).with_fov(PI / 2.0);
+
+        let [top, middle, bottom] = left_edge(camera.clone());
+        assert!(top.approx_eq(middle));
+        assert!(bottom.approx_eq(middle));
+        assert_eq!(
+            left_edge(camera.clone().with_distortion(0.0, 0.0)),
+            [top, middle, bottom]
+        );
+
+        // Barrel: the corners see farther, so straight lines bow outward, toward the border.
+        let [top, middle, bottom] = left_edge(camera.clone().with_distortion(-0.2, 0.0));
+        assert!(top > middle + 0.05);
+        assert!(bottom.approx_eq(top));
+
+        // Pincushion: straight lines bow inward.
+        let [top, middle, bottom] = left_edge(camera.clone().with_distortion(0.2, 0.05));
+        assert!(top < middle - 0.05);
+        assert!(bottom.approx_eq(top));
+
+        // The center of the image is not distorted.
+        let center = |c: Camera| c.ray_for_pixel(50, 50, 0.5, 0.5).unwrap().direction;
+        assert_eq!(
+            center(camera.clone().with_distortion(-0.2, 0.1)),
+            center(camera)
+        );
+    }
+
+    #[test]
+    fn anti_aliased_renderings_do_not_depend_on_the_number_of_threads() {
+        let w = crate::rtc::world::tests::default_world();
+        let c = Camera::new()
+            .with_size(40, 30)
+            .with_fov(PI / 3.0)
+            .with_anti_aliasing(3)
+            .with_transformation(&view_transform(
+                &Point::new(0.0, 1.5, -5.0),
+                &Point::new(0.0, 0.0, 0.0),
+                &Vector::new(0.0, 1.0, 0.0),
+            ));
+
+        let bits = |image: &Canvas| {
+            (0..image.height())
+                .flat_map(|row| (0..image.width()).map(move |col| image[row][col]))
+                .flat_map(|color| [color.r.to_bits(), color.g.to_bits(), color.b.to_bits()])
+                .collect::<Vec<_>>()
+        };
+
+        let reference = bits(&c.render(&w, ParallelRendering::Threads(1), None).unwrap());
+        assert_eq!(
+            reference,
+            bits(&c.render(&w, ParallelRendering::Threads(8), None).unwrap())
+        );
+        assert_eq!(reference, bits(&c.sequential_render(&w, None)));
+        assert_eq!(reference, bits(&c.render_samples(&w).0));
+    }
+
+    #[test]
+    fn compensated_sums_of_colors() {
+        assert_eq!(compensated_sum([]), Color::black());
+        assert_eq!(compensated_sum([Color::red()]), Color::red());
+        assert_eq!(
+            compensated_sum([Color::red(), Color::white(), Color::new(0.0, 0.5, 0.25)]),
+            Color::new(2.0, 1.5, 1.25)
+        );
+
+        // Each tiny color alone is lost when added to 1, but not their sum.
+        let tiny = Color::new(1.0e-16, 1.0e-16, 1.0e-16);
+        let sum = compensated_sum(std::iter::once(Color::white()).chain([tiny; 10]));
+        assert!(sum.r > 1.0);
+
+        let sum = compensated_sum([Color::red(), Color::new(f64::INFINITY, 0.0, 0.0)]);
+        assert_eq!(sum.r, f64::INFINITY);
+    }
+
+    #[test]
+    fn auto_exposure_brings_scenes_to_middle_gray() {
+        let world = |intensity: f64| {
+            World::new()
+                .with_objects(vec![Object::new_plane()])
+                .with_lights(vec![Light::new_point_light(
+                    Color::new(intensity, intensity, intensity),
+                    Point::new(0.0, 10.0, 0.0),
+                )])
+        };
+        let c = Camera::new()
+            .with_size(20, 20)
+            .with_fov(PI / 3.0)
+            .with_transformation(&view_transform(
+                &Point::new(0.0, 5.0, -5.0),
+                &Point::new(0.0, 0.0, 0.0),
+                &Vector::new(0.0, 1.0, 0.0),
+            ));
+
+        let (bright, bright_exposure) = c.render_auto_exposed(&world(100.0));
+        let (dark, dark_exposure) = c.render_auto_exposed(&world(0.01));
+
+        assert!(bright_exposure < 1.0);
+        assert!(dark_exposure > 1.0);
+        assert!(bright_exposure < dark_exposure);
+
+        // Middle gray, after tone mapping.
+        let gray = AUTO_EXPOSURE_MIDDLE_GRAY / (1.0 + AUTO_EXPOSURE_MIDDLE_GRAY);
+        for image in [bright, dark] {
+            assert!((image.log_average_luminance() - gray).abs() < 0.02);
+        }
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -1,14 +1,74 @@
 /* ---------------------------------------------------------------------------------------------- */
 
 use crate::rtc::Color;
+use sha3::{Digest, Sha3_256};
+use std::{cmp::Ordering, error::Error, fmt};
 
 /* ---------------------------------------------------------------------------------------------- */
 
 #[derive(Debug, PartialEq)]
+pub enum CanvasError {
+    DimensionMismatch((usize, usize), (usize, usize)),
+    OutOfBounds((usize, usize), (usize, usize)),
+}
+
+impl fmt::Display for CanvasError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CanvasError::DimensionMismatch((w1, h1), (w2, h2)) => {
+                write!(
+                    f,
+                    "Canvas dimensions mismatch: {}x{} vs {}x{}",
+                    w1, h1, w2, h2
+                )
+            }
+            CanvasError::OutOfBounds((w, h), (row, col)) => {
+                write!(
+                    f,
+                    "A {}x{} canvas does not fit at row {}, column {}",
+                    w, h, row, col
+                )
+            }
+        }
+    }
+}
+
+impl Error for CanvasError {}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Errors which can't be compared to the tolerance, i.e. NaNs, exceed it.
+fn exceeds(error: f64, tolerance: f64) -> bool {
+    !matches!(
+        error.partial_cmp(&tolerance),
+        Some(Ordering::Less | Ordering::Equal)
+    )
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Differences between two canvases, the error of a pixel being the largest difference of its
+// channels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DiffStats {
+    pub max_error: f64,
+    pub mean_error: f64,
+    // Number of pixels whose error exceeds the tolerance.
+    pub nb_differing: usize,
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+const LOG_AVERAGE_LUMINANCE_OFFSET: f64 = 1e-4;
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Canvas {
     width: usize,
     height: usize,
     pixels: Vec<Color>,
+    alphas: Vec<f64>,
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -23,11 +83,239 @@ impl Canvas {
             width,
             height,
             pixels: vec![color; width * height],
+            alphas: vec![1.0; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn alpha(&self, row: usize, col: usize) -> f64 {
+        self.alphas[row * self.width + col]
+    }
+
+    pub fn set_alpha(&mut self, row: usize, col: usize, alpha: f64) {
+        self.alphas[row * self.width + col] = alpha.clamp(0.0, 1.0);
+    }
+
+    fn check_dimensions(&self, other: &Canvas) -> Result<(), CanvasError> {
+        if self.width != other.width || self.height != other.height {
+            Err(CanvasError::DimensionMismatch(
+                (self.width, self.height),
+                (other.width, other.height),
+            ))
+        } else {
+            Ok(())
         }
     }
 
+    // Porter-Duff "over" operator, self being the foreground.
+    pub fn over(&self, background: &Canvas) -> Result<Canvas, CanvasError> {
+        self.check_dimensions(background)?;
+
+        let (pixels, alphas) = self
+            .pixels
+            .iter()
+            .zip(self.alphas.iter())
+            .zip(background.pixels.iter().zip(background.alphas.iter()))
+            .map(|((fg, fg_alpha), (bg, bg_alpha))| fg.over(*fg_alpha, bg, *bg_alpha))
+            .unzip();
+
+        Ok(Canvas {
+            width: self.width,
+            height: self.height,
+            pixels,
+            alphas,
+        })
+    }
+
+    // A non-finite channel, e.g. NaN, makes the error of its pixel infinite, as max ignores NaNs.
+    fn pixel_errors<'a>(&'a self, other: &'a Canvas) -> impl Iterator<Item = f64> + 'a {
+        self.pixels
+            .iter()
+            .zip(other.pixels.iter())
+            .map(|(lhs, rhs)| {
+                [lhs.r - rhs.r, lhs.g - rhs.g, lhs.b - rhs.b].iter().fold(
+                    0.0,
+                    |error: f64, difference| {
+                        if difference.is_finite() {
+                            error.max(difference.abs())
+                        } else {
+                            f64::INFINITY
+                        }
+                    },
+                )
+            })
+    }
+
+    // Compares a render to a reference one. Alphas are ignored.
+    pub fn diff(&self, other: &Canvas, tolerance: f64) -> Result<DiffStats, CanvasError> {
+        self.check_dimensions(other)?;
+
+        let (max_error, sum, nb_differing) = self.pixel_errors(other).fold(
+            (0.0, 0.0, 0),
+            |(max_error, sum, nb_differing), error: f64| {
+                (
+                    error.max(max_error),
+                    sum + error,
+                    nb_differing + usize::from(exceeds(error, tolerance)),
+                )
+            },
+        );
+
+        Ok(DiffStats {
+            max_error,
+            mean_error: if self.pixels.is_empty() {
+                0.0
+            } else {
+                sum / self.pixels.len() as f64
+            },
+            nb_differing,
+        })
+    }
+
+    // Pixels differing by more than the tolerance are red, the others are a dimmed gray version
+    // of self, to locate the differences in the image.
+    pub fn diff_image(&self, other: &Canvas, tolerance: f64) -> Result<Canvas, CanvasError> {
+        self.check_dimensions(other)?;
+
+        let pixels = self
+            .pixel_errors(other)
+            .zip(self.pixels.iter())
+            .map(|(error, pixel)| {
+                if exceeds(error, tolerance) {
+                    Color::red()
+                } else {
+                    let gray = pixel.luminance() * 0.25;
+                    Color::new(gray, gray, gray)
+                }
+            })
+            .collect();
+
+        Ok(Canvas {
+            width: self.width,
+            height: self.height,
+            pixels,
+            alphas: vec![1.0; self.width * self.height],
+        })
+    }
+
+    // Copies source into self, with its top left corner at (row, col).
+    pub fn blit(&mut self, source: &Canvas, row: usize, col: usize) -> Result<(), CanvasError> {
+        if row + source.height > self.height || col + source.width > self.width {
+            return Err(CanvasError::OutOfBounds(
+                (source.width, source.height),
+                (row, col),
+            ));
+        }
+
+        for source_row in 0..source.height {
+            let start = (row + source_row) * self.width + col;
+            let source_start = source_row * source.width;
+
+            self.pixels[start..start + source.width]
+                .copy_from_slice(&source.pixels[source_start..source_start + source.width]);
+            self.alphas[start..start + source.width]
+                .copy_from_slice(&source.alphas[source_start..source_start + source.width]);
+        }
+
+        Ok(())
+    }
+
+    // Darkens pixels with the square of their distance to the center, corners being multiplied by
+    // 1 - strength.
+    pub fn vignette(&self, strength: f64) -> Canvas {
+        self.map_pixels(|row, col| {
+            let (x, y) = self.centered_position(row, col);
+            let factor = (1.0 - strength * (x * x + y * y) / 2.0).max(0.0);
+
+            self[row][col] * factor
+        })
+    }
+
+    // Red is scaled away from the center and blue toward it, by the given fraction of the distance
+    // to the center, like the dispersion of a lens.
+    pub fn chromatic_aberration(&self, amount: f64) -> Canvas {
+        self.map_pixels(|row, col| {
+            let (x, y) = self.centered_position(row, col);
+            let red = self.nearest(x * (1.0 - amount), y * (1.0 - amount));
+            let blue = self.nearest(x * (1.0 + amount), y * (1.0 + amount));
+
+            Color::new(red.r, self[row][col].g, blue.b)
+        })
+    }
+
+    // Position of the center of a pixel, from -1 to 1 on each axis.
+    fn centered_position(&self, row: usize, col: usize) -> (f64, f64) {
+        (
+            2.0 * (col as f64 + 0.5) / self.width as f64 - 1.0,
+            2.0 * (row as f64 + 0.5) / self.height as f64 - 1.0,
+        )
+    }
+
+    // Pixel at a position given by centered_position(), clamped to the edges.
+    fn nearest(&self, x: f64, y: f64) -> Color {
+        let col = ((x + 1.0) / 2.0 * self.width as f64).max(0.0) as usize;
+        let row = ((y + 1.0) / 2.0 * self.height as f64).max(0.0) as usize;
+
+        self[row.min(self.height - 1)][col.min(self.width - 1)]
+    }
+
+    // Alphas are kept.
+    fn map_pixels(&self, pixel_at: impl Fn(usize, usize) -> Color) -> Canvas {
+        Canvas {
+            width: self.width,
+            height: self.height,
+            pixels: (0..self.height)
+                .flat_map(|row| (0..self.width).map(move |col| (row, col)))
+                .map(|(row, col)| pixel_at(row, col))
+                .collect(),
+            alphas: self.alphas.clone(),
+        }
+    }
+
+    // The alpha channel is only written when some pixels are not opaque, as not all formats
+    // support it.
     pub fn export(&self, path: &str) -> image::ImageResult<()> {
-        let mut img = image::ImageBuffer::new(self.width as u32, self.height as u32);
+        if self.alphas.iter().all(|alpha| *alpha >= 1.0) {
+            self.to_rgb_image().save(path)
+        } else {
+            self.to_transparent_rgba_image().save(path)
+        }
+    }
+
+    // PNG image embedded in a string, to display a render inline without touching the filesystem.
+    pub fn to_data_url(&self) -> String {
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgb8(self.to_rgb_image())
+            .write_to(&mut png, image::ImageOutputFormat::Png)
+            .expect("Cannot encode PNG in memory");
+
+        format!("data:image/png;base64,{}", base64::encode(png))
+    }
+
+    // Hash of the pixels as exported, to detect regressions without storing reference images.
+    // Dimensions are hashed as little-endian integers, so the hash doesn't depend on the platform.
+    pub fn content_hash(&self) -> u64 {
+        let hash = Sha3_256::new()
+            .chain((self.width as u64).to_le_bytes())
+            .chain((self.height as u64).to_le_bytes())
+            .chain(self.to_rgb_image().into_raw())
+            .finalize();
+
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&hash[..8]);
+
+        u64::from_le_bytes(bytes)
+    }
+
+    fn to_rgb_image(&self) -> image::RgbImage {
+        let mut img = image::RgbImage::new(self.width as u32, self.height as u32);
 
         for (x, y, pixel) in img.enumerate_pixels_mut() {
             let color = &self[y as usize][x as usize];
@@ -35,7 +323,49 @@ impl Canvas {
             *pixel = image::Rgb([r, g, b]);
         }
 
-        img.save(path)
+        img
+    }
+
+    fn to_transparent_rgba_image(&self) -> image::RgbaImage {
+        let mut img = image::RgbaImage::new(self.width as u32, self.height as u32);
+
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            let (row, col) = (y as usize, x as usize);
+            let (r, g, b) = scale_color(&self[row][col]);
+            let a = (self.alpha(row, col) * 255.0).round() as u8;
+            *pixel = image::Rgba([r, g, b, a]);
+        }
+
+        img
+    }
+
+    // Fully opaque, whatever the alpha of the pixels.
+    pub fn to_rgba_image(&self) -> image::RgbaImage {
+        let mut img = image::RgbaImage::new(self.width as u32, self.height as u32);
+
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            let color = &self[y as usize][x as usize];
+            let (r, g, b) = scale_color(color);
+            *pixel = image::Rgba([r, g, b, 255]);
+        }
+
+        img
+    }
+
+    // Geometric mean of the luminance of pixels, which is less sensitive than the arithmetic mean
+    // to a few very bright pixels. Black pixels are slightly offset to keep the logarithm finite.
+    pub fn log_average_luminance(&self) -> f64 {
+        if self.pixels.is_empty() {
+            return 0.0;
+        }
+
+        let sum = self
+            .pixels
+            .iter()
+            .map(|pixel| (LOG_AVERAGE_LUMINANCE_OFFSET + pixel.luminance().max(0.0)).ln())
+            .sum::<f64>();
+
+        (sum / self.pixels.len() as f64).exp()
     }
 
     pub fn pixels(&mut self) -> &mut Vec<Color> {
@@ -54,15 +384,7 @@ fn scale_color(color: &Color) -> (u8, u8, u8) {
 }
 
 fn scale_color_component(component: f64) -> u8 {
-    let component = if component < 0.0 {
-        0.0
-    } else if component > 1.0 {
-        1.0
-    } else {
-        component
-    };
-
-    (component * 255.0) as u8
+    (component.clamp(0.0, 1.0) * 255.0) as u8
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -90,6 +412,7 @@ impl std::ops::IndexMut<usize> for Canvas {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::float::ApproxEq;
 
     #[test]
     fn set_pixel() {
@@ -99,6 +422,207 @@ mod tests {
         assert_eq!(canvas[2][3], Color::red());
         assert_eq!(canvas[0][1], Color::black());
     }
+
+    #[test]
+    fn compositing_a_half_opaque_canvas_over_another_one() {
+        let mut foreground = Canvas::new_with_color(4, 2, Color::red());
+        for row in 0..2 {
+            for col in 0..4 {
+                foreground.set_alpha(row, col, 0.5);
+            }
+        }
+        let background = Canvas::new_with_color(4, 2, Color::blue());
+
+        let canvas = foreground.over(&background).unwrap();
+
+        assert_eq!(canvas[1][3], Color::new(0.5, 0.0, 0.5));
+        assert_eq!(canvas.alpha(1, 3), 1.0);
+    }
+
+    #[test]
+    fn compositing_canvases_of_different_dimensions_fails() {
+        let foreground = Canvas::new(4, 2);
+        let background = Canvas::new(2, 4);
+
+        assert_eq!(
+            foreground.over(&background),
+            Err(CanvasError::DimensionMismatch((4, 2), (2, 4)))
+        );
+    }
+
+    #[test]
+    fn blitting_a_canvas_into_another_one() {
+        let mut canvas = Canvas::new(4, 3);
+        let mut source = Canvas::new_with_color(2, 2, Color::red());
+        source.set_alpha(1, 1, 0.5);
+
+        assert_eq!(canvas.blit(&source, 1, 2), Ok(()));
+        for row in 0..3 {
+            for col in 0..4 {
+                let expected = if row >= 1 && col >= 2 {
+                    Color::red()
+                } else {
+                    Color::black()
+                };
+                assert_eq!(canvas[row][col], expected);
+            }
+        }
+        assert_eq!(canvas.alpha(2, 3), 0.5);
+
+        assert_eq!(
+            canvas.blit(&source, 2, 0),
+            Err(CanvasError::OutOfBounds((2, 2), (2, 0)))
+        );
+    }
+
+    #[test]
+    fn exporting_to_a_data_url() {
+        let mut canvas = Canvas::new(5, 3);
+        canvas[1][4] = Color::red();
+
+        let url = canvas.to_data_url();
+        let prefix = "data:image/png;base64,";
+        assert!(url.starts_with(prefix));
+
+        let png = base64::decode(&url[prefix.len()..]).unwrap();
+        let img = image::load_from_memory_with_format(&png, image::ImageFormat::Png)
+            .unwrap()
+            .to_rgb8();
+
+        assert_eq!(img.dimensions(), (5, 3));
+        assert_eq!(*img.get_pixel(4, 1), image::Rgb([255, 0, 0]));
+        assert_eq!(*img.get_pixel(0, 0), image::Rgb([0, 0, 0]));
+    }
+
+    #[test]
+    fn exporting_keeps_the_alpha_of_pixels() {
+        let mut canvas = Canvas::new(5, 3);
+        canvas[1][4] = Color::red();
+        canvas.set_alpha(1, 4, 0.5);
+
+        let path = std::env::temp_dir().join(format!("alpha_{}.png", std::process::id()));
+        let path = path.to_str().unwrap();
+        canvas.export(path).unwrap();
+        let img = image::open(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(img.color(), image::ColorType::Rgba8);
+        let img = img.to_rgba8();
+        assert_eq!(*img.get_pixel(4, 1), image::Rgba([255, 0, 0, 128]));
+        assert_eq!(*img.get_pixel(0, 0), image::Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn content_hashes_of_canvases() {
+        let mut canvas = Canvas::new(10, 20);
+        canvas[2][3] = Color::new(0.5, 0.25, 1.0);
+        let mut other = Canvas::new(10, 20);
+        other[2][3] = Color::new(0.5, 0.25, 1.0);
+
+        assert_eq!(canvas.content_hash(), other.content_hash());
+        // A golden hash, which must not depend on the platform.
+        assert_eq!(canvas.content_hash(), 10920908951555136968);
+
+        // Changes smaller than a quantization step are not detected.
+        other[2][3] = Color::new(0.5, 0.25, 1.0001);
+        assert_eq!(canvas.content_hash(), other.content_hash());
+
+        other[19][9] = Color::white();
+        assert_ne!(canvas.content_hash(), other.content_hash());
+
+        // Same pixels, different dimensions.
+        assert_ne!(
+            Canvas::new(10, 20).content_hash(),
+            Canvas::new(20, 10).content_hash()
+        );
+    }
+
+    #[test]
+    fn vignetting_darkens_the_corners() {
+        let canvas = Canvas::new_with_color(21, 11, Color::white());
+        let vignetted = canvas.vignette(0.5);
+
+        assert_eq!(vignetted[5][10], Color::white());
+        for (row, col) in [(0, 0), (0, 20), (10, 0), (10, 20)] {
+            assert!(vignetted[row][col].r < 0.6);
+            assert!(vignetted[row][col].r > 0.5);
+        }
+        assert!(vignetted[5][0].r < vignetted[5][5].r);
+
+        assert_eq!(canvas.vignette(0.0), canvas);
+    }
+
+    #[test]
+    fn chromatic_aberration_shifts_red_and_blue_radially() {
+        let mut canvas = Canvas::new(21, 21);
+        for row in 0..21 {
+            for col in 0..21 {
+                let c = (row * 21 + col) as f64 / 441.0;
+                canvas[row][col] = Color::new(c, 1.0 - c, c * c);
+            }
+        }
+        canvas.set_alpha(3, 4, 0.5);
+
+        assert_eq!(canvas.chromatic_aberration(0.0), canvas);
+
+        let mut dot = Canvas::new(21, 21);
+        dot[10][15] = Color::white();
+        let shifted = dot.chromatic_aberration(0.2);
+
+        // Red moves outward, blue inward and green stays.
+        assert_eq!(shifted[10][15].g, 1.0);
+        assert_eq!(shifted[10][15].r, 0.0);
+        assert_eq!(shifted[10][15].b, 0.0);
+        assert_eq!(shifted[10][16].r, 1.0);
+        assert_eq!(shifted[10][14].b, 1.0);
+        assert_eq!(shifted[10][10], Color::black());
+    }
+
+    #[test]
+    fn comparing_canvases() {
+        let canvas = Canvas::new_with_color(4, 3, Color::new(0.2, 0.4, 0.6));
+        let mut other = Canvas::new_with_color(4, 3, Color::new(0.2, 0.4, 0.6));
+
+        assert_eq!(
+            canvas.diff(&other, 0.0),
+            Ok(DiffStats {
+                max_error: 0.0,
+                mean_error: 0.0,
+                nb_differing: 0
+            })
+        );
+
+        other[1][2] = Color::new(0.2, 0.1, 0.6);
+        let stats = canvas.diff(&other, 0.01).unwrap();
+        assert!(stats.max_error.approx_eq(0.3));
+        assert!(stats.mean_error.approx_eq(0.3 / 12.0));
+        assert_eq!(stats.nb_differing, 1);
+        assert_eq!(canvas.diff(&other, 0.5).unwrap().nb_differing, 0);
+
+        let image = canvas.diff_image(&other, 0.01).unwrap();
+        assert_eq!(image[1][2], Color::red());
+        assert_ne!(image[0][0], Color::red());
+
+        assert_eq!(
+            canvas.diff(&Canvas::new(3, 4), 0.0),
+            Err(CanvasError::DimensionMismatch((4, 3), (3, 4)))
+        );
+    }
+
+    #[test]
+    fn nan_pixels_differ_from_any_pixel() {
+        let canvas = Canvas::new_with_color(2, 2, Color::new(0.2, 0.4, 0.6));
+        let mut other = canvas.clone();
+        other[0][1] = Color::new(0.2, f64::NAN, 0.6);
+
+        let stats = canvas.diff(&other, 1.0).unwrap();
+        assert_eq!(stats.max_error, f64::INFINITY);
+        assert_eq!(stats.nb_differing, 1);
+
+        let image = canvas.diff_image(&other, 1.0).unwrap();
+        assert_eq!(image[0][1], Color::red());
+        assert_ne!(image[1][1], Color::red());
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
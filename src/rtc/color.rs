@@ -2,6 +2,7 @@
 
 use crate::float::ApproxEq;
 use serde::{Deserialize, Serialize};
+use std::{cell::Cell, error::Error, fmt};
 
 /* ---------------------------------------------------------------------------------------------- */
 
@@ -14,11 +15,86 @@ pub struct Color {
 
 /* ---------------------------------------------------------------------------------------------- */
 
+#[derive(Debug, PartialEq)]
+pub enum ColorError {
+    NotFinite(f64),
+    Negative(f64),
+}
+
+impl fmt::Display for ColorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ColorError::NotFinite(c) => write!(f, "Color component is not finite: {}", c),
+            ColorError::Negative(c) => write!(f, "Color component is negative: {}", c),
+        }
+    }
+}
+
+impl Error for ColorError {}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+thread_local! {
+    static NAN_CHECKS: Cell<bool> = const { Cell::new(true) };
+}
+
+// Renderings which show non-finite colors on purpose don't want the NaN checks of debug builds to
+// panic. They are disabled for the current thread while f runs.
+pub(crate) fn without_nan_checks<T>(f: impl FnOnce() -> T) -> T {
+    let previous = NAN_CHECKS.with(|checks| checks.replace(false));
+    let result = f();
+    NAN_CHECKS.with(|checks| checks.set(previous));
+
+    result
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
 impl Color {
     pub fn new(r: f64, g: f64, b: f64) -> Color {
         Color { r, g, b }
     }
 
+    // Colors picked in an image editor or a color picker are usually in sRGB space, whereas
+    // lighting is computed in a linear space.
+    pub fn from_srgb(r: f64, g: f64, b: f64) -> Color {
+        let linearize = |c: f64| {
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+
+        Color::new(linearize(r), linearize(g), linearize(b))
+    }
+
+    pub fn try_new(r: f64, g: f64, b: f64) -> Result<Color, ColorError> {
+        for c in [r, g, b] {
+            if !c.is_finite() {
+                return Err(ColorError::NotFinite(c));
+            }
+            if c < 0.0 {
+                return Err(ColorError::Negative(c));
+            }
+        }
+
+        Ok(Color { r, g, b })
+    }
+
+    // NaNs silently propagate to the final image, so catch them as soon as possible in debug
+    // builds.
+    fn debug_check(self) -> Color {
+        debug_assert!(
+            !NAN_CHECKS.with(Cell::get)
+                || (!self.r.is_nan() && !self.g.is_nan() && !self.b.is_nan()),
+            "NaN color component: {:?}",
+            self
+        );
+
+        self
+    }
+
     pub fn black() -> Color {
         Color {
             r: 0.0,
@@ -58,6 +134,37 @@ impl Color {
             b: 1.0,
         }
     }
+
+    pub fn magenta() -> Color {
+        Color {
+            r: 1.0,
+            g: 0.0,
+            b: 1.0,
+        }
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.r.is_finite() && self.g.is_finite() && self.b.is_finite()
+    }
+
+    // Perceived brightness, with the Rec. 709 weights of linear RGB.
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
+    // Porter-Duff "over" operator on straight (non-premultiplied) alpha colors.
+    pub fn over(&self, alpha: f64, background: &Color, background_alpha: f64) -> (Color, f64) {
+        let out_alpha = alpha + background_alpha * (1.0 - alpha);
+
+        if out_alpha == 0.0 {
+            (Color::black(), 0.0)
+        } else {
+            let color = (*self * alpha + *background * (background_alpha * (1.0 - alpha)))
+                * (1.0 / out_alpha);
+
+            (color, out_alpha)
+        }
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -81,6 +188,7 @@ impl std::ops::Add for Color {
             g: self.g + rhs.g,
             b: self.b + rhs.b,
         }
+        .debug_check()
     }
 }
 
@@ -95,6 +203,7 @@ impl std::ops::Sub for Color {
             g: self.g - rhs.g,
             b: self.b - rhs.b,
         }
+        .debug_check()
     }
 }
 
@@ -109,6 +218,7 @@ impl std::ops::Mul<f64> for Color {
             g: self.g * rhs,
             b: self.b * rhs,
         }
+        .debug_check()
     }
 }
 
@@ -134,6 +244,7 @@ impl std::ops::Mul for Color {
             g: self.g * rhs.g,
             b: self.b * rhs.b,
         }
+        .debug_check()
     }
 }
 
@@ -148,6 +259,7 @@ impl std::ops::Div<f64> for Color {
             g: self.g / rhs,
             b: self.b / rhs,
         }
+        .debug_check()
     }
 }
 
@@ -180,4 +292,62 @@ mod tests {
 
         assert_eq!(res, expected);
     }
+
+    #[test]
+    fn checked_color_construction() {
+        assert_eq!(Color::try_new(0.1, 1.5, 0.0), Ok(Color::new(0.1, 1.5, 0.0)));
+        assert_eq!(
+            Color::try_new(0.1, f64::NAN, 0.0).unwrap_err().to_string(),
+            "Color component is not finite: NaN"
+        );
+        assert_eq!(
+            Color::try_new(f64::INFINITY, 0.0, 0.0),
+            Err(ColorError::NotFinite(f64::INFINITY))
+        );
+        assert_eq!(
+            Color::try_new(0.0, 0.0, -0.5),
+            Err(ColorError::Negative(-0.5))
+        );
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn introducing_a_nan_in_a_color_panics_in_debug_builds() {
+        let _ = Color::black() * f64::NAN;
+    }
+
+    #[test]
+    fn nan_checks_can_be_disabled() {
+        let color = without_nan_checks(|| Color::black() * f64::NAN);
+
+        assert!(color.r.is_nan());
+        assert!(NAN_CHECKS.with(Cell::get));
+    }
+
+    #[test]
+    fn compositing_a_color_over_a_translucent_background() {
+        let (color, alpha) = Color::red().over(0.5, &Color::blue(), 0.5);
+
+        assert_eq!(color, Color::new(2.0 / 3.0, 0.0, 1.0 / 3.0));
+        assert!(alpha.approx_eq(0.75));
+
+        let (color, alpha) = Color::red().over(0.0, &Color::blue(), 0.0);
+
+        assert_eq!(color, Color::black());
+        assert_eq!(alpha, 0.0);
+    }
+
+    #[test]
+    fn converting_a_srgb_color_to_linear() {
+        let c = Color::from_srgb(0.5, 0.5, 0.5);
+
+        assert!(c.r < 0.5);
+        assert_eq!(c, Color::new(0.21404, 0.21404, 0.21404));
+
+        assert_eq!(
+            Color::from_srgb(0.0, 1.0, 0.02),
+            Color::new(0.0, 1.0, 0.02 / 12.92)
+        );
+    }
 }
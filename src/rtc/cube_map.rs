@@ -0,0 +1,196 @@
+/* ---------------------------------------------------------------------------------------------- */
+
+use crate::{
+    primitive::{Tuple, Vector},
+    rtc::{Canvas, Color},
+};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CubeFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+const FACES: [CubeFace; 6] = [
+    CubeFace::PositiveX,
+    CubeFace::NegativeX,
+    CubeFace::PositiveY,
+    CubeFace::NegativeY,
+    CubeFace::PositiveZ,
+    CubeFace::NegativeZ,
+];
+
+impl CubeFace {
+    // Direction the face looks at, and directions of its columns and of its rows, from the top.
+    fn frame(&self) -> (Vector, Vector, Vector) {
+        match self {
+            CubeFace::PositiveX => (
+                Vector::new(1.0, 0.0, 0.0),
+                Vector::new(0.0, 0.0, -1.0),
+                Vector::new(0.0, 1.0, 0.0),
+            ),
+            CubeFace::NegativeX => (
+                Vector::new(-1.0, 0.0, 0.0),
+                Vector::new(0.0, 0.0, 1.0),
+                Vector::new(0.0, 1.0, 0.0),
+            ),
+            CubeFace::PositiveY => (
+                Vector::new(0.0, 1.0, 0.0),
+                Vector::new(1.0, 0.0, 0.0),
+                Vector::new(0.0, 0.0, -1.0),
+            ),
+            CubeFace::NegativeY => (
+                Vector::new(0.0, -1.0, 0.0),
+                Vector::new(1.0, 0.0, 0.0),
+                Vector::new(0.0, 0.0, 1.0),
+            ),
+            CubeFace::PositiveZ => (
+                Vector::new(0.0, 0.0, 1.0),
+                Vector::new(1.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            ),
+            CubeFace::NegativeZ => (
+                Vector::new(0.0, 0.0, -1.0),
+                Vector::new(-1.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            ),
+        }
+    }
+
+    fn index(&self) -> usize {
+        FACES.iter().position(|face| face == self).unwrap()
+    }
+
+    // The face crossed by a direction is the one of its largest component.
+    fn of(direction: &Vector) -> CubeFace {
+        let (x, y, z) = (direction.x(), direction.y(), direction.z());
+
+        if x.abs() >= y.abs() && x.abs() >= z.abs() {
+            if x >= 0.0 {
+                CubeFace::PositiveX
+            } else {
+                CubeFace::NegativeX
+            }
+        } else if y.abs() >= z.abs() {
+            if y >= 0.0 {
+                CubeFace::PositiveY
+            } else {
+                CubeFace::NegativeY
+            }
+        } else if z >= 0.0 {
+            CubeFace::PositiveZ
+        } else {
+            CubeFace::NegativeZ
+        }
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Colors seen in all directions from a point, on the six square faces of a cube around it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CubeMap {
+    resolution: usize,
+    // Faces one after the other, in the order of CubeFace.
+    pixels: Vec<Color>,
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+impl CubeMap {
+    // The color of each pixel is given by the direction through its center.
+    pub fn new<F>(resolution: usize, color_at: F) -> Self
+    where
+        F: Fn(&Vector) -> Color + Sync,
+    {
+        assert!(resolution > 0, "A cube map can't be empty");
+
+        let pixels = (0..6 * resolution * resolution)
+            .into_par_iter()
+            .map(|i| {
+                let face = FACES[i / (resolution * resolution)];
+                let (row, col) = ((i / resolution) % resolution, i % resolution);
+                color_at(&CubeMap::direction(face, row, col, resolution))
+            })
+            .collect();
+
+        CubeMap { resolution, pixels }
+    }
+
+    fn direction(face: CubeFace, row: usize, col: usize, resolution: usize) -> Vector {
+        let (forward, right, up) = face.frame();
+        let u = (col as f64 + 0.5) / resolution as f64 * 2.0 - 1.0;
+        let v = 1.0 - (row as f64 + 0.5) / resolution as f64 * 2.0;
+
+        (forward + right * u + up * v).normalize()
+    }
+
+    pub fn resolution(&self) -> usize {
+        self.resolution
+    }
+
+    pub fn face(&self, face: CubeFace) -> Canvas {
+        let size = self.resolution * self.resolution;
+        let start = face.index() * size;
+        let mut canvas = Canvas::new(self.resolution, self.resolution);
+        canvas
+            .pixels()
+            .copy_from_slice(&self.pixels[start..start + size]);
+
+        canvas
+    }
+
+    // Color of the nearest pixel in a direction.
+    pub fn color_at(&self, direction: &Vector) -> Color {
+        let face = CubeFace::of(direction);
+        let (forward, right, up) = face.frame();
+        let depth = direction.dot(forward);
+        let (u, v) = (direction.dot(right) / depth, direction.dot(up) / depth);
+
+        let to_index = |x: f64| ((x * self.resolution as f64) as usize).min(self.resolution - 1);
+        let (row, col) = (to_index((1.0 - v) / 2.0), to_index((u + 1.0) / 2.0));
+
+        self.pixels[face.index() * self.resolution * self.resolution + row * self.resolution + col]
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn looking_up_a_cube_map_in_the_directions_of_its_pixels() {
+        let resolution = 4;
+        // Each pixel has its own color.
+        let map = CubeMap::new(resolution, |direction| {
+            Color::new(direction.x(), direction.y(), direction.z())
+        });
+
+        for face in FACES {
+            let canvas = map.face(face);
+            for row in 0..resolution {
+                for col in 0..resolution {
+                    let direction = CubeMap::direction(face, row, col, resolution);
+                    assert_eq!(map.color_at(&direction), canvas[row][col]);
+                    assert_eq!(map.color_at(&(direction * 3.0)), canvas[row][col]);
+                }
+            }
+        }
+
+        // Axes go through the faces of their sign.
+        assert!(map.color_at(&Vector::new(0.0, -2.0, 0.0)).g < -0.5);
+        assert!(map.face(CubeFace::NegativeY)[0][0].g < -0.5);
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
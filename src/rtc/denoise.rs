@@ -0,0 +1,135 @@
+/* ---------------------------------------------------------------------------------------------- */
+
+use crate::rtc::{Canvas, Color};
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Each iteration doubles the spacing of the taps of the filter.
+const ITERATIONS: usize = 5;
+
+// B3-spline, separable.
+const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+// How different two pixels can be before they stop being averaged together. The color one is
+// halved at each iteration, as the image gets smoother.
+const COLOR_SIGMA: f64 = 0.6;
+const NORMAL_SIGMA: f64 = 0.3;
+const ALBEDO_SIGMA: f64 = 0.1;
+
+/* ---------------------------------------------------------------------------------------------- */
+
+fn distance_squared(lhs: Color, rhs: Color) -> f64 {
+    let d = lhs - rhs;
+    d.r * d.r + d.g * d.g + d.b * d.b
+}
+
+// Edge-avoiding À-Trous wavelet filter. Albedos and normals don't suffer from the noise of the
+// lighting, they tell which neighbors belong to the same surface. Normals are stored as colors,
+// their x, y and z in r, g and b.
+pub fn denoise(beauty: &Canvas, albedo: &Canvas, normal: &Canvas) -> Canvas {
+    assert!(
+        beauty.width() == albedo.width()
+            && beauty.width() == normal.width()
+            && beauty.height() == albedo.height()
+            && beauty.height() == normal.height(),
+        "Images to denoise must have the same dimensions"
+    );
+
+    let (width, height) = (beauty.width() as isize, beauty.height() as isize);
+    let mut image = beauty.clone();
+
+    for iteration in 0..ITERATIONS {
+        let step = 1 << iteration;
+        let color_sigma = COLOR_SIGMA / step as f64;
+        let mut filtered = Canvas::new(beauty.width(), beauty.height());
+
+        for row in 0..height {
+            for col in 0..width {
+                let (r, c) = (row as usize, col as usize);
+                let (color, n, a) = (image[r][c], normal[r][c], albedo[r][c]);
+                let mut sum = Color::black();
+                let mut total_weight = 0.0;
+
+                for (i, ky) in KERNEL.iter().enumerate() {
+                    for (j, kx) in KERNEL.iter().enumerate() {
+                        // Borders are clamped.
+                        let qr = (row + (i as isize - 2) * step).clamp(0, height - 1) as usize;
+                        let qc = (col + (j as isize - 2) * step).clamp(0, width - 1) as usize;
+                        let q_color = image[qr][qc];
+
+                        let weight = kx
+                            * ky
+                            * (-distance_squared(color, q_color) / color_sigma.powi(2)).exp()
+                            * (-distance_squared(n, normal[qr][qc]) / NORMAL_SIGMA.powi(2)).exp()
+                            * (-distance_squared(a, albedo[qr][qc]) / ALBEDO_SIGMA.powi(2)).exp();
+
+                        sum = sum + q_color * weight;
+                        total_weight += weight;
+                    }
+                }
+
+                // The center pixel always has a weight.
+                filtered[r][c] = sum / total_weight;
+            }
+        }
+
+        image = filtered;
+    }
+
+    image
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+    fn variance(image: &Canvas, rows: std::ops::Range<usize>, cols: std::ops::Range<usize>) -> f64 {
+        let pixels = rows
+            .flat_map(|row| cols.clone().map(move |col| image[row][col].r))
+            .collect::<Vec<_>>();
+        let mean = pixels.iter().sum::<f64>() / pixels.len() as f64;
+
+        pixels.iter().map(|p| (p - mean).powi(2)).sum::<f64>() / pixels.len() as f64
+    }
+
+    #[test]
+    fn denoising_smooths_surfaces_but_keeps_their_edges() {
+        // A red surface on the left, a blue one on the right.
+        let (width, height) = (32, 16);
+        let mut albedo = Canvas::new(width, height);
+        let mut beauty = Canvas::new(width, height);
+        let normal = Canvas::new_with_color(width, height, Color::new(0.0, 0.0, 1.0));
+        let mut rng = SmallRng::seed_from_u64(0);
+
+        for row in 0..height {
+            for col in 0..width {
+                albedo[row][col] = if col < width / 2 {
+                    Color::red()
+                } else {
+                    Color::blue()
+                };
+                let noise = rng.gen_range(-0.2..0.2);
+                beauty[row][col] = albedo[row][col] * 0.5 + Color::new(noise, noise, noise);
+            }
+        }
+
+        let denoised = denoise(&beauty, &albedo, &normal);
+
+        let before = variance(&beauty, 0..height, 0..width / 2);
+        let after = variance(&denoised, 0..height, 0..width / 2);
+        assert!(after < before / 10.0);
+
+        // Both sides of the edge keep their own color.
+        for row in 0..height {
+            let left = denoised[row][width / 2 - 1];
+            let right = denoised[row][width / 2];
+            assert!((left.r - 0.5).abs() < 0.1 && left.b.abs() < 0.1);
+            assert!((right.b - 0.5).abs() < 0.1 && right.r.abs() < 0.1);
+        }
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
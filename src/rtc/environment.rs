@@ -0,0 +1,255 @@
+/* ---------------------------------------------------------------------------------------------- */
+
+use crate::{
+    primitive::{Tuple, Vector},
+    rtc::{Canvas, Color},
+};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// The map is averaged down to this size before integrating the irradiance.
+const SOURCE_WIDTH: usize = 64;
+const SOURCE_HEIGHT: usize = 32;
+
+// Irradiance varies slowly with the normal, a small map is enough.
+const IRRADIANCE_WIDTH: usize = 32;
+const IRRADIANCE_HEIGHT: usize = 16;
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct LatLongMap {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl LatLongMap {
+    fn texel(&self, row: usize, col: usize) -> Color {
+        self.pixels[row * self.width + col]
+    }
+
+    // Direction through the center of a texel.
+    fn direction(&self, row: usize, col: usize) -> Vector {
+        let phi = (row as f64 + 0.5) / self.height as f64 * PI;
+        let theta = ((col as f64 + 0.5) / self.width as f64 - 0.5) * 2.0 * PI;
+
+        Vector::new(phi.sin() * theta.sin(), phi.cos(), phi.sin() * theta.cos())
+    }
+
+    // Fractional column and row of a direction.
+    fn coordinates(&self, direction: &Vector) -> (f64, f64) {
+        let direction = direction.normalize();
+        let u = 0.5 + direction.x().atan2(direction.z()) / (2.0 * PI);
+        let v = direction.y().clamp(-1.0, 1.0).acos() / PI;
+
+        (u * self.width as f64, v * self.height as f64)
+    }
+
+    fn nearest(&self, direction: &Vector) -> Color {
+        let (x, y) = self.coordinates(direction);
+        let col = (x as usize).min(self.width - 1);
+        let row = (y as usize).min(self.height - 1);
+
+        self.texel(row, col)
+    }
+
+    // Columns wrap around, rows are clamped at the poles.
+    fn bilinear(&self, direction: &Vector) -> Color {
+        let (x, y) = self.coordinates(direction);
+        let (x, y) = (x - 0.5, (y - 0.5).clamp(0.0, (self.height - 1) as f64));
+        let (fx, fy) = (x - x.floor(), y - y.floor());
+
+        let col0 = x.floor().rem_euclid(self.width as f64) as usize;
+        let col1 = (col0 + 1) % self.width;
+        let row0 = y.floor() as usize;
+        let row1 = (row0 + 1).min(self.height - 1);
+
+        let top = self.texel(row0, col0) * (1.0 - fx) + self.texel(row0, col1) * fx;
+        let bottom = self.texel(row1, col0) * (1.0 - fx) + self.texel(row1, col1) * fx;
+
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Light coming from infinitely far away, given by a latitude-longitude map: columns go around the
+// y axis, starting from -z, and rows go from +y to -y.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Environment {
+    map: LatLongMap,
+    // Cosine-weighted average of the map over the hemisphere around each normal.
+    irradiance: LatLongMap,
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+impl Environment {
+    pub fn new(map: &Canvas) -> Self {
+        assert!(
+            map.width() > 0 && map.height() > 0,
+            "An environment map can't be empty"
+        );
+
+        let map = LatLongMap {
+            width: map.width(),
+            height: map.height(),
+            pixels: (0..map.height())
+                .flat_map(|row| map[row].iter().copied())
+                .collect(),
+        };
+        let irradiance = Environment::mk_irradiance(&Environment::downsample(&map));
+
+        Environment { map, irradiance }
+    }
+
+    fn downsample(map: &LatLongMap) -> LatLongMap {
+        let width = map.width.min(SOURCE_WIDTH);
+        let height = map.height.min(SOURCE_HEIGHT);
+        let mut sums = vec![(Color::black(), 0); width * height];
+
+        for row in 0..map.height {
+            for col in 0..map.width {
+                let index = (row * height / map.height) * width + col * width / map.width;
+                sums[index] = (sums[index].0 + map.texel(row, col), sums[index].1 + 1);
+            }
+        }
+
+        LatLongMap {
+            width,
+            height,
+            pixels: sums.into_iter().map(|(sum, nb)| sum / nb as f64).collect(),
+        }
+    }
+
+    fn mk_irradiance(source: &LatLongMap) -> LatLongMap {
+        let texels = (0..source.height)
+            .flat_map(|row| (0..source.width).map(move |col| (row, col)))
+            .map(|(row, col)| {
+                // Texels get smaller toward the poles.
+                let solid_angle = ((row as f64 + 0.5) / source.height as f64 * PI).sin();
+                (
+                    source.direction(row, col),
+                    source.texel(row, col),
+                    solid_angle,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut irradiance = LatLongMap {
+            width: IRRADIANCE_WIDTH,
+            height: IRRADIANCE_HEIGHT,
+            pixels: vec![],
+        };
+
+        irradiance.pixels = (0..IRRADIANCE_HEIGHT)
+            .flat_map(|row| (0..IRRADIANCE_WIDTH).map(move |col| (row, col)))
+            .map(|(row, col)| {
+                let normal = irradiance.direction(row, col);
+
+                let (sum, total_weight) = texels.iter().fold(
+                    (Color::black(), 0.0),
+                    |(sum, total_weight), (direction, color, solid_angle)| {
+                        let weight = normal.dot(*direction).max(0.0) * solid_angle;
+                        (sum + *color * weight, total_weight + weight)
+                    },
+                );
+
+                if total_weight > 0.0 {
+                    sum / total_weight
+                } else {
+                    Color::black()
+                }
+            })
+            .collect();
+
+        irradiance
+    }
+
+    // Color seen in a direction.
+    pub fn color_at(&self, direction: &Vector) -> Color {
+        self.map.nearest(direction)
+    }
+
+    // Light received by a surface, divided by π so that a uniform environment gives its own color.
+    pub fn irradiance_at(&self, normal: &Vector) -> Color {
+        self.irradiance.bilinear(normal)
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::float::ApproxEq;
+
+    fn sky() -> Canvas {
+        // Bright above the horizon, dark below.
+        let mut canvas = Canvas::new(16, 8);
+        for row in 0..4 {
+            for col in 0..16 {
+                canvas[row][col] = Color::white();
+            }
+        }
+
+        canvas
+    }
+
+    #[test]
+    fn the_color_of_an_environment_in_a_direction() {
+        let mut canvas = Canvas::new(4, 2);
+        canvas[0][0] = Color::red();
+        canvas[1][2] = Color::blue();
+        let environment = Environment::new(&canvas);
+
+        assert_eq!(
+            environment.color_at(&Vector::new(-1.0, 1.0, -1.0)),
+            Color::red()
+        );
+        assert_eq!(
+            environment.color_at(&Vector::new(1.0, -1.0, 1.0)),
+            Color::blue()
+        );
+        assert_eq!(
+            environment.color_at(&Vector::new(1.0, 1.0, 1.0)),
+            Color::black()
+        );
+    }
+
+    #[test]
+    fn the_irradiance_of_a_uniform_environment_is_its_color() {
+        let color = Color::new(0.2, 0.4, 0.8);
+        let environment = Environment::new(&Canvas::new_with_color(100, 50, color));
+
+        for normal in [
+            Vector::new(0.0, 1.0, 0.0),
+            Vector::new(0.0, -1.0, 0.0),
+            Vector::new(1.0, 2.0, -3.0),
+        ] {
+            let irradiance = environment.irradiance_at(&normal);
+            assert!(irradiance.r.approx_eq(color.r));
+            assert!(irradiance.g.approx_eq(color.g));
+            assert!(irradiance.b.approx_eq(color.b));
+        }
+    }
+
+    #[test]
+    fn the_irradiance_of_a_sky() {
+        let environment = Environment::new(&sky());
+
+        let up = environment.irradiance_at(&Vector::new(0.0, 1.0, 0.0));
+        let down = environment.irradiance_at(&Vector::new(0.0, -1.0, 0.0));
+        let side = environment.irradiance_at(&Vector::new(1.0, 0.0, 0.0));
+
+        assert!(up.r > 0.99);
+        assert!(down.r < 0.01);
+        // A vertical surface sees half of the sky.
+        assert!((side.r - 0.5).abs() < 0.05);
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
@@ -3,7 +3,7 @@
 use crate::{
     float::EPSILON,
     primitive::{Point, Vector},
-    rtc::{Object, Ray},
+    rtc::{Material, Object, Ray},
 };
 use smallvec::SmallVec;
 use std::cmp::Ordering;
@@ -14,8 +14,9 @@ use std::cmp::Ordering;
 pub struct Intersection<'a> {
     t: f64,
     object: &'a Object,
-    u: f64, // used by smooth triangles
-    v: f64, // used by smooth triangles
+    u: f64,      // used by smooth triangles
+    v: f64,      // used by smooth triangles
+    face: usize, // used by meshes
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -27,6 +28,7 @@ impl<'a> Intersection<'a> {
             object,
             u: 0.0,
             v: 0.0,
+            face: 0,
         }
     }
 
@@ -37,6 +39,12 @@ impl<'a> Intersection<'a> {
         self
     }
 
+    pub fn with_face(mut self, face: usize) -> Self {
+        self.face = face;
+
+        self
+    }
+
     pub fn t(&self) -> f64 {
         self.t
     }
@@ -52,6 +60,10 @@ impl<'a> Intersection<'a> {
     pub fn v(&self) -> f64 {
         self.v
     }
+
+    pub fn face(&self) -> usize {
+        self.face
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -112,10 +124,24 @@ impl<'a> Intersections<'a> {
         self
     }
 
+    // Collapse consecutive intersections with the same object and nearly the same t, as produced
+    // by groups or CSG on shared surfaces. Expects sorted intersections.
+    pub fn dedup(mut self) -> Self {
+        self.intersections.dedup_by(|rhs, lhs| {
+            std::ptr::eq(lhs.object, rhs.object) && (lhs.t - rhs.t).abs() < EPSILON
+        });
+
+        self
+    }
+
     pub fn push(&mut self, i: Intersection<'a>) {
         self.intersections.push(i);
     }
 
+    pub fn clear(&mut self) {
+        self.intersections.clear();
+    }
+
     pub fn is_empty(&self) -> bool {
         self.intersections.is_empty()
     }
@@ -124,7 +150,7 @@ impl<'a> Intersections<'a> {
         self.intersections.len()
     }
 
-    pub fn hit(&self) -> Option<&Intersection> {
+    pub fn hit(&self) -> Option<&Intersection<'a>> {
         self.intersections.iter().find(|i| i.t >= 0.0)
     }
 
@@ -132,7 +158,7 @@ impl<'a> Intersections<'a> {
         self.intersections.iter().position(|i| i.t >= 0.0)
     }
 
-    pub fn iter(&self) -> std::slice::Iter<Intersection> {
+    pub fn iter(&self) -> std::slice::Iter<'_, Intersection<'a>> {
         self.intersections.iter()
     }
 }
@@ -163,6 +189,8 @@ pub struct IntersectionState<'a> {
     eye_v: Vector,
     n1: f64,
     n2: f64,
+    // Refractive indices of the RGB channels, when a medium is dispersive.
+    dispersive_n: Option<([f64; 3], [f64; 3])>,
     normal_v: Vector,
     object: &'a Object,
     over_point: Point,
@@ -174,8 +202,22 @@ pub struct IntersectionState<'a> {
 
 impl<'a> IntersectionState<'a> {
     pub fn new(intersections: &Intersections<'a>, intersection_index: usize, ray: &Ray) -> Self {
+        Self::new_with_epsilon(intersections, intersection_index, ray, EPSILON)
+    }
+
+    // The over and under points are offset from the surface by epsilon.
+    pub fn new_with_epsilon(
+        intersections: &Intersections<'a>,
+        intersection_index: usize,
+        ray: &Ray,
+        epsilon: f64,
+    ) -> Self {
         let intersection = &intersections[intersection_index];
 
+        // Objects containing the ray, in the order they were entered. An object is removed when
+        // exited, wherever it is in this list, so volumes don't have to be nested: where they
+        // overlap, the medium is the one of the last entered object, media are never mixed.
+        // Up to 32 nested containers are stored inline, without any allocation.
         let mut containers = SmallVec::<[&Object; 32]>::new();
 
         let mut n1 = None;
@@ -185,9 +227,7 @@ impl<'a> IntersectionState<'a> {
             let is_intersection = index == intersection_index;
 
             if is_intersection {
-                n1 = containers
-                    .last()
-                    .map(|object| object.material().refractive_index);
+                n1 = containers.last().map(|object| object.material());
             }
 
             match containers
@@ -201,14 +241,21 @@ impl<'a> IntersectionState<'a> {
             }
 
             if is_intersection {
-                n2 = containers
-                    .last()
-                    .map(|object| object.material().refractive_index);
+                n2 = containers.last().map(|object| object.material());
 
                 break;
             }
         }
 
+        let dispersive_n = if n1.and_then(|m| m.dispersion).is_some()
+            || n2.and_then(|m| m.dispersion).is_some()
+        {
+            let indices = |m: Option<&Material>| m.map_or([1.0; 3], Material::refractive_indices);
+            Some((indices(n1), indices(n2)))
+        } else {
+            None
+        };
+
         let point = ray.position(intersection.t);
 
         let eye_v = -ray.direction;
@@ -219,14 +266,15 @@ impl<'a> IntersectionState<'a> {
             normal_v
         };
         let reflect_v = ray.direction.reflect(&normal_v);
-        let over_point = point + normal_v * EPSILON;
-        let under_point = point - normal_v * EPSILON;
+        let over_point = point + normal_v * epsilon;
+        let under_point = point - normal_v * epsilon;
 
         Self {
             cos_i: normal_v ^ eye_v,
             eye_v,
-            n1: n1.unwrap_or(1.0),
-            n2: n2.unwrap_or(1.0),
+            n1: n1.map_or(1.0, |m| m.refractive_index),
+            n2: n2.map_or(1.0, |m| m.refractive_index),
+            dispersive_n,
             normal_v,
             object: intersection.object,
             over_point,
@@ -266,6 +314,10 @@ impl<'a> IntersectionState<'a> {
         (self.n1, self.n2)
     }
 
+    pub fn dispersive_n(&self) -> Option<([f64; 3], [f64; 3])> {
+        self.dispersive_n
+    }
+
     pub fn normal_v(&self) -> Vector {
         self.normal_v
     }
@@ -289,9 +341,39 @@ impl<'a> IntersectionState<'a> {
 
 /* ---------------------------------------------------------------------------------------------- */
 
+// Intersections of different objects closer than EPSILON come from coplanar faces: their order
+// is decided by rounding errors. Splits sorted intersections into runs of such intersections, so
+// operations combining objects, like CSG, can handle each run as a whole and don't flicker.
+pub fn coplanar_runs<T>(sorted: &[T], t: impl Fn(&T) -> f64) -> impl Iterator<Item = &[T]> {
+    let mut start = 0;
+
+    std::iter::from_fn(move || {
+        if start == sorted.len() {
+            return None;
+        }
+
+        let t_start = t(&sorted[start]);
+        let end = start
+            + sorted[start..]
+                .iter()
+                .take_while(|x| t(x) - t_start < EPSILON)
+                .count();
+        let run = &sorted[start..end];
+        start = end;
+
+        Some(run)
+    })
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
 pub trait IntersectionPusher<'a> {
     fn t(&mut self, t: f64);
     fn t_u_v(&mut self, t: f64, u: f64, v: f64);
+    // Only pushers which keep the intersections of meshes need to record the face which was hit.
+    fn t_u_v_face(&mut self, t: f64, u: f64, v: f64, _face: usize) {
+        self.t_u_v(t, u, v);
+    }
     fn set_object(&mut self, object: &'a Object);
 }
 
@@ -532,6 +614,57 @@ mod tests {
         assert!(comps.schlick().approx_eq_low_precision(0.48873));
     }
 
+    #[test]
+    fn deduplicating_intersections() {
+        let s1 = Object::new_sphere();
+        let s2 = Object::new_sphere();
+        let xs = Intersections::new()
+            .with_intersections(vec![
+                Intersection::new(1.0, &s1),
+                Intersection::new(1.0 + EPSILON / 2.0, &s1),
+                Intersection::new(1.0, &s2),
+                Intersection::new(2.0, &s1),
+            ])
+            .dedup();
+
+        assert_eq!(xs.len(), 3);
+        assert_eq!(xs[0].t(), 1.0);
+        assert!(std::ptr::eq(xs[1].object(), &s2));
+        assert_eq!(xs[2].t(), 2.0);
+    }
+
+    #[test]
+    fn duplicate_intersections_do_not_corrupt_refractive_indices() {
+        let a = glassy_sphere();
+        let b = glassy_sphere().with_material(
+            Material::new()
+                .with_transparency(1.0)
+                .with_refractive_index(2.0),
+        );
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -4.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        // The entry into a is reported twice.
+        let xs = Intersections::new().with_intersections(vec![
+            Intersection::new(2.0, &a),
+            Intersection::new(2.0, &a),
+            Intersection::new(3.0, &b),
+            Intersection::new(4.0, &b),
+            Intersection::new(5.0, &a),
+        ]);
+
+        let comps = IntersectionState::new(&xs, 2, &ray);
+        assert_eq!(comps.n(), (1.0, 2.0));
+
+        let xs = xs.dedup();
+        assert_eq!(xs.len(), 4);
+
+        let comps = IntersectionState::new(&xs, 1, &ray);
+        assert_eq!(comps.n(), (1.5, 2.0));
+    }
+
     #[test]
     fn an_intersection_can_encapsulates_u_and_v() {
         let object = Object::new_test_shape();
@@ -540,6 +673,123 @@ mod tests {
         assert_eq!(i.u(), 0.2);
         assert_eq!(i.v(), 0.4);
     }
+
+    #[test]
+    fn differencing_two_cubes_sharing_a_face_leaves_no_surface_on_the_seam() {
+        // The second cube spans z in [-1, -0.4] and shares its front face with the first one, up
+        // to rounding errors: which one is hit first on the seam changes from one ray to another.
+        let cubes = [
+            Object::new_cube(),
+            Object::new_cube()
+                .scale(1.0, 1.0, 0.3)
+                .translate(0.0, 0.0, -0.7)
+                .transform(),
+        ];
+
+        for i in 0..10 {
+            for j in 0..10 {
+                let ray = Ray {
+                    origin: Point::new(-0.9 + 0.19 * i as f64, -0.9 + 0.19 * j as f64, -5.0),
+                    direction: Vector::new(0.0, 0.0, 1.0),
+                };
+                let xs = ray
+                    .intersects(&cubes, Intersections::new())
+                    .iter()
+                    .cloned()
+                    .collect::<Vec<_>>();
+
+                // The surfaces of the difference of the first cube and of the second one.
+                let (mut in_left, mut in_right) = (false, false);
+                let mut surfaces = vec![];
+                for run in coplanar_runs(&xs, Intersection::t) {
+                    let was_inside = in_left && !in_right;
+                    for x in run {
+                        if x.object() == &cubes[0] {
+                            in_left = !in_left;
+                        } else {
+                            in_right = !in_right;
+                        }
+                    }
+                    if was_inside != (in_left && !in_right) {
+                        surfaces.push(run[0].t());
+                    }
+                }
+
+                assert_eq!(surfaces.len(), 2);
+                assert!(surfaces[0].approx_eq(4.6));
+                assert!(surfaces[1].approx_eq(6.0));
+            }
+        }
+    }
+
+    #[test]
+    fn refractive_indices_of_overlapping_volumes() {
+        let a = glassy_sphere().translate(-0.5, 0.0, 0.0).transform();
+        let b = Object::new_sphere()
+            .with_material(
+                Material::new()
+                    .with_transparency(1.0)
+                    .with_refractive_index(2.0),
+            )
+            .translate(0.5, 0.0, 0.0)
+            .transform();
+        let objects = vec![a, b];
+
+        let refractive_indices = |origin: Point, direction: Vector| {
+            let ray = Ray { origin, direction };
+            let xs = ray.intersects(&objects, Intersections::new());
+            assert_eq!(xs.len(), 4);
+
+            (0..xs.len())
+                .map(|index| IntersectionState::new(&xs, index, &ray).n())
+                .collect::<Vec<_>>()
+        };
+
+        // The first sphere is exited inside the second one: it's not a boundary anymore.
+        assert_eq!(
+            refractive_indices(Point::new(-5.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0)),
+            vec![(1.0, 1.5), (1.5, 2.0), (2.0, 2.0), (2.0, 1.0)]
+        );
+        assert_eq!(
+            refractive_indices(Point::new(5.0, 0.0, 0.0), Vector::new(-1.0, 0.0, 0.0)),
+            vec![(1.0, 2.0), (2.0, 1.5), (1.5, 1.5), (1.5, 1.0)]
+        );
+    }
+
+    #[test]
+    fn refractive_indices_of_concentric_shells() {
+        // From the outermost to the innermost shell.
+        let shells = (1..=5)
+            .map(|i| {
+                let radius = 6.0 - i as f64;
+                Object::new_sphere()
+                    .with_material(
+                        Material::new()
+                            .with_transparency(1.0)
+                            .with_refractive_index(1.0 + 0.1 * i as f64),
+                    )
+                    .scale(radius, radius, radius)
+                    .transform()
+            })
+            .collect::<Vec<_>>();
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -10.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+        let xs = ray.intersects(&shells, Intersections::new());
+        assert_eq!(xs.len(), 10);
+
+        let ns = (0..xs.len())
+            .map(|index| IntersectionState::new(&xs, index, &ray).n())
+            .collect::<Vec<_>>();
+        let entering = [(1.0, 1.1), (1.1, 1.2), (1.2, 1.3), (1.3, 1.4), (1.4, 1.5)];
+
+        for (index, (n1, n2)) in entering.iter().enumerate() {
+            assert!(ns[index].0.approx_eq(*n1) && ns[index].1.approx_eq(*n2));
+            // Shells are exited in the reverse order.
+            assert!(ns[9 - index].0.approx_eq(*n2) && ns[9 - index].1.approx_eq(*n1));
+        }
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
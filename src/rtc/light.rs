@@ -3,8 +3,8 @@
 use crate::{
     primitive::{Point, Vector},
     rtc::{
-        lights::{AreaLight, PointLight},
-        Color, World,
+        lights::{AreaLight, Falloff, PointLight},
+        Color, Pattern, World,
     },
 };
 use serde::{Deserialize, Serialize};
@@ -48,6 +48,45 @@ impl Light {
         }
     }
 
+    // Only area lights have a falloff.
+    pub fn with_falloff(mut self, falloff: Falloff) -> Self {
+        if let LightType::AreaLight(l) = self.light {
+            self.light = LightType::AreaLight(l.with_falloff(falloff));
+        }
+
+        self
+    }
+
+    // Only point lights have a gobo. The pattern is projected toward the direction.
+    pub fn with_gobo(mut self, pattern: Pattern, direction: Vector) -> Self {
+        if let LightType::PointLight(l) = self.light {
+            self.light = LightType::PointLight(l.with_gobo(pattern, direction));
+        }
+
+        self
+    }
+
+    // Lights of different kinds can't be interpolated: the closest one is used.
+    pub fn lerp(a: &Light, b: &Light, t: f64) -> Self {
+        let light = match (&a.light, &b.light) {
+            (LightType::AreaLight(l1), LightType::AreaLight(l2)) => {
+                LightType::AreaLight(AreaLight::lerp(l1, l2, t))
+            }
+            (LightType::PointLight(l1), LightType::PointLight(l2)) => {
+                LightType::PointLight(PointLight::lerp(l1, l2, t))
+            }
+            _ => {
+                if t < 0.5 {
+                    a.light.clone()
+                } else {
+                    b.light.clone()
+                }
+            }
+        };
+
+        Light { light }
+    }
+
     pub fn intensity(&self) -> Color {
         match &self.light {
             LightType::AreaLight(l) => l.intensity(),
@@ -62,6 +101,20 @@ impl Light {
         }
     }
 
+    pub fn weights(&self) -> &[f64] {
+        match &self.light {
+            LightType::AreaLight(l) => l.weights(),
+            LightType::PointLight(l) => l.weights(),
+        }
+    }
+
+    pub fn gobo_at(&self, point: &Point) -> Color {
+        match &self.light {
+            LightType::AreaLight(_) => Color::white(),
+            LightType::PointLight(l) => l.gobo_at(point),
+        }
+    }
+
     #[must_use]
     pub fn intensity_at(&self, world: &World, point: &Point) -> f64 {
         match &self.light {
@@ -97,6 +150,54 @@ mod tests {
             assert_eq!(light.intensity_at(&w, &point), result);
         }
     }
+
+    #[test]
+    fn lerping_point_lights_moves_them_linearly() {
+        let a = Light::new_point_light(Color::white(), Point::new(-10.0, 10.0, 0.0));
+        let b = Light::new_point_light(Color::black(), Point::new(10.0, 0.0, 4.0));
+
+        let tests = vec![
+            (0.0, Point::new(-10.0, 10.0, 0.0), Color::white()),
+            (
+                0.25,
+                Point::new(-5.0, 7.5, 1.0),
+                Color::new(0.75, 0.75, 0.75),
+            ),
+            (0.5, Point::new(0.0, 5.0, 2.0), Color::new(0.5, 0.5, 0.5)),
+            (1.0, Point::new(10.0, 0.0, 4.0), Color::black()),
+        ];
+
+        for (t, position, intensity) in tests.into_iter() {
+            let light = Light::lerp(&a, &b, t);
+            assert_eq!(light.positions(), &[position]);
+            assert_eq!(light.intensity(), intensity);
+        }
+    }
+
+    #[test]
+    fn lerping_area_lights_moves_their_cells() {
+        let a = Light::new_area_light(
+            Color::white(),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(2.0, 0.0, 0.0),
+            2,
+            Vector::new(0.0, 0.0, 2.0),
+            1,
+        );
+        let b = Light::new_area_light(
+            Color::white(),
+            Point::new(0.0, 4.0, 0.0),
+            Vector::new(4.0, 0.0, 0.0),
+            2,
+            Vector::new(0.0, 0.0, 2.0),
+            1,
+        );
+
+        assert_eq!(
+            Light::lerp(&a, &b, 0.5).positions(),
+            &[Point::new(0.75, 2.0, 1.0), Point::new(2.25, 2.0, 1.0)]
+        );
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
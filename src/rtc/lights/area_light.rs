@@ -1,12 +1,23 @@
 /* ---------------------------------------------------------------------------------------------- */
 
 use crate::{
+    float::ApproxEq,
     primitive::{Point, Vector},
     rtc::{Color, World},
 };
 use rand::rngs::SmallRng;
 use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
+use std::f64::consts::FRAC_PI_2;
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// How the emitted light decreases from the center of the light toward its edges.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Falloff {
+    Uniform,
+    Cosine,
+}
 
 /* ---------------------------------------------------------------------------------------------- */
 
@@ -20,6 +31,8 @@ pub struct AreaLight {
     vsteps: u32,
     samples: u32,
     positions: Vec<Point>,
+    falloff: Falloff,
+    weights: Vec<f64>,
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -33,6 +46,10 @@ impl AreaLight {
         vvec: Vector,
         vsteps: u32,
     ) -> Self {
+        // A light without any step would have no sample at all.
+        let usteps = usteps.max(1);
+        let vsteps = vsteps.max(1);
+
         let uvec = uvec / usteps as f64;
         let vvec = vvec / vsteps as f64;
         let samples = usteps * vsteps;
@@ -58,6 +75,48 @@ impl AreaLight {
             vsteps,
             samples,
             positions,
+            falloff: Falloff::Uniform,
+            weights: vec![1.0; samples as usize],
+        }
+    }
+
+    pub fn with_falloff(mut self, falloff: Falloff) -> Self {
+        self.falloff = falloff;
+        self.weights = (0..self.vsteps)
+            .flat_map(|v| (0..self.usteps).map(move |u| (u, v)))
+            .map(|(u, v)| self.weight(u, v))
+            .collect();
+
+        self
+    }
+
+    // The number of steps and the falloff are taken from the closest light.
+    pub fn lerp(a: &AreaLight, b: &AreaLight, t: f64) -> Self {
+        let closest = if t < 0.5 { a } else { b };
+        let full_uvec = |l: &AreaLight| l.uvec * l.usteps as f64;
+        let full_vvec = |l: &AreaLight| l.vvec * l.vsteps as f64;
+
+        AreaLight::new(
+            a.intensity + t * (b.intensity - a.intensity),
+            a.corner + (b.corner - a.corner) * t,
+            full_uvec(a) + (full_uvec(b) - full_uvec(a)) * t,
+            closest.usteps,
+            full_vvec(a) + (full_vvec(b) - full_vvec(a)) * t,
+            closest.vsteps,
+        )
+        .with_falloff(closest.falloff)
+    }
+
+    fn weight(&self, u: u32, v: u32) -> f64 {
+        match self.falloff {
+            Falloff::Uniform => 1.0,
+            Falloff::Cosine => {
+                // Position of the center of the cell, from -1 on an edge to 1 on the opposite one.
+                let centered = |i: u32, steps: u32| 2.0 * (i as f64 + 0.5) / steps as f64 - 1.0;
+
+                (centered(u, self.usteps) * FRAC_PI_2).cos()
+                    * (centered(v, self.vsteps) * FRAC_PI_2).cos()
+            }
         }
     }
 
@@ -75,6 +134,11 @@ impl AreaLight {
         &self.positions
     }
 
+    // The weight of each position, in the same order.
+    pub fn weights(&self) -> &[f64] {
+        &self.weights
+    }
+
     fn point_on_light<T>(&self, u: u32, v: u32, mut random: T) -> Point
     where
         T: FnMut() -> f64,
@@ -86,18 +150,32 @@ impl AreaLight {
     where
         T: FnMut() -> f64,
     {
+        let center = self.corner
+            + self.uvec * (self.usteps as f64 / 2.0)
+            + self.vvec * (self.vsteps as f64 / 2.0);
+        let softness = world.render_options().shadow_softness();
+
+        if softness.approx_eq(0.0) {
+            return if world.is_shadowed(&center, point) {
+                0.0
+            } else {
+                1.0
+            };
+        }
+
         let mut total = 0.0;
 
         for v in 0..self.vsteps {
             for u in 0..self.usteps {
-                let light_position = self.point_on_light(u, v, &mut random);
+                let light_position =
+                    center + (self.point_on_light(u, v, &mut random) - center) * softness;
                 if !world.is_shadowed(&light_position, point) {
-                    total += 1.0;
+                    total += self.weights[(v * self.usteps + u) as usize];
                 }
             }
         }
 
-        total / self.samples as f64
+        total / self.weights.iter().sum::<f64>()
     }
 }
 
@@ -106,7 +184,7 @@ impl AreaLight {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::primitive::Tuple;
+    use crate::{primitive::Tuple, rtc::RenderOptions};
 
     #[test]
     fn creating_an_area_light() {
@@ -123,6 +201,24 @@ mod tests {
         assert_eq!(light.samples, 8);
     }
 
+    #[test]
+    fn an_area_light_has_at_least_one_step_in_each_direction() {
+        let corner = Point::zero();
+        let v1 = Vector::new(2.0, 0.0, 0.0);
+        let v2 = Vector::new(0.0, 0.0, 1.0);
+        let light = AreaLight::new(Color::white(), corner, v1, 0, v2, 0);
+
+        assert_eq!(light.usteps, 1);
+        assert_eq!(light.vsteps, 1);
+        assert_eq!(light.samples, 1);
+        assert_eq!(light.uvec, v1);
+        assert_eq!(light.positions(), &[Point::new(1.0, 0.0, 0.5)]);
+
+        let w = crate::rtc::world::tests::default_world();
+        let intensity = light.intensity_at_impl(&w, &Point::new(0.0, 0.0, -2.0), || 0.5);
+        assert!(intensity.is_finite());
+    }
+
     #[test]
     fn finding_a_single_point_on_an_area_light() {
         let corner = Point::zero();
@@ -164,6 +260,61 @@ mod tests {
             assert_eq!(light.intensity_at_impl(&w, &point, || 0.5), result);
         }
     }
+
+    #[test]
+    fn an_area_light_casts_hard_shadows_without_softness() {
+        let w = crate::rtc::world::tests::default_world()
+            .with_render_options(RenderOptions::new().with_shadow_softness(0.0));
+
+        let corner = Point::new(-0.5, -0.5, -5.0);
+        let v1 = Vector::new(1.0, 0.0, 0.0);
+        let v2 = Vector::new(0.0, 1.0, 0.0);
+        let light = AreaLight::new(Color::white(), corner, v1, 2, v2, 2);
+
+        // These points are in the penumbra with the full softness.
+        let tests = vec![
+            (Point::new(1.0, -1.0, 2.0), 0.0),
+            (Point::new(1.5, 0.0, 2.0), 1.0),
+            (Point::new(1.25, 1.25, 3.0), 1.0),
+        ];
+
+        for (point, result) in tests.into_iter() {
+            for _ in 0..10 {
+                assert_eq!(light.intensity_at(&w, &point), result);
+            }
+        }
+    }
+
+    #[test]
+    fn edge_samples_contribute_less_than_center_samples_with_a_cosine_falloff() {
+        let corner = Point::zero();
+        let v1 = Vector::new(3.0, 0.0, 0.0);
+        let v2 = Vector::new(0.0, 0.0, 3.0);
+
+        let light = AreaLight::new(Color::white(), corner, v1, 3, v2, 3);
+        assert!(light.weights().iter().all(|w| *w == 1.0));
+
+        let light = light.with_falloff(Falloff::Cosine);
+        let positions = light.positions().to_vec();
+        let weights = light.weights();
+        assert_eq!(weights.len(), positions.len());
+
+        // The center cell is the fifth one.
+        assert_eq!(positions[4], Point::new(1.5, 0.0, 1.5));
+        assert!(weights[4].approx_eq(1.0));
+        for (i, weight) in weights.iter().enumerate().filter(|(i, _)| *i != 4) {
+            assert!(*weight < weights[4], "sample {}", i);
+            assert!(*weight > 0.0);
+        }
+        // Corners contribute less than the middle of edges.
+        assert!(weights[0] < weights[1]);
+
+        // The weights don't change the positions.
+        assert_eq!(
+            AreaLight::new(Color::white(), corner, v1, 3, v2, 3).positions(),
+            &positions[..]
+        );
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -1,17 +1,28 @@
 /* ---------------------------------------------------------------------------------------------- */
 
 use crate::{
-    primitive::Point,
-    rtc::{Color, World},
+    primitive::{Matrix, Point, Tuple, Vector},
+    rtc::{view_transform, Color, Pattern, World},
 };
 use serde::{Deserialize, Serialize};
 
 /* ---------------------------------------------------------------------------------------------- */
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PointLight {
     intensity: Color,
     position: [Point; 1],
+    gobo: Option<Box<Gobo>>,
+}
+
+// Pattern in front of the light, like a slide in a projector. It lies on the plane at distance 1
+// from the light, orthogonal to its direction.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Gobo {
+    pattern: Pattern,
+    direction: Vector,
+    // From the world to the frame of the light, which looks toward -z.
+    view: Matrix,
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -21,6 +32,39 @@ impl PointLight {
         PointLight {
             intensity,
             position: [position],
+            gobo: None,
+        }
+    }
+
+    pub fn with_gobo(mut self, pattern: Pattern, direction: Vector) -> Self {
+        let direction = direction.normalize();
+        let position = self.position[0];
+        // Any up vector will do, as long as it isn't along the direction.
+        let up = if direction.y().abs() > 0.9 {
+            Vector::new(0.0, 0.0, 1.0)
+        } else {
+            Vector::new(0.0, 1.0, 0.0)
+        };
+
+        self.gobo = Some(Box::new(Gobo {
+            pattern,
+            direction,
+            view: view_transform(&position, &(position + direction), &up),
+        }));
+
+        self
+    }
+
+    // The gobo follows the light.
+    pub fn lerp(a: &PointLight, b: &PointLight, t: f64) -> Self {
+        let light = PointLight::new(
+            a.intensity + t * (b.intensity - a.intensity),
+            a.position[0] + (b.position[0] - a.position[0]) * t,
+        );
+
+        match &a.gobo {
+            Some(gobo) => light.with_gobo(gobo.pattern.clone(), gobo.direction),
+            None => light,
         }
     }
 
@@ -36,9 +80,34 @@ impl PointLight {
         }
     }
 
+    // Filter applied to the light reaching a point. Nothing goes behind a gobo.
+    pub fn gobo_at(&self, point: &Point) -> Color {
+        match &self.gobo {
+            None => Color::white(),
+            Some(gobo) => {
+                let local = gobo.view * *point;
+                let depth = -local.z();
+
+                if depth <= 0.0 {
+                    Color::black()
+                } else {
+                    gobo.pattern.pattern_at_point(&Point::new(
+                        local.x() / depth,
+                        local.y() / depth,
+                        0.0,
+                    ))
+                }
+            }
+        }
+    }
+
     pub fn positions(&self) -> &[Point] {
         &self.position
     }
+
+    pub fn weights(&self) -> &[f64] {
+        &[1.0]
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -1,24 +1,47 @@
 /* ---------------------------------------------------------------------------------------------- */
 
 use crate::{
-    float::ApproxEq,
+    float::{ApproxEq, EPSILON},
     primitive::{Point, Vector},
-    rtc::{Color, Light, Object, Pattern},
+    rtc::{Color, CubeMap, Light, Object, Pattern},
 };
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// How light is scattered by the surface. Oren-Nayar models rough surfaces made of tiny facets,
+// which look flatter than Lambertian ones. Its roughness is the standard deviation of the angle
+// of the facets, in radians.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DiffuseModel {
+    Lambert,
+    OrenNayar { roughness: f64 },
+}
 
 /* ---------------------------------------------------------------------------------------------- */
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Material {
     pub ambient: f64,
+    pub clearcoat: f64,
+    pub clearcoat_roughness: f64,
     pub pattern: Pattern,
     pub diffuse: f64,
+    pub diffuse_model: DiffuseModel,
+    // Abbe number: the lower, the more the refractive index varies with the wavelength.
+    pub dispersion: Option<f64>,
+    pub emissive: Color,
     pub reflective: f64,
+    // Reflections are looked up in a cube map instead of being traced.
+    pub reflection_probe: Option<Arc<CubeMap>>,
     pub refractive_index: f64,
     pub shininess: f64,
     pub specular: f64,
+    pub subsurface: f64,
+    pub thin_film: Option<f64>,
     pub transparency: f64,
+    pub two_sided: bool,
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -34,6 +57,14 @@ impl Material {
         self
     }
 
+    // A varnish layer on top of the material, with its own sharp highlight and reflection.
+    pub fn with_clearcoat(mut self, strength: f64, roughness: f64) -> Material {
+        self.clearcoat = strength;
+        self.clearcoat_roughness = roughness;
+
+        self
+    }
+
     pub fn with_color(mut self, color: Color) -> Material {
         self.pattern = Pattern::new_plain(color);
 
@@ -46,6 +77,19 @@ impl Material {
         self
     }
 
+    pub fn with_diffuse_model(mut self, diffuse_model: DiffuseModel) -> Material {
+        self.diffuse_model = diffuse_model;
+
+        self
+    }
+
+    // Light emitted by the surface itself, whatever the lights. It doesn't light other objects.
+    pub fn with_emissive(mut self, color: Color) -> Material {
+        self.emissive = color;
+
+        self
+    }
+
     pub fn with_pattern(mut self, pattern: Pattern) -> Material {
         self.pattern = pattern;
 
@@ -58,6 +102,25 @@ impl Material {
         self
     }
 
+    // Each RGB channel is refracted with its own index, which splits white light like a prism.
+    // Refractions are then traced once per channel.
+    pub fn with_dispersion(mut self, abbe_number: f64) -> Material {
+        assert!(
+            abbe_number > 0.0 && abbe_number.is_finite(),
+            "The Abbe number of dispersive materials must be positive"
+        );
+        self.dispersion = Some(abbe_number);
+
+        self
+    }
+
+    // Captured with World::capture_probe. Cheap, but only exact for points near its position.
+    pub fn with_reflection_probe(mut self, probe: CubeMap) -> Material {
+        self.reflection_probe = Some(Arc::new(probe));
+
+        self
+    }
+
     pub fn with_refractive_index(mut self, index: f64) -> Material {
         self.refractive_index = index;
 
@@ -76,12 +139,77 @@ impl Material {
         self
     }
 
+    // Cheap subsurface scattering for skin or wax: the diffuse term is wrapped around the
+    // terminator, so light slightly bleeds into the dark side.
+    pub fn with_subsurface(mut self, wrap: f64) -> Material {
+        self.subsurface = wrap.max(0.0);
+
+        self
+    }
+
+    pub fn with_thin_film(mut self, thickness_nm: f64) -> Material {
+        self.thin_film = Some(thickness_nm);
+
+        self
+    }
+
+    pub fn with_two_sided(mut self, two_sided: bool) -> Material {
+        self.two_sided = two_sided;
+
+        self
+    }
+
     pub fn with_transparency(mut self, transparency: f64) -> Material {
         self.transparency = transparency;
 
         self
     }
 
+    // Coefficients are interpolated, patterns are blended. Properties which can't be interpolated
+    // are taken from the closest material.
+    pub fn lerp(a: &Material, b: &Material, t: f64) -> Material {
+        let lerp = |x: f64, y: f64| x + t * (y - x);
+        let closest = if t < 0.5 { a } else { b };
+
+        Material {
+            ambient: lerp(a.ambient, b.ambient),
+            clearcoat: lerp(a.clearcoat, b.clearcoat),
+            clearcoat_roughness: lerp(a.clearcoat_roughness, b.clearcoat_roughness),
+            pattern: if a.pattern == b.pattern {
+                a.pattern.clone()
+            } else {
+                Pattern::new_blend(a.pattern.clone(), b.pattern.clone(), t)
+            },
+            diffuse: lerp(a.diffuse, b.diffuse),
+            diffuse_model: match (a.diffuse_model, b.diffuse_model) {
+                (
+                    DiffuseModel::OrenNayar { roughness: x },
+                    DiffuseModel::OrenNayar { roughness: y },
+                ) => DiffuseModel::OrenNayar {
+                    roughness: lerp(x, y),
+                },
+                _ => closest.diffuse_model,
+            },
+            dispersion: match (a.dispersion, b.dispersion) {
+                (Some(x), Some(y)) => Some(lerp(x, y)),
+                _ => closest.dispersion,
+            },
+            emissive: a.emissive + t * (b.emissive - a.emissive),
+            reflective: lerp(a.reflective, b.reflective),
+            reflection_probe: closest.reflection_probe.clone(),
+            refractive_index: lerp(a.refractive_index, b.refractive_index),
+            shininess: lerp(a.shininess, b.shininess),
+            specular: lerp(a.specular, b.specular),
+            subsurface: lerp(a.subsurface, b.subsurface),
+            thin_film: match (a.thin_film, b.thin_film) {
+                (Some(x), Some(y)) => Some(lerp(x, y)),
+                _ => closest.thin_film,
+            },
+            transparency: lerp(a.transparency, b.transparency),
+            two_sided: closest.two_sided,
+        }
+    }
+
     pub fn lighting(
         &self,
         object: &Object,
@@ -99,15 +227,21 @@ impl Material {
             ambient
         } else {
             let mut sum = Color::black();
-            let nb_samples = light.positions().len() as f64;
+            let mut clearcoat_sum = Color::black();
+            let total_weight = light.weights().iter().sum::<f64>();
 
-            for light_position in light.positions().iter() {
+            for (light_position, weight) in light.positions().iter().zip(light.weights()) {
                 let light_v = (*light_position - *position).normalize();
                 let light_dot_normal = light_v ^ *normal_v;
+                let wrapped_dot_normal =
+                    (light_dot_normal + self.subsurface) / (1.0 + self.subsurface);
 
                 if light_dot_normal >= 0.0 {
-                    let diffuse = effective_color * self.diffuse * light_dot_normal;
-                    sum = sum + diffuse;
+                    let diffuse = effective_color
+                        * self.diffuse
+                        * wrapped_dot_normal
+                        * self.diffuse_factor(&light_v, eye_v, normal_v);
+                    sum = sum + diffuse * *weight;
 
                     let reflect_v = (-light_v).reflect(normal_v);
                     let reflect_dot_eye = reflect_v ^ *eye_v;
@@ -116,29 +250,161 @@ impl Material {
                         let factor = f64::powf(reflect_dot_eye, self.shininess);
                         let specular = light.intensity() * self.specular * factor;
 
-                        sum = sum + specular;
+                        sum = sum + specular * *weight;
+
+                        if self.clearcoat > 0.0 {
+                            let factor = f64::powf(reflect_dot_eye, self.clearcoat_shininess());
+                            let specular = light.intensity() * self.clearcoat * factor;
+
+                            clearcoat_sum = clearcoat_sum + specular * *weight;
+                        }
                     }
+                } else if self.two_sided {
+                    // Light coming from behind goes through thin surfaces.
+                    let diffuse = effective_color * self.diffuse * -light_dot_normal;
+                    sum = sum + diffuse * *weight;
+                } else if wrapped_dot_normal > 0.0 {
+                    let diffuse = effective_color * self.diffuse * wrapped_dot_normal;
+                    sum = sum + diffuse * *weight;
                 }
             }
 
-            ambient + (sum / nb_samples) * intensity
+            // The light reflected by the clear-coat doesn't reach the base layer.
+            let base = sum * (1.0 - self.clearcoat_fresnel(*eye_v ^ *normal_v));
+
+            ambient + ((base + clearcoat_sum) / total_weight) * intensity * light.gobo_at(position)
+        }
+    }
+
+    // Scaling of the Lambertian term by the qualitative Oren-Nayar model. Light is scattered back
+    // toward its source, especially at grazing angles.
+    fn diffuse_factor(&self, light_v: &Vector, eye_v: &Vector, normal_v: &Vector) -> f64 {
+        match self.diffuse_model {
+            DiffuseModel::Lambert => 1.0,
+            DiffuseModel::OrenNayar { roughness } => {
+                let sigma2 = roughness * roughness;
+                let a = 1.0 - 0.5 * sigma2 / (sigma2 + 0.33);
+                let b = 0.45 * sigma2 / (sigma2 + 0.09);
+
+                let cos_i = light_v.dot(*normal_v).clamp(0.0, 1.0);
+                let cos_r = eye_v.dot(*normal_v).clamp(0.0, 1.0);
+                let (theta_i, theta_r) = (cos_i.acos(), cos_r.acos());
+                let (alpha, beta) = (theta_i.max(theta_r), theta_i.min(theta_r));
+
+                // Cosine of the azimuth between the light and the eye, on the tangent plane.
+                let tangent = |v: &Vector| *v - *normal_v * v.dot(*normal_v);
+                let (light_t, eye_t) = (tangent(light_v), tangent(eye_v));
+                let cos_phi = if light_t.magnitude() < EPSILON || eye_t.magnitude() < EPSILON {
+                    0.0
+                } else {
+                    light_t.normalize().dot(eye_t.normalize())
+                };
+
+                a + b * cos_phi.max(0.0) * alpha.sin() * beta.tan()
+            }
+        }
+    }
+
+    // Fraction of the light reflected by the clear-coat, using Schlick's approximation for a
+    // varnish-like refractive index.
+    pub fn clearcoat_fresnel(&self, cos_i: f64) -> f64 {
+        if self.clearcoat > 0.0 {
+            let r0 =
+                ((1.0 - CLEARCOAT_REFRACTIVE_INDEX) / (1.0 + CLEARCOAT_REFRACTIVE_INDEX)).powi(2);
+
+            self.clearcoat * (r0 + (1.0 - r0) * (1.0 - cos_i.clamp(0.0, 1.0)).powi(5))
+        } else {
+            0.0
+        }
+    }
+
+    // Phong exponent equivalent to the roughness of the clear-coat.
+    fn clearcoat_shininess(&self) -> f64 {
+        let roughness = self.clearcoat_roughness.clamp(0.01, 1.0);
+
+        2.0 / (roughness * roughness) - 2.0
+    }
+
+    // Refractive indices of the RGB channels. The refractive index of the material is the one of
+    // green. With dispersion, the indices follow Cauchy's equation n = A + B / λ², fitted to the
+    // Abbe number.
+    pub fn refractive_indices(&self) -> [f64; 3] {
+        match self.dispersion {
+            None => [self.refractive_index; 3],
+            Some(abbe_number) => {
+                let [red, green, blue] = DISPERSION_WAVELENGTHS;
+                let spread = (self.refractive_index - 1.0) / abbe_number;
+                let b = spread / (blue.powi(-2) - red.powi(-2));
+                let a = self.refractive_index - b * green.powi(-2);
+
+                DISPERSION_WAVELENGTHS.map(|wavelength| a + b * wavelength.powi(-2))
+            }
+        }
+    }
+
+    // Tint applied to the reflected color by the interference of a thin film laid on the
+    // surface. The interference is evaluated for one wavelength per RGB channel.
+    pub fn thin_film_color(&self, cos_i: f64) -> Color {
+        match self.thin_film {
+            None => Color::white(),
+            Some(thickness) => {
+                // Refraction inside the film (Snell's law, coming from the air).
+                let sin2_t = (1.0 - cos_i * cos_i) / THIN_FILM_REFRACTIVE_INDEX.powi(2);
+                let cos_t = (1.0 - sin2_t).sqrt();
+                let path_difference = 2.0 * THIN_FILM_REFRACTIVE_INDEX * thickness * cos_t;
+
+                // The reflection on the upper side of the film is shifted by half a wavelength.
+                let interference = |wavelength: f64| {
+                    let phase = 2.0 * std::f64::consts::PI * path_difference / wavelength;
+                    0.5 - 0.5 * phase.cos()
+                };
+
+                Color::new(
+                    interference(THIN_FILM_WAVELENGTHS[0]),
+                    interference(THIN_FILM_WAVELENGTHS[1]),
+                    interference(THIN_FILM_WAVELENGTHS[2]),
+                )
+            }
         }
     }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
 
+const CLEARCOAT_REFRACTIVE_INDEX: f64 = 1.5;
+
+// Fraunhofer C, d and F lines (in nm), between which the Abbe number is defined, representing the
+// red, green and blue channels.
+const DISPERSION_WAVELENGTHS: [f64; 3] = [656.3, 587.6, 486.1];
+
+// Soap-like film.
+const THIN_FILM_REFRACTIVE_INDEX: f64 = 1.33;
+
+// Wavelengths (in nm) representing the red, green and blue channels.
+const THIN_FILM_WAVELENGTHS: [f64; 3] = [650.0, 510.0, 475.0];
+
+/* ---------------------------------------------------------------------------------------------- */
+
 impl Default for Material {
     fn default() -> Self {
         Material {
             ambient: 0.1,
+            clearcoat: 0.0,
+            clearcoat_roughness: 0.1,
             pattern: Pattern::new_plain(Color::white()),
             diffuse: 0.9,
+            diffuse_model: DiffuseModel::Lambert,
+            dispersion: None,
+            emissive: Color::black(),
             reflective: 0.0,
+            reflection_probe: None,
             refractive_index: 1.0,
             shininess: 200.0,
             specular: 0.9,
+            subsurface: 0.0,
+            thin_film: None,
             transparency: 0.0,
+            two_sided: false,
         }
     }
 }
@@ -148,7 +414,12 @@ impl Default for Material {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{primitive::Tuple, rtc::World};
+    use crate::{
+        float::EPSILON,
+        primitive::Tuple,
+        rtc::{Transform, World},
+    };
+    use std::f64::consts::PI;
 
     #[test]
     fn lighting_with_the_eye_between_light_and_surface() {
@@ -171,6 +442,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn a_checker_gobo_casts_alternating_bands() {
+        // On the floor, 10 units below the light, the squares of the checker are 1 unit wide.
+        let gobo = Pattern::new_checker(Color::white(), Color::black())
+            .scale(0.1, 0.1, 0.1)
+            .transform();
+        let light = Light::new_point_light(Color::white(), Point::new(0.0, 10.0, 0.0))
+            .with_gobo(gobo, Vector::new(0.0, -1.0, 0.0));
+        let m = Material::new();
+        let up = Vector::new(0.0, 1.0, 0.0);
+
+        let colors = (0..6)
+            .map(|i| {
+                let position = Point::new(i as f64 + 0.5, 0.0, 0.5);
+                let eye_v = (Point::new(0.0, 10.0, 0.0) - position).normalize();
+                m.lighting(&Object::new_plane(), &light, &position, &eye_v, &up, 1.0)
+            })
+            .collect::<Vec<_>>();
+
+        for pair in colors.windows(2) {
+            let (lit, dark) = if pair[0].r > pair[1].r {
+                (pair[0], pair[1])
+            } else {
+                (pair[1], pair[0])
+            };
+            assert!(lit.r > 0.5);
+            // Only the ambient light is left.
+            assert_eq!(dark, Color::new(0.1, 0.1, 0.1));
+        }
+
+        // A light without a gobo lights everything.
+        let plain = Light::new_point_light(Color::white(), Point::new(0.0, 10.0, 0.0));
+        assert_eq!(plain.gobo_at(&Point::new(1.5, 0.0, 0.5)), Color::white());
+    }
+
     #[test]
     fn lighting_with_the_eye_between_light_and_surface_eye_offset_45() {
         let m = Material::new();
@@ -255,6 +561,55 @@ mod tests {
         );
     }
 
+    #[test]
+    fn lighting_a_two_sided_plane_from_behind() {
+        let plane = Object::new_plane();
+        let position = Point::zero();
+        let eye_v = Vector::new(0.0, 1.0, 0.0);
+        let normal_v = Vector::new(0.0, 1.0, 0.0);
+        let light = Light::new_point_light(Color::white(), Point::new(0.0, -10.0, 0.0));
+
+        let one_sided = Material::new();
+        assert_eq!(
+            one_sided.lighting(&plane, &light, &position, &eye_v, &normal_v, 1.0),
+            Color::new(0.1, 0.1, 0.1)
+        );
+
+        let two_sided = Material::new().with_two_sided(true);
+        assert_eq!(
+            two_sided.lighting(&plane, &light, &position, &eye_v, &normal_v, 1.0),
+            Color::new(1.0, 1.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn lighting_just_past_the_terminator_with_subsurface_scattering() {
+        let position = Point::zero();
+        let eye_v = Vector::new(0.0, 0.0, -1.0);
+        let normal_v = Vector::new(0.0, 0.0, -1.0);
+        // Slightly behind the surface.
+        let light = Light::new_point_light(Color::white(), Point::new(0.0, 10.0, 0.5));
+        let lighting = |m: Material| {
+            m.lighting(
+                &Object::new_sphere(),
+                &light,
+                &position,
+                &eye_v,
+                &normal_v,
+                1.0,
+            )
+        };
+
+        assert_eq!(lighting(Material::new()), Color::new(0.1, 0.1, 0.1));
+
+        let wax = lighting(Material::new().with_subsurface(0.5));
+        assert!(wax.r > 0.1 + EPSILON);
+        assert_eq!(wax.r, wax.g);
+
+        // A larger wrap lets more light through.
+        assert!(lighting(Material::new().with_subsurface(1.0)).r > wax.r);
+    }
+
     #[test]
     fn lighting_with_the_surface_in_shadow() {
         let m = Material::new();
@@ -350,12 +705,31 @@ mod tests {
             assert_eq!(
                 object
                     .material()
-                    .lighting(&object, &light, &point, &eye_v, &normal_v, intensity),
+                    .lighting(&object, light, &point, &eye_v, &normal_v, intensity),
                 result
             );
         }
     }
 
+    #[test]
+    fn no_thin_film_does_not_tint_reflections() {
+        let m = Material::new();
+
+        assert_eq!(m.thin_film_color(1.0), Color::white());
+        assert_eq!(m.thin_film_color(0.1), Color::white());
+    }
+
+    #[test]
+    fn a_thin_film_shifts_the_hue_of_reflections_with_the_view_angle() {
+        let m = Material::new().with_thin_film(400.0);
+
+        let head_on = m.thin_film_color(1.0);
+        let grazing = m.thin_film_color(0.2);
+
+        assert_ne!(head_on, grazing);
+        assert!(!(head_on.r / head_on.g).approx_eq_low_precision(grazing.r / grazing.g));
+    }
+
     #[test]
     fn lighting_samples_the_area_light() {
         let corner = Point::new(-0.5, -0.5, -5.0);
@@ -379,7 +753,7 @@ mod tests {
                 Color::new(0.9965, 0.9965, 0.9965),
             ),
             (
-                Point::new(0.0, 0.7071, -0.7071),
+                Point::new(0.0, f64::sqrt(2.0) / 2.0, -f64::sqrt(2.0) / 2.0),
                 Color::new(0.6232, 0.6232, 0.6232),
             ),
         ];
@@ -396,6 +770,172 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn a_clear_coat_adds_a_tighter_highlight_on_top_of_the_base_one() {
+        let base = Material::new().with_shininess(10.0);
+        let coated = base.clone().with_clearcoat(0.5, 0.05);
+
+        let position = Point::zero();
+        let normal_v = Vector::new(0.0, 0.0, -1.0);
+        let light = Light::new_point_light(Color::white(), Point::new(0.0, 10.0, -10.0));
+
+        let lighting = |m: &Material, angle: f64| {
+            // The eye is rotated by angle from the direction of the reflection vector.
+            let eye_v = Vector::new(0.0, -(PI / 4.0 + angle).sin(), -(PI / 4.0 + angle).cos());
+            m.lighting(
+                &Object::new_sphere(),
+                &light,
+                &position,
+                &eye_v,
+                &normal_v,
+                1.0,
+            )
+        };
+
+        // In the direction of the reflection, both highlights add up.
+        let highlight = lighting(&coated, 0.0) - lighting(&base, 0.0);
+        assert!(highlight.r > 0.4);
+
+        // Slightly off, only the broader base highlight remains, a bit dimmed by the coat.
+        let base_color = lighting(&base, 0.2);
+        let coated_color = lighting(&coated, 0.2);
+        let matte_color = lighting(&Material::new().with_specular(0.0), 0.2);
+        assert!(base_color.r - matte_color.r > 0.5);
+        assert!(coated_color.r <= base_color.r);
+        assert!(coated_color.r > base_color.r - 0.05);
+
+        // No clear-coat doesn't change anything.
+        assert_eq!(base.clearcoat_fresnel(1.0), 0.0);
+        assert_eq!(
+            lighting(&base.clone().with_clearcoat(0.0, 0.05), 0.0),
+            lighting(&base, 0.0)
+        );
+    }
+
+    #[test]
+    fn lerping_materials_at_the_midpoint_averages_their_coefficients() {
+        let a = Material::new()
+            .with_ambient(0.0)
+            .with_clearcoat(0.2, 0.0)
+            .with_diffuse(1.0)
+            .with_emissive(Color::red())
+            .with_reflective(0.5)
+            .with_refractive_index(1.0)
+            .with_shininess(10.0)
+            .with_specular(0.0)
+            .with_subsurface(0.0)
+            .with_thin_film(300.0)
+            .with_transparency(1.0);
+        let b = Material::new()
+            .with_ambient(1.0)
+            .with_clearcoat(0.4, 0.2)
+            .with_diffuse(0.0)
+            .with_emissive(Color::black())
+            .with_reflective(0.0)
+            .with_refractive_index(2.0)
+            .with_shininess(20.0)
+            .with_specular(1.0)
+            .with_subsurface(0.5)
+            .with_thin_film(500.0)
+            .with_transparency(0.0);
+
+        let m = Material::lerp(&a, &b, 0.5);
+
+        assert!(m.ambient.approx_eq(0.5));
+        assert!(m.clearcoat.approx_eq(0.3));
+        assert!(m.clearcoat_roughness.approx_eq(0.1));
+        assert!(m.diffuse.approx_eq(0.5));
+        assert_eq!(m.emissive, Color::new(0.5, 0.0, 0.0));
+        assert!(m.reflective.approx_eq(0.25));
+        assert!(m.refractive_index.approx_eq(1.5));
+        assert!(m.shininess.approx_eq(15.0));
+        assert!(m.specular.approx_eq(0.5));
+        assert!(m.subsurface.approx_eq(0.25));
+        assert!(m.thin_film.unwrap().approx_eq(400.0));
+        assert!(m.transparency.approx_eq(0.5));
+
+        assert_eq!(Material::lerp(&a, &b, 0.0), a);
+    }
+
+    #[test]
+    fn lerping_materials_blends_their_patterns() {
+        let a = Material::new().with_color(Color::red());
+        let b =
+            Material::new().with_pattern(Pattern::new_stripe(vec![Color::white(), Color::black()]));
+        let m = Material::lerp(&a, &b, 0.25);
+        let object = Object::new_sphere();
+
+        assert_eq!(
+            m.pattern
+                .pattern_at_object(&object, &Point::new(0.25, 0.0, 0.0)),
+            Color::new(1.0, 0.25, 0.25)
+        );
+        assert_eq!(
+            m.pattern
+                .pattern_at_object(&object, &Point::new(0.75, 0.0, 0.0)),
+            Color::new(0.75, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn refractive_indices_of_dispersive_materials() {
+        let glass = Material::new().with_refractive_index(1.5);
+        assert_eq!(glass.refractive_indices(), [1.5; 3]);
+
+        let [red, green, blue] = glass.with_dispersion(50.0).refractive_indices();
+        assert!(green.approx_eq(1.5));
+        assert!(red < green && green < blue);
+        // Definition of the Abbe number.
+        assert!(((green - 1.0) / (blue - red)).approx_eq(50.0));
+    }
+
+    #[test]
+    fn abbe_numbers_must_be_positive_and_finite() {
+        for abbe_number in [0.0, -50.0, f64::NAN, f64::INFINITY] {
+            let result = std::panic::catch_unwind(|| Material::new().with_dispersion(abbe_number));
+            assert!(result.is_err(), "{} was accepted", abbe_number);
+        }
+    }
+
+    #[test]
+    fn rough_diffuse_surfaces_scatter_light_back_near_the_terminator() {
+        let lambert = Material::new().with_ambient(0.0).with_specular(0.0);
+        let oren_nayar = lambert
+            .clone()
+            .with_diffuse_model(DiffuseModel::OrenNayar { roughness: 1.0 });
+        let smooth_oren_nayar = lambert
+            .clone()
+            .with_diffuse_model(DiffuseModel::OrenNayar { roughness: 0.0 });
+
+        let object = Object::new_sphere();
+        let normal_v = Vector::new(0.0, 0.0, -1.0);
+        let lighting = |m: &Material, eye_v: &Vector, light_position: Point| {
+            let light = Light::new_point_light(Color::white(), light_position);
+            m.lighting(&object, &light, &Point::zero(), eye_v, &normal_v, 1.0)
+        };
+
+        // The light grazes the surface, and the eye looks from the light.
+        let grazing = 80.0 * PI / 180.0;
+        let light_position = Point::new(10.0 * grazing.sin(), 0.0, -10.0 * grazing.cos());
+        let eye_v = Vector::new(grazing.sin(), 0.0, -grazing.cos());
+
+        let lambert_color = lighting(&lambert, &eye_v, light_position);
+        let oren_nayar_color = lighting(&oren_nayar, &eye_v, light_position);
+        assert!(oren_nayar_color.r > 2.0 * lambert_color.r);
+
+        // Without roughness, both models are the same.
+        assert_eq!(
+            lighting(&smooth_oren_nayar, &eye_v, light_position),
+            lambert_color
+        );
+
+        // Rough surfaces are darker when lit and seen from the front.
+        assert!(
+            lighting(&oren_nayar, &normal_v, Point::new(0.0, 0.0, -10.0)).r
+                < lighting(&lambert, &normal_v, Point::new(0.0, 0.0, -10.0)).r
+        );
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
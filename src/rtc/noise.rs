@@ -0,0 +1,130 @@
+/* ---------------------------------------------------------------------------------------------- */
+
+use crate::primitive::{Point, Tuple};
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Deterministic hash of a lattice point, instead of the usual permutation table.
+fn hash(x: i64, y: i64, z: i64) -> u64 {
+    let mut h = (x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        ^ (y as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f)
+        ^ (z as u64).wrapping_mul(0x1656_67b1_9e37_79f9);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    h ^= h >> 33;
+
+    h
+}
+
+// One of the 12 directions to the edges of a cube, picked by the hash.
+fn gradient(hash: u64, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash % 12;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 { y } else { z };
+
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Improved Perlin noise, in [-1, 1] and null on the points of the integer lattice.
+pub fn noise(point: &Point) -> f64 {
+    let (x0, y0, z0) = (point.x().floor(), point.y().floor(), point.z().floor());
+    let (x, y, z) = (point.x() - x0, point.y() - y0, point.z() - z0);
+    let (xi, yi, zi) = (x0 as i64, y0 as i64, z0 as i64);
+    let (u, v, w) = (fade(x), fade(y), fade(z));
+
+    let corner = |dx: i64, dy: i64, dz: i64| {
+        gradient(
+            hash(xi + dx, yi + dy, zi + dz),
+            x - dx as f64,
+            y - dy as f64,
+            z - dz as f64,
+        )
+    };
+
+    let value = lerp(
+        w,
+        lerp(
+            v,
+            lerp(u, corner(0, 0, 0), corner(1, 0, 0)),
+            lerp(u, corner(0, 1, 0), corner(1, 1, 0)),
+        ),
+        lerp(
+            v,
+            lerp(u, corner(0, 0, 1), corner(1, 0, 1)),
+            lerp(u, corner(0, 1, 1), corner(1, 1, 1)),
+        ),
+    );
+
+    value.clamp(-1.0, 1.0)
+}
+
+// Sum of the absolute values of octaves of noise, each of double frequency and half amplitude.
+pub fn turbulence(point: &Point, octaves: usize) -> f64 {
+    (0..octaves)
+        .map(|octave| {
+            let frequency = (1 << octave) as f64;
+            noise(&Point::new(
+                point.x() * frequency,
+                point.y() * frequency,
+                point.z() * frequency,
+            ))
+            .abs()
+                / frequency
+        })
+        .sum()
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::float::ApproxEq;
+
+    #[test]
+    fn noise_is_null_on_the_lattice() {
+        for (x, y, z) in [(0.0, 0.0, 0.0), (1.0, -2.0, 3.0), (-7.0, 5.0, 11.0)] {
+            assert!(noise(&Point::new(x, y, z)).approx_eq(0.0));
+        }
+    }
+
+    #[test]
+    fn noise_is_bounded_and_continuous() {
+        let mut nb_non_null = 0;
+
+        for i in 0..1000 {
+            let point = Point::new(i as f64 * 0.137, i as f64 * 0.071 - 20.0, i as f64 * 0.013);
+            let value = noise(&point);
+            let next = noise(&Point::new(point.x() + 0.001, point.y(), point.z()));
+
+            assert!((-1.0..=1.0).contains(&value));
+            assert!((value - next).abs() < 0.01);
+            if value.abs() > 0.1 {
+                nb_non_null += 1;
+            }
+        }
+
+        assert!(nb_non_null > 100);
+    }
+
+    #[test]
+    fn turbulence_is_positive() {
+        for i in 0..100 {
+            let point = Point::new(i as f64 * 0.37, 0.5, -(i as f64) * 0.21);
+            assert!(turbulence(&point, 4) >= 0.0);
+            assert!(turbulence(&point, 4) >= turbulence(&point, 1));
+        }
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
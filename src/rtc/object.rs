@@ -1,37 +1,180 @@
 /* ---------------------------------------------------------------------------------------------- */
 
 use crate::{
-    primitive::{Matrix, Point, Vector},
+    primitive::{Matrix, Point, Tuple, Vector},
     rtc::{
-        shapes::{Cone, Cylinder, GroupBuilder, SmoothTriangle, Sphere, TestShape, Triangle},
-        BoundingBox, Intersection, IntersectionPusher, Material, Ray, Shape, Transform,
+        reflection,
+        shapes::{
+            Capsule, Clip, Cone, Csg, CsgOperation, Cylinder, Disk, GroupBuilder, HeightField, Lod,
+            Mesh, MetaBall, SmoothTriangle, Sphere, TestShape, Torus, Triangle,
+        },
+        subdivision, Axis, BoundingBox, Canvas, Intersection, IntersectionPusher, Material,
+        Pattern, Ray, Shape, Transform,
     },
 };
-use serde::{Deserialize, Serialize};
+use rand::Rng;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{f64::consts::PI, sync::OnceLock};
 
 /* ---------------------------------------------------------------------------------------------- */
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct Object {
-    bounding_box: BoundingBox,
+    // Computed on first use after a transformation, as objects are often transformed many times
+    // while building a scene.
+    bounding_box: LazyBoundingBox,
+    // Transformation at the end of the motion of a moving object.
+    end_transformation: Option<Matrix>,
     has_shadow: bool,
+    light_mask: u64,
     material: Material,
     shape: Shape,
     transformation: Matrix,
     transformation_inverse: Matrix,
     transformation_inverse_transpose: Matrix,
+    transform_kind: TransformKind,
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[derive(Clone, Debug, Default)]
+struct LazyBoundingBox(OnceLock<BoundingBox>);
+
+impl From<BoundingBox> for LazyBoundingBox {
+    fn from(bounding_box: BoundingBox) -> Self {
+        LazyBoundingBox(OnceLock::from(bounding_box))
+    }
+}
+
+// The bounding box derives from the other fields of an object, whether it's been computed yet
+// doesn't matter.
+impl PartialEq for LazyBoundingBox {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Serialize for LazyBoundingBox {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.get().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for LazyBoundingBox {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Option::<BoundingBox>::deserialize(deserializer)?
+            .map_or_else(LazyBoundingBox::default, LazyBoundingBox::from))
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Most objects are only translated or scaled. In these cases, transforming a ray to object space
+// doesn't need a full matrix multiplication.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+enum TransformKind {
+    Identity,
+    Translation(f64, f64, f64),
+    Scaling(f64, f64, f64),
+    General,
+}
+
+impl TransformKind {
+    // Detected on the inverse matrix, as it's the one used to transform rays. Entries are compared
+    // exactly so that the fast paths give the same results as the general one (the sign of a
+    // zero coordinate aside).
+    #[allow(clippy::float_cmp)]
+    fn new(m: &Matrix) -> Self {
+        let is = |row: usize, col: usize, value: f64| m[(row, col)] == value;
+
+        let last_row_is_id = is(3, 0, 0.0) && is(3, 1, 0.0) && is(3, 2, 0.0) && is(3, 3, 1.0);
+        let no_rotation = is(0, 1, 0.0)
+            && is(0, 2, 0.0)
+            && is(1, 0, 0.0)
+            && is(1, 2, 0.0)
+            && is(2, 0, 0.0)
+            && is(2, 1, 0.0);
+        let no_scaling = is(0, 0, 1.0) && is(1, 1, 1.0) && is(2, 2, 1.0);
+        let no_translation = is(0, 3, 0.0) && is(1, 3, 0.0) && is(2, 3, 0.0);
+
+        if !last_row_is_id || !no_rotation {
+            TransformKind::General
+        } else if no_scaling && no_translation {
+            TransformKind::Identity
+        } else if no_scaling {
+            TransformKind::Translation(m[(0, 3)], m[(1, 3)], m[(2, 3)])
+        } else if no_translation {
+            TransformKind::Scaling(m[(0, 0)], m[(1, 1)], m[(2, 2)])
+        } else {
+            TransformKind::General
+        }
+    }
+
+    fn transform_ray(&self, ray: &Ray, m: &Matrix) -> Ray {
+        match *self {
+            TransformKind::Identity => *ray,
+            TransformKind::Translation(x, y, z) => Ray {
+                origin: Point::new(ray.origin.x() + x, ray.origin.y() + y, ray.origin.z() + z),
+                direction: ray.direction,
+            },
+            TransformKind::Scaling(x, y, z) => Ray {
+                origin: Point::new(ray.origin.x() * x, ray.origin.y() * y, ray.origin.z() * z),
+                direction: Vector::new(
+                    ray.direction.x() * x,
+                    ray.direction.y() * y,
+                    ray.direction.z() * z,
+                ),
+            },
+            TransformKind::General => ray.transform(m),
+        }
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
 
 impl Object {
-    pub fn new_cone(min: f64, max: f64, closed: bool) -> Self {
+    pub fn new_capsule(min: f64, max: f64) -> Self {
+        let shape = Shape::Capsule(Capsule::new(min, max));
+        let bounding_box = shape.bounds();
+
+        Object {
+            shape,
+            bounding_box: bounding_box.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn new_clip(object: Object, point: Point, normal: Vector, capped: bool) -> Self {
+        let shape = Shape::Clip(Clip::new(object, point, normal, capped));
+        let bounding_box = shape.bounds();
+
+        Object {
+            shape,
+            bounding_box: bounding_box.into(),
+            ..Default::default()
+        }
+    }
+
+    // Bounds given in the wrong order are swapped. When they are equal, a closed cone is a disk
+    // and an open one is empty.
+    pub fn new_cone(min: Option<f64>, max: Option<f64>, closed: bool) -> Self {
         let shape = Shape::Cone(Cone::new(min, max, closed));
         let bounding_box = shape.bounds();
 
         Object {
             shape,
-            bounding_box,
+            bounding_box: bounding_box.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn new_csg(operation: CsgOperation, left: Object, right: Object) -> Self {
+        let shape = Shape::Csg(Csg::new(operation, left, right));
+        let bounding_box = shape.bounds();
+
+        Object {
+            shape,
+            bounding_box: bounding_box.into(),
             ..Default::default()
         }
     }
@@ -42,18 +185,20 @@ impl Object {
 
         Object {
             shape: Shape::Cube(),
-            bounding_box,
+            bounding_box: bounding_box.into(),
             ..Default::default()
         }
     }
 
-    pub fn new_cylinder(min: f64, max: f64, closed: bool) -> Self {
+    // Bounds given in the wrong order are swapped. When they are equal, a closed cylinder is a
+    // disk and an open one is empty.
+    pub fn new_cylinder(min: Option<f64>, max: Option<f64>, closed: bool) -> Self {
         let shape = Shape::Cylinder(Cylinder::new(min, max, closed));
         let bounding_box = shape.bounds();
 
         Object {
             shape,
-            bounding_box,
+            bounding_box: bounding_box.into(),
             ..Default::default()
         }
     }
@@ -66,36 +211,124 @@ impl Object {
     }
 
     pub fn new_group(children: Vec<Object>) -> Self {
-        let children_group_builders = children
-            .iter()
-            .filter_map(|child| match child.shape() {
+        let capacity = children.len();
+        Object::new_group_with_capacity(children, capacity)
+    }
+
+    pub fn new_group_from_iter(children: impl IntoIterator<Item = Object>) -> Self {
+        let children = children.into_iter();
+        let capacity = children.size_hint().0;
+        Object::new_group_with_capacity(children, capacity)
+    }
+
+    // The capacity is only a hint for the number of children, avoiding reallocations when the
+    // iterator can't tell its length.
+    pub fn new_group_with_capacity(
+        children: impl IntoIterator<Item = Object>,
+        capacity: usize,
+    ) -> Self {
+        let mut children_group_builders = Vec::with_capacity(capacity);
+        children_group_builders.extend(children.into_iter().filter_map(
+            |child| match child.shape() {
                 Shape::Group(g) => {
                     if g.children().is_empty() {
                         None
                     } else {
-                        Some(GroupBuilder::from_object(child))
+                        Some(GroupBuilder::from_object(&child))
                     }
                 }
 
-                _ => Some(GroupBuilder::from_object(child)),
-            })
-            .collect();
+                _ => Some(GroupBuilder::from_object(&child)),
+            },
+        ));
         let group_builder = GroupBuilder::Node(Object::new_dummy(), children_group_builders);
         let object = group_builder.build();
 
         Object {
-            bounding_box: object.shape.bounds(),
+            bounding_box: object.shape.bounds().into(),
             ..object
         }
     }
 
+    pub fn new_lod(levels: Vec<(f64, Object)>) -> Self {
+        let shape = Shape::Lod(Lod::new(levels));
+        let bounding_box = shape.bounds();
+
+        Object {
+            shape,
+            bounding_box: bounding_box.into(),
+            ..Default::default()
+        }
+    }
+
+    // Flat ring of the xz plane, around the y axis. A disk has an inner radius of 0.
+    pub fn new_disk(inner: f64, outer: f64) -> Self {
+        let shape = Shape::Disk(Disk::new(inner, outer));
+        let bounding_box = shape.bounds();
+
+        Object {
+            shape,
+            bounding_box: bounding_box.into(),
+            ..Default::default()
+        }
+    }
+
+    // Terrain over the unit square of the xz plane, of which the luminance of the pixels gives the
+    // elevation. Columns go along x and rows along z.
+    pub fn new_height_field(canvas: &Canvas) -> Self {
+        let shape = Shape::HeightField(HeightField::new(canvas));
+        let bounding_box = shape.bounds();
+
+        Object {
+            shape,
+            bounding_box: bounding_box.into(),
+            ..Default::default()
+        }
+    }
+
+    // Triangles sharing their vertices and normals, given as indices of vertices. Without normals,
+    // triangles are flat.
+    pub fn new_mesh(vertices: Vec<Point>, normals: Vec<Vector>, faces: Vec<[usize; 3]>) -> Self {
+        let shape = Shape::Mesh(Mesh::new(vertices, normals, faces));
+        let bounding_box = shape.bounds();
+
+        Object {
+            shape,
+            bounding_box: bounding_box.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn new_triangle_strip(vertices: Vec<Point>, normals: Vec<Vector>) -> Self {
+        let faces = Mesh::strip_faces(vertices.len());
+        Object::new_mesh(vertices, normals, faces)
+    }
+
+    pub fn new_triangle_fan(vertices: Vec<Point>, normals: Vec<Vector>) -> Self {
+        let faces = Mesh::fan_faces(vertices.len());
+        Object::new_mesh(vertices, normals, faces)
+    }
+
+    // Blobby surface of balls given as (center, radius, strength), where the sum of their fields
+    // equals the threshold.
+    pub fn new_metaball(balls: Vec<(Point, f64, f64)>, threshold: f64) -> Self {
+        let shape = Shape::MetaBall(MetaBall::new(balls, threshold));
+        let bounding_box = shape.bounds();
+
+        Object {
+            shape,
+            bounding_box: bounding_box.into(),
+            ..Default::default()
+        }
+    }
+
     pub fn new_plane() -> Self {
         let shape = Shape::Plane();
         let bounding_box = shape.bounds();
 
         Object {
             shape,
-            bounding_box,
+            bounding_box: bounding_box.into(),
             ..Default::default()
         }
     }
@@ -113,7 +346,7 @@ impl Object {
 
         Object {
             shape,
-            bounding_box,
+            bounding_box: bounding_box.into(),
             ..Default::default()
         }
     }
@@ -124,7 +357,7 @@ impl Object {
 
         Object {
             shape,
-            bounding_box,
+            bounding_box: bounding_box.into(),
             ..Default::default()
         }
     }
@@ -137,13 +370,25 @@ impl Object {
         }
     }
 
+    // Ring around the y axis, of radius major, whose tube has a radius of minor.
+    pub fn new_torus(major: f64, minor: f64) -> Self {
+        let shape = Shape::Torus(Torus::new(major, minor));
+        let bounding_box = shape.bounds();
+
+        Object {
+            shape,
+            bounding_box: bounding_box.into(),
+            ..Default::default()
+        }
+    }
+
     pub fn new_triangle(p1: Point, p2: Point, p3: Point) -> Self {
         let shape = Shape::Triangle(Triangle::new(p1, p2, p3));
         let bounding_box = shape.bounds();
 
         Object {
             shape,
-            bounding_box,
+            bounding_box: bounding_box.into(),
             ..Default::default()
         }
     }
@@ -160,9 +405,17 @@ impl Object {
         self
     }
 
+    // Bit i is set when the i-th light of the world lights the object. Lights after the 64th
+    // light every object.
+    pub fn with_light_mask(mut self, light_mask: u64) -> Self {
+        self.light_mask = light_mask;
+
+        self
+    }
+
     pub fn with_shape(mut self, shape: Shape) -> Self {
         self.shape = shape;
-        self.bounding_box = self.shape.bounds();
+        self.bounding_box = self.shape.bounds().into();
 
         self
     }
@@ -171,7 +424,15 @@ impl Object {
         self.transformation = transformation;
         self.transformation_inverse = self.transformation.invert();
         self.transformation_inverse_transpose = self.transformation_inverse.transpose();
-        self.bounding_box = self.shape_bounds().transform(&self.transformation);
+        self.transform_kind = TransformKind::new(&self.transformation_inverse);
+        self.bounding_box = LazyBoundingBox::default();
+
+        self
+    }
+
+    // The object moves from its transformation to the given one.
+    pub fn with_motion(mut self, end_transformation: Matrix) -> Self {
+        self.end_transformation = Some(end_transformation);
 
         self
     }
@@ -180,7 +441,9 @@ impl Object {
         if self.shape.skip_world_to_local() {
             self.shape.intersects(ray, push)
         } else {
-            let transformed_ray = ray.transform(&self.transformation_inverse);
+            let transformed_ray = self
+                .transform_kind
+                .transform_ray(ray, &self.transformation_inverse);
 
             self.shape.intersects(&transformed_ray, push)
         }
@@ -193,6 +456,12 @@ impl Object {
         self.normal_to_world(&local_normal)
     }
 
+    pub fn sample_surface(&self, rng: &mut impl Rng) -> Option<(Point, Vector)> {
+        self.shape
+            .sample_surface(rng)
+            .map(|(point, normal)| (self.transformation * point, self.normal_to_world(&normal)))
+    }
+
     fn world_to_object(&self, world_point: &Point) -> Point {
         self.transformation_inverse * *world_point
     }
@@ -205,6 +474,14 @@ impl Object {
         self.has_shadow
     }
 
+    pub fn light_mask(&self) -> u64 {
+        self.light_mask
+    }
+
+    pub fn is_lit_by(&self, light_index: usize) -> bool {
+        light_index >= u64::BITS as usize || self.light_mask & (1 << light_index) != 0
+    }
+
     pub fn material(&self) -> &Material {
         &self.material
     }
@@ -226,7 +503,24 @@ impl Object {
     }
 
     pub fn bounding_box(&self) -> BoundingBox {
-        self.bounding_box
+        *self
+            .bounding_box
+            .0
+            .get_or_init(|| self.shape_bounds().transform(&self.transformation))
+    }
+
+    pub fn end_transformation(&self) -> Option<&Matrix> {
+        self.end_transformation.as_ref()
+    }
+
+    // Encloses the object at the start and at the end of its motion.
+    pub fn motion_bounding_box(&self) -> BoundingBox {
+        match &self.end_transformation {
+            Some(end_transformation) => {
+                self.bounding_box() + self.shape_bounds().transform(end_transformation)
+            }
+            None => self.bounding_box(),
+        }
     }
 
     pub fn divide(self, threshold: usize) -> Self {
@@ -235,6 +529,379 @@ impl Object {
             ..self
         }
     }
+
+    // Cutaway of the object, of which the part behind the plane, opposite to its normal, is
+    // discarded. The object is left open along the cut, showing its inside.
+    pub fn clipped_by_plane(self, point: Point, normal: Vector) -> Self {
+        Object::new_clip(self, point, normal, false)
+    }
+
+    // Same as clipped_by_plane(), but the cut is closed by the plane, so the object looks solid.
+    pub fn capped_by_plane(self, point: Point, normal: Vector) -> Self {
+        Object::new_clip(self, point, normal, true)
+    }
+
+    // Unlike a negative scaling, the winding of triangles is reversed so it keeps agreeing with
+    // their normals, which is what exporters and mesh tools rely on.
+    pub fn mirror(self, axis: Axis) -> Self {
+        let reflection = reflection(axis);
+        // The reflection expressed in object space, so the transformation of triangles is kept.
+        let local = self.transformation_inverse * reflection * self.transformation;
+        let local_normal = local.invert().transpose();
+
+        match &self.shape {
+            Shape::Group(g) => {
+                let group = Object::new_group(
+                    g.children()
+                        .iter()
+                        .map(|child| child.clone().mirror(axis))
+                        .collect(),
+                );
+
+                Object {
+                    bounding_box: group.bounding_box,
+                    shape: group.shape,
+                    ..self
+                }
+            }
+            Shape::Lod(l) => {
+                let lod = Object::new_lod(
+                    l.levels()
+                        .iter()
+                        .map(|(distance, level)| (*distance, level.clone().mirror(axis)))
+                        .collect(),
+                );
+
+                Object {
+                    bounding_box: lod.bounding_box,
+                    shape: lod.shape,
+                    ..self
+                }
+            }
+            Shape::Clip(c) => {
+                let clip = Object::new_clip(
+                    c.object().clone().mirror(axis),
+                    reflection * c.point(),
+                    reflection * c.normal(),
+                    c.cap().is_some(),
+                );
+
+                Object {
+                    bounding_box: clip.bounding_box,
+                    shape: clip.shape,
+                    ..self
+                }
+            }
+            Shape::Csg(c) => {
+                let csg = Object::new_csg(
+                    c.operation(),
+                    c.left().clone().mirror(axis),
+                    c.right().clone().mirror(axis),
+                );
+
+                Object {
+                    bounding_box: csg.bounding_box,
+                    shape: csg.shape,
+                    ..self
+                }
+            }
+            Shape::Triangle(t) => {
+                let triangle = Triangle::new(local * t.p1(), local * t.p3(), local * t.p2());
+                let shape = Shape::Triangle(match t.uvs() {
+                    Some([uv1, uv2, uv3]) => triangle.with_uvs([uv1, uv3, uv2]),
+                    None => triangle,
+                });
+                let transformation = self.transformation;
+
+                Object { shape, ..self }.with_transformation(transformation)
+            }
+            Shape::SmoothTriangle(t) => {
+                let triangle = SmoothTriangle::new(
+                    local * t.p1(),
+                    local * t.p3(),
+                    local * t.p2(),
+                    (local_normal * t.n1()).normalize(),
+                    (local_normal * t.n3()).normalize(),
+                    (local_normal * t.n2()).normalize(),
+                );
+                let shape = Shape::SmoothTriangle(match t.uvs() {
+                    Some([uv1, uv2, uv3]) => triangle.with_uvs([uv1, uv3, uv2]),
+                    None => triangle,
+                });
+                let transformation = self.transformation;
+
+                Object { shape, ..self }.with_transformation(transformation)
+            }
+            Shape::Mesh(m) => {
+                let shape = Shape::Mesh(Mesh::new(
+                    m.vertices().iter().map(|p| local * *p).collect(),
+                    m.normals()
+                        .iter()
+                        .map(|n| (local_normal * *n).normalize())
+                        .collect(),
+                    m.faces().iter().map(|&[v1, v2, v3]| [v1, v3, v2]).collect(),
+                ));
+                let transformation = self.transformation;
+
+                Object { shape, ..self }.with_transformation(transformation)
+            }
+            _ => self.transform(&reflection),
+        }
+    }
+
+    // Texture coordinates of the triangles, from the projection of their vertices on the plane
+    // orthogonal to the axis. The bounding box of the object is mapped to [0, 1].
+    pub fn generate_planar_uv(self, axis: Axis) -> Self {
+        let bbox = self.leaves_bounding_box();
+        let (min, max) = (bbox.min(), bbox.max());
+        let normalized = |x: f64, min: f64, max: f64| {
+            if max - min > 0.0 {
+                (x - min) / (max - min)
+            } else {
+                0.0
+            }
+        };
+
+        self.with_generated_uvs(&|p: &Point| match axis {
+            Axis::X => (
+                normalized(p.z(), min.z(), max.z()),
+                normalized(p.y(), min.y(), max.y()),
+            ),
+            Axis::Y => (
+                normalized(p.x(), min.x(), max.x()),
+                normalized(p.z(), min.z(), max.z()),
+            ),
+            Axis::Z => (
+                normalized(p.x(), min.x(), max.x()),
+                normalized(p.y(), min.y(), max.y()),
+            ),
+        })
+    }
+
+    // Texture coordinates of the triangles, from the longitude and latitude of their vertices
+    // around the center of the object.
+    pub fn generate_spherical_uv(self) -> Self {
+        let bbox = self.leaves_bounding_box();
+        let center = bbox.min() + (bbox.max() - bbox.min()) / 2.0;
+
+        self.with_generated_uvs(&|p: &Point| spherical_uv(&(*p - center)))
+    }
+
+    // Unlike the one of groups, this bounding box is not padded.
+    fn leaves_bounding_box(&self) -> BoundingBox {
+        self.leaves()
+            .iter()
+            .fold(BoundingBox::new(), |acc, leaf| acc + leaf.bounding_box())
+    }
+
+    // Other shapes than triangles are kept as is.
+    fn with_generated_uvs(self, uv_at: &impl Fn(&Point) -> (f64, f64)) -> Self {
+        let uvs = |p1: Point, p2: Point, p3: Point| {
+            [
+                uv_at(&(self.transformation * p1)),
+                uv_at(&(self.transformation * p2)),
+                uv_at(&(self.transformation * p3)),
+            ]
+        };
+
+        match &self.shape {
+            Shape::Group(g) => {
+                let group = Object::new_group(
+                    g.children()
+                        .iter()
+                        .map(|child| child.clone().with_generated_uvs(uv_at))
+                        .collect(),
+                );
+
+                Object {
+                    bounding_box: group.bounding_box,
+                    shape: group.shape,
+                    ..self
+                }
+            }
+            Shape::Triangle(t) => {
+                let shape = Shape::Triangle(t.with_uvs(uvs(t.p1(), t.p2(), t.p3())));
+                Object { shape, ..self }
+            }
+            Shape::SmoothTriangle(t) => {
+                let shape = Shape::SmoothTriangle(t.with_uvs(uvs(t.p1(), t.p2(), t.p3())));
+                Object { shape, ..self }
+            }
+            _ => self,
+        }
+    }
+
+    // Texture coordinates at a hit, for triangles which have some.
+    pub fn uv_at(&self, hit: &Intersection) -> Option<(f64, f64)> {
+        match &self.shape {
+            Shape::Triangle(t) => t.uv_at(hit.u(), hit.v()),
+            Shape::SmoothTriangle(t) => t.uv_at(hit.u(), hit.v()),
+            _ => None,
+        }
+    }
+
+    // Texture coordinates at a point of the surface, for triangles which have some.
+    pub fn uv_at_point(&self, world_point: &Point) -> Option<(f64, f64)> {
+        let local_point = self.world_to_object(world_point);
+
+        match &self.shape {
+            Shape::Triangle(t) => {
+                let (u, v) = t.barycentric(&local_point);
+                t.uv_at(u, v)
+            }
+            Shape::SmoothTriangle(t) => {
+                let (u, v) = t.barycentric(&local_point);
+                t.uv_at(u, v)
+            }
+            _ => None,
+        }
+    }
+
+    // Meshes have many triangles sharing the transformation of their group. Storing their
+    // vertices in world space saves the transformation of rays for each of them.
+    pub(in crate::rtc) fn with_baked_transformation(self) -> Self {
+        let shape = match &self.shape {
+            _ if self.transform_kind == TransformKind::Identity => return self,
+            // Baking would lose the motion, which is relative to the transformation.
+            _ if self.end_transformation.is_some() => return self,
+            // Patterns are evaluated in object space, which would become the world space.
+            _ if !self.material.pattern.is_plain() => return self,
+            Shape::Triangle(t) => Shape::Triangle(
+                t.transform(&self.transformation, &self.transformation_inverse_transpose),
+            ),
+            Shape::SmoothTriangle(t) => Shape::SmoothTriangle(
+                t.transform(&self.transformation, &self.transformation_inverse_transpose),
+            ),
+            _ => return self,
+        };
+
+        Object { shape, ..self }.with_transformation(Matrix::id())
+    }
+
+    // Catmull-Clark subdivision of the triangles and the indexed meshes of a mesh, which are
+    // welded by position. Other shapes are kept as is. Each level replaces a face of n vertices by
+    // n quads, split in two triangles.
+    pub fn subdivide_surface(&self, levels: usize, smooth_normals: bool) -> Self {
+        let leaves = self.flatten();
+        let (triangles, others): (Vec<_>, Vec<_>) = leaves.iter().partition(|leaf| {
+            matches!(
+                leaf.shape(),
+                Shape::Triangle(_) | Shape::SmoothTriangle(_) | Shape::Mesh(_)
+            )
+        });
+
+        let mesh = (0..levels).fold(subdivision::Mesh::from_triangles(&triangles), |mesh, _| {
+            mesh.subdivide()
+        });
+
+        let mut children = mesh.into_triangles(smooth_normals);
+        children.extend(others.into_iter().cloned());
+
+        Object::new_group(children)
+    }
+
+    // Moves the surface of the triangles and the indexed meshes of a mesh along its normals, by the
+    // luminance of the pattern times the scale. The mesh is first split into smaller faces, without
+    // changing its shape, so the displacement adds actual detail: each level splits a face of n
+    // vertices into n quads. Other shapes are kept as is.
+    pub fn displace(&self, pattern: &Pattern, scale: f64, levels: usize) -> Self {
+        let leaves = self.flatten();
+        let (triangles, others): (Vec<_>, Vec<_>) = leaves.iter().partition(|leaf| {
+            matches!(
+                leaf.shape(),
+                Shape::Triangle(_) | Shape::SmoothTriangle(_) | Shape::Mesh(_)
+            )
+        });
+
+        let mesh = (0..levels)
+            .fold(subdivision::Mesh::from_triangles(&triangles), |mesh, _| {
+                mesh.split()
+            })
+            .displace(|point| pattern.pattern_at_object(self, point).luminance() * scale);
+
+        let mut children = mesh.into_triangles(true);
+        children.extend(others.into_iter().cloned());
+
+        Object::new_group(children)
+    }
+
+    // Translates the object so its lowest point touches the y = 0 plane.
+    pub fn grounded(self) -> Self {
+        let min_y = self.bounding_box().min().y();
+
+        self.translate(0.0, -min_y, 0.0).transform()
+    }
+
+    // Leaves already have the transformations of their groups baked in by GroupBuilder.
+    // For levels of detail, only the most detailed level is kept.
+    pub fn flatten(&self) -> Vec<Object> {
+        match self.shape() {
+            Shape::Group(g) => g.children().iter().flat_map(Object::flatten).collect(),
+            Shape::Lod(l) => l
+                .levels()
+                .first()
+                .map_or_else(Vec::new, |(_, object)| object.flatten()),
+            _ => vec![self.clone()],
+        }
+    }
+
+    // Sets the viewpoint of the levels of detail within the object.
+    pub(crate) fn set_lod_viewpoint(&mut self, viewpoint: &Point) {
+        match &mut self.shape {
+            Shape::Group(g) => {
+                for child in g.children_mut() {
+                    child.set_lod_viewpoint(viewpoint);
+                }
+            }
+            Shape::Lod(l) => l.set_viewpoint(viewpoint),
+            Shape::Clip(c) => c.object_mut().set_lod_viewpoint(viewpoint),
+            Shape::Csg(c) => {
+                c.left_mut().set_lod_viewpoint(viewpoint);
+                c.right_mut().set_lod_viewpoint(viewpoint);
+            }
+            _ => {}
+        }
+    }
+
+    // The objects that can actually be hit by a ray, in a depth-first order.
+    pub(crate) fn leaves(&self) -> Vec<&Object> {
+        match self.shape() {
+            Shape::Group(g) => g.children().iter().flat_map(Object::leaves).collect(),
+            Shape::Lod(l) => l
+                .levels()
+                .iter()
+                .flat_map(|(_, object)| object.leaves())
+                .collect(),
+            Shape::Clip(c) => {
+                let mut leaves = c.object().leaves();
+                leaves.extend(c.cap());
+                leaves
+            }
+            Shape::Csg(c) => {
+                let mut leaves = c.left().leaves();
+                leaves.extend(c.right().leaves());
+                leaves
+            }
+            _ => vec![self],
+        }
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Spherical mapping of a point relative to the center of the sphere: u follows the longitude and
+// v the latitude, both in [0, 1].
+fn spherical_uv(v: &Vector) -> (f64, f64) {
+    let radius = v.magnitude();
+    if radius == 0.0 {
+        return (0.0, 0.0);
+    }
+
+    let theta = v.x().atan2(v.z());
+    let phi = (v.y() / radius).clamp(-1.0, 1.0).acos();
+    let raw_u = theta / (2.0 * PI);
+
+    (1.0 - (raw_u + 0.5), 1.0 - phi / PI)
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -242,13 +909,16 @@ impl Object {
 impl Default for Object {
     fn default() -> Self {
         Object {
-            bounding_box: Sphere::bounds(),
+            bounding_box: Sphere::bounds().into(),
+            end_transformation: None,
             has_shadow: true,
+            light_mask: u64::MAX,
             material: Material::new(),
             shape: Shape::Sphere(),
             transformation: Matrix::id(),
             transformation_inverse: Matrix::id(),
             transformation_inverse_transpose: Matrix::id(),
+            transform_kind: TransformKind::Identity,
         }
     }
 }
@@ -263,11 +933,8 @@ impl Transform for Object {
                 // which is easier to manipulate. It's not the most efficient, but as this
                 // is only peformed when constructing objects of a world, it has no impact on
                 // the rendering itself.
-                let children_group_builders = g
-                    .children()
-                    .iter()
-                    .map(GroupBuilder::from_object)
-                    .collect();
+                let children_group_builders =
+                    g.children().iter().map(GroupBuilder::from_object).collect();
 
                 // We then create a new top GroupBuilder Node from which the new transformation is
                 // applied.
@@ -279,9 +946,42 @@ impl Transform for Object {
                 // Convert back to a Group.
                 group_builder.build()
             }
+            Shape::Lod(l) => {
+                let shape = Shape::Lod(l.clone().transform(new_transformation));
+
+                Object {
+                    bounding_box: shape.bounds().into(),
+                    shape,
+                    ..self
+                }
+            }
+            Shape::Clip(c) => {
+                let shape = Shape::Clip(c.clone().transform(new_transformation));
+
+                Object {
+                    bounding_box: shape.bounds().into(),
+                    shape,
+                    ..self
+                }
+            }
+            Shape::Csg(c) => {
+                let shape = Shape::Csg(c.clone().transform(new_transformation));
+
+                Object {
+                    bounding_box: shape.bounds().into(),
+                    shape,
+                    ..self
+                }
+            }
             _other_shape => {
+                let end_transformation =
+                    self.end_transformation.map(|end| *new_transformation * end);
                 let new_transformation = *new_transformation * self.transformation;
-                self.with_transformation(new_transformation)
+
+                Object {
+                    end_transformation,
+                    ..self.with_transformation(new_transformation)
+                }
             }
         }
     }
@@ -292,7 +992,11 @@ impl Transform for Object {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::primitive::Tuple;
+    use crate::{
+        float::ApproxEq,
+        rtc::{rotation_x, rotation_y, scaling, translation, Color, Intersections},
+    };
+    use rand::{rngs::SmallRng, SeedableRng};
 
     #[test]
     fn an_object_default_transformation_is_id() {
@@ -371,6 +1075,48 @@ mod tests {
         );
     }
 
+    #[test]
+    fn flattening_a_transformed_nested_group() {
+        let s1 = Object::new_sphere().translate(5.0, 0.0, 0.0).transform();
+        let s2 = Object::new_cube().scale(0.5, 1.0, 0.5).transform();
+        let g2 = Object::new_group(vec![s1, s2])
+            .scale(2.0, 2.0, 2.0)
+            .transform();
+        let c = Object::new_cylinder(Some(0.0), Some(1.0), true);
+        let g1 = Object::new_group(vec![g2, c])
+            .translate(0.0, 1.0, 0.0)
+            .transform();
+
+        let leaves = g1.flatten();
+
+        assert_eq!(leaves.len(), 3);
+        assert!(leaves.iter().all(|leaf| leaf.shape().as_group().is_none()));
+
+        assert_eq!(leaves[0].bounding_box().min(), Point::new(8.0, -1.0, -2.0));
+        assert_eq!(leaves[0].bounding_box().max(), Point::new(12.0, 3.0, 2.0));
+        assert_eq!(leaves[1].bounding_box().min(), Point::new(-1.0, -1.0, -1.0));
+        assert_eq!(leaves[1].bounding_box().max(), Point::new(1.0, 3.0, 1.0));
+        assert_eq!(leaves[2].bounding_box().min(), Point::new(-1.0, 1.0, -1.0));
+        assert_eq!(leaves[2].bounding_box().max(), Point::new(1.0, 2.0, 1.0));
+
+        let bbox = leaves
+            .iter()
+            .fold(BoundingBox::new(), |acc, leaf| acc + leaf.bounding_box());
+        assert_eq!(bbox, g1.bounding_box());
+
+        let ray = Ray {
+            origin: Point::new(10.0, 1.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+        let xs = ray.intersects(std::slice::from_ref(&g1), Intersections::new());
+        let flat_xs = ray.intersects(&leaves, Intersections::new());
+
+        assert_eq!(xs.len(), 2);
+        assert_eq!(flat_xs.len(), 2);
+        assert_eq!(xs[0].t(), flat_xs[0].t());
+        assert_eq!(xs[1].t(), flat_xs[1].t());
+    }
+
     #[test]
     fn finding_the_normal_on_a_child_object() {
         let s = Object::new_sphere().translate(5.0, 0.0, 0.0).transform();
@@ -384,13 +1130,409 @@ mod tests {
         let group_s = group_g2.shape().as_group().unwrap().children()[0].clone();
 
         let dummy_object = Object::new_test_shape();
-        let dummy_intersection = Intersection::new(std::f64::INFINITY, &dummy_object);
+        let dummy_intersection = Intersection::new(f64::INFINITY, &dummy_object);
 
         assert_eq!(
             group_s.normal_at(&Point::new(1.7321, 1.1547, -5.5774), &dummy_intersection),
             Vector::new(0.2857, 0.4286, -0.8571)
         );
     }
+
+    #[test]
+    fn sampling_the_surface_of_a_transformed_object() {
+        let mut rng = SmallRng::seed_from_u64(0);
+
+        assert_eq!(Object::new_plane().sample_surface(&mut rng), None);
+
+        let s = Object::new_sphere()
+            .scale(2.0, 2.0, 2.0)
+            .translate(1.0, 2.0, 3.0)
+            .transform();
+        let center = Point::new(1.0, 2.0, 3.0);
+
+        for _ in 0..100 {
+            let (point, normal) = s.sample_surface(&mut rng).unwrap();
+
+            assert!((point - center).magnitude().approx_eq(2.0));
+            assert_eq!(normal, (point - center).normalize());
+        }
+    }
+
+    #[test]
+    fn translated_and_scaled_objects_use_a_fast_path_with_identical_results() {
+        let objects = vec![
+            (
+                Object::new_sphere().translate(1.0, -2.0, 3.5).transform(),
+                TransformKind::Translation(-1.0, 2.0, -3.5),
+            ),
+            (
+                Object::new_sphere().scale(2.0, 2.0, 2.0).transform(),
+                TransformKind::Scaling(0.5, 0.5, 0.5),
+            ),
+            (Object::new_sphere(), TransformKind::Identity),
+            (
+                Object::new_sphere().rotate_y(0.3).transform(),
+                TransformKind::General,
+            ),
+        ];
+
+        let mut rng = SmallRng::seed_from_u64(0);
+        let rays = (0..100)
+            .map(|_| Ray {
+                origin: Point::new(
+                    rng.gen_range(-5.0..5.0),
+                    rng.gen_range(-5.0..5.0),
+                    rng.gen_range(-5.0..5.0),
+                ),
+                direction: Vector::new(
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                ),
+            })
+            .collect::<Vec<_>>();
+
+        for (object, kind) in objects {
+            assert_eq!(object.transform_kind, kind);
+
+            let general = Object {
+                transform_kind: TransformKind::General,
+                ..object.clone()
+            };
+
+            for ray in &rays {
+                let xs = ray.intersects(std::slice::from_ref(&object), Intersections::new());
+                let general_xs =
+                    ray.intersects(std::slice::from_ref(&general), Intersections::new());
+
+                let ts = xs.iter().map(|x| x.t().to_bits()).collect::<Vec<_>>();
+                let general_ts = general_xs
+                    .iter()
+                    .map(|x| x.t().to_bits())
+                    .collect::<Vec<_>>();
+                assert_eq!(ts, general_ts);
+            }
+        }
+    }
+
+    #[test]
+    fn the_bounding_box_is_computed_once_after_a_chain_of_transformations() {
+        let transformations = [
+            translation(1.0, 2.0, 3.0),
+            rotation_x(0.3),
+            scaling(2.0, 0.5, 1.0),
+            rotation_y(-1.2),
+            translation(-4.0, 0.0, 0.5),
+        ];
+
+        let objects = vec![
+            Object::new_cube(),
+            Object::new_cylinder(Some(-1.0), Some(2.0), true),
+            Object::new_group(vec![
+                Object::new_sphere().translate(2.0, 0.0, 0.0).transform(),
+                Object::new_cube(),
+            ]),
+        ];
+
+        let cube = Object::new_cube().translate(1.0, 0.0, 0.0).transform();
+        assert!(cube.bounding_box.0.get().is_none());
+        cube.bounding_box();
+        assert!(cube.bounding_box.0.get().is_some());
+
+        for object in objects {
+            let (lazy, eager) = transformations.iter().fold(
+                (object.clone(), object),
+                |(lazy, eager), transformation| {
+                    let eager = eager.transform(transformation);
+                    eager.bounding_box();
+                    (lazy.transform(transformation), eager)
+                },
+            );
+
+            assert_eq!(lazy.bounding_box(), eager.bounding_box());
+            assert_eq!(lazy, eager);
+        }
+    }
+
+    #[test]
+    fn the_motion_bounding_box_of_a_moving_sphere() {
+        let s = Object::new_sphere()
+            .with_motion(translation(4.0, 0.0, 0.0))
+            .translate(0.0, 2.0, 0.0)
+            .transform();
+
+        assert_eq!(s.bounding_box().min(), Point::new(-1.0, 1.0, -1.0));
+        assert_eq!(s.bounding_box().max(), Point::new(1.0, 3.0, 1.0));
+
+        assert_eq!(s.motion_bounding_box().min(), Point::new(-1.0, 1.0, -1.0));
+        assert_eq!(s.motion_bounding_box().max(), Point::new(5.0, 3.0, 1.0));
+
+        let still = Object::new_sphere().translate(0.0, 2.0, 0.0).transform();
+        assert_eq!(still.motion_bounding_box(), still.bounding_box());
+    }
+
+    #[test]
+    fn a_mirrored_mesh_keeps_outward_facing_triangles() {
+        let (a, b, c, d) = (
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(0.0, 0.0, 1.0),
+        );
+        let tetrahedron = Object::new_group(vec![
+            Object::new_triangle(a, b, c),
+            Object::new_triangle(a, d, b),
+            Object::new_triangle(a, c, d),
+            Object::new_triangle(b, d, c),
+        ])
+        .translate(2.0, 0.0, 0.0)
+        .transform();
+
+        // For each face, whether the normal given by the winding of its world space vertices
+        // points outward, and whether it agrees with the normal used for shading.
+        let faces = |mesh: &Object| {
+            let triangles = mesh.flatten();
+            let vertices = triangles
+                .iter()
+                .map(|o| {
+                    let t = o.shape().as_triangle().unwrap();
+                    [t.p1(), t.p2(), t.p3()].map(|p| o.transformation * p)
+                })
+                .collect::<Vec<_>>();
+            let center = Point::zero()
+                + vertices
+                    .iter()
+                    .flatten()
+                    .fold(Vector::zero(), |acc, p| acc + (*p - Point::zero()))
+                    / 12.0;
+
+            triangles
+                .iter()
+                .zip(&vertices)
+                .map(|(o, [p1, p2, p3])| {
+                    let winding = Triangle::new(*p1, *p2, *p3).normal_at(p1);
+                    let centroid = *p1 + ((*p2 - *p1) + (*p3 - *p1)) / 3.0;
+                    let shading = o.normal_at(&centroid, &Intersection::new(1.0, o));
+
+                    (
+                        winding.dot(centroid - center) > 0.0,
+                        winding.dot(shading).approx_eq(1.0),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(faces(&tetrahedron), vec![(true, true); 4]);
+
+        for axis in [Axis::X, Axis::Y, Axis::Z] {
+            assert_eq!(
+                faces(&tetrahedron.clone().mirror(axis)),
+                vec![(true, true); 4]
+            );
+        }
+
+        // A negative scaling turns the mesh inside out.
+        let scaled = tetrahedron.clone().scale(-1.0, 1.0, 1.0).transform();
+        assert_eq!(faces(&scaled), vec![(false, false); 4]);
+
+        let mirrored = tetrahedron.mirror(Axis::X);
+        assert_eq!(mirrored.bounding_box(), scaled.bounding_box());
+    }
+
+    #[test]
+    fn groups_built_from_iterators_are_the_same_as_groups_built_from_vectors() {
+        let children = || {
+            vec![
+                Object::new_sphere().translate(1.0, 0.0, 0.0).transform(),
+                Object::new_group(vec![]),
+                Object::new_group(vec![Object::new_cube(), Object::new_sphere()])
+                    .scale(2.0, 2.0, 2.0)
+                    .transform(),
+            ]
+        };
+
+        let group = Object::new_group(children());
+
+        assert_eq!(Object::new_group_from_iter(children()), group);
+        assert_eq!(
+            Object::new_group_from_iter(children().into_iter().filter(|_| true)),
+            group
+        );
+        assert_eq!(Object::new_group_with_capacity(children(), 0), group);
+        assert_eq!(Object::new_group_with_capacity(children(), 100), group);
+    }
+
+    #[test]
+    fn generating_spherical_uvs_of_a_sphere_approximation() {
+        // An octahedron centered on (1, 2, 3).
+        let c = Vector::new(1.0, 2.0, 3.0);
+        let (top, bottom) = (Point::new(0.0, 1.0, 0.0), Point::new(0.0, -1.0, 0.0));
+        let equator = [
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(0.0, 0.0, 1.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(0.0, 0.0, -1.0),
+        ];
+        let octahedron = Object::new_group(
+            (0..4)
+                .flat_map(|i| {
+                    let (a, b) = (equator[i] + c, equator[(i + 1) % 4] + c);
+                    [
+                        Object::new_triangle(top + c, a, b),
+                        Object::new_triangle(bottom + c, b, a),
+                    ]
+                })
+                .collect(),
+        );
+
+        let unwrapped = octahedron.generate_spherical_uv();
+        assert_eq!(unwrapped.leaves().len(), 8);
+
+        for leaf in unwrapped.leaves() {
+            let t = leaf.shape().as_triangle().unwrap();
+            let uvs = t.uvs().unwrap();
+
+            for (p, (u, v)) in [t.p1(), t.p2(), t.p3()].iter().zip(uvs) {
+                assert!((0.0..=1.0).contains(&u) && (0.0..=1.0).contains(&v));
+
+                let expected = spherical_uv(&(*p - Point::zero() - c));
+                assert!(u.approx_eq(expected.0) && v.approx_eq(expected.1));
+            }
+        }
+
+        // Poles are at the top and at the bottom of the texture, the equator in the middle.
+        assert_eq!(spherical_uv(&Vector::new(0.0, 1.0, 0.0)).1, 1.0);
+        assert_eq!(spherical_uv(&Vector::new(0.0, -1.0, 0.0)).1, 0.0);
+        assert_eq!(spherical_uv(&Vector::new(0.0, 0.0, 1.0)), (0.5, 0.5));
+        assert_eq!(spherical_uv(&Vector::new(1.0, 0.0, 0.0)), (0.25, 0.5));
+    }
+
+    #[test]
+    fn generating_planar_uvs_and_interpolating_them() {
+        let triangle = Object::new_triangle(
+            Point::new(-1.0, 0.0, 2.0),
+            Point::new(1.0, 0.0, 2.0),
+            Point::new(1.0, 5.0, 4.0),
+        )
+        .generate_planar_uv(Axis::Y);
+
+        assert_eq!(
+            triangle.shape().as_triangle().unwrap().uvs(),
+            Some([(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)])
+        );
+
+        let hit = Intersection::new(1.0, &triangle).with_u_and_v(0.5, 0.25);
+        assert_eq!(triangle.uv_at(&hit), Some((0.75, 0.25)));
+
+        // Mirroring keeps the texture coordinates of the vertices.
+        let mirrored = triangle.clone().mirror(Axis::X);
+        assert_eq!(
+            mirrored.shape().as_triangle().unwrap().uvs(),
+            Some([(0.0, 0.0), (1.0, 1.0), (1.0, 0.0)])
+        );
+
+        assert_eq!(
+            Object::new_sphere().generate_planar_uv(Axis::Y).uv_at(&hit),
+            None
+        );
+    }
+
+    #[test]
+    fn displacing_a_plane_moves_its_vertices_along_its_normal() {
+        // A square of the xz plane, facing +y.
+        let plane = Object::new_group(vec![
+            Object::new_triangle(
+                Point::new(-1.0, 0.0, -1.0),
+                Point::new(1.0, 0.0, -1.0),
+                Point::new(1.0, 0.0, 1.0),
+            ),
+            Object::new_triangle(
+                Point::new(-1.0, 0.0, -1.0),
+                Point::new(1.0, 0.0, 1.0),
+                Point::new(-1.0, 0.0, 1.0),
+            ),
+        ]);
+        let pattern = Pattern::new_stripe(vec![Color::white(), Color::black()]);
+        let scale = 0.5;
+
+        let displaced = plane.displace(&pattern, scale, 3);
+
+        let leaves = displaced.flatten();
+        assert!(leaves.len() > 2);
+        let vertices = leaves
+            .iter()
+            .flat_map(|leaf| match leaf.shape() {
+                Shape::SmoothTriangle(t) => [t.p1(), t.p2(), t.p3()],
+                _ => panic!(),
+            })
+            .collect::<Vec<_>>();
+
+        for p in &vertices {
+            assert!((-1.0..=1.0).contains(&p.x()) && (-1.0..=1.0).contains(&p.z()));
+            let expected = scale * pattern.pattern_at_point(&Point::new(p.x(), 0.0, p.z())).r;
+            assert!(p.y().approx_eq(expected));
+        }
+        assert!(vertices.iter().any(|p| p.y().approx_eq(0.0)));
+        assert!(vertices.iter().any(|p| p.y().approx_eq(scale)));
+
+        let (before, after) = (plane.bounding_box(), displaced.bounding_box());
+        assert!(after.min().y().approx_eq(before.min().y()));
+        assert!(after.max().y().approx_eq(before.max().y() + scale));
+    }
+
+    #[test]
+    fn displacing_an_indexed_mesh_is_like_displacing_its_triangles() {
+        let vertices = vec![
+            Point::new(-1.0, 0.0, -1.0),
+            Point::new(1.0, 0.0, -1.0),
+            Point::new(1.0, 0.0, 1.0),
+            Point::new(-1.0, 0.0, 1.0),
+        ];
+        let faces = vec![[0, 1, 2], [0, 2, 3]];
+        let mesh = Object::new_mesh(vertices.clone(), vec![], faces.clone());
+        let triangles = Object::new_group(
+            faces
+                .iter()
+                .map(|&[v1, v2, v3]| Object::new_triangle(vertices[v1], vertices[v2], vertices[v3]))
+                .collect(),
+        );
+        let pattern = Pattern::new_stripe(vec![Color::white(), Color::black()]);
+
+        for levels in 0..3 {
+            assert_eq!(
+                mesh.displace(&pattern, 0.5, levels),
+                triangles.displace(&pattern, 0.5, levels)
+            );
+        }
+
+        // Each level splits the two triangles into smaller ones.
+        assert_eq!(mesh.displace(&pattern, 0.5, 0).flatten().len(), 2);
+        assert_eq!(mesh.displace(&pattern, 0.5, 1).flatten().len(), 12);
+    }
+
+    #[test]
+    fn subdividing_an_indexed_mesh_is_like_subdividing_its_triangles() {
+        let vertices = vec![
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(0.0, 0.0, 1.0),
+            Point::new(1.0, 0.0, 0.0),
+        ];
+        let faces = vec![[0, 1, 2], [0, 2, 3], [1, 3, 2]];
+        let mesh = Object::new_mesh(vertices.clone(), vec![], faces.clone());
+        let triangles = Object::new_group(
+            faces
+                .iter()
+                .map(|&[v1, v2, v3]| Object::new_triangle(vertices[v1], vertices[v2], vertices[v3]))
+                .collect(),
+        );
+
+        for smooth_normals in [false, true] {
+            let subdivided = mesh.subdivide_surface(2, smooth_normals);
+            assert_eq!(subdivided, triangles.subdivide_surface(2, smooth_normals));
+            assert!(subdivided.flatten().len() > faces.len());
+        }
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
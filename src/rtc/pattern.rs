@@ -3,9 +3,22 @@
 use crate::{
     float::ApproxEq,
     primitive::{Matrix, Point, Tuple},
-    rtc::{Color, Object, Transform},
+    rtc::{
+        noise::{noise, turbulence},
+        Color, Object, Transform,
+    },
 };
 use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// How far, in rings, the noise moves the rings of wood.
+const WOOD_DISTORTION: f64 = 0.15;
+
+// How far, in radians of the sine, the turbulence moves the veins of marble.
+const MARBLE_DISTORTION: f64 = 5.0;
+const MARBLE_OCTAVES: usize = 4;
 
 /* ---------------------------------------------------------------------------------------------- */
 
@@ -19,6 +32,18 @@ pub struct Pattern {
 /* ---------------------------------------------------------------------------------------------- */
 
 impl Pattern {
+    // Mix of two patterns, from only lhs at weight 0 to only rhs at weight 1.
+    pub fn new_blend(lhs: Pattern, rhs: Pattern, weight: f64) -> Self {
+        Pattern {
+            pattern: Patterns::Blend(BlendPattern {
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+                weight,
+            }),
+            ..Default::default()
+        }
+    }
+
     pub fn new_checker(c1: Color, c2: Color) -> Self {
         Pattern {
             pattern: Patterns::Checker(CheckerPattern { c1, c2 }),
@@ -26,6 +51,30 @@ impl Pattern {
         }
     }
 
+    // Checker whose cells are separated by grout lines of the given width and color.
+    pub fn new_checker_with_border(c1: Color, c2: Color, width: f64, border: Color) -> Self {
+        Pattern {
+            pattern: Patterns::BorderedChecker(BorderedCheckerPattern {
+                checker: CheckerPattern { c1, c2 },
+                width,
+                border,
+            }),
+            ..Default::default()
+        }
+    }
+
+    // Without any stop, the color map falls back to a plain black pattern.
+    pub fn new_colormap(stops: Vec<(f64, Color)>) -> Self {
+        if stops.is_empty() {
+            return Pattern::new_plain(Color::black());
+        }
+
+        Pattern {
+            pattern: Patterns::ColorMap(ColorMapPattern::new(stops)),
+            ..Default::default()
+        }
+    }
+
     pub fn new_gradient(from: Color, to: Color) -> Self {
         Pattern {
             pattern: Patterns::Gradient(GradientPattern { from, to }),
@@ -33,6 +82,14 @@ impl Pattern {
         }
     }
 
+    // Veins along x, from c1 to c2, with the given number of veins per unit.
+    pub fn new_marble(c1: Color, c2: Color, veins: f64) -> Self {
+        Pattern {
+            pattern: Patterns::Marble(MarblePattern { c1, c2, veins }),
+            ..Default::default()
+        }
+    }
+
     pub fn new_plain(color: Color) -> Self {
         Pattern {
             pattern: Patterns::Plain(PlainPattern { color }),
@@ -53,6 +110,25 @@ impl Pattern {
             ..Default::default()
         }
     }
+
+    // Rings around the y axis, going from c1 to c2 and back, with the given number of rings per
+    // unit.
+    pub fn new_wood(c1: Color, c2: Color, rings: f64) -> Self {
+        Pattern {
+            pattern: Patterns::Wood(WoodPattern { c1, c2, rings }),
+            ..Default::default()
+        }
+    }
+
+    // Red is u and green is v, to check the texture coordinates of objects. Objects without
+    // texture coordinates are black.
+    pub fn new_uv_debug() -> Self {
+        Pattern {
+            pattern: Patterns::UvDebug(UvDebugPattern {}),
+            ..Default::default()
+        }
+    }
+
     pub fn new_test() -> Self {
         Pattern {
             pattern: Patterns::Test(TestPattern {}),
@@ -62,16 +138,38 @@ impl Pattern {
 
     fn pattern_at(&self, point: &Point) -> Color {
         match &self.pattern {
+            Patterns::Blend(p) => p.pattern_at(point),
+            Patterns::BorderedChecker(p) => p.pattern_at(point),
             Patterns::Checker(p) => p.pattern_at(point),
+            Patterns::ColorMap(p) => p.pattern_at(point),
             Patterns::Gradient(p) => p.pattern_at(point),
+            Patterns::Marble(p) => p.pattern_at(point),
             Patterns::Plain(p) => p.pattern_at(point),
             Patterns::Ring(p) => p.pattern_at(point),
             Patterns::Stripe(p) => p.pattern_at(point),
             Patterns::Test(p) => p.pattern_at(point),
+            Patterns::Wood(p) => p.pattern_at(point),
+            // Texture coordinates don't depend on the point in pattern space.
+            Patterns::UvDebug(_) => Color::black(),
         }
     }
 
+    // For patterns which aren't on an object.
+    pub fn pattern_at_point(&self, point: &Point) -> Color {
+        self.pattern_at(&(self.transformation_inverse * *point))
+    }
+
+    pub(crate) fn is_plain(&self) -> bool {
+        matches!(self.pattern, Patterns::Plain(_))
+    }
+
     pub fn pattern_at_object(&self, object: &Object, world_point: &Point) -> Color {
+        if let Patterns::UvDebug(p) = &self.pattern {
+            return object
+                .uv_at_point(world_point)
+                .map_or_else(Color::black, |(u, v)| p.pattern_at_uv(u, v));
+        }
+
         let object_transformation_inv = object.transformation_inverse();
         let object_point = *object_transformation_inv * *world_point;
 
@@ -113,12 +211,41 @@ impl Transform for Pattern {
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 enum Patterns {
+    Blend(BlendPattern),
+    BorderedChecker(BorderedCheckerPattern),
     Checker(CheckerPattern),
+    ColorMap(ColorMapPattern),
     Gradient(GradientPattern),
+    Marble(MarblePattern),
     Plain(PlainPattern),
     Ring(RingPattern),
     Stripe(StripePattern),
     Test(TestPattern),
+    UvDebug(UvDebugPattern),
+    Wood(WoodPattern),
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BlendPattern {
+    lhs: Box<Pattern>,
+    rhs: Box<Pattern>,
+    weight: f64,
+}
+
+impl BlendPattern {
+    // Each blended pattern keeps its own transformation.
+    fn pattern_at(&self, point: &Point) -> Color {
+        let lhs = self
+            .lhs
+            .pattern_at(&(self.lhs.transformation_inverse * *point));
+        let rhs = self
+            .rhs
+            .pattern_at(&(self.rhs.transformation_inverse * *point));
+
+        lhs + self.weight * (rhs - lhs)
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -142,6 +269,69 @@ impl CheckerPattern {
 
 /* ---------------------------------------------------------------------------------------------- */
 
+// Grout lines follow the cell boundaries along x and z only, as for a tiled floor: points of a
+// plane at y = 0 all lie on a boundary along y.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BorderedCheckerPattern {
+    checker: CheckerPattern,
+    width: f64,
+    border: Color,
+}
+
+impl BorderedCheckerPattern {
+    fn pattern_at(&self, point: &Point) -> Color {
+        let distance_to_boundary = |x: f64| {
+            let fract = x - x.floor();
+            fract.min(1.0 - fract)
+        };
+        let distance = distance_to_boundary(point.x()).min(distance_to_boundary(point.z()));
+
+        if distance < self.width / 2.0 {
+            self.border
+        } else {
+            self.checker.pattern_at(point)
+        }
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Piecewise-linear color ramp of the x coordinate, clamped to the first and last stops.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ColorMapPattern {
+    stops: Vec<(f64, Color)>,
+}
+
+impl ColorMapPattern {
+    fn new(mut stops: Vec<(f64, Color)>) -> Self {
+        stops.sort_by(|(lhs, _), (rhs, _)| lhs.total_cmp(rhs));
+
+        ColorMapPattern { stops }
+    }
+
+    pub fn color_at(&self, value: f64) -> Color {
+        let upper = self.stops.iter().position(|(stop, _)| value < *stop);
+
+        match upper {
+            Some(0) => self.stops[0].1,
+            None => self.stops[self.stops.len() - 1].1,
+            Some(upper) => {
+                let (from_value, from) = self.stops[upper - 1];
+                let (to_value, to) = self.stops[upper];
+                let t = (value - from_value) / (to_value - from_value);
+
+                from + t * (to - from)
+            }
+        }
+    }
+
+    fn pattern_at(&self, point: &Point) -> Color {
+        self.color_at(point.x())
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct GradientPattern {
     from: Color,
@@ -156,6 +346,25 @@ impl GradientPattern {
 
 /* ---------------------------------------------------------------------------------------------- */
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MarblePattern {
+    c1: Color,
+    c2: Color,
+    veins: f64,
+}
+
+impl MarblePattern {
+    fn pattern_at(&self, point: &Point) -> Color {
+        let phase = 2.0 * PI * point.x() * self.veins
+            + MARBLE_DISTORTION * turbulence(point, MARBLE_OCTAVES);
+        let t = 0.5 + 0.5 * phase.sin();
+
+        self.c1 + t * (self.c2 - self.c1)
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct PlainPattern {
     color: Color,
@@ -212,11 +421,42 @@ impl TestPattern {
 
 /* ---------------------------------------------------------------------------------------------- */
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UvDebugPattern {}
+
+impl UvDebugPattern {
+    fn pattern_at_uv(&self, u: f64, v: f64) -> Color {
+        Color::new(u, v, 0.0)
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WoodPattern {
+    c1: Color,
+    c2: Color,
+    rings: f64,
+}
+
+impl WoodPattern {
+    fn pattern_at(&self, point: &Point) -> Color {
+        let distance = (point.x() * point.x() + point.z() * point.z()).sqrt();
+        let rings = distance * self.rings + WOOD_DISTORTION * noise(point);
+        let t = 0.5 - 0.5 * (2.0 * PI * rings).cos();
+
+        self.c1 + t * (self.c2 - self.c1)
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
 #[cfg(test)]
 mod tests {
     use std::vec;
 
     use super::*;
+    use crate::primitive::Vector;
 
     #[test]
     fn a_stripe_pattern_is_constant_in_y_and_z() {
@@ -335,6 +575,96 @@ mod tests {
         );
     }
 
+    #[test]
+    fn a_color_map_returns_the_exact_color_of_its_stops() {
+        let pattern = Pattern::new_colormap(vec![
+            (1.0, Color::red()),
+            (-1.0, Color::blue()),
+            (0.0, Color::green()),
+        ]);
+
+        assert_eq!(
+            pattern.pattern_at(&Point::new(-1.0, 0.0, 0.0)),
+            Color::blue()
+        );
+        assert_eq!(
+            pattern.pattern_at(&Point::new(0.0, 5.0, 0.0)),
+            Color::green()
+        );
+        assert_eq!(pattern.pattern_at(&Point::new(1.0, 0.0, 3.0)), Color::red());
+
+        // Values outside of the stops are clamped.
+        assert_eq!(
+            pattern.pattern_at(&Point::new(-3.0, 0.0, 0.0)),
+            Color::blue()
+        );
+        assert_eq!(pattern.pattern_at(&Point::new(3.0, 0.0, 0.0)), Color::red());
+    }
+
+    #[test]
+    fn a_color_map_interpolates_between_two_stops() {
+        let pattern = ColorMapPattern::new(vec![
+            (0.0, Color::black()),
+            (2.0, Color::new(1.0, 0.5, 0.0)),
+            (4.0, Color::white()),
+        ]);
+
+        assert_eq!(pattern.color_at(1.0), Color::new(0.5, 0.25, 0.0));
+        assert_eq!(pattern.color_at(3.5), Color::new(1.0, 0.875, 0.75));
+    }
+
+    #[test]
+    fn a_color_map_without_stops_is_plain_black() {
+        let pattern = Pattern::new_colormap(vec![]);
+
+        assert_eq!(pattern, Pattern::new_plain(Color::black()));
+        assert_eq!(
+            pattern.pattern_at(&Point::new(1.0, 0.0, 0.0)),
+            Color::black()
+        );
+    }
+
+    #[test]
+    fn a_bordered_checker_has_grout_near_cell_boundaries() {
+        let grout = Color::new(0.5, 0.5, 0.5);
+        let pattern = Pattern::new_checker_with_border(Color::white(), Color::black(), 0.1, grout);
+
+        assert_eq!(pattern.pattern_at(&Point::new(0.99, 0.0, 0.5)), grout);
+        assert_eq!(pattern.pattern_at(&Point::new(1.01, 0.0, 0.5)), grout);
+        assert_eq!(pattern.pattern_at(&Point::new(0.5, 0.0, -0.02)), grout);
+        assert_eq!(
+            pattern.pattern_at(&Point::new(0.94, 0.0, 0.5)),
+            Color::white()
+        );
+    }
+
+    #[test]
+    fn a_bordered_checker_has_tile_colors_at_cell_centers() {
+        let pattern = Pattern::new_checker_with_border(
+            Color::white(),
+            Color::black(),
+            0.1,
+            Color::new(0.5, 0.5, 0.5),
+        );
+
+        assert_eq!(
+            pattern.pattern_at(&Point::new(0.5, 0.0, 0.5)),
+            Color::white()
+        );
+        assert_eq!(
+            pattern.pattern_at(&Point::new(1.5, 0.0, 0.5)),
+            Color::black()
+        );
+        assert_eq!(
+            pattern.pattern_at(&Point::new(-0.5, 0.0, 0.5)),
+            Color::black()
+        );
+        assert_eq!(
+            pattern.pattern_at(&Point::new(1.5, 0.0, 1.5)),
+            Color::white()
+        );
+    }
+
     #[test]
     fn a_ring_should_extend_in_both_x_and_z() {
         let pattern = Pattern::new_ring(vec![Color::white(), Color::black()]);
@@ -356,6 +686,105 @@ mod tests {
             Color::black()
         );
     }
+
+    #[test]
+    fn the_uv_debug_pattern_shows_texture_coordinates() {
+        let p = UvDebugPattern {};
+        assert_eq!(p.pattern_at_uv(0.0, 0.0), Color::black());
+        assert_eq!(p.pattern_at_uv(1.0, 0.0), Color::red());
+        assert_eq!(p.pattern_at_uv(0.0, 1.0), Color::green());
+        assert_eq!(p.pattern_at_uv(1.0, 1.0), Color::new(1.0, 1.0, 0.0));
+
+        let pattern = Pattern::new_uv_debug();
+        let triangle = Object::new_triangle(
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(0.0, 1.0, 0.0),
+        )
+        .generate_planar_uv(crate::rtc::Axis::Z)
+        .translate(2.0, 3.0, 4.0)
+        .transform();
+
+        let tests = vec![
+            (Point::new(2.0, 3.0, 4.0), Color::black()),
+            (Point::new(3.0, 3.0, 4.0), Color::red()),
+            (Point::new(2.0, 4.0, 4.0), Color::green()),
+            (Point::new(2.5, 3.5, 4.0), Color::new(0.5, 0.5, 0.0)),
+            (Point::new(2.25, 3.5, 4.0), Color::new(0.25, 0.5, 0.0)),
+        ];
+
+        for (point, color) in tests.into_iter() {
+            assert_eq!(pattern.pattern_at_object(&triangle, &point), color);
+        }
+
+        // No texture coordinates.
+        assert_eq!(
+            pattern.pattern_at_object(&Object::new_sphere(), &Point::new(1.0, 0.0, 0.0)),
+            Color::black()
+        );
+    }
+
+    #[test]
+    fn wood_alternates_along_the_radius() {
+        let pattern = Pattern::new_wood(Color::black(), Color::white(), 2.0);
+        let at = |distance: f64| {
+            // Off the axes, so rings are distorted.
+            let direction = Vector::new(0.6, 0.0, 0.8);
+            let point = Point::new(0.0, 0.3, 0.0) + direction * distance;
+            pattern.pattern_at(&point).r
+        };
+
+        for ring in 1..10 {
+            let distance = ring as f64 / 2.0;
+            assert!(at(distance) < 0.25);
+            assert!(at(distance + 0.25) > 0.75);
+        }
+
+        // Two transitions per ring.
+        let transitions = (0..1000)
+            .map(|i| at(i as f64 * 0.005) > 0.5)
+            .collect::<Vec<_>>()
+            .windows(2)
+            .filter(|w| w[0] != w[1])
+            .count();
+        assert_eq!(transitions, 20);
+    }
+
+    #[test]
+    fn wood_rings_are_distorted_by_noise() {
+        let pattern = Pattern::new_wood(Color::black(), Color::white(), 2.0);
+
+        // Same distance to the axis, different colors.
+        let colors = (0..100)
+            .map(|i| {
+                let angle = i as f64 * PI / 50.0;
+                let point = Point::new(1.3 * angle.cos(), 0.7, 1.3 * angle.sin());
+                pattern.pattern_at(&point).r
+            })
+            .collect::<Vec<_>>();
+        let min = colors.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = colors.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        assert!(max - min > 0.1);
+    }
+
+    #[test]
+    fn marble_varies_smoothly_along_x() {
+        let pattern = Pattern::new_marble(Color::black(), Color::white(), 1.0);
+        let at = |x: f64, y: f64| pattern.pattern_at(&Point::new(x, y, 0.3)).r;
+
+        let mut max_distortion: f64 = 0.0;
+        for i in 0..2000 {
+            let x = i as f64 * 0.002;
+            assert!((0.0..=1.0).contains(&at(x, 0.5)));
+            assert!((at(x, 0.5) - at(x + 0.002, 0.5)).abs() < 0.1);
+
+            let undistorted = 0.5 + 0.5 * (2.0 * PI * x).sin();
+            max_distortion = max_distortion.max((at(x, 0.5) - undistorted).abs());
+        }
+        // Veins are not straight: they depend on the noise, thus on y.
+        assert!(max_distortion > 0.2);
+        assert!((0..100).any(|i| (at(i as f64 * 0.1, 0.5) - at(i as f64 * 0.1, 2.5)).abs() > 0.2));
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -5,6 +5,7 @@ use crate::{
     rtc::{Intersection, IntersectionPusher, Intersections, Object, Transform},
 };
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 /* ---------------------------------------------------------------------------------------------- */
 
@@ -31,6 +32,14 @@ impl<'a> IntersectionPusher<'a> for RayIntersectionPusher<'a> {
             .push(Intersection::new(t, self.object).with_u_and_v(u, v));
     }
 
+    fn t_u_v_face(&mut self, t: f64, u: f64, v: f64, face: usize) {
+        self.intersections.push(
+            Intersection::new(t, self.object)
+                .with_u_and_v(u, v)
+                .with_face(face),
+        );
+    }
+
     fn set_object(&mut self, object: &'a Object) {
         self.object = object;
     }
@@ -61,6 +70,54 @@ impl Ray {
             })
             .sort()
     }
+
+    // Hits of many rays at once, in the order of the rays. Rays are processed one after the other
+    // for now, sharing the same buffer of intersections, but a vectorized backend could process
+    // them together.
+    pub fn intersects_batch<'a>(
+        rays: &[Ray],
+        objects: &'a [Object],
+    ) -> Vec<Option<Intersection<'a>>> {
+        let mut intersections = Intersections::new();
+
+        rays.iter()
+            .map(|ray| {
+                intersections.clear();
+                intersections = ray.intersects(objects, std::mem::take(&mut intersections));
+
+                intersections.hit().cloned()
+            })
+            .collect()
+    }
+
+    // Same as intersects(), but also adds to costs[i] the number of intersections found with
+    // objects[i].
+    pub fn intersects_and_count<'a>(
+        &self,
+        objects: &'a [Object],
+        intersections: Intersections<'a>,
+        costs: &[AtomicUsize],
+    ) -> Intersections<'a> {
+        objects
+            .iter()
+            .zip(costs)
+            .fold(intersections, |acc, (object, cost)| {
+                let nb_before = acc.len();
+                let mut pusher = RayIntersectionPusher {
+                    intersections: acc,
+                    object,
+                };
+                object.intersects(self, &mut pusher);
+
+                let nb = pusher.intersections.len() - nb_before;
+                if nb > 0 {
+                    cost.fetch_add(nb, Ordering::Relaxed);
+                }
+
+                pusher.intersections
+            })
+            .sort()
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -119,6 +176,38 @@ mod tests {
         assert_eq!(r1.origin, Point::new(2.0, 6.0, 12.0));
         assert_eq!(r1.direction, Vector::new(0.0, 3.0, 0.0));
     }
+
+    #[test]
+    fn batches_of_rays_hit_like_single_rays() {
+        let objects = vec![
+            Object::new_sphere(),
+            Object::new_plane().translate(0.0, -1.0, 0.0).transform(),
+            Object::new_cube().translate(3.0, 0.0, 0.0).transform(),
+        ];
+
+        let rays = [
+            (Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0)),
+            (Point::new(3.0, 0.5, -5.0), Vector::new(0.0, 0.0, 1.0)),
+            (Point::new(0.0, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0)),
+            (Point::new(0.0, 5.0, -5.0), Vector::new(0.0, 1.0, 0.0)),
+            (Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0)),
+            (Point::new(-10.0, 0.0, -5.0), Vector::new(1.0, -0.2, 0.5)),
+        ]
+        .iter()
+        .map(|&(origin, direction)| Ray { origin, direction })
+        .collect::<Vec<_>>();
+
+        let batch = Ray::intersects_batch(&rays, &objects);
+        assert_eq!(batch.len(), rays.len());
+
+        for (ray, hit) in rays.iter().zip(&batch) {
+            let xs = ray.intersects(&objects, Intersections::new());
+            assert_eq!(hit.as_ref(), xs.hit());
+        }
+
+        assert!(batch[3].is_none());
+        assert!(Ray::intersects_batch(&[], &objects).is_empty());
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
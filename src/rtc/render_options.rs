@@ -0,0 +1,128 @@
+/* ---------------------------------------------------------------------------------------------- */
+
+use crate::float::EPSILON;
+use serde::{Deserialize, Serialize};
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RenderOptions {
+    min_throughput: f64,
+    shadows: bool,
+    dedup_intersections: bool,
+    caustics: f64,
+    epsilon: f64,
+    debug_non_finite: bool,
+    shadow_softness: f64,
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+impl RenderOptions {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    // Secondary rays whose contribution to the final color would fall below this threshold are
+    // not traced.
+    pub fn with_min_throughput(mut self, min_throughput: f64) -> Self {
+        self.min_throughput = min_throughput.max(0.0);
+
+        self
+    }
+
+    pub fn min_throughput(&self) -> f64 {
+        self.min_throughput
+    }
+
+    // Without shadows, every light is considered to fully reach every point.
+    pub fn with_shadows(mut self, shadows: bool) -> Self {
+        self.shadows = shadows;
+
+        self
+    }
+
+    pub fn shadows(&self) -> bool {
+        self.shadows
+    }
+
+    // Collapse numerically coincident intersections with the same object, which would otherwise
+    // confuse the refractive indices computation.
+    pub fn with_dedup_intersections(mut self, dedup_intersections: bool) -> Self {
+        self.dedup_intersections = dedup_intersections;
+
+        self
+    }
+
+    pub fn dedup_intersections(&self) -> bool {
+        self.dedup_intersections
+    }
+
+    // Strength of the bright spots added behind transparent objects focusing the light. This is
+    // an artistic approximation, not physically based.
+    pub fn with_caustics(mut self, strength: f64) -> Self {
+        self.caustics = strength.max(0.0);
+
+        self
+    }
+
+    pub fn caustics(&self) -> f64 {
+        self.caustics
+    }
+
+    // Offset of the points from which secondary rays are cast off a surface. It should follow
+    // the scale of the scene: too large and shadows detach from tiny objects, too small and huge
+    // scenes show acne.
+    pub fn with_epsilon(mut self, epsilon: f64) -> Self {
+        self.epsilon = epsilon.abs();
+
+        self
+    }
+
+    pub fn epsilon(&self) -> f64 {
+        self.epsilon
+    }
+
+    // Colors with a NaN or infinite component, which come from shading bugs, are rendered in
+    // magenta to stand out. Otherwise, they are rendered black.
+    pub fn with_debug_non_finite(mut self, debug_non_finite: bool) -> Self {
+        self.debug_non_finite = debug_non_finite;
+
+        self
+    }
+
+    pub fn debug_non_finite(&self) -> bool {
+        self.debug_non_finite
+    }
+
+    // Scales the penumbrae of all area lights at once, on top of their own size: shadow rays
+    // head to the light shrunk, or enlarged, around its center by this factor. At 0, area lights
+    // cast hard shadows with a single ray, for fast previews.
+    pub fn with_shadow_softness(mut self, softness: f64) -> Self {
+        self.shadow_softness = softness.max(0.0);
+
+        self
+    }
+
+    pub fn shadow_softness(&self) -> f64 {
+        self.shadow_softness
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            min_throughput: 0.0,
+            shadows: true,
+            dedup_intersections: false,
+            caustics: 0.0,
+            epsilon: EPSILON,
+            debug_non_finite: false,
+            shadow_softness: 1.0,
+        }
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
@@ -0,0 +1,182 @@
+/* ---------------------------------------------------------------------------------------------- */
+
+use crate::{
+    primitive::Matrix,
+    rtc::{Camera, Light, Material, Object, World},
+};
+use std::{error::Error, fmt};
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[derive(Debug, PartialEq)]
+pub enum SceneError {
+    // The name of the method called before any object was added.
+    NoObject(&'static str),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::NoObject(method) => {
+                write!(f, "{} called before any object was added", method)
+            }
+        }
+    }
+}
+
+impl Error for SceneError {}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[derive(Debug, Default)]
+pub struct SceneBuilder {
+    objects: Vec<Object>,
+    lights: Vec<Light>,
+    camera: Camera,
+    // First misuse of the builder, reported when building the scene.
+    error: Option<SceneError>,
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+impl SceneBuilder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn add_object(mut self, object: Object) -> Self {
+        self.objects.push(object);
+
+        self
+    }
+
+    pub fn add_cube(self) -> Self {
+        self.add_object(Object::new_cube())
+    }
+
+    pub fn add_plane(self) -> Self {
+        self.add_object(Object::new_plane())
+    }
+
+    pub fn add_sphere(self) -> Self {
+        self.add_object(Object::new_sphere())
+    }
+
+    // Applies to the last added object.
+    pub fn with_material(mut self, material: Material) -> Self {
+        if let Some(object) = self.last_object("with_material") {
+            *object = std::mem::take(object).with_material(material);
+        }
+
+        self
+    }
+
+    // Applies to the last added object.
+    pub fn with_transformation(mut self, transformation: Matrix) -> Self {
+        if let Some(object) = self.last_object("with_transformation") {
+            *object = std::mem::take(object).with_transformation(transformation);
+        }
+
+        self
+    }
+
+    pub fn light(mut self, light: Light) -> Self {
+        self.lights.push(light);
+
+        self
+    }
+
+    pub fn camera(mut self, camera: Camera) -> Self {
+        self.camera = camera;
+
+        self
+    }
+
+    pub fn build(self) -> Result<(World, Camera), SceneError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        Ok((
+            World::new()
+                .with_objects(self.objects)
+                .with_lights(self.lights),
+            self.camera,
+        ))
+    }
+
+    // Records an error when there is no object yet.
+    fn last_object(&mut self, method: &'static str) -> Option<&mut Object> {
+        if self.objects.is_empty() && self.error.is_none() {
+            self.error = Some(SceneError::NoObject(method));
+        }
+
+        self.objects.last_mut()
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        primitive::{Point, Tuple, Vector},
+        rtc::{scaling, view_transform, Color, Pattern, Transform},
+    };
+    use std::f64::consts::PI;
+
+    #[test]
+    fn building_a_scene_is_equivalent_to_a_manual_construction() {
+        let material = Material::new()
+            .with_pattern(Pattern::new_plain(Color::new(0.8, 1.0, 0.6)))
+            .with_diffuse(0.7)
+            .with_specular(0.2);
+        let light = Light::new_point_light(Color::white(), Point::new(-10.0, 10.0, -10.0));
+        let camera = Camera::new()
+            .with_size(20, 20)
+            .with_fov(PI / 2.0)
+            .with_transformation(&view_transform(
+                &Point::new(0.0, 0.0, -5.0),
+                &Point::zero(),
+                &Vector::new(0.0, 1.0, 0.0),
+            ));
+
+        let (world, camera) = SceneBuilder::new()
+            .add_sphere()
+            .with_material(material.clone())
+            .add_sphere()
+            .with_transformation(scaling(0.5, 0.5, 0.5))
+            .light(light.clone())
+            .camera(camera)
+            .build()
+            .unwrap();
+
+        let manual_world = World::new()
+            .with_objects(vec![
+                Object::new_sphere().with_material(material),
+                Object::new_sphere().scale(0.5, 0.5, 0.5).transform(),
+            ])
+            .with_lights(vec![light]);
+
+        assert_eq!(world.objects().len(), 2);
+        assert_eq!(world.lights().len(), 1);
+        assert_eq!(
+            camera.sequential_render(&world, None),
+            camera.sequential_render(&manual_world, None)
+        );
+    }
+
+    #[test]
+    fn modifying_an_object_before_adding_one_is_an_error() {
+        let result = SceneBuilder::new()
+            .with_material(Material::new())
+            .add_sphere()
+            .with_transformation(scaling(2.0, 2.0, 2.0))
+            .build();
+
+        assert_eq!(result.unwrap_err(), SceneError::NoObject("with_material"));
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
@@ -0,0 +1,14 @@
+/* ---------------------------------------------------------------------------------------------- */
+
+use crate::rtc::{Color, IntersectionState, World};
+use std::fmt::Debug;
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Replaces the built-in Phong shading (with reflections and refractions) of a World. Secondary
+// rays are traced with World::color_at_with_depth.
+pub trait Shader: Debug + Send + Sync {
+    fn shade(&self, world: &World, comps: &IntersectionState, remaining_recursions: u8) -> Color;
+}
+
+/* ---------------------------------------------------------------------------------------------- */
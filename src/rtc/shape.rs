@@ -3,25 +3,38 @@
 use crate::{
     primitive::{Point, Vector},
     rtc::{
-        shapes::{Cone, Cube, Cylinder, Group, Plane, SmoothTriangle, Sphere, TestShape, Triangle},
+        shapes::{
+            Capsule, Clip, Cone, Csg, Cube, Cylinder, Disk, Group, HeightField, Lod, Mesh,
+            MetaBall, Plane, SmoothTriangle, Sphere, TestShape, Torus, Triangle,
+        },
         BoundingBox, Intersection, IntersectionPusher, Ray,
     },
 };
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 
 /* ---------------------------------------------------------------------------------------------- */
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Shape {
+    Capsule(Capsule),
+    Clip(Clip),
     Cone(Cone),
+    Csg(Csg),
     Cube(),
     Dummy(), // Does not exist on its own
     Cylinder(Cylinder),
+    Disk(Disk),
     Group(Group),
+    HeightField(HeightField),
+    Lod(Lod),
+    Mesh(Mesh),
+    MetaBall(MetaBall),
     Plane(),
     SmoothTriangle(SmoothTriangle),
     Sphere(),
     TestShape(TestShape),
+    Torus(Torus),
     Triangle(Triangle),
 }
 
@@ -30,52 +43,108 @@ pub enum Shape {
 impl Shape {
     pub fn intersects<'a>(&'a self, ray: &Ray, push: &mut impl IntersectionPusher<'a>) {
         match self {
+            Shape::Capsule(c) => c.intersects(ray, push),
+            Shape::Clip(c) => c.intersects(ray, push),
             Shape::Cone(c) => c.intersects(ray, push),
+            Shape::Csg(c) => c.intersects(ray, push),
             Shape::Cube() => Cube::intersects(ray, push),
             Shape::Cylinder(c) => c.intersects(ray, push),
+            Shape::Disk(d) => d.intersects(ray, push),
             Shape::Dummy() => unreachable!("Dummy::intersects() should never be called"),
             Shape::Group(g) => g.intersects(ray, push),
+            Shape::HeightField(h) => h.intersects(ray, push),
+            Shape::Lod(l) => l.intersects(ray, push),
+            Shape::Mesh(m) => m.intersects(ray, push),
+            Shape::MetaBall(m) => m.intersects(ray, push),
             Shape::Plane() => Plane::intersects(ray, push),
             Shape::SmoothTriangle(t) => t.intersects(ray, push),
             Shape::Sphere() => Sphere::intersects(ray, push),
             Shape::TestShape(t) => t.intersects(ray, push),
+            Shape::Torus(t) => t.intersects(ray, push),
             Shape::Triangle(t) => t.intersects(ray, push),
         }
     }
 
     pub fn normal_at(&self, object_point: &Point, hit: &Intersection) -> Vector {
         match self {
+            Shape::Capsule(c) => c.normal_at(object_point),
+            Shape::Clip(c) => c.normal_at(object_point),
             Shape::Cone(c) => c.normal_at(object_point),
+            Shape::Csg(c) => c.normal_at(object_point),
             Shape::Cube() => Cube::normal_at(object_point),
             Shape::Cylinder(c) => c.normal_at(object_point),
+            Shape::Disk(d) => d.normal_at(object_point),
             Shape::Dummy() => unreachable!("Dummy::normal_at() should never be called"),
             Shape::Group(g) => g.normal_at(object_point),
+            Shape::HeightField(h) => h.normal_at(object_point),
+            Shape::Lod(l) => l.normal_at(object_point),
+            Shape::Mesh(m) => m.normal_at(object_point, hit),
+            Shape::MetaBall(m) => m.normal_at(object_point),
             Shape::Plane() => Plane::normal_at(object_point),
             Shape::SmoothTriangle(t) => t.normal_at(object_point, hit),
             Shape::Sphere() => Sphere::normal_at(object_point),
             Shape::TestShape(t) => t.normal_at(object_point),
+            Shape::Torus(t) => t.normal_at(object_point),
             Shape::Triangle(t) => t.normal_at(object_point),
         }
     }
 
     pub fn bounds(&self) -> BoundingBox {
         match self {
+            Shape::Capsule(c) => c.bounds(),
+            Shape::Clip(c) => c.bounds(),
             Shape::Cone(c) => c.bounds(),
+            Shape::Csg(c) => c.bounds(),
             Shape::Cube() => Cube::bounds(),
             Shape::Cylinder(c) => c.bounds(),
+            Shape::Disk(d) => d.bounds(),
             Shape::Dummy() => BoundingBox::new(),
             Shape::Group(g) => g.bounds(),
+            Shape::HeightField(h) => h.bounds(),
+            Shape::Lod(l) => l.bounds(),
+            Shape::Mesh(m) => m.bounds(),
+            Shape::MetaBall(m) => m.bounds(),
             Shape::Plane() => Plane::bounds(),
             Shape::SmoothTriangle(t) => t.bounds(),
             Shape::Sphere() => Sphere::bounds(),
             Shape::TestShape(t) => t.bounds(),
+            Shape::Torus(t) => t.bounds(),
             Shape::Triangle(t) => t.bounds(),
         }
     }
 
+    // Returns a point uniformly distributed on the surface along with its normal, in object
+    // space. Infinite and composite shapes can't be sampled.
+    pub fn sample_surface(&self, rng: &mut impl Rng) -> Option<(Point, Vector)> {
+        match self {
+            Shape::Capsule(c) => Some(c.sample_surface(rng)),
+            Shape::Clip(_) => None,
+            Shape::Cone(c) => c.sample_surface(rng),
+            Shape::Csg(_) => None,
+            Shape::Cube() => Some(Cube::sample_surface(rng)),
+            Shape::Cylinder(c) => c.sample_surface(rng),
+            Shape::Disk(d) => Some(d.sample_surface(rng)),
+            Shape::Dummy() => unreachable!("Dummy::sample_surface() should never be called"),
+            Shape::Group(_) => None,
+            Shape::HeightField(_) => None,
+            Shape::Lod(_) => None,
+            Shape::Mesh(m) => m.sample_surface(rng),
+            Shape::MetaBall(_) => None,
+            Shape::Plane() => None,
+            Shape::SmoothTriangle(t) => Some(t.sample_surface(rng)),
+            Shape::Sphere() => Some(Sphere::sample_surface(rng)),
+            Shape::TestShape(_) => None,
+            Shape::Torus(t) => t.sample_surface(rng),
+            Shape::Triangle(t) => Some(t.sample_surface(rng)),
+        }
+    }
+
     pub fn divide(self, threshold: usize) -> Self {
         match self {
+            Shape::Clip(c) => Shape::Clip(c.divide(threshold)),
+            Shape::Csg(c) => Shape::Csg(c.divide(threshold)),
             Shape::Group(g) => Shape::Group(g.divide(threshold)),
+            Shape::Lod(l) => Shape::Lod(l.divide(threshold)),
             _ => self,
         }
     }
@@ -83,7 +152,12 @@ impl Shape {
     pub fn skip_world_to_local(&self) -> bool {
         // Skip world to local conversion for groups, since the transformation matrix
         // has been propagated to children at build time via GroupBuilder.
-        matches!(self, Shape::Group(_))
+        // Levels of detail, CSG and clips also propagate their transformations to their levels
+        // and operands.
+        matches!(
+            self,
+            Shape::Clip(_) | Shape::Csg(_) | Shape::Group(_) | Shape::Lod(_)
+        )
     }
 
     pub fn as_group(&self) -> Option<&Group> {
@@ -93,6 +167,13 @@ impl Shape {
         }
     }
 
+    pub fn as_mesh(&self) -> Option<&Mesh> {
+        match self {
+            Shape::Mesh(m) => Some(m),
+            _ => None,
+        }
+    }
+
     pub fn as_smooth_triangle(&self) -> Option<&SmoothTriangle> {
         match self {
             Shape::SmoothTriangle(t) => Some(t),
@@ -0,0 +1,224 @@
+/* ---------------------------------------------------------------------------------------------- */
+
+use crate::{
+    float::ApproxEq,
+    primitive::{Point, Tuple, Vector},
+    rtc::{shapes::Sphere, BoundingBox, IntersectionPusher, Ray},
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Cylinder of radius 1 around the y axis, from min to max, capped with hemispheres.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Capsule {
+    min: f64,
+    max: f64,
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+impl Capsule {
+    pub fn new(min: f64, max: f64) -> Self {
+        Capsule {
+            min: min.min(max),
+            max: min.max(max),
+        }
+    }
+
+    // The body and the caps only keep the hits on their part of the surface, so a ray entering
+    // and leaving the capsule always gets two intersections.
+    pub fn intersects<'a>(&self, ray: &Ray, push: &mut impl IntersectionPusher<'a>) {
+        let a = ray.direction.x().powi(2) + ray.direction.z().powi(2);
+
+        if !a.approx_eq(0.0) {
+            let b = 2.0 * (ray.origin.x() * ray.direction.x() + ray.origin.z() * ray.direction.z());
+            let c = ray.origin.x().powi(2) + ray.origin.z().powi(2) - 1.0;
+
+            for t in Capsule::solve(a, b, c).into_iter().flatten() {
+                let y = ray.origin.y() + t * ray.direction.y();
+                if self.min < y && y < self.max {
+                    push.t(t);
+                }
+            }
+        }
+
+        // The bottom cap is below min, the top one above max.
+        for (center, side) in [(self.min, -1.0), (self.max, 1.0)] {
+            let center_to_ray = ray.origin - Point::new(0.0, center, 0.0);
+            let a = ray.direction.dot(ray.direction);
+            let b = 2.0 * ray.direction.dot(center_to_ray);
+            let c = center_to_ray.dot(center_to_ray) - 1.0;
+
+            for t in Capsule::solve(a, b, c).into_iter().flatten() {
+                let y = ray.origin.y() + t * ray.direction.y();
+                if (y - center) * side >= 0.0 {
+                    push.t(t);
+                }
+            }
+        }
+    }
+
+    fn solve(a: f64, b: f64, c: f64) -> Option<[f64; 2]> {
+        let discriminant = b.powi(2) - 4.0 * a * c;
+
+        if discriminant < 0.0 {
+            None
+        } else {
+            let sqrt = discriminant.sqrt();
+            Some([(-b - sqrt) / (2.0 * a), (-b + sqrt) / (2.0 * a)])
+        }
+    }
+
+    pub fn normal_at(&self, object_point: &Point) -> Vector {
+        if object_point.y() > self.max {
+            *object_point - Point::new(0.0, self.max, 0.0)
+        } else if object_point.y() < self.min {
+            *object_point - Point::new(0.0, self.min, 0.0)
+        } else {
+            Vector::new(object_point.x(), 0.0, object_point.z())
+        }
+    }
+
+    pub fn bounds(&self) -> BoundingBox {
+        BoundingBox::new()
+            .with_min(Point::new(-1.0, self.min - 1.0, -1.0))
+            .with_max(Point::new(1.0, self.max + 1.0, 1.0))
+    }
+
+    // Both caps together form a sphere, split between the ends of the body.
+    pub fn sample_surface(&self, rng: &mut impl Rng) -> (Point, Vector) {
+        let side_area = 2.0 * PI * (self.max - self.min);
+        let caps_area = 4.0 * PI;
+
+        if rng.gen::<f64>() * (side_area + caps_area) < side_area {
+            let y = self.min + (self.max - self.min) * rng.gen::<f64>();
+            let theta = 2.0 * PI * rng.gen::<f64>();
+            let normal = Vector::new(theta.cos(), 0.0, theta.sin());
+
+            (Point::new(normal.x(), y, normal.z()), normal)
+        } else {
+            let (point, normal) = Sphere::sample_surface(rng);
+            let center = if normal.y() < 0.0 { self.min } else { self.max };
+
+            (point + Vector::new(0.0, center, 0.0), normal)
+        }
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtc::{IntersectionPusher, Object};
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    struct Push {
+        pub xs: Vec<f64>,
+    }
+
+    impl IntersectionPusher<'_> for Push {
+        fn t(&mut self, t: f64) {
+            self.xs.push(t);
+        }
+        fn t_u_v(&mut self, _t: f64, _u: f64, _v: f64) {
+            panic!();
+        }
+        fn set_object(&mut self, _object: &'_ Object) {
+            panic!();
+        }
+    }
+
+    fn intersects(capsule: &Capsule, origin: Point, direction: Vector) -> Vec<f64> {
+        let mut push = Push { xs: vec![] };
+        capsule.intersects(
+            &Ray {
+                origin,
+                direction: direction.normalize(),
+            },
+            &mut push,
+        );
+        push.xs.sort_by(|lhs, rhs| lhs.partial_cmp(rhs).unwrap());
+
+        push.xs
+    }
+
+    #[test]
+    fn a_ray_hits_the_body_of_a_capsule() {
+        let c = Capsule::new(-1.0, 1.0);
+
+        let xs = intersects(&c, Point::new(0.0, 0.5, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(xs.len(), 2);
+        assert!(xs[0].approx_eq(4.0));
+        assert!(xs[1].approx_eq(6.0));
+
+        assert_eq!(
+            c.normal_at(&Point::new(0.0, 0.5, -1.0)),
+            Vector::new(0.0, 0.0, -1.0)
+        );
+    }
+
+    #[test]
+    fn a_ray_hits_the_caps_of_a_capsule() {
+        let c = Capsule::new(-1.0, 1.0);
+
+        // Along the axis, through both caps.
+        let xs = intersects(&c, Point::new(0.0, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        assert_eq!(xs.len(), 2);
+        assert!(xs[0].approx_eq(3.0));
+        assert!(xs[1].approx_eq(7.0));
+
+        // Through the top cap only.
+        let xs = intersects(&c, Point::new(0.0, 1.5, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let half_chord = 0.75_f64.sqrt();
+        assert_eq!(xs.len(), 2);
+        assert!(xs[0].approx_eq(5.0 - half_chord));
+        assert!(xs[1].approx_eq(5.0 + half_chord));
+
+        assert_eq!(
+            c.normal_at(&Point::new(0.0, 2.0, 0.0)),
+            Vector::new(0.0, 1.0, 0.0)
+        );
+        assert_eq!(
+            c.normal_at(&Point::new(0.0, -1.5, -half_chord)),
+            Vector::new(0.0, -0.5, -half_chord)
+        );
+    }
+
+    #[test]
+    fn a_ray_misses_a_capsule() {
+        let c = Capsule::new(-1.0, 1.0);
+
+        // Beside the body.
+        assert!(intersects(&c, Point::new(1.5, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0)).is_empty());
+        // Within the bounding box, next to the rounded end.
+        assert!(intersects(&c, Point::new(0.9, 1.9, -5.0), Vector::new(0.0, 0.0, 1.0)).is_empty());
+        // Above the capsule.
+        assert!(intersects(&c, Point::new(0.0, 2.5, -5.0), Vector::new(0.0, 0.0, 1.0)).is_empty());
+    }
+
+    #[test]
+    fn the_bounds_of_a_capsule_include_its_caps() {
+        let b = Capsule::new(2.0, -1.0).bounds();
+
+        assert_eq!(b.min(), Point::new(-1.0, -2.0, -1.0));
+        assert_eq!(b.max(), Point::new(1.0, 3.0, 1.0));
+    }
+
+    #[test]
+    fn sampling_the_surface_of_a_capsule() {
+        let c = Capsule::new(-1.0, 1.0);
+        let mut rng = SmallRng::seed_from_u64(0);
+
+        for _ in 0..1000 {
+            let (point, normal) = c.sample_surface(&mut rng);
+            assert_eq!(c.normal_at(&point).normalize(), normal.normalize());
+            assert!(c.bounds().contains_point(&point));
+        }
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
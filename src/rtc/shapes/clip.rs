@@ -0,0 +1,300 @@
+/* ---------------------------------------------------------------------------------------------- */
+
+use crate::{
+    float::EPSILON,
+    primitive::{Matrix, Point, Tuple, Vector},
+    rtc::{
+        rotation_axis, scaling, translation, BoundingBox, IntersectionPusher, Object, Ray,
+        Transform,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Object cut by a plane, of which only the part on the side of the normal is kept. A capped clip
+// closes the cut with the plane, which is then expected to go through a closed object.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Clip {
+    object: Box<Object>,
+    point: Point,
+    normal: Vector,
+    cap: Option<Box<Object>>,
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+struct ClipHit<'a> {
+    t: f64,
+    u_v_face: Option<(f64, f64, usize)>,
+    object: &'a Object,
+}
+
+// Collects the intersections with the object before discarding the clipped ones.
+struct ClipPusher<'a> {
+    hits: Vec<ClipHit<'a>>,
+    object: &'a Object,
+}
+
+impl<'a> IntersectionPusher<'a> for ClipPusher<'a> {
+    fn t(&mut self, t: f64) {
+        self.hits.push(ClipHit {
+            t,
+            u_v_face: None,
+            object: self.object,
+        });
+    }
+
+    fn t_u_v(&mut self, t: f64, u: f64, v: f64) {
+        self.t_u_v_face(t, u, v, 0);
+    }
+
+    fn t_u_v_face(&mut self, t: f64, u: f64, v: f64, face: usize) {
+        self.hits.push(ClipHit {
+            t,
+            u_v_face: Some((u, v, face)),
+            object: self.object,
+        });
+    }
+
+    fn set_object(&mut self, object: &'a Object) {
+        self.object = object;
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+impl Clip {
+    pub fn new(object: Object, point: Point, normal: Vector, capped: bool) -> Self {
+        let normal = normal.normalize();
+
+        let cap = if capped {
+            // Planes face the y axis, the cap faces the discarded side, out of the object.
+            let outward = -normal;
+            let axis = Vector::new(0.0, 1.0, 0.0).cross(outward);
+            let rotation = if axis.magnitude() < EPSILON {
+                scaling(1.0, outward.y().signum(), 1.0)
+            } else {
+                rotation_axis(axis, outward.y().clamp(-1.0, 1.0).acos())
+            };
+
+            Some(Box::new(
+                Object::new_plane()
+                    .with_material(object.material().clone())
+                    .with_transformation(translation(point.x(), point.y(), point.z()) * rotation),
+            ))
+        } else {
+            None
+        };
+
+        Clip {
+            object: Box::new(object),
+            point,
+            normal,
+            cap,
+        }
+    }
+
+    fn is_kept(&self, point: &Point) -> bool {
+        (*point - self.point).dot(self.normal) >= 0.0
+    }
+
+    pub fn intersects<'a>(&'a self, ray: &Ray, push: &mut impl IntersectionPusher<'a>) {
+        if !self.object.bounding_box().is_intersected(ray) {
+            return;
+        }
+
+        let mut pusher = ClipPusher {
+            hits: vec![],
+            object: &self.object,
+        };
+        self.object.intersects(ray, &mut pusher);
+
+        for hit in pusher.hits.iter() {
+            if self.is_kept(&ray.position(hit.t)) {
+                push.set_object(hit.object);
+                match hit.u_v_face {
+                    Some((u, v, face)) => push.t_u_v_face(hit.t, u, v, face),
+                    None => push.t(hit.t),
+                }
+            }
+        }
+
+        if let Some(cap) = &self.cap {
+            let denominator = ray.direction.dot(self.normal);
+            if denominator.abs() < EPSILON {
+                return;
+            }
+
+            // The cap is inside the object when the rest of the ray crosses its surface an odd
+            // number of times.
+            let t = (self.point - ray.origin).dot(self.normal) / denominator;
+            if pusher.hits.iter().filter(|hit| hit.t > t).count() % 2 == 1 {
+                push.set_object(cap);
+                push.t(t);
+            }
+        }
+    }
+
+    pub fn normal_at(&self, _object_point: &Point) -> Vector {
+        unreachable!()
+    }
+
+    pub fn bounds(&self) -> BoundingBox {
+        self.object.bounding_box()
+    }
+
+    pub fn object(&self) -> &Object {
+        &self.object
+    }
+
+    pub fn object_mut(&mut self) -> &mut Object {
+        &mut self.object
+    }
+
+    pub fn point(&self) -> Point {
+        self.point
+    }
+
+    pub fn normal(&self) -> Vector {
+        self.normal
+    }
+
+    pub fn cap(&self) -> Option<&Object> {
+        self.cap.as_deref()
+    }
+
+    pub fn divide(self, threshold: usize) -> Self {
+        let capped = self.cap.is_some();
+
+        Clip::new(
+            self.object.divide(threshold),
+            self.point,
+            self.normal,
+            capped,
+        )
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+impl Transform for Clip {
+    fn transform(self, transformation: &Matrix) -> Self {
+        let normal = transformation.invert().transpose() * self.normal;
+
+        Clip::new(
+            self.object.transform(transformation),
+            *transformation * self.point,
+            normal,
+            self.cap.is_some(),
+        )
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        float::ApproxEq,
+        primitive::Tuple,
+        rtc::{Color, IntersectionState, Intersections, Light, Material, Pattern, Shape, World},
+    };
+
+    fn ray() -> Ray {
+        Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        }
+    }
+
+    #[test]
+    fn a_ray_only_hits_the_kept_part_of_a_clipped_sphere() {
+        let clip = Object::new_sphere().clipped_by_plane(Point::zero(), Vector::new(0.0, 0.0, 1.0));
+
+        let xs = ray().intersects(std::slice::from_ref(&clip), Intersections::new());
+        let clip = match clip.shape() {
+            Shape::Clip(c) => c,
+            _ => panic!(),
+        };
+
+        assert_eq!(xs.len(), 1);
+        assert!(xs[0].t().approx_eq(6.0));
+        assert_eq!(xs[0].object(), clip.object());
+    }
+
+    #[test]
+    fn a_ray_hits_the_cap_of_a_capped_sphere() {
+        let capped =
+            Object::new_sphere().capped_by_plane(Point::zero(), Vector::new(0.0, 0.0, 1.0));
+
+        let xs = ray().intersects(std::slice::from_ref(&capped), Intersections::new());
+        let clip = match capped.shape() {
+            Shape::Clip(c) => c,
+            _ => panic!(),
+        };
+
+        assert_eq!(xs.len(), 2);
+        assert!(xs[0].t().approx_eq(5.0));
+        assert_eq!(xs[0].object(), clip.cap().unwrap());
+        assert!(xs[1].t().approx_eq(6.0));
+        assert_eq!(xs[1].object(), clip.object());
+
+        // The cap faces the discarded side.
+        let comps = IntersectionState::new(&xs, 0, &ray());
+        assert_eq!(comps.normal_v(), Vector::new(0.0, 0.0, -1.0));
+
+        // Outside of the sphere, the plane isn't part of the cap.
+        let ray = Ray {
+            origin: Point::new(2.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+        assert!(ray
+            .intersects(std::slice::from_ref(&capped), Intersections::new())
+            .is_empty());
+    }
+
+    #[test]
+    fn a_clipped_sphere_shows_its_hollow_interior() {
+        let sphere = Object::new_sphere()
+            .with_material(Material::new().with_pattern(Pattern::new_plain(Color::red())));
+        let clip = sphere
+            .clone()
+            .clipped_by_plane(Point::zero(), Vector::new(0.0, 0.0, 1.0));
+        let lights = vec![Light::new_point_light(
+            Color::white(),
+            Point::new(-10.0, 10.0, -10.0),
+        )];
+
+        let xs = ray().intersects(std::slice::from_ref(&clip), Intersections::new());
+        let comps = IntersectionState::new(&xs, 0, &ray());
+        // The far side of the sphere is seen from the inside.
+        assert_eq!(comps.over_point().z().round(), 1.0);
+        assert_eq!(comps.normal_v(), Vector::new(0.0, 0.0, -1.0));
+
+        let w = World::new()
+            .with_objects(vec![clip])
+            .with_lights(lights.clone());
+        let inside = w.color_at(&ray());
+        assert!(inside.r > 0.0);
+
+        let w = World::new().with_objects(vec![sphere]).with_lights(lights);
+        assert_ne!(w.color_at(&ray()), inside);
+    }
+
+    #[test]
+    fn transforming_a_clipped_object_transforms_its_plane() {
+        let clip = Object::new_sphere()
+            .clipped_by_plane(Point::zero(), Vector::new(0.0, 0.0, 1.0))
+            .translate(0.0, 0.0, 1.0)
+            .transform();
+
+        // The plane now goes through z = 1, the ray only hits the back of the sphere.
+        let xs = ray().intersects(std::slice::from_ref(&clip), Intersections::new());
+        assert_eq!(xs.len(), 1);
+        assert!(xs[0].t().approx_eq(7.0));
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
@@ -5,26 +5,39 @@ use crate::{
     primitive::{Point, Tuple, Vector},
     rtc::{BoundingBox, IntersectionPusher, Ray},
 };
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use std::f64::consts::{PI, SQRT_2};
 
 /* ---------------------------------------------------------------------------------------------- */
 
-#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct Cone {
-    min: f64,
-    max: f64,
+    min: Option<f64>,
+    max: Option<f64>,
     closed: bool,
 }
 
 /* ---------------------------------------------------------------------------------------------- */
 
 impl Cone {
-    pub fn new(min: f64, max: f64, closed: bool) -> Self {
-        let (min, max) = if min < max { (min, max) } else { (max, min) };
+    pub fn new(min: Option<f64>, max: Option<f64>, closed: bool) -> Self {
+        let (min, max) = match (min, max) {
+            (Some(min), Some(max)) if max < min => (Some(max), Some(min)),
+            _ => (min, max),
+        };
 
         Cone { min, max, closed }
     }
 
+    fn min(&self) -> f64 {
+        self.min.unwrap_or(f64::NEG_INFINITY)
+    }
+
+    fn max(&self) -> f64 {
+        self.max.unwrap_or(f64::INFINITY)
+    }
+
     pub fn intersects<'a>(&self, ray: &Ray, push: &mut impl IntersectionPusher<'a>) {
         let a = ray.direction.x().powi(2) - ray.direction.y().powi(2) + ray.direction.z().powi(2);
 
@@ -49,12 +62,12 @@ impl Cone {
             let t1 = (-b + discriminant.sqrt()) / double_a;
 
             let y0 = ray.origin.y() + t0 * ray.direction.y();
-            if self.min < y0 && y0 < self.max {
+            if self.min() < y0 && y0 < self.max() {
                 push.t(t0);
             }
 
             let y1 = ray.origin.y() + t1 * ray.direction.y();
-            if self.min < y1 && y1 < self.max {
+            if self.min() < y1 && y1 < self.max() {
                 push.t(t1);
             }
         }
@@ -73,23 +86,28 @@ impl Cone {
             return;
         }
 
-        let t = (self.min - ray.origin.y()) / ray.direction.y();
-        if Self::check_cap(ray, t, self.min) {
-            push.t(t);
-        }
+        // An infinite extent has no cap. Without height, both caps are the same disk, which is
+        // only hit once.
+        let caps = if self.min == self.max {
+            [self.min, None]
+        } else {
+            [self.min, self.max]
+        };
 
-        let t = (self.max - ray.origin.y()) / ray.direction.y();
-        if Self::check_cap(ray, t, self.max) {
-            push.t(t);
+        for cap in caps.iter().flatten() {
+            let t = (cap - ray.origin.y()) / ray.direction.y();
+            if Self::check_cap(ray, t, *cap) {
+                push.t(t);
+            }
         }
     }
 
     pub fn normal_at(&self, object_point: &Point) -> Vector {
         let dist = object_point.x().powi(2) + object_point.z().powi(2);
 
-        if dist < 1.0 && object_point.y() >= (self.max - EPSILON) {
+        if dist < 1.0 && object_point.y() >= (self.max() - EPSILON) {
             Vector::new(0.0, 1.0, 0.0)
-        } else if dist < 1.0 && object_point.y() <= (self.min + EPSILON) {
+        } else if dist < 1.0 && object_point.y() <= (self.min() + EPSILON) {
             Vector::new(0.0, -1.0, 0.0)
         } else {
             Vector::new(
@@ -106,19 +124,56 @@ impl Cone {
 
     pub fn bounds(&self) -> BoundingBox {
         BoundingBox::new()
-            .with_min(Point::new(self.min, self.min, self.min))
-            .with_max(Point::new(self.max, self.max, self.max))
+            .with_min(Point::new(self.min(), self.min(), self.min()))
+            .with_max(Point::new(self.max(), self.max(), self.max()))
     }
-}
 
-/* ---------------------------------------------------------------------------------------------- */
+    // Only a bounded cone has a finite area that can be uniformly sampled.
+    pub fn sample_surface(&self, rng: &mut impl Rng) -> Option<(Point, Vector)> {
+        let (min, max) = (self.min?, self.max?);
 
-impl Default for Cone {
-    fn default() -> Self {
-        Cone {
-            min: f64::NEG_INFINITY,
-            max: f64::INFINITY,
-            closed: false,
+        // The radius at height y is |y|, so the area of the side between min and max is
+        // proportional to the integral of |y|, whose primitive is y|y|/2.
+        let primitive = |y: f64| y * y.abs() / 2.0;
+        let integral = primitive(max) - primitive(min);
+
+        let side_area = 2.0 * PI * SQRT_2 * integral;
+        let (min_cap_area, max_cap_area) = if self.closed {
+            (PI * min * min, PI * max * max)
+        } else {
+            (0.0, 0.0)
+        };
+        let area = side_area + min_cap_area + max_cap_area;
+        if area <= 0.0 {
+            return None;
+        }
+
+        let pick = rng.gen::<f64>() * area;
+        let theta = 2.0 * PI * rng.gen::<f64>();
+
+        if pick < side_area {
+            // Invert the primitive to get a height distributed proportionally to the radius.
+            let target = primitive(min) + integral * rng.gen::<f64>();
+            let y = target.signum() * (2.0 * target.abs()).sqrt();
+            let radius = y.abs();
+            let point = Point::new(radius * theta.cos(), y, radius * theta.sin());
+
+            Some((
+                point,
+                Vector::new(point.x(), -point.y(), point.z()).normalize(),
+            ))
+        } else {
+            let (y, normal) = if pick < side_area + min_cap_area {
+                (min, Vector::new(0.0, -1.0, 0.0))
+            } else {
+                (max, Vector::new(0.0, 1.0, 0.0))
+            };
+            let radius = y.abs() * rng.gen::<f64>().sqrt();
+
+            Some((
+                Point::new(radius * theta.cos(), y, radius * theta.sin()),
+                normal,
+            ))
         }
     }
 }
@@ -130,6 +185,7 @@ pub mod tests {
     use super::*;
     use crate::rtc::IntersectionPusher;
     use crate::rtc::Object;
+    use rand::{rngs::SmallRng, SeedableRng};
 
     struct Push {
         pub xs: Vec<f64>,
@@ -209,7 +265,7 @@ pub mod tests {
             (Point::new(0.0, 0.0, -0.25), Vector::new(0.0, 1.0, 0.0), 4),
         ];
 
-        let c = Cone::new(-0.5, 0.5, true);
+        let c = Cone::new(Some(-0.5), Some(0.5), true);
         for (origin, direction, count) in tests.into_iter() {
             let mut push = Push { xs: vec![] };
             c.intersects(
@@ -223,6 +279,28 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn the_bounds_of_a_cone_are_ordered_and_may_be_equal() {
+        assert_eq!(
+            Cone::new(Some(0.5), Some(-0.5), true),
+            Cone::new(Some(-0.5), Some(0.5), true)
+        );
+
+        let ray = Ray {
+            origin: Point::new(0.0, 3.0, 0.5),
+            direction: Vector::new(0.0, -1.0, 0.0),
+        };
+
+        // A disk of radius 1.
+        let mut push = Push { xs: vec![] };
+        Cone::new(Some(-1.0), Some(-1.0), true).intersects(&ray, &mut push);
+        assert_eq!(push.xs, vec![4.0]);
+
+        let mut push = Push { xs: vec![] };
+        Cone::new(Some(-1.0), Some(-1.0), false).intersects(&ray, &mut push);
+        assert!(push.xs.is_empty());
+    }
+
     #[test]
     fn normal_vector_on_a_conee() {
         let c: Cone = Default::default();
@@ -242,7 +320,7 @@ pub mod tests {
 
     #[test]
     fn an_unbounded_cone_has_a_bounding_box() {
-        let c = Object::new_cone(f64::NEG_INFINITY, f64::INFINITY, false);
+        let c = Object::new_cone(None, None, false);
         assert_eq!(
             c.shape_bounds().min(),
             Point::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY)
@@ -255,10 +333,40 @@ pub mod tests {
 
     #[test]
     fn a_bounded_cone_has_a_bounding_box() {
-        let c = Object::new_cone(-5.0, 3.0, false);
+        let c = Object::new_cone(Some(-5.0), Some(3.0), false);
         assert_eq!(c.shape_bounds().min(), Point::new(-5.0, -5.0, -5.0));
         assert_eq!(c.shape_bounds().max(), Point::new(3.0, 3.0, 3.0));
     }
+
+    #[test]
+    fn sampling_the_surface_of_a_cone() {
+        let mut rng = SmallRng::seed_from_u64(0);
+
+        assert_eq!(Cone::new(None, None, false).sample_surface(&mut rng), None);
+
+        let c = Cone::new(Some(-1.0), Some(2.0), true);
+        let (mut nb_side, mut nb_caps) = (0, 0);
+
+        for _ in 0..100 {
+            let (point, normal) = c.sample_surface(&mut rng).unwrap();
+            let dist = point.x().powi(2) + point.z().powi(2);
+
+            if normal.x() != 0.0 || normal.z() != 0.0 {
+                nb_side += 1;
+                assert!(dist.approx_eq(point.y().powi(2)));
+                assert!(-1.0 <= point.y() && point.y() <= 2.0);
+                assert_eq!(normal, c.normal_at(&point).normalize());
+                // Outward
+                assert!((normal ^ Vector::new(point.x(), 0.0, point.z())) > 0.0);
+            } else {
+                nb_caps += 1;
+                assert!(dist <= point.y().powi(2));
+                assert_eq!(point.y(), if normal.y() > 0.0 { 2.0 } else { -1.0 });
+            }
+        }
+
+        assert!(nb_side > 0 && nb_caps > 0);
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -0,0 +1,380 @@
+/* ---------------------------------------------------------------------------------------------- */
+
+use crate::{
+    primitive::{Matrix, Point, Vector},
+    rtc::{coplanar_runs, BoundingBox, IntersectionPusher, Object, Ray, Transform},
+};
+use serde::{Deserialize, Serialize};
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Csg {
+    operation: CsgOperation,
+    bounding_box: BoundingBox,
+    left: Box<Object>,
+    right: Box<Object>,
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+struct CsgHit<'a> {
+    t: f64,
+    u_v_face: Option<(f64, f64, usize)>,
+    object: &'a Object,
+    is_left: bool,
+}
+
+// Collects the intersections of both operands before filtering them.
+struct CsgPusher<'a> {
+    hits: Vec<CsgHit<'a>>,
+    object: &'a Object,
+    is_left: bool,
+}
+
+impl<'a> IntersectionPusher<'a> for CsgPusher<'a> {
+    fn t(&mut self, t: f64) {
+        self.hits.push(CsgHit {
+            t,
+            u_v_face: None,
+            object: self.object,
+            is_left: self.is_left,
+        });
+    }
+
+    fn t_u_v(&mut self, t: f64, u: f64, v: f64) {
+        self.t_u_v_face(t, u, v, 0);
+    }
+
+    fn t_u_v_face(&mut self, t: f64, u: f64, v: f64, face: usize) {
+        self.hits.push(CsgHit {
+            t,
+            u_v_face: Some((u, v, face)),
+            object: self.object,
+            is_left: self.is_left,
+        });
+    }
+
+    fn set_object(&mut self, object: &'a Object) {
+        self.object = object;
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+impl Csg {
+    pub fn new(operation: CsgOperation, left: Object, right: Object) -> Self {
+        let bounding_box = (left.bounding_box() + right.bounding_box()).padded();
+
+        Self {
+            operation,
+            bounding_box,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    fn is_inside(&self, in_left: bool, in_right: bool) -> bool {
+        match self.operation {
+            CsgOperation::Union => in_left || in_right,
+            CsgOperation::Intersection => in_left && in_right,
+            CsgOperation::Difference => in_left && !in_right,
+        }
+    }
+
+    // An intersection is kept when it's on the boundary of the result of the operation.
+    pub fn intersection_allowed(&self, hit_left: bool, in_left: bool, in_right: bool) -> bool {
+        let inside = self.is_inside(in_left, in_right);
+
+        if hit_left {
+            inside != self.is_inside(!in_left, in_right)
+        } else {
+            inside != self.is_inside(in_left, !in_right)
+        }
+    }
+
+    pub fn intersects<'a>(&'a self, ray: &Ray, push: &mut impl IntersectionPusher<'a>) {
+        if !self.bounding_box.is_intersected(ray) {
+            return;
+        }
+
+        let mut pusher = CsgPusher {
+            hits: vec![],
+            object: &self.left,
+            is_left: true,
+        };
+        self.left.intersects(ray, &mut pusher);
+
+        pusher.set_object(&self.right);
+        pusher.is_left = false;
+        self.right.intersects(ray, &mut pusher);
+
+        let mut hits = pusher.hits;
+        hits.sort_by(|lhs, rhs| lhs.t.partial_cmp(&rhs.t).unwrap());
+
+        let (mut in_left, mut in_right) = (false, false);
+
+        // Coplanar intersections are filtered as a whole, otherwise the result would depend on
+        // their order.
+        for coplanar in coplanar_runs(&hits, |hit| hit.t) {
+            let (next_in_left, next_in_right) =
+                coplanar.iter().fold((in_left, in_right), |(l, r), hit| {
+                    if hit.is_left {
+                        (!l, r)
+                    } else {
+                        (l, !r)
+                    }
+                });
+
+            // Only one surface is kept when several coincide.
+            if self.is_inside(in_left, in_right) != self.is_inside(next_in_left, next_in_right) {
+                let hit = coplanar
+                    .iter()
+                    .find(|hit| self.intersection_allowed(hit.is_left, in_left, in_right))
+                    .unwrap_or(&coplanar[0]);
+
+                push.set_object(hit.object);
+                match hit.u_v_face {
+                    Some((u, v, face)) => push.t_u_v_face(hit.t, u, v, face),
+                    None => push.t(hit.t),
+                }
+            }
+
+            in_left = next_in_left;
+            in_right = next_in_right;
+        }
+    }
+
+    pub fn normal_at(&self, _object_point: &Point) -> Vector {
+        unreachable!()
+    }
+
+    pub fn bounds(&self) -> BoundingBox {
+        self.bounding_box
+    }
+
+    pub fn operation(&self) -> CsgOperation {
+        self.operation
+    }
+
+    pub fn left(&self) -> &Object {
+        &self.left
+    }
+
+    pub fn right(&self) -> &Object {
+        &self.right
+    }
+
+    pub fn left_mut(&mut self) -> &mut Object {
+        &mut self.left
+    }
+
+    pub fn right_mut(&mut self) -> &mut Object {
+        &mut self.right
+    }
+
+    pub fn divide(self, threshold: usize) -> Self {
+        Csg::new(
+            self.operation,
+            self.left.divide(threshold),
+            self.right.divide(threshold),
+        )
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+impl Transform for Csg {
+    fn transform(self, transformation: &Matrix) -> Self {
+        Csg::new(
+            self.operation,
+            self.left.transform(transformation),
+            self.right.transform(transformation),
+        )
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        float::ApproxEq,
+        primitive::Tuple,
+        rtc::{Intersections, Shape},
+    };
+
+    #[test]
+    fn evaluating_the_rule_for_a_csg_operation() {
+        let tests = vec![
+            (
+                CsgOperation::Union,
+                [false, true, false, true, false, false, true, true],
+            ),
+            (
+                CsgOperation::Intersection,
+                [true, false, true, false, true, true, false, false],
+            ),
+            (
+                CsgOperation::Difference,
+                [false, true, false, true, true, true, false, false],
+            ),
+        ];
+
+        for (operation, results) in tests {
+            let csg = Csg::new(operation, Object::new_sphere(), Object::new_cube());
+            let mut i = 0;
+            for hit_left in [true, false] {
+                for in_left in [true, false] {
+                    for in_right in [true, false] {
+                        assert_eq!(
+                            csg.intersection_allowed(hit_left, in_left, in_right),
+                            results[i]
+                        );
+                        i += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn a_ray_hits_a_csg_object() {
+        let csg = Object::new_csg(
+            CsgOperation::Union,
+            Object::new_sphere(),
+            Object::new_sphere().translate(0.0, 0.0, 0.5).transform(),
+        );
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let xs = ray.intersects(std::slice::from_ref(&csg), Intersections::new());
+        let csg = match csg.shape() {
+            Shape::Csg(c) => c,
+            _ => panic!(),
+        };
+
+        assert_eq!(xs.len(), 2);
+        assert!(xs[0].t().approx_eq(4.0));
+        assert_eq!(xs[0].object(), csg.left());
+        assert!(xs[1].t().approx_eq(6.5));
+        assert_eq!(xs[1].object(), csg.right());
+    }
+
+    #[test]
+    fn a_ray_misses_a_csg_object() {
+        let csg = Object::new_csg(
+            CsgOperation::Union,
+            Object::new_sphere(),
+            Object::new_cube(),
+        );
+        let ray = Ray {
+            origin: Point::new(0.0, 2.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let xs = ray.intersects(std::slice::from_ref(&csg), Intersections::new());
+        assert!(xs.is_empty());
+    }
+
+    #[test]
+    fn differencing_cubes_sharing_a_face_leaves_no_surface_on_the_seam() {
+        // The subtracted cube spans z in [-1, -0.4] and shares its front face with the first
+        // cube, up to rounding errors.
+        let csg = Object::new_csg(
+            CsgOperation::Difference,
+            Object::new_cube(),
+            Object::new_cube()
+                .scale(1.0, 1.0, 0.3)
+                .translate(0.0, 0.0, -0.7)
+                .transform(),
+        );
+
+        for i in 0..10 {
+            for j in 0..10 {
+                let ray = Ray {
+                    origin: Point::new(-0.9 + 0.19 * i as f64, -0.9 + 0.19 * j as f64, -5.0),
+                    direction: Vector::new(0.0, 0.0, 1.0),
+                };
+
+                let xs = ray.intersects(std::slice::from_ref(&csg), Intersections::new());
+                assert_eq!(xs.len(), 2);
+                assert!(xs[0].t().approx_eq(4.6));
+                assert!(xs[1].t().approx_eq(6.0));
+            }
+        }
+    }
+
+    #[test]
+    fn the_union_of_cubes_sharing_a_face_has_no_inner_surface() {
+        let csg = Object::new_csg(
+            CsgOperation::Union,
+            Object::new_cube(),
+            Object::new_cube().translate(0.0, 0.0, 2.0).transform(),
+        );
+        let ray = Ray {
+            origin: Point::new(0.5, 0.5, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let xs = ray.intersects(std::slice::from_ref(&csg), Intersections::new());
+        assert_eq!(xs.len(), 2);
+        assert!(xs[0].t().approx_eq(4.0));
+        assert!(xs[1].t().approx_eq(8.0));
+    }
+
+    #[test]
+    fn transforming_a_csg_object_transforms_its_operands() {
+        let csg = Object::new_csg(
+            CsgOperation::Intersection,
+            Object::new_sphere(),
+            Object::new_cube(),
+        )
+        .translate(0.0, 0.0, 10.0)
+        .transform();
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, 0.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let xs = ray.intersects(std::slice::from_ref(&csg), Intersections::new());
+        assert_eq!(xs.len(), 2);
+        assert!(xs[0].t().approx_eq(9.0));
+        assert!(xs[1].t().approx_eq(11.0));
+    }
+
+    #[test]
+    fn the_bounds_of_a_csg_object_contain_both_operands() {
+        let left = Object::new_sphere().translate(-3.0, 0.0, 0.0).transform();
+        let right = Object::new_cube().scale(1.0, 4.0, 1.0).transform();
+
+        // Even for an intersection or a difference, so the BVH never misses a part of them.
+        for operation in [
+            CsgOperation::Union,
+            CsgOperation::Intersection,
+            CsgOperation::Difference,
+        ] {
+            let b = Csg::new(operation, left.clone(), right.clone()).bounds();
+
+            assert!(b.contains(&left.bounding_box()));
+            assert!(b.contains(&right.bounding_box()));
+            assert!(b.contains_point(&Point::new(-4.0, -4.0, -1.0)));
+            assert!(b.contains_point(&Point::new(1.0, 4.0, 1.0)));
+            assert!(!b.contains_point(&Point::new(0.0, 5.0, 0.0)));
+        }
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
@@ -5,6 +5,7 @@ use crate::{
     primitive::{Point, Tuple, Vector},
     rtc::{BoundingBox, IntersectionPusher, Ray},
 };
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -97,6 +98,27 @@ impl Cube {
             .with_min(Point::new(-1.0, -1.0, -1.0))
             .with_max(Point::new(1.0, 1.0, 1.0))
     }
+
+    pub fn sample_surface(rng: &mut impl Rng) -> (Point, Vector) {
+        // All faces have the same area.
+        let face = rng.gen_range(0..6);
+        let axis = face / 2;
+        let side = if face % 2 == 0 { 1.0 } else { -1.0 };
+
+        let mut coordinates = [0.0; 3];
+        for (i, coordinate) in coordinates.iter_mut().enumerate() {
+            *coordinate = if i == axis {
+                side
+            } else {
+                rng.gen_range(-1.0..=1.0)
+            };
+        }
+
+        let mut normal = [0.0; 3];
+        normal[axis] = side;
+
+        (Point::from(coordinates), Vector::from(normal))
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -105,6 +127,7 @@ impl Cube {
 pub mod tests {
     use super::*;
     use crate::rtc::{IntersectionPusher, Object};
+    use rand::{rngs::SmallRng, SeedableRng};
 
     struct Push {
         pub xs: Vec<f64>,
@@ -246,6 +269,19 @@ pub mod tests {
         assert_eq!(c.shape_bounds().min(), Point::new(-1.0, -1.0, -1.0));
         assert_eq!(c.shape_bounds().max(), Point::new(1.0, 1.0, 1.0));
     }
+
+    #[test]
+    fn sampling_the_surface_of_a_cube() {
+        let mut rng = SmallRng::seed_from_u64(0);
+
+        for _ in 0..100 {
+            let (point, normal) = Cube::sample_surface(&mut rng);
+
+            let max_c = point.x().abs().max(point.y().abs()).max(point.z().abs());
+            assert_eq!(max_c, 1.0);
+            assert_eq!(normal, Cube::normal_at(&point));
+        }
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -5,26 +5,39 @@ use crate::{
     primitive::{Point, Tuple, Vector},
     rtc::{BoundingBox, IntersectionPusher, Ray},
 };
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
 
 /* ---------------------------------------------------------------------------------------------- */
 
-#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct Cylinder {
-    min: f64,
-    max: f64,
+    min: Option<f64>,
+    max: Option<f64>,
     closed: bool,
 }
 
 /* ---------------------------------------------------------------------------------------------- */
 
 impl Cylinder {
-    pub fn new(min: f64, max: f64, closed: bool) -> Self {
-        let (min, max) = if min < max { (min, max) } else { (max, min) };
+    pub fn new(min: Option<f64>, max: Option<f64>, closed: bool) -> Self {
+        let (min, max) = match (min, max) {
+            (Some(min), Some(max)) if max < min => (Some(max), Some(min)),
+            _ => (min, max),
+        };
 
         Cylinder { min, max, closed }
     }
 
+    fn min(&self) -> f64 {
+        self.min.unwrap_or(f64::NEG_INFINITY)
+    }
+
+    fn max(&self) -> f64 {
+        self.max.unwrap_or(f64::INFINITY)
+    }
+
     pub fn intersects<'a>(&self, ray: &Ray, push: &mut impl IntersectionPusher<'a>) {
         let a = ray.direction.x().powi(2) + ray.direction.z().powi(2);
 
@@ -45,12 +58,12 @@ impl Cylinder {
             let t1 = (-b + discriminant.sqrt()) / double_a;
 
             let y0 = ray.origin.y() + t0 * ray.direction.y();
-            if self.min < y0 && y0 < self.max {
+            if self.min() < y0 && y0 < self.max() {
                 push.t(t0);
             }
 
             let y1 = ray.origin.y() + t1 * ray.direction.y();
-            if self.min < y1 && y1 < self.max {
+            if self.min() < y1 && y1 < self.max() {
                 push.t(t1);
             }
 
@@ -70,23 +83,28 @@ impl Cylinder {
             return;
         }
 
-        let t = (self.min - ray.origin.y()) / ray.direction.y();
-        if Self::check_cap(ray, t) {
-            push.t(t);
-        }
+        // An infinite extent has no cap. Without height, both caps are the same disk, which is
+        // only hit once.
+        let caps = if self.min == self.max {
+            [self.min, None]
+        } else {
+            [self.min, self.max]
+        };
 
-        let t = (self.max - ray.origin.y()) / ray.direction.y();
-        if Self::check_cap(ray, t) {
-            push.t(t);
+        for cap in caps.iter().flatten() {
+            let t = (cap - ray.origin.y()) / ray.direction.y();
+            if Self::check_cap(ray, t) {
+                push.t(t);
+            }
         }
     }
 
     pub fn normal_at(&self, object_point: &Point) -> Vector {
         let dist = object_point.x().powi(2) + object_point.z().powi(2);
 
-        if dist < 1.0 && object_point.y() >= (self.max - EPSILON) {
+        if dist < 1.0 && object_point.y() >= (self.max() - EPSILON) {
             Vector::new(0.0, 1.0, 0.0)
-        } else if dist < 1.0 && object_point.y() <= (self.min + EPSILON) {
+        } else if dist < 1.0 && object_point.y() <= (self.min() + EPSILON) {
             Vector::new(0.0, -1.0, 0.0)
         } else {
             Vector::new(object_point.x(), 0.0, object_point.z())
@@ -95,19 +113,41 @@ impl Cylinder {
 
     pub fn bounds(&self) -> BoundingBox {
         BoundingBox::new()
-            .with_min(Point::new(-1.0, self.min, -1.0))
-            .with_max(Point::new(1.0, self.max, 1.0))
+            .with_min(Point::new(-1.0, self.min(), -1.0))
+            .with_max(Point::new(1.0, self.max(), 1.0))
     }
-}
 
-/* ---------------------------------------------------------------------------------------------- */
+    // Only a bounded cylinder has a finite area that can be uniformly sampled.
+    pub fn sample_surface(&self, rng: &mut impl Rng) -> Option<(Point, Vector)> {
+        let (min, max) = (self.min?, self.max?);
+
+        let side_area = 2.0 * PI * (max - min);
+        let cap_area = if self.closed { PI } else { 0.0 };
+        let area = side_area + 2.0 * cap_area;
+        if area <= 0.0 {
+            return None;
+        }
+
+        let pick = rng.gen::<f64>() * area;
+        let theta = 2.0 * PI * rng.gen::<f64>();
+
+        if pick < side_area {
+            let y = min + (max - min) * rng.gen::<f64>();
+            let normal = Vector::new(theta.cos(), 0.0, theta.sin());
 
-impl Default for Cylinder {
-    fn default() -> Self {
-        Cylinder {
-            min: f64::NEG_INFINITY,
-            max: f64::INFINITY,
-            closed: false,
+            Some((Point::new(normal.x(), y, normal.z()), normal))
+        } else {
+            let (y, normal) = if pick < side_area + cap_area {
+                (min, Vector::new(0.0, -1.0, 0.0))
+            } else {
+                (max, Vector::new(0.0, 1.0, 0.0))
+            };
+            let radius = rng.gen::<f64>().sqrt();
+
+            Some((
+                Point::new(radius * theta.cos(), y, radius * theta.sin()),
+                normal,
+            ))
         }
     }
 }
@@ -117,7 +157,8 @@ impl Default for Cylinder {
 #[cfg(test)]
 pub mod tests {
     use super::*;
-    use crate::rtc::{IntersectionPusher, Object};
+    use crate::rtc::{IntersectionPusher, Object, Shape};
+    use rand::{rngs::SmallRng, SeedableRng};
 
     struct Push {
         pub xs: Vec<f64>,
@@ -138,9 +179,9 @@ pub mod tests {
     #[test]
     fn the_default_values_for_a_cylinder() {
         let c: Cylinder = Default::default();
-        assert_eq!(c.min, f64::NEG_INFINITY);
-        assert_eq!(c.max, f64::INFINITY);
-        assert_eq!(c.closed, false);
+        assert_eq!(c.min, None);
+        assert_eq!(c.max, None);
+        assert!(!c.closed);
     }
 
     #[test]
@@ -192,7 +233,7 @@ pub mod tests {
             (Point::new(0.0, 1.5, -2.0), Vector::new(0.0, 0.0, 1.0), 2),
         ];
 
-        let c = Cylinder::new(1.0, 2.0, false);
+        let c = Cylinder::new(Some(1.0), Some(2.0), false);
         for (origin, direction, count) in tests.into_iter() {
             let mut push = Push { xs: vec![] };
             c.intersects(
@@ -216,7 +257,7 @@ pub mod tests {
             (Point::new(0.0, -1.0, -2.0), Vector::new(0.0, 1.0, 1.0), 2),
         ];
 
-        let c = Cylinder::new(1.0, 2.0, true);
+        let c = Cylinder::new(Some(1.0), Some(2.0), true);
         for (origin, direction, count) in tests.into_iter() {
             let mut push = Push { xs: vec![] };
             c.intersects(
@@ -230,6 +271,84 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn a_half_bounded_closed_cylinder_only_has_a_top_cap() {
+        let c = Object::new_cylinder(None, Some(2.0), true);
+        let c = match c.shape() {
+            Shape::Cylinder(c) => *c,
+            _ => panic!(),
+        };
+
+        let tests = vec![
+            (
+                Point::new(0.0, 3.0, 0.0),
+                Vector::new(0.0, -1.0, 0.0),
+                vec![1.0],
+            ),
+            (
+                Point::new(0.0, -3.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+                vec![5.0],
+            ),
+            (
+                Point::new(0.0, -100.0, -2.0),
+                Vector::new(0.0, 0.0, 1.0),
+                vec![1.0, 3.0],
+            ),
+        ];
+
+        for (origin, direction, xs) in tests.into_iter() {
+            let mut push = Push { xs: vec![] };
+            c.intersects(&Ray { origin, direction }, &mut push);
+            assert_eq!(push.xs, xs);
+        }
+
+        assert_eq!(
+            c.normal_at(&Point::new(0.5, 2.0, 0.0)),
+            Vector::new(0.0, 1.0, 0.0)
+        );
+        assert_eq!(
+            c.normal_at(&Point::new(0.0, -1000.0, 1.0)),
+            Vector::new(0.0, 0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn the_bounds_of_a_cylinder_are_ordered() {
+        assert_eq!(
+            Cylinder::new(Some(2.0), Some(1.0), true),
+            Cylinder::new(Some(1.0), Some(2.0), true)
+        );
+
+        let c = Cylinder::new(Some(2.0), Some(1.0), false);
+        let mut push = Push { xs: vec![] };
+        c.intersects(
+            &Ray {
+                origin: Point::new(0.0, 1.5, -5.0),
+                direction: Vector::new(0.0, 0.0, 1.0),
+            },
+            &mut push,
+        );
+        assert_eq!(push.xs.len(), 2);
+    }
+
+    #[test]
+    fn a_cylinder_without_height() {
+        let ray = Ray {
+            origin: Point::new(0.0, 3.0, 0.0),
+            direction: Vector::new(0.0, -1.0, 0.0),
+        };
+
+        // A disk.
+        let mut push = Push { xs: vec![] };
+        Cylinder::new(Some(1.0), Some(1.0), true).intersects(&ray, &mut push);
+        assert_eq!(push.xs, vec![2.0]);
+
+        let mut push = Push { xs: vec![] };
+        Cylinder::new(Some(1.0), Some(1.0), false).intersects(&ray, &mut push);
+        assert!(push.xs.is_empty());
+    }
+
     #[test]
     fn a_ray_misses_a_cylinder() {
         fn test(origin: Point, direction: Vector) {
@@ -280,7 +399,7 @@ pub mod tests {
             (Point::new(0.0, 2.0, 0.5), Vector::new(0.0, 1.0, 0.0)),
         ];
 
-        let c = Cylinder::new(1.0, 2.0, true);
+        let c = Cylinder::new(Some(1.0), Some(2.0), true);
         for (point, normal) in tests.into_iter() {
             assert_eq!(c.normal_at(&point), normal);
         }
@@ -288,7 +407,7 @@ pub mod tests {
 
     #[test]
     fn an_unbounded_cylinder_has_a_bounding_box() {
-        let c = Object::new_cylinder(f64::NEG_INFINITY, f64::INFINITY, false);
+        let c = Object::new_cylinder(None, None, false);
         assert_eq!(
             c.shape_bounds().min(),
             Point::new(-1.0, f64::NEG_INFINITY, -1.0)
@@ -298,10 +417,41 @@ pub mod tests {
 
     #[test]
     fn a_bounded_cylinder_has_a_bounding_box() {
-        let c = Object::new_cylinder(-5.0, 3.0, false);
+        let c = Object::new_cylinder(Some(-5.0), Some(3.0), false);
         assert_eq!(c.shape_bounds().min(), Point::new(-1.0, -5.0, -1.0));
         assert_eq!(c.shape_bounds().max(), Point::new(1.0, 3.0, 1.0));
     }
+
+    #[test]
+    fn sampling_the_surface_of_a_cylinder() {
+        let mut rng = SmallRng::seed_from_u64(0);
+
+        assert_eq!(
+            Cylinder::new(None, Some(2.0), true).sample_surface(&mut rng),
+            None
+        );
+
+        let c = Cylinder::new(Some(1.0), Some(2.0), true);
+        let (mut nb_side, mut nb_caps) = (0, 0);
+
+        for _ in 0..100 {
+            let (point, normal) = c.sample_surface(&mut rng).unwrap();
+            let dist = point.x().powi(2) + point.z().powi(2);
+
+            if normal.y() == 0.0 {
+                nb_side += 1;
+                assert!(dist.approx_eq(1.0));
+                assert!(1.0 <= point.y() && point.y() <= 2.0);
+                assert_eq!(normal, Vector::new(point.x(), 0.0, point.z()));
+            } else {
+                nb_caps += 1;
+                assert!(dist <= 1.0);
+                assert_eq!(point.y(), if normal.y() > 0.0 { 2.0 } else { 1.0 });
+            }
+        }
+
+        assert!(nb_side > 0 && nb_caps > 0);
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
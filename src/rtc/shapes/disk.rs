@@ -0,0 +1,164 @@
+/* ---------------------------------------------------------------------------------------------- */
+
+use crate::{
+    float::EPSILON,
+    primitive::{Point, Tuple, Vector},
+    rtc::{BoundingBox, IntersectionPusher, Ray},
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Flat ring of the xz plane around the origin, from the inner radius to the outer one. A disk
+// has an inner radius of 0.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Disk {
+    inner: f64,
+    outer: f64,
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+impl Disk {
+    pub fn new(inner: f64, outer: f64) -> Self {
+        let (inner, outer) = (inner.abs(), outer.abs());
+
+        Disk {
+            inner: inner.min(outer),
+            outer: inner.max(outer),
+        }
+    }
+
+    pub fn intersects<'a>(&self, ray: &Ray, push: &mut impl IntersectionPusher<'a>) {
+        if ray.direction.y().abs() < EPSILON {
+            return;
+        }
+
+        let t = -ray.origin.y() / ray.direction.y();
+        let x = ray.origin.x() + t * ray.direction.x();
+        let z = ray.origin.z() + t * ray.direction.z();
+        let distance2 = x * x + z * z;
+
+        if self.inner * self.inner <= distance2 && distance2 <= self.outer * self.outer {
+            push.t(t);
+        }
+    }
+
+    pub fn normal_at(&self, _object_point: &Point) -> Vector {
+        Vector::new(0.0, 1.0, 0.0)
+    }
+
+    pub fn bounds(&self) -> BoundingBox {
+        BoundingBox::new()
+            .with_min(Point::new(-self.outer, 0.0, -self.outer))
+            .with_max(Point::new(self.outer, 0.0, self.outer))
+    }
+
+    // The area within a radius grows with its square.
+    pub fn sample_surface(&self, rng: &mut impl Rng) -> (Point, Vector) {
+        let (inner2, outer2) = (self.inner * self.inner, self.outer * self.outer);
+        let radius = (inner2 + (outer2 - inner2) * rng.gen::<f64>()).sqrt();
+        let theta = 2.0 * PI * rng.gen::<f64>();
+
+        (
+            Point::new(radius * theta.cos(), 0.0, radius * theta.sin()),
+            Vector::new(0.0, 1.0, 0.0),
+        )
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{float::ApproxEq, rtc::Object};
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    struct Push {
+        pub xs: Vec<f64>,
+    }
+
+    impl IntersectionPusher<'_> for Push {
+        fn t(&mut self, t: f64) {
+            self.xs.push(t);
+        }
+        fn t_u_v(&mut self, _t: f64, _u: f64, _v: f64) {
+            panic!();
+        }
+        fn set_object(&mut self, _object: &'_ Object) {
+            panic!();
+        }
+    }
+
+    fn intersects(disk: &Disk, origin: Point, direction: Vector) -> Vec<f64> {
+        let mut push = Push { xs: vec![] };
+        disk.intersects(&Ray { origin, direction }, &mut push);
+
+        push.xs
+    }
+
+    #[test]
+    fn a_ray_hits_an_annulus() {
+        let d = Disk::new(1.0, 2.0);
+
+        let xs = intersects(&d, Point::new(1.5, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        assert_eq!(xs.len(), 1);
+        assert!(xs[0].approx_eq(5.0));
+
+        // From below, obliquely.
+        let xs = intersects(&d, Point::new(0.0, -1.0, -3.0), Vector::new(0.0, 1.0, 1.5));
+        assert_eq!(xs.len(), 1);
+        assert!(xs[0].approx_eq(1.0));
+
+        assert_eq!(
+            d.normal_at(&Point::new(-1.5, 0.0, 0.0)),
+            Vector::new(0.0, 1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn a_ray_misses_an_annulus() {
+        let d = Disk::new(1.0, 2.0);
+
+        // Through the hole.
+        assert!(intersects(&d, Point::new(0.5, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0)).is_empty());
+        // Beyond the outer radius.
+        assert!(intersects(&d, Point::new(0.0, 5.0, 2.5), Vector::new(0.0, -1.0, 0.0)).is_empty());
+        // Parallel to the disk.
+        assert!(intersects(&d, Point::new(-5.0, 0.0, 1.5), Vector::new(1.0, 0.0, 0.0)).is_empty());
+
+        // Without an inner radius, the center is part of the disk.
+        let d = Disk::new(0.0, 2.0);
+        assert_eq!(
+            intersects(&d, Point::new(0.0, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0)).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn the_bounds_of_a_disk_are_flat() {
+        let b = Disk::new(2.0, 1.0).bounds();
+
+        assert_eq!(b.min(), Point::new(-2.0, 0.0, -2.0));
+        assert_eq!(b.max(), Point::new(2.0, 0.0, 2.0));
+    }
+
+    #[test]
+    fn sampling_the_surface_of_an_annulus() {
+        let d = Disk::new(1.0, 2.0);
+        let mut rng = SmallRng::seed_from_u64(0);
+
+        for _ in 0..1000 {
+            let (point, normal) = d.sample_surface(&mut rng);
+            let radius = (point.x() * point.x() + point.z() * point.z()).sqrt();
+            assert!((1.0..=2.0).contains(&radius));
+            assert!(point.y().approx_eq(0.0));
+            assert_eq!(normal, Vector::new(0.0, 1.0, 0.0));
+        }
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
@@ -43,6 +43,10 @@ impl Group {
         &self.children
     }
 
+    pub fn children_mut(&mut self) -> &mut Vec<Object> {
+        &mut self.children
+    }
+
     pub fn bounds(&self) -> BoundingBox {
         self.bounding_box
     }
@@ -54,9 +58,9 @@ impl Group {
 
         let (left_bbox, right_bbox) = self.bounding_box.split();
         for child in self.children {
-            if left_bbox.contains(&child.bounding_box()) {
+            if left_bbox.contains(&child.motion_bounding_box()) {
                 left_children.push(child);
-            } else if right_bbox.contains(&child.bounding_box()) {
+            } else if right_bbox.contains(&child.motion_bounding_box()) {
                 right_children.push(child);
             } else {
                 // All children that are neither contained in the left nor right
@@ -99,10 +103,10 @@ impl Group {
     fn mk_bounding_box(children: &[Object]) -> BoundingBox {
         let mut bbox = BoundingBox::new();
         for child in children {
-            bbox = bbox + child.bounding_box();
+            bbox = bbox + child.motion_bounding_box();
         }
 
-        bbox
+        bbox.padded()
     }
 }
 
@@ -121,7 +125,7 @@ impl GroupBuilder {
 
     fn rec(gb: Self, transform: &Matrix) -> Object {
         match gb {
-            GroupBuilder::Leaf(o) => o.transform(transform),
+            GroupBuilder::Leaf(o) => o.transform(transform).with_baked_transformation(),
             GroupBuilder::Node(group, children) => {
                 let child_transform = *transform * *group.transformation();
                 let new_children = children
@@ -169,8 +173,12 @@ impl GroupBuilder {
 mod tests {
     use super::*;
     use crate::{
+        float::ApproxEq,
         primitive::{Point, Tuple, Vector},
-        rtc::{IntersectionPusher, Intersections},
+        rtc::{
+            rotation_x, rotation_z, scaling, translation, Color, Intersection, IntersectionPusher,
+            Intersections, Material, Pattern,
+        },
     };
 
     struct Push<'a> {
@@ -196,6 +204,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn a_ray_tangent_to_a_sphere_in_a_group_is_not_culled() {
+        let s = Object::new_sphere();
+        let g = Object::new_group(vec![s.clone()]);
+
+        // Rounding errors make the sphere report a hit, even if the ray is just outside of
+        // the sphere bounding box.
+        let ray = Ray {
+            origin: Point::new(1.0 + f64::EPSILON, 0.0, -10.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let xs = ray.intersects(std::slice::from_ref(&s), Intersections::new());
+        assert_eq!(xs.len(), 2);
+
+        let xs = ray.intersects(std::slice::from_ref(&g), Intersections::new());
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].t(), 10.0);
+    }
+
     #[test]
     fn intersecting_a_ray_with_an_empty_group() {
         let group = Object::new_group(vec![]);
@@ -225,7 +253,7 @@ mod tests {
             direction: Vector::new(0.0, 0.0, 1.0),
         };
 
-        let objects = vec![group];
+        let objects = [group];
         let xs = ray.intersects(&objects[..], Intersections::new());
 
         assert_eq!(xs.len(), 4);
@@ -250,7 +278,7 @@ mod tests {
                 direction: Vector::new(0.0, 0.0, 1.0),
             };
 
-            let objects = vec![group_2];
+            let objects = [group_2];
             let xs = ray.intersects(&objects[..], Intersections::new());
 
             assert_eq!(xs.len(), 4);
@@ -272,7 +300,7 @@ mod tests {
                 direction: Vector::new(0.0, 0.0, 1.0),
             };
 
-            let objects = vec![group_2];
+            let objects = [group_2];
             let xs = ray.intersects(&objects[..], Intersections::new());
 
             assert_eq!(xs.len(), 4);
@@ -294,7 +322,7 @@ mod tests {
             direction: Vector::new(0.0, 0.0, 1.0),
         };
 
-        let objects = vec![group];
+        let objects = [group];
         let xs = ray.intersects(&objects[..], Intersections::new());
 
         assert_eq!(xs.len(), 2);
@@ -315,7 +343,7 @@ mod tests {
                 direction: Vector::new(0.0, 0.0, 1.0),
             };
 
-            let objects = vec![group_2];
+            let objects = [group_2];
             let xs = ray.intersects(&objects[..], Intersections::new());
 
             assert_eq!(xs.len(), 2);
@@ -331,7 +359,7 @@ mod tests {
                 direction: Vector::new(0.0, 0.0, 1.0),
             };
 
-            let objects = vec![group_2];
+            let objects = [group_2];
             let xs = ray.intersects(&objects[..], Intersections::new());
 
             assert_eq!(xs.len(), 2);
@@ -408,13 +436,53 @@ mod tests {
         }
     }
 
+    #[test]
+    fn transforming_a_group_twice_is_like_transforming_it_once() {
+        let first = translation(1.0, -2.0, 0.5) * rotation_z(0.4);
+        let second = scaling(1.0, 3.0, 0.5) * rotation_x(-1.1);
+
+        let mk_group = || {
+            let s = Object::new_sphere().translate(2.0, 0.0, 0.0).transform();
+            Object::new_group(vec![Object::new_group(vec![s]).rotate_y(0.7).transform()])
+        };
+        let twice = mk_group().transform(&first).transform(&second);
+        let once = mk_group().transform(&(second * first));
+
+        let sphere = |g: &Object| {
+            let g2 = g.shape().as_group().unwrap().children[0].clone();
+            let s = g2.shape().as_group().unwrap().children[0].clone();
+            (g2, s)
+        };
+        let (twice_g2, twice_s) = sphere(&twice);
+        let (_, once_s) = sphere(&once);
+
+        // Transformations of groups are given to their children, there is nothing left to be
+        // inverted in the groups themselves.
+        for g in [&twice, &twice_g2] {
+            assert_eq!(g.transformation(), &Matrix::id());
+            assert_eq!(g.transformation_inverse(), &Matrix::id());
+        }
+
+        assert_eq!(twice_s.transformation(), once_s.transformation());
+        assert_eq!(
+            twice_s.transformation_inverse(),
+            once_s.transformation_inverse()
+        );
+
+        let point = *once_s.transformation() * Point::new(0.6, 0.0, 0.8);
+        assert_eq!(
+            twice_s.normal_at(&point, &Intersection::new(0.0, &twice_s)),
+            once_s.normal_at(&point, &Intersection::new(0.0, &once_s))
+        );
+    }
+
     #[test]
     fn a_group_has_a_bounding_box_that_contains_its_children() {
         let s = Object::new_sphere()
             .scale(2.0, 2.0, 2.0)
             .translate(2.0, 5.0, -3.0)
             .transform();
-        let c = Object::new_cylinder(-2.0, 2.0, true)
+        let c = Object::new_cylinder(Some(-2.0), Some(2.0), true)
             .scale(0.5, 1.0, 0.5)
             .translate(-4.0, -1.0, 4.0)
             .transform();
@@ -488,6 +556,117 @@ mod tests {
         // right child
         assert_eq!(g_children[2].shape().as_group().unwrap().children()[0], s2);
     }
+
+    #[test]
+    fn groups_are_bounded_by_the_motion_of_their_children() {
+        let moving = Object::new_sphere()
+            .translate(-2.0, 0.0, 0.0)
+            .transform()
+            .with_motion(translation(2.0, 0.0, 0.0));
+        let still = Object::new_sphere().translate(-2.0, 0.0, 0.0).transform();
+
+        let g = Object::new_group(vec![moving.clone(), still.clone()]);
+        assert!(g.bounding_box().contains(&moving.motion_bounding_box()));
+
+        // The moving sphere crosses the middle of the group, so it stays at the top level.
+        let g = g.shape().as_group().unwrap().clone().partition();
+        assert_eq!(g.children()[0], moving);
+        assert_eq!(
+            g.children()[1].shape().as_group().unwrap().children()[0],
+            still
+        );
+    }
+
+    #[test]
+    fn a_baked_mesh_is_intersected_like_transformed_triangles() {
+        let mesh = vec![
+            Object::new_triangle(
+                Point::new(0.0, 1.0, 0.0),
+                Point::new(-1.0, 0.0, 0.0),
+                Point::new(1.0, 0.0, 0.0),
+            ),
+            Object::new_smooth_triangle(
+                Point::new(-1.0, 0.0, 0.0),
+                Point::new(0.0, -1.0, 0.5),
+                Point::new(1.0, 0.0, 0.0),
+                Vector::new(-1.0, 0.0, -1.0),
+                Vector::new(0.0, -1.0, -1.0),
+                Vector::new(1.0, 0.0, -1.0),
+            ),
+        ];
+        let transform = |o: Object| {
+            o.scale(1.0, 2.0, -0.5)
+                .rotate_y(0.7)
+                .translate(1.0, 2.0, 3.0)
+                .transform()
+        };
+
+        let baked = transform(Object::new_group(mesh.clone()));
+        let transformed = mesh.into_iter().map(transform).collect::<Vec<_>>();
+
+        for child in baked.shape().as_group().unwrap().children() {
+            assert_eq!(*child.transformation(), Matrix::id());
+        }
+
+        // Rays aim at points inside each triangle, away from the shared edge.
+        let m = *transformed[0].transformation();
+        let targets = [
+            m * Point::new(0.1, 0.4, 0.0),
+            m * Point::new(-0.1, -0.4, 0.2),
+        ];
+        for target in targets {
+            for i in 0..10 {
+                let origin = Point::new(-4.0 + i as f64, 2.0, -5.0);
+                let ray = Ray {
+                    origin,
+                    direction: (target - origin).normalize(),
+                };
+
+                let xs = ray.intersects(std::slice::from_ref(&baked), Intersections::new());
+                let expected = ray.intersects(&transformed, Intersections::new());
+                assert!(!xs.is_empty());
+                assert_eq!(xs.len(), expected.len());
+
+                for (x, e) in xs.iter().zip(expected.iter()) {
+                    assert!(x.t().approx_eq(e.t()));
+
+                    let point = ray.position(x.t());
+                    assert_eq!(
+                        x.object().normal_at(&point, x),
+                        e.object().normal_at(&point, e)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn a_baked_mesh_keeps_the_object_space_of_its_patterns() {
+        let triangle = Object::new_triangle(
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+        )
+        .with_material(
+            Material::new().with_pattern(Pattern::new_stripe(vec![Color::white(), Color::black()])),
+        );
+        let transform = |o: Object| o.scale(0.25, 1.0, 1.0).translate(0.3, 0.0, 0.0).transform();
+
+        let group = transform(Object::new_group(vec![triangle.clone()]));
+        let child = &group.shape().as_group().unwrap().children()[0];
+        let transformed = transform(triangle);
+
+        for x in [-0.2, 0.1, 0.3, 0.45] {
+            let point = Point::new(x, 0.1, 0.0);
+            assert_eq!(
+                child.material().pattern.pattern_at_object(child, &point),
+                transformed
+                    .material()
+                    .pattern
+                    .pattern_at_object(&transformed, &point)
+            );
+        }
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
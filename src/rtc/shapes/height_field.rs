@@ -0,0 +1,391 @@
+/* ---------------------------------------------------------------------------------------------- */
+
+use crate::{
+    float::EPSILON,
+    primitive::{Point, Tuple, Vector},
+    rtc::{BoundingBox, Canvas, IntersectionPusher, Ray},
+};
+use serde::{Deserialize, Serialize};
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Terrain over the unit square of the xz plane. Pixels are the vertices of a regular grid, columns
+// going along x and rows along z, and their luminance is their elevation. Each cell of the grid is
+// split in two triangles, but normals are interpolated over the cell for a smooth look.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HeightField {
+    width: usize,
+    depth: usize,
+    heights: Vec<f64>,
+    normals: Vec<Vector>,
+    min: f64,
+    max: f64,
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+impl HeightField {
+    // The grid needs at least 2x2 vertices: the last row and column of smaller canvases are
+    // repeated, and an empty canvas is flat.
+    pub fn new(canvas: &Canvas) -> Self {
+        let (width, depth) = (canvas.width().max(2), canvas.height().max(2));
+        let heights = (0..depth)
+            .flat_map(|row| (0..width).map(move |col| (row, col)))
+            .map(|(row, col)| {
+                if canvas.width() == 0 || canvas.height() == 0 {
+                    0.0
+                } else {
+                    canvas[row.min(canvas.height() - 1)][col.min(canvas.width() - 1)].luminance()
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let min = heights.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = heights.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+
+        let mut height_field = HeightField {
+            width,
+            depth,
+            heights,
+            normals: vec![],
+            min,
+            max,
+        };
+        height_field.normals = (0..depth)
+            .flat_map(|row| (0..width).map(move |col| (row, col)))
+            .map(|(row, col)| height_field.vertex_normal(row, col))
+            .collect();
+
+        height_field
+    }
+
+    fn height(&self, row: usize, col: usize) -> f64 {
+        self.heights[row * self.width + col]
+    }
+
+    fn vertex(&self, row: usize, col: usize) -> Point {
+        Point::new(
+            col as f64 / (self.width - 1) as f64,
+            self.height(row, col),
+            row as f64 / (self.depth - 1) as f64,
+        )
+    }
+
+    // Central differences, one-sided on the borders.
+    fn vertex_normal(&self, row: usize, col: usize) -> Vector {
+        let (left, right) = (col.saturating_sub(1), (col + 1).min(self.width - 1));
+        let (back, front) = (row.saturating_sub(1), (row + 1).min(self.depth - 1));
+
+        let dx = (right - left) as f64 / (self.width - 1) as f64;
+        let dz = (front - back) as f64 / (self.depth - 1) as f64;
+        let slope_x = (self.height(row, right) - self.height(row, left)) / dx;
+        let slope_z = (self.height(front, col) - self.height(back, col)) / dz;
+
+        Vector::new(-slope_x, 1.0, -slope_z).normalize()
+    }
+
+    // Cell containing the projection of a point, and the position of the point within it.
+    fn cell_at(&self, x: f64, z: f64) -> (usize, usize, f64, f64) {
+        let gx = (x * (self.width - 1) as f64).clamp(0.0, (self.width - 1) as f64);
+        let gz = (z * (self.depth - 1) as f64).clamp(0.0, (self.depth - 1) as f64);
+        let col = (gx.floor() as usize).min(self.width - 2);
+        let row = (gz.floor() as usize).min(self.depth - 2);
+
+        (row, col, gx - col as f64, gz - row as f64)
+    }
+
+    pub fn intersects<'a>(&self, ray: &Ray, push: &mut impl IntersectionPusher<'a>) {
+        let (t_enter, t_exit) = match self.clip(ray) {
+            Some(range) => range,
+            None => return,
+        };
+
+        // Walk the cells crossed by the projection of the ray on the xz plane.
+        let entry = ray.position(t_enter);
+        let (mut row, mut col, _, _) = self.cell_at(entry.x(), entry.z());
+
+        let axis = |direction: f64, origin: f64, cell: usize, nb_cells: usize| {
+            let cell_size = 1.0 / nb_cells as f64;
+            if direction.abs() < EPSILON {
+                (0, f64::INFINITY, f64::INFINITY)
+            } else if direction > 0.0 {
+                let boundary = (cell + 1) as f64 * cell_size;
+                (1, (boundary - origin) / direction, cell_size / direction)
+            } else {
+                let boundary = cell as f64 * cell_size;
+                (-1, (boundary - origin) / direction, -cell_size / direction)
+            }
+        };
+        let (step_col, mut t_max_x, t_delta_x) =
+            axis(ray.direction.x(), ray.origin.x(), col, self.width - 1);
+        let (step_row, mut t_max_z, t_delta_z) =
+            axis(ray.direction.z(), ray.origin.z(), row, self.depth - 1);
+
+        // Hits on the edges of cells are found twice.
+        let mut ts = vec![];
+        let mut t_cell_enter = t_enter;
+        loop {
+            let t_cell_exit = t_max_x.min(t_max_z).min(t_exit);
+            self.intersects_cell(ray, row, col, t_cell_enter, t_cell_exit, &mut ts);
+
+            if t_cell_exit >= t_exit {
+                break;
+            }
+
+            if t_max_x < t_max_z {
+                if (step_col < 0 && col == 0) || (step_col > 0 && col == self.width - 2) {
+                    break;
+                }
+                col = (col as isize + step_col) as usize;
+                t_max_x += t_delta_x;
+            } else {
+                if (step_row < 0 && row == 0) || (step_row > 0 && row == self.depth - 2) {
+                    break;
+                }
+                row = (row as isize + step_row) as usize;
+                t_max_z += t_delta_z;
+            }
+            t_cell_enter = t_cell_exit;
+        }
+
+        ts.sort_by(|lhs, rhs| lhs.partial_cmp(rhs).unwrap());
+        ts.dedup_by(|lhs, rhs| (*lhs - *rhs).abs() < EPSILON);
+        for t in ts {
+            push.t(t);
+        }
+    }
+
+    // Range of the ray within the bounding box, slightly enlarged so a flat field can be hit.
+    fn clip(&self, ray: &Ray) -> Option<(f64, f64)> {
+        let slab = |origin: f64, direction: f64, min: f64, max: f64| {
+            let (min, max) = (min - EPSILON, max + EPSILON);
+            if direction.abs() < EPSILON {
+                if origin < min || origin > max {
+                    (f64::INFINITY, f64::NEG_INFINITY)
+                } else {
+                    (f64::NEG_INFINITY, f64::INFINITY)
+                }
+            } else {
+                let (t0, t1) = ((min - origin) / direction, (max - origin) / direction);
+                (t0.min(t1), t0.max(t1))
+            }
+        };
+
+        let (x0, x1) = slab(ray.origin.x(), ray.direction.x(), 0.0, 1.0);
+        let (y0, y1) = slab(ray.origin.y(), ray.direction.y(), self.min, self.max);
+        let (z0, z1) = slab(ray.origin.z(), ray.direction.z(), 0.0, 1.0);
+
+        let (t_enter, t_exit) = (x0.max(y0).max(z0), x1.min(y1).min(z1));
+        if t_enter <= t_exit {
+            Some((t_enter, t_exit))
+        } else {
+            None
+        }
+    }
+
+    fn intersects_cell(
+        &self,
+        ray: &Ray,
+        row: usize,
+        col: usize,
+        t_enter: f64,
+        t_exit: f64,
+        ts: &mut Vec<f64>,
+    ) {
+        // The ray can only hit the cell if it crosses its range of heights.
+        let corners = [
+            (row, col),
+            (row, col + 1),
+            (row + 1, col),
+            (row + 1, col + 1),
+        ];
+        let heights = corners.map(|(row, col)| self.height(row, col));
+        let cell_min = heights.iter().cloned().fold(f64::INFINITY, f64::min);
+        let cell_max = heights.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let (y_enter, y_exit) = (ray.position(t_enter).y(), ray.position(t_exit).y());
+        if y_enter.max(y_exit) < cell_min - EPSILON || y_enter.min(y_exit) > cell_max + EPSILON {
+            return;
+        }
+
+        let [p00, p01, p10, p11] = corners.map(|(row, col)| self.vertex(row, col));
+        ts.extend(
+            [(p00, p01, p11), (p00, p11, p10)]
+                .into_iter()
+                .filter_map(|(p1, p2, p3)| HeightField::intersects_triangle(ray, p1, p2, p3)),
+        );
+    }
+
+    // Möller–Trumbore, with some tolerance on the edges so that no ray slips between triangles.
+    fn intersects_triangle(ray: &Ray, p1: Point, p2: Point, p3: Point) -> Option<f64> {
+        let e1 = p2 - p1;
+        let e2 = p3 - p1;
+        let dir_cross_e2 = ray.direction.cross(e2);
+        let det = e1.dot(dir_cross_e2);
+        if det.abs() < EPSILON {
+            return None;
+        }
+
+        let f = 1.0 / det;
+        let p1_to_origin = ray.origin - p1;
+        let u = f * p1_to_origin.dot(dir_cross_e2);
+        if !(-EPSILON..=1.0 + EPSILON).contains(&u) {
+            return None;
+        }
+
+        let origin_cross_e1 = p1_to_origin.cross(e1);
+        let v = f * ray.direction.dot(origin_cross_e1);
+        if v < -EPSILON || u + v > 1.0 + EPSILON {
+            return None;
+        }
+
+        Some(f * e2.dot(origin_cross_e1))
+    }
+
+    // Bilinear interpolation of the normals of the corners of the cell.
+    pub fn normal_at(&self, object_point: &Point) -> Vector {
+        let (row, col, fx, fz) = self.cell_at(object_point.x(), object_point.z());
+        let normal = |row: usize, col: usize| self.normals[row * self.width + col];
+
+        let back = normal(row, col) * (1.0 - fx) + normal(row, col + 1) * fx;
+        let front = normal(row + 1, col) * (1.0 - fx) + normal(row + 1, col + 1) * fx;
+
+        back * (1.0 - fz) + front * fz
+    }
+
+    pub fn bounds(&self) -> BoundingBox {
+        BoundingBox::new()
+            .with_min(Point::new(0.0, self.min, 0.0))
+            .with_max(Point::new(1.0, self.max, 1.0))
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        float::ApproxEq,
+        rtc::{Color, Object},
+    };
+
+    struct Push {
+        pub xs: Vec<f64>,
+    }
+
+    impl IntersectionPusher<'_> for Push {
+        fn t(&mut self, t: f64) {
+            self.xs.push(t);
+        }
+        fn t_u_v(&mut self, _t: f64, _u: f64, _v: f64) {
+            panic!();
+        }
+        fn set_object(&mut self, _object: &'_ Object) {
+            panic!();
+        }
+    }
+
+    fn intersects(height_field: &HeightField, origin: Point, direction: Vector) -> Vec<f64> {
+        let mut push = Push { xs: vec![] };
+        height_field.intersects(
+            &Ray {
+                origin,
+                direction: direction.normalize(),
+            },
+            &mut push,
+        );
+        push.xs.sort_by(|lhs, rhs| lhs.partial_cmp(rhs).unwrap());
+
+        push.xs
+    }
+
+    // Elevation of a pixel is its column divided by the number of columns minus one, so the
+    // surface is the plane y = x.
+    fn ramp() -> HeightField {
+        let mut canvas = Canvas::new(5, 4);
+        for row in 0..4 {
+            for col in 0..5 {
+                let height = col as f64 / 4.0;
+                canvas[row][col] = Color::new(height, height, height);
+            }
+        }
+
+        HeightField::new(&canvas)
+    }
+
+    #[test]
+    fn a_flat_height_field_is_like_a_plane() {
+        let h = HeightField::new(&Canvas::new_with_color(8, 6, Color::new(0.5, 0.5, 0.5)));
+
+        for (x, z) in [(0.1, 0.1), (0.5, 0.5), (0.93, 0.27), (0.3, 0.99)] {
+            let xs = intersects(&h, Point::new(x, 2.0, z), Vector::new(0.0, -1.0, 0.0));
+            assert_eq!(xs.len(), 1);
+            assert!(xs[0].approx_eq(1.5));
+
+            assert_eq!(
+                h.normal_at(&Point::new(x, 0.5, z)).normalize(),
+                Vector::new(0.0, 1.0, 0.0)
+            );
+        }
+
+        // Across many cells.
+        let xs = intersects(&h, Point::new(-0.5, 1.5, 0.2), Vector::new(1.0, -1.0, 0.5));
+        assert_eq!(xs.len(), 1);
+        assert!(xs[0].approx_eq(1.5));
+
+        // Parallel to the surface, or outside of the grid.
+        assert!(intersects(&h, Point::new(-1.0, 1.0, 0.5), Vector::new(1.0, 0.0, 0.0)).is_empty());
+        assert!(intersects(&h, Point::new(1.5, 2.0, 0.5), Vector::new(0.0, -1.0, 0.0)).is_empty());
+    }
+
+    #[test]
+    fn the_normal_of_a_ramp_follows_its_slope() {
+        let h = ramp();
+
+        let expected = Vector::new(-1.0, 1.0, 0.0).normalize();
+        for (x, z) in [(0.1, 0.5), (0.5, 0.5), (0.6, 0.2), (0.95, 0.95)] {
+            assert_eq!(h.normal_at(&Point::new(x, x, z)).normalize(), expected);
+        }
+
+        // Coming from above, the ray hits the plane y = x.
+        let xs = intersects(&h, Point::new(0.3, 2.0, 0.4), Vector::new(0.0, -1.0, 0.0));
+        assert_eq!(xs.len(), 1);
+        assert!(xs[0].approx_eq(1.7));
+
+        // Grazing along x, it climbs the ramp.
+        let xs = intersects(&h, Point::new(-1.0, 0.5, 0.7), Vector::new(1.0, 0.0, 0.0));
+        assert_eq!(xs.len(), 1);
+        assert!(xs[0].approx_eq(1.5));
+    }
+
+    #[test]
+    fn canvases_smaller_than_the_grid_are_stretched() {
+        let gray = Color::new(0.5, 0.5, 0.5);
+        let flat = HeightField::new(&Canvas::new_with_color(2, 2, gray));
+
+        assert_eq!(HeightField::new(&Canvas::new_with_color(1, 1, gray)), flat);
+        assert_eq!(HeightField::new(&Canvas::new_with_color(1, 2, gray)), flat);
+        assert_eq!(
+            HeightField::new(&Canvas::new(0, 0)),
+            HeightField::new(&Canvas::new(2, 2))
+        );
+
+        let mut canvas = Canvas::new(3, 1);
+        canvas[0][2] = Color::white();
+        let h = HeightField::new(&canvas);
+        assert_eq!(h.bounds().max(), Point::new(1.0, 1.0, 1.0));
+        assert!(
+            !intersects(&h, Point::new(0.75, 2.0, 0.5), Vector::new(0.0, -1.0, 0.0)).is_empty()
+        );
+    }
+
+    #[test]
+    fn the_bounds_of_a_height_field() {
+        let b = ramp().bounds();
+
+        assert_eq!(b.min(), Point::new(0.0, 0.0, 0.0));
+        assert_eq!(b.max(), Point::new(1.0, 1.0, 1.0));
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
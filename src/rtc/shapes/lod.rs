@@ -0,0 +1,197 @@
+/* ---------------------------------------------------------------------------------------------- */
+
+use crate::{
+    primitive::{Matrix, Point, Vector},
+    rtc::{BoundingBox, IntersectionPusher, Object, Ray, Transform},
+};
+use serde::{Deserialize, Serialize};
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Levels of detail, each associated to the minimal distance at which it is used.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Lod {
+    bounding_box: BoundingBox,
+    levels: Vec<(f64, Object)>,
+    // The distance is measured from this point, usually the position of the camera, so all rays
+    // see the same level. Without it, each ray measures it from its origin: shadows and reflections
+    // may then see other levels than the camera.
+    viewpoint: Option<Point>,
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+impl Lod {
+    pub fn new(mut levels: Vec<(f64, Object)>) -> Self {
+        levels.sort_by(|(lhs, _), (rhs, _)| lhs.total_cmp(rhs));
+
+        let bounding_box = levels
+            .iter()
+            .fold(BoundingBox::new(), |acc, (_, object)| {
+                acc + object.bounding_box()
+            })
+            .padded();
+
+        Self {
+            bounding_box,
+            levels,
+            viewpoint: None,
+        }
+    }
+
+    pub fn intersects<'a>(&'a self, ray: &Ray, push: &mut impl IntersectionPusher<'a>) {
+        if self.bounding_box.is_intersected(ray) {
+            let viewpoint = self.viewpoint.unwrap_or(ray.origin);
+            if let Some(object) = self.level(self.bounding_box.distance_to(&viewpoint)) {
+                push.set_object(object);
+                object.intersects(ray, push);
+            }
+        }
+    }
+
+    pub fn normal_at(&self, _object_point: &Point) -> Vector {
+        unreachable!()
+    }
+
+    pub fn bounds(&self) -> BoundingBox {
+        self.bounding_box
+    }
+
+    pub fn level(&self, distance: f64) -> Option<&Object> {
+        self.levels
+            .iter()
+            .rev()
+            .find(|(min_distance, _)| *min_distance <= distance)
+            .or_else(|| self.levels.first())
+            .map(|(_, object)| object)
+    }
+
+    pub fn levels(&self) -> &Vec<(f64, Object)> {
+        &self.levels
+    }
+
+    // Also applies to the levels of detail nested in the levels.
+    pub fn set_viewpoint(&mut self, viewpoint: &Point) {
+        self.viewpoint = Some(*viewpoint);
+        for (_, object) in &mut self.levels {
+            object.set_lod_viewpoint(viewpoint);
+        }
+    }
+
+    pub fn divide(self, threshold: usize) -> Self {
+        let levels = self
+            .levels
+            .into_iter()
+            .map(|(distance, object)| (distance, object.divide(threshold)))
+            .collect();
+
+        Self { levels, ..self }
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+impl Transform for Lod {
+    fn transform(self, transformation: &Matrix) -> Self {
+        Lod {
+            viewpoint: self.viewpoint,
+            ..Lod::new(
+                self.levels
+                    .into_iter()
+                    .map(|(distance, object)| (distance, object.transform(transformation)))
+                    .collect(),
+            )
+        }
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        primitive::Tuple,
+        rtc::{Intersections, Shape},
+    };
+
+    #[test]
+    fn the_level_of_detail_depends_on_the_distance_to_the_object() {
+        let lod = Object::new_lod(vec![
+            (20.0, Object::new_cube()),
+            (0.0, Object::new_sphere()),
+        ])
+        .translate(0.0, 0.0, 10.0)
+        .transform();
+
+        let close_ray = Ray {
+            origin: Point::new(0.0, 0.0, 0.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+        let xs = close_ray.intersects(std::slice::from_ref(&lod), Intersections::new());
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs.hit().unwrap().object().shape(), &Shape::Sphere());
+        assert_eq!(xs.hit().unwrap().t(), 9.0);
+
+        let far_ray = Ray {
+            origin: Point::new(0.9, 0.9, -50.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+        let xs = far_ray.intersects(std::slice::from_ref(&lod), Intersections::new());
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs.hit().unwrap().object().shape(), &Shape::Cube());
+        assert_eq!(xs.hit().unwrap().t(), 59.0);
+    }
+
+    #[test]
+    fn a_lod_has_the_bounding_box_of_all_its_levels() {
+        let lod = Lod::new(vec![
+            (
+                0.0,
+                Object::new_sphere().translate(0.0, 2.0, 0.0).transform(),
+            ),
+            (10.0, Object::new_cube().scale(3.0, 1.0, 1.0).transform()),
+        ]);
+
+        assert_eq!(lod.bounds().min(), Point::new(-3.0, -1.0, -1.0));
+        assert_eq!(lod.bounds().max(), Point::new(3.0, 3.0, 1.0));
+    }
+
+    #[test]
+    fn the_level_of_detail_depends_on_the_distance_to_the_viewpoint() {
+        let mut lod = Object::new_lod(vec![
+            (20.0, Object::new_cube()),
+            (0.0, Object::new_sphere()),
+        ])
+        .translate(0.0, 0.0, 10.0)
+        .transform();
+        lod.set_lod_viewpoint(&Point::zero());
+
+        // A shadow ray coming from far away sees the level seen from the viewpoint.
+        let far_ray = Ray {
+            origin: Point::new(0.9, 0.9, -50.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+        let xs = far_ray.intersects(std::slice::from_ref(&lod), Intersections::new());
+        assert!(xs.is_empty());
+
+        let far_ray = Ray {
+            origin: Point::new(0.0, 0.0, -50.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+        let xs = far_ray.intersects(std::slice::from_ref(&lod), Intersections::new());
+        assert_eq!(xs.hit().unwrap().object().shape(), &Shape::Sphere());
+    }
+
+    #[test]
+    fn levels_with_a_nan_distance_do_not_panic() {
+        let lod = Lod::new(vec![
+            (f64::NAN, Object::new_cube()),
+            (0.0, Object::new_sphere()),
+        ]);
+
+        assert_eq!(lod.level(5.0).unwrap().shape(), &Shape::Sphere());
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
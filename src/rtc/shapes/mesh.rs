@@ -0,0 +1,328 @@
+/* ---------------------------------------------------------------------------------------------- */
+
+use crate::{
+    primitive::{Point, Vector},
+    rtc::{shapes::Triangle, BoundingBox, Intersection, IntersectionPusher, Ray},
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Triangles sharing their vertices and a single object (material, transformation, bounding box).
+// Faces are given as indices of vertices. Without normals, faces are flat.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Mesh {
+    vertices: Vec<Point>,
+    normals: Vec<Vector>,
+    faces: Vec<[usize; 3]>,
+    // Cumulative areas of the faces, to sample them by area.
+    cumulative_areas: Vec<f64>,
+    bounding_box: BoundingBox,
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+impl Mesh {
+    pub fn new(vertices: Vec<Point>, normals: Vec<Vector>, faces: Vec<[usize; 3]>) -> Self {
+        assert!(
+            normals.is_empty() || normals.len() == vertices.len(),
+            "A mesh needs either no normals or one per vertex"
+        );
+        assert!(
+            faces.iter().flatten().all(|&index| index < vertices.len()),
+            "A face of a mesh refers to a non-existing vertex"
+        );
+
+        let bounding_box = vertices
+            .iter()
+            .fold(BoundingBox::new(), |acc, p| acc.add_point(*p));
+
+        let cumulative_areas = faces
+            .iter()
+            .scan(0.0, |sum, [v1, v2, v3]| {
+                let p1 = vertices[*v1];
+                *sum += ((vertices[*v2] - p1) * (vertices[*v3] - p1)).magnitude() / 2.0;
+                Some(*sum)
+            })
+            .collect();
+
+        Mesh {
+            vertices,
+            normals,
+            faces,
+            cumulative_areas,
+            bounding_box,
+        }
+    }
+
+    // Each vertex after the first two adds a face with the two previous ones. Every other face is
+    // flipped, so all faces have the same winding.
+    pub fn strip_faces(nb_vertices: usize) -> Vec<[usize; 3]> {
+        (2..nb_vertices.max(2))
+            .map(|i| {
+                if i % 2 == 0 {
+                    [i - 2, i - 1, i]
+                } else {
+                    [i - 1, i - 2, i]
+                }
+            })
+            .collect()
+    }
+
+    // Each vertex after the first two adds a face with the first and the previous ones.
+    pub fn fan_faces(nb_vertices: usize) -> Vec<[usize; 3]> {
+        (2..nb_vertices.max(2)).map(|i| [0, i - 1, i]).collect()
+    }
+
+    pub fn intersects<'a>(&self, ray: &Ray, push: &mut impl IntersectionPusher<'a>) {
+        if !self.bounding_box.is_intersected(ray) {
+            return;
+        }
+
+        for (face, [v1, v2, v3]) in self.faces.iter().enumerate() {
+            let p1 = self.vertices[*v1];
+            let e1 = self.vertices[*v2] - p1;
+            let e2 = self.vertices[*v3] - p1;
+
+            if let Some((t, u, v)) = Triangle::intersection(p1, e1, e2, ray) {
+                push.t_u_v_face(t, u, v, face);
+            }
+        }
+    }
+
+    // Same normals as the ones of Triangle and SmoothTriangle.
+    pub fn normal_at(&self, _object_point: &Point, hit: &Intersection) -> Vector {
+        let [v1, v2, v3] = self.faces[hit.face()];
+
+        if self.normals.is_empty() {
+            let p1 = self.vertices[v1];
+            ((self.vertices[v3] - p1) * (self.vertices[v2] - p1)).normalize()
+        } else {
+            self.normals[v2] * hit.u()
+                + self.normals[v3] * hit.v()
+                + self.normals[v1] * (1.0 - hit.u() - hit.v())
+        }
+    }
+
+    pub fn bounds(&self) -> BoundingBox {
+        self.bounding_box
+    }
+
+    // A face is picked with a probability proportional to its area, then a point within it like
+    // Triangle and SmoothTriangle do. Meshes without area can't be sampled.
+    pub fn sample_surface(&self, rng: &mut impl Rng) -> Option<(Point, Vector)> {
+        let total_area = *self.cumulative_areas.last()?;
+        if total_area <= 0.0 {
+            return None;
+        }
+
+        let target = rng.gen::<f64>() * total_area;
+        let face = self
+            .cumulative_areas
+            .partition_point(|&area| area <= target)
+            .min(self.faces.len() - 1);
+        let [v1, v2, v3] = self.faces[face];
+
+        let (u, v) = Triangle::sample_u_v(rng);
+        let p1 = self.vertices[v1];
+        let (e1, e2) = (self.vertices[v2] - p1, self.vertices[v3] - p1);
+        let point = p1 + e1 * u + e2 * v;
+
+        let normal = if self.normals.is_empty() {
+            e2 * e1
+        } else {
+            self.normals[v2] * u + self.normals[v3] * v + self.normals[v1] * (1.0 - u - v)
+        };
+
+        Some((point, normal.normalize()))
+    }
+
+    pub fn vertices(&self) -> &[Point] {
+        &self.vertices
+    }
+
+    pub fn normals(&self) -> &[Vector] {
+        &self.normals
+    }
+
+    pub fn faces(&self) -> &[[usize; 3]] {
+        &self.faces
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        float::EPSILON,
+        primitive::Tuple,
+        rtc::{Axis, Intersections, Object, Transform},
+    };
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    fn vertices() -> Vec<Point> {
+        vec![
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(0.0, -1.0, 0.0),
+        ]
+    }
+
+    fn rays() -> Vec<Ray> {
+        [
+            (Point::new(0.0, 0.5, -2.0), Vector::new(0.0, 0.0, 1.0)),
+            (Point::new(0.2, -0.5, -2.0), Vector::new(0.0, 0.0, 1.0)),
+            (Point::new(-0.3, -0.3, 2.0), Vector::new(0.1, 0.0, -1.0)),
+            (Point::new(0.9, 0.9, -2.0), Vector::new(0.0, 0.0, 1.0)),
+            (Point::new(0.0, 0.0, -2.0), Vector::new(0.0, 1.0, 0.0)),
+        ]
+        .iter()
+        .map(|&(origin, direction)| Ray { origin, direction })
+        .collect()
+    }
+
+    fn hits(objects: &[Object], ray: &Ray) -> Vec<(f64, Vector)> {
+        ray.intersects(objects, Intersections::new())
+            .sort()
+            .iter()
+            .map(|i| {
+                let point = ray.position(i.t());
+                (i.t(), i.object().normal_at(&point, i))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_mesh_intersects_like_separate_triangles() {
+        let v = vertices();
+        let mesh = [Object::new_mesh(
+            v.clone(),
+            vec![],
+            vec![[0, 1, 2], [1, 3, 2]],
+        )];
+        let triangles = [
+            Object::new_triangle(v[0], v[1], v[2]),
+            Object::new_triangle(v[1], v[3], v[2]),
+        ];
+
+        for ray in rays() {
+            assert_eq!(hits(&mesh, &ray), hits(&triangles, &ray));
+        }
+    }
+
+    #[test]
+    fn a_smooth_mesh_intersects_like_separate_smooth_triangles() {
+        let v = vertices();
+        let n = vec![
+            Vector::new(0.0, 1.0, -1.0),
+            Vector::new(-1.0, 0.0, -1.0),
+            Vector::new(1.0, 0.0, -1.0),
+            Vector::new(0.0, -1.0, -1.0),
+        ];
+        let mesh = [Object::new_mesh(
+            v.clone(),
+            n.clone(),
+            vec![[0, 1, 2], [1, 3, 2]],
+        )];
+        let triangles = [
+            Object::new_smooth_triangle(v[0], v[1], v[2], n[0], n[1], n[2]),
+            Object::new_smooth_triangle(v[1], v[3], v[2], n[1], n[3], n[2]),
+        ];
+
+        for ray in rays() {
+            assert_eq!(hits(&mesh, &ray), hits(&triangles, &ray));
+        }
+    }
+
+    #[test]
+    fn a_mirrored_mesh_intersects_like_mirrored_triangles() {
+        let v = vertices();
+        let n = vec![
+            Vector::new(0.0, 1.0, -1.0),
+            Vector::new(-1.0, 0.0, -1.0),
+            Vector::new(1.0, 0.0, -1.0),
+            Vector::new(0.0, -1.0, -1.0),
+        ];
+        let mesh = |normals: Vec<Vector>| {
+            Object::new_mesh(v.clone(), normals, vec![[0, 1, 2], [1, 3, 2]])
+                .translate(0.5, 0.0, 0.0)
+                .transform()
+                .mirror(Axis::X)
+        };
+        let triangles = [
+            Object::new_triangle(v[0], v[1], v[2]),
+            Object::new_triangle(v[1], v[3], v[2]),
+        ]
+        .map(|t| t.translate(0.5, 0.0, 0.0).transform().mirror(Axis::X));
+        let smooth_triangles = [
+            Object::new_smooth_triangle(v[0], v[1], v[2], n[0], n[1], n[2]),
+            Object::new_smooth_triangle(v[1], v[3], v[2], n[1], n[3], n[2]),
+        ]
+        .map(|t| t.translate(0.5, 0.0, 0.0).transform().mirror(Axis::X));
+
+        for ray in rays() {
+            assert_eq!(hits(&[mesh(vec![])], &ray), hits(&triangles, &ray));
+            assert_eq!(
+                hits(&[mesh(n.clone())], &ray),
+                hits(&smooth_triangles, &ray)
+            );
+        }
+    }
+
+    #[test]
+    fn faces_of_strips_and_fans() {
+        assert_eq!(Mesh::strip_faces(5), vec![[0, 1, 2], [2, 1, 3], [2, 3, 4]]);
+        assert_eq!(Mesh::fan_faces(5), vec![[0, 1, 2], [0, 2, 3], [0, 3, 4]]);
+        assert!(Mesh::strip_faces(2).is_empty());
+        assert!(Mesh::fan_faces(0).is_empty());
+    }
+
+    #[test]
+    fn the_bounds_of_a_mesh() {
+        let mesh = Mesh::new(vertices(), vec![], vec![[0, 1, 2]]);
+
+        assert_eq!(mesh.bounds().min(), Point::new(-1.0, -1.0, 0.0));
+        assert_eq!(mesh.bounds().max(), Point::new(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn sampling_the_surface_of_a_mesh_picks_faces_by_area() {
+        // The face at z = 1 is three times larger than the one at z = 0.
+        let v = vec![
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(0.0, 0.0, 1.0),
+            Point::new(0.0, 1.0, 1.0),
+            Point::new(3.0, 0.0, 1.0),
+        ];
+        let mesh = Mesh::new(v.clone(), vec![], vec![[0, 1, 2], [3, 4, 5]]);
+        let small = Triangle::new(v[0], v[1], v[2]);
+        let mut rng = SmallRng::seed_from_u64(0);
+
+        let nb_samples = 4000;
+        let mut nb_large = 0;
+        for _ in 0..nb_samples {
+            let (point, normal) = mesh.sample_surface(&mut rng).unwrap();
+            assert_eq!(normal, small.normal_at(&point));
+            assert!(point.x() >= 0.0 && point.y() >= 0.0);
+
+            if point.z() == 1.0 {
+                assert!(point.x() / 3.0 + point.y() <= 1.0 + EPSILON);
+                nb_large += 1;
+            } else {
+                assert_eq!(point.z(), 0.0);
+                assert!(point.x() + point.y() <= 1.0 + EPSILON);
+            }
+        }
+        assert!((nb_large as f64 / nb_samples as f64 - 0.75).abs() < 0.03);
+
+        assert_eq!(Mesh::new(v, vec![], vec![]).sample_surface(&mut rng), None);
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
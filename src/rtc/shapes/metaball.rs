@@ -0,0 +1,305 @@
+/* ---------------------------------------------------------------------------------------------- */
+
+use crate::{
+    primitive::{Point, Tuple, Vector},
+    rtc::{BoundingBox, IntersectionPusher, Ray},
+};
+use serde::{Deserialize, Serialize};
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Number of marching steps across the smallest ball.
+const STEPS_PER_BALL: f64 = 16.0;
+// Number of bisections to refine a crossing of the surface.
+const REFINEMENTS: usize = 40;
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Ball {
+    center: Point,
+    radius: f64,
+    strength: f64,
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Implicit surface where the sum of the fields of the balls equals the threshold. The field of a
+// ball smoothly decreases from its strength at its center to 0 at its radius.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MetaBall {
+    balls: Vec<Ball>,
+    threshold: f64,
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+impl MetaBall {
+    // Balls are given as (center, radius, strength).
+    pub fn new(balls: Vec<(Point, f64, f64)>, threshold: f64) -> Self {
+        assert!(
+            threshold > 0.0 && threshold.is_finite(),
+            "The threshold of metaballs must be positive"
+        );
+        assert!(
+            balls.iter().all(|(center, radius, strength)| {
+                [center.x(), center.y(), center.z(), *strength]
+                    .iter()
+                    .all(|v| v.is_finite())
+                    && radius.abs() > 0.0
+                    && radius.is_finite()
+            }),
+            "The balls of metaballs must have a positive radius and finite values"
+        );
+
+        MetaBall {
+            balls: balls
+                .into_iter()
+                .map(|(center, radius, strength)| Ball {
+                    center,
+                    radius: radius.abs(),
+                    strength,
+                })
+                .collect(),
+            threshold,
+        }
+    }
+
+    fn field(&self, point: &Point) -> f64 {
+        self.balls
+            .iter()
+            .map(|ball| {
+                let v = *point - ball.center;
+                let d2 = v.dot(v) / ball.radius.powi(2);
+                if d2 < 1.0 {
+                    ball.strength * (1.0 - d2).powi(2)
+                } else {
+                    0.0
+                }
+            })
+            .sum::<f64>()
+            - self.threshold
+    }
+
+    // Parts of the ray within the influence of at least one ball, sorted and merged.
+    fn influence_intervals(&self, ray: &Ray) -> Vec<(f64, f64)> {
+        let a = ray.direction.dot(ray.direction);
+
+        let mut intervals = self
+            .balls
+            .iter()
+            .filter_map(|ball| {
+                let ball_to_ray = ray.origin - ball.center;
+                let b = 2.0 * ray.direction.dot(ball_to_ray);
+                let c = ball_to_ray.dot(ball_to_ray) - ball.radius.powi(2);
+                let discriminant = b.powi(2) - 4.0 * a * c;
+
+                if discriminant < 0.0 {
+                    None
+                } else {
+                    let sqrt = discriminant.sqrt();
+                    Some(((-b - sqrt) / (2.0 * a), (-b + sqrt) / (2.0 * a)))
+                }
+            })
+            .collect::<Vec<_>>();
+
+        intervals.sort_by(|lhs, rhs| lhs.0.total_cmp(&rhs.0));
+
+        intervals
+            .into_iter()
+            .fold(Vec::<(f64, f64)>::new(), |mut acc, (t0, t1)| {
+                match acc.last_mut() {
+                    Some(last) if t0 <= last.1 => last.1 = last.1.max(t1),
+                    _ => acc.push((t0, t1)),
+                }
+                acc
+            })
+    }
+
+    pub fn intersects<'a>(&self, ray: &Ray, push: &mut impl IntersectionPusher<'a>) {
+        let min_radius = self
+            .balls
+            .iter()
+            .map(|ball| ball.radius)
+            .fold(f64::INFINITY, f64::min);
+        let step = min_radius / STEPS_PER_BALL / ray.direction.magnitude();
+
+        let field_at = |t: f64| self.field(&ray.position(t));
+
+        for (start, end) in self.influence_intervals(ray) {
+            let mut t = start;
+            let mut value = field_at(t);
+
+            while t < end {
+                let next_t = (t + step).min(end);
+                let next_value = field_at(next_t);
+
+                if (value < 0.0) != (next_value < 0.0) {
+                    let (mut low, mut high) = (t, next_t);
+                    for _ in 0..REFINEMENTS {
+                        let middle = 0.5 * (low + high);
+                        if (field_at(middle) < 0.0) == (value < 0.0) {
+                            low = middle;
+                        } else {
+                            high = middle;
+                        }
+                    }
+                    push.t(0.5 * (low + high));
+                }
+
+                t = next_t;
+                value = next_value;
+            }
+        }
+    }
+
+    // The field decreases outward, so the normal is opposite to its gradient.
+    pub fn normal_at(&self, object_point: &Point) -> Vector {
+        self.balls
+            .iter()
+            .fold(Vector::zero(), |acc, ball| {
+                let v = *object_point - ball.center;
+                let r2 = ball.radius.powi(2);
+                let d2 = v.dot(v) / r2;
+
+                if d2 < 1.0 {
+                    acc + v * (4.0 * ball.strength * (1.0 - d2) / r2)
+                } else {
+                    acc
+                }
+            })
+            .normalize()
+    }
+
+    pub fn bounds(&self) -> BoundingBox {
+        self.balls.iter().fold(BoundingBox::new(), |acc, ball| {
+            let r = Vector::new(ball.radius, ball.radius, ball.radius);
+            acc.add_point(ball.center - r).add_point(ball.center + r)
+        })
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        float::ApproxEq,
+        rtc::{IntersectionPusher, Object},
+    };
+
+    struct Push {
+        pub xs: Vec<f64>,
+    }
+
+    impl IntersectionPusher<'_> for Push {
+        fn t(&mut self, t: f64) {
+            self.xs.push(t);
+        }
+        fn t_u_v(&mut self, _t: f64, _u: f64, _v: f64) {
+            panic!();
+        }
+        fn set_object(&mut self, _object: &'_ Object) {
+            panic!();
+        }
+    }
+
+    fn intersects(metaball: &MetaBall, origin: Point, direction: Vector) -> Vec<f64> {
+        let mut push = Push { xs: vec![] };
+        metaball.intersects(&Ray { origin, direction }, &mut push);
+
+        push.xs
+    }
+
+    fn two_balls() -> MetaBall {
+        MetaBall::new(
+            vec![
+                (Point::new(-0.6, 0.0, 0.0), 1.0, 1.0),
+                (Point::new(0.6, 0.0, 0.0), 1.0, 1.0),
+            ],
+            0.5,
+        )
+    }
+
+    #[test]
+    fn overlapping_metaballs_merge_into_a_single_surface() {
+        let single = MetaBall::new(vec![(Point::new(-0.6, 0.0, 0.0), 1.0, 1.0)], 0.5);
+        let between = (Point::new(0.0, -5.0, 0.0), Vector::new(0.0, 1.0, 0.0));
+
+        // Between the balls, neither of them alone reaches the threshold.
+        assert!(intersects(&single, between.0, between.1).is_empty());
+        assert_eq!(intersects(&two_balls(), between.0, between.1).len(), 2);
+
+        // Along the balls, the surface is entered and exited only once.
+        let xs = intersects(
+            &two_balls(),
+            Point::new(-5.0, 0.0, 0.0),
+            Vector::new(1.0, 0.0, 0.0),
+        );
+        // The field of a single ball reaches the threshold at 1 - sqrt(0.5) of its squared radius.
+        let radius = (1.0 - 0.5_f64.sqrt()).sqrt();
+        assert_eq!(xs.len(), 2);
+        assert!(xs[0].approx_eq(5.0 - 0.6 - radius));
+        assert!(xs[1].approx_eq(5.0 + 0.6 + radius));
+    }
+
+    #[test]
+    fn a_ray_misses_metaballs() {
+        let xs = intersects(
+            &two_balls(),
+            Point::new(0.0, 5.0, -5.0),
+            Vector::new(0.0, 0.0, 1.0),
+        );
+
+        assert!(xs.is_empty());
+    }
+
+    #[test]
+    fn the_normal_of_metaballs() {
+        let m = two_balls();
+        let radius = (1.0 - 0.5_f64.sqrt()).sqrt();
+
+        assert_eq!(
+            m.normal_at(&Point::new(-0.6 - radius, 0.0, 0.0)),
+            Vector::new(-1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            m.normal_at(&Point::new(0.0, 0.3, 0.0)),
+            Vector::new(0.0, 1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn the_bounds_of_metaballs_enclose_their_influence() {
+        let b = two_balls().bounds();
+
+        assert_eq!(b.min(), Point::new(-1.6, -1.0, -1.0));
+        assert_eq!(b.max(), Point::new(1.6, 1.0, 1.0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn metaballs_need_a_positive_radius() {
+        MetaBall::new(vec![(Point::zero(), 0.0, 1.0)], 0.5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn metaballs_need_finite_values() {
+        MetaBall::new(vec![(Point::new(f64::NAN, 0.0, 0.0), 1.0, 1.0)], 0.5);
+    }
+
+    #[test]
+    fn a_ray_with_a_non_finite_direction_misses_metaballs() {
+        let xs = intersects(
+            &two_balls(),
+            Point::new(-5.0, 0.0, 0.0),
+            Vector::new(f64::NAN, 0.0, 0.0),
+        );
+
+        assert!(xs.is_empty());
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
@@ -76,7 +76,7 @@ mod tests {
         let mut push = Push { xs: vec![] };
 
         p.intersects(&ray, &mut push);
-        assert!(push.xs.len() == 0);
+        assert!(push.xs.is_empty());
     }
 
     #[test]
@@ -90,7 +90,7 @@ mod tests {
         let mut push = Push { xs: vec![] };
 
         p.intersects(&ray, &mut push);
-        assert!(push.xs.len() == 0);
+        assert!(push.xs.is_empty());
     }
 
     #[test]
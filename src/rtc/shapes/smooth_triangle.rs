@@ -1,9 +1,10 @@
 /* ---------------------------------------------------------------------------------------------- */
 
 use crate::{
-    primitive::{Point, Vector},
+    primitive::{Matrix, Point, Vector},
     rtc::{shapes::Triangle, BoundingBox, Intersection, IntersectionPusher, Ray},
 };
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -41,6 +42,25 @@ impl SmoothTriangle {
         self.triangle.bounds()
     }
 
+    // Normals are not normalized, so interpolating them gives the same direction as
+    // interpolating the original ones and then transforming the result.
+    pub fn transform(&self, transformation: &Matrix, inverse_transpose: &Matrix) -> Self {
+        Self {
+            triangle: self.triangle.transform(transformation, inverse_transpose),
+            n1: *inverse_transpose * self.n1,
+            n2: *inverse_transpose * self.n2,
+            n3: *inverse_transpose * self.n3,
+        }
+    }
+
+    pub fn sample_surface(&self, rng: &mut impl Rng) -> (Point, Vector) {
+        let (u, v) = Triangle::sample_u_v(rng);
+        let point = self.p1() + (self.p2() - self.p1()) * u + (self.p3() - self.p1()) * v;
+        let normal = self.n2 * u + self.n3 * v + self.n1 * (1.0 - u - v);
+
+        (point, normal.normalize())
+    }
+
     pub fn p1(&self) -> Point {
         self.triangle.p1()
     }
@@ -53,6 +73,24 @@ impl SmoothTriangle {
         self.triangle.p3()
     }
 
+    pub fn with_uvs(mut self, uvs: [(f64, f64); 3]) -> Self {
+        self.triangle = self.triangle.with_uvs(uvs);
+
+        self
+    }
+
+    pub fn uvs(&self) -> Option<[(f64, f64); 3]> {
+        self.triangle.uvs()
+    }
+
+    pub fn barycentric(&self, point: &Point) -> (f64, f64) {
+        self.triangle.barycentric(point)
+    }
+
+    pub fn uv_at(&self, u: f64, v: f64) -> Option<(f64, f64)> {
+        self.triangle.uv_at(u, v)
+    }
+
     pub fn n1(&self) -> Vector {
         self.n1
     }
@@ -76,7 +114,6 @@ pub mod tests {
         primitive::Tuple,
         rtc::{IntersectionState, Intersections, Object},
     };
-    use std::f64::INFINITY;
 
     struct Push {
         pub xs: Vec<(f64, f64, f64)>,
@@ -84,7 +121,7 @@ pub mod tests {
 
     impl IntersectionPusher<'_> for Push {
         fn t(&mut self, t: f64) {
-            self.xs.push((t, INFINITY, INFINITY));
+            self.xs.push((t, f64::INFINITY, f64::INFINITY));
         }
         fn t_u_v(&mut self, t: f64, u: f64, v: f64) {
             self.xs.push((t, u, v));
@@ -4,6 +4,7 @@ use crate::{
     primitive::{Point, Tuple, Vector},
     rtc::{BoundingBox, IntersectionPusher, Ray},
 };
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -17,24 +18,21 @@ pub struct Sphere {}
 /* ---------------------------------------------------------------------------------------------- */
 
 impl Sphere {
-    #[allow(clippy::eq_op)]
+    // Geometric method: the center of the sphere is projected on the ray, and misses are rejected
+    // from their distance to the center. It's as fast as solving the quadratic equation, but
+    // suffers less from cancellation for distant spheres.
     pub fn intersects<'a>(ray: &Ray, push: &mut impl IntersectionPusher<'a>) {
-        let sphere_to_ray = ray.origin - Point::new(0.0, 0.0, 0.0);
+        let ray_to_sphere = Point::new(0.0, 0.0, 0.0) - ray.origin;
 
-        let a = ray.direction ^ ray.direction;
-        let b = 2.0 * (ray.direction ^ sphere_to_ray);
-        let c = (sphere_to_ray ^ sphere_to_ray) - 1.0;
-        let discriminant = b.powi(2) - (4.0 * a * c);
+        let a = ray.direction.dot(ray.direction);
+        let t_center = ray.direction.dot(ray_to_sphere) / a;
+        let distance2 = ray_to_sphere.dot(ray_to_sphere) - t_center * t_center * a;
 
-        if discriminant >= 0.0 {
-            let sqrt_discriminant = f64::sqrt(discriminant);
-            let double_a = 2.0 * a;
+        if distance2 <= 1.0 {
+            let half_chord = f64::sqrt((1.0 - distance2) / a);
 
-            let t1 = (-b - sqrt_discriminant) / double_a;
-            let t2 = (-b + sqrt_discriminant) / double_a;
-
-            push.t(t1);
-            push.t(t2);
+            push.t(t_center - half_chord);
+            push.t(t_center + half_chord);
         }
     }
 
@@ -47,6 +45,16 @@ impl Sphere {
             .with_min(Point::new(-1.0, -1.0, -1.0))
             .with_max(Point::new(1.0, 1.0, 1.0))
     }
+
+    pub fn sample_surface(rng: &mut impl Rng) -> (Point, Vector) {
+        let z = 1.0 - 2.0 * rng.gen::<f64>();
+        let radius = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * std::f64::consts::PI * rng.gen::<f64>();
+
+        let normal = Vector::new(radius * phi.cos(), radius * phi.sin(), z);
+
+        (Point::zero() + normal, normal)
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -54,9 +62,11 @@ impl Sphere {
 #[cfg(test)]
 pub mod tests {
     use super::*;
+    use crate::float::ApproxEq;
     use crate::rtc::{
         scaling, Intersection, IntersectionState, Intersections, Material, Object, Transform,
     };
+    use rand::{rngs::SmallRng, SeedableRng};
 
     struct Push {
         pub xs: Vec<f64>,
@@ -213,11 +223,12 @@ pub mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn normal_on_a_translated_sphere() {
         let s = Object::new_sphere().translate(0.0, 1.0, 0.0).transform();
         let dummy_object = Object::new_test_shape();
 
-        let dummy_intersection = Intersection::new(std::f64::INFINITY, &dummy_object);
+        let dummy_intersection = Intersection::new(f64::INFINITY, &dummy_object);
         assert_eq!(
             s.normal_at(&Point::new(0.0, 1.70711, -0.70711), &dummy_intersection),
             Vector::new(0.0, 0.70711, -0.70711)
@@ -232,7 +243,7 @@ pub mod tests {
             .transform();
 
         let dummy_object = Object::new_test_shape();
-        let dummy_intersection = Intersection::new(std::f64::INFINITY, &dummy_object);
+        let dummy_intersection = Intersection::new(f64::INFINITY, &dummy_object);
 
         assert_eq!(
             s.normal_at(
@@ -304,6 +315,114 @@ pub mod tests {
         assert_eq!(s.bounding_box().min(), Point::new(0.5, -5.0, 1.0));
         assert_eq!(s.bounding_box().max(), Point::new(1.5, -1.0, 9.0));
     }
+
+    #[test]
+    fn sampling_the_surface_of_a_sphere() {
+        let mut rng = SmallRng::seed_from_u64(0);
+
+        for _ in 0..100 {
+            let (point, normal) = Sphere::sample_surface(&mut rng);
+
+            assert!((point - Point::zero()).magnitude().approx_eq(1.0));
+            assert_eq!(normal, Sphere::normal_at(&point));
+        }
+    }
+
+    // The algebraic method, solving the quadratic equation, used to be the default one.
+    fn intersects_algebraic(ray: &Ray) -> Vec<f64> {
+        let sphere_to_ray = ray.origin - Point::new(0.0, 0.0, 0.0);
+
+        let a = ray.direction.dot(ray.direction);
+        let b = 2.0 * ray.direction.dot(sphere_to_ray);
+        let c = sphere_to_ray.dot(sphere_to_ray) - 1.0;
+        let discriminant = b.powi(2) - (4.0 * a * c);
+
+        if discriminant >= 0.0 {
+            let sqrt_discriminant = f64::sqrt(discriminant);
+
+            vec![
+                (-b - sqrt_discriminant) / (2.0 * a),
+                (-b + sqrt_discriminant) / (2.0 * a),
+            ]
+        } else {
+            vec![]
+        }
+    }
+
+    #[test]
+    fn geometric_and_algebraic_intersections_agree() {
+        let test = |origin: Point, direction: Vector| {
+            let ray = Ray { origin, direction };
+            let mut push = Push { xs: vec![] };
+            Sphere::intersects(&ray, &mut push);
+
+            let expected = intersects_algebraic(&ray);
+            assert_eq!(push.xs.len(), expected.len());
+            for (t, expected) in push.xs.iter().zip(expected) {
+                assert!(t.approx_eq(expected));
+            }
+
+            push.xs.len()
+        };
+
+        // Hits, from outside, inside and behind.
+        assert_eq!(
+            test(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0)),
+            2
+        );
+        assert_eq!(
+            test(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0)),
+            2
+        );
+        assert_eq!(
+            test(Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, 1.0)),
+            2
+        );
+        assert_eq!(
+            test(Point::new(0.5, 0.2, -0.3), Vector::new(-1.0, 2.0, 0.5)),
+            2
+        );
+        assert_eq!(
+            test(Point::new(0.3, -0.2, -5.0), Vector::new(0.1, 0.2, 3.0)),
+            2
+        );
+
+        // Tangents.
+        assert_eq!(
+            test(Point::new(0.0, 1.0, -5.0), Vector::new(0.0, 0.0, 1.0)),
+            2
+        );
+        assert_eq!(
+            test(Point::new(-1.0, 0.0, -5.0), Vector::new(0.0, 0.0, 2.0)),
+            2
+        );
+
+        // Misses.
+        assert_eq!(
+            test(Point::new(0.0, 2.0, -5.0), Vector::new(0.0, 0.0, 1.0)),
+            0
+        );
+        assert_eq!(
+            test(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 1.0, 0.0)),
+            0
+        );
+
+        let mut rng = SmallRng::seed_from_u64(0);
+        for _ in 0..1000 {
+            test(
+                Point::new(
+                    rng.gen_range(-3.0..3.0),
+                    rng.gen_range(-3.0..3.0),
+                    rng.gen_range(-5.0..-2.0),
+                ),
+                Vector::new(
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(0.5..2.0),
+                ),
+            );
+        }
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
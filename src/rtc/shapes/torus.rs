@@ -0,0 +1,310 @@
+/* ---------------------------------------------------------------------------------------------- */
+
+use crate::{
+    primitive::{Point, Tuple, Vector},
+    rtc::{BoundingBox, IntersectionPusher, Ray},
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Enough to reach the precision of f64 on any interval the ray can cross.
+const BISECTION_ITERATIONS: usize = 64;
+
+// Degree of the polynomial of the intersections of a ray with a torus.
+const MAX_DEGREE: usize = 4;
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Ring around the y axis: a circle of radius minor, whose center goes around a circle of radius
+// major in the xz plane.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Torus {
+    major: f64,
+    minor: f64,
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+impl Torus {
+    pub fn new(major: f64, minor: f64) -> Self {
+        Torus {
+            major: major.abs(),
+            minor: minor.abs(),
+        }
+    }
+
+    pub fn intersects<'a>(&self, ray: &Ray, push: &mut impl IntersectionPusher<'a>) {
+        // Roots are only looked for where the ray crosses the sphere around the torus.
+        let radius = self.major + self.minor;
+        let origin = Vector::new(ray.origin.x(), ray.origin.y(), ray.origin.z());
+        let a = ray.direction.dot(ray.direction);
+        let b = 2.0 * ray.direction.dot(origin);
+        let c = origin.dot(origin) - radius * radius;
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            return;
+        }
+        let t_min = (-b - discriminant.sqrt()) / (2.0 * a);
+        let t_max = (-b + discriminant.sqrt()) / (2.0 * a);
+
+        // (x² + y² + z² + R² - r²)² = 4R²(x² + z²), along the ray.
+        let (major2, minor2) = (self.major * self.major, self.minor * self.minor);
+        let (oy, dy) = (ray.origin.y(), ray.direction.y());
+        let e = origin.dot(origin) - major2 - minor2;
+        let f = origin.dot(ray.direction);
+
+        let coefficients = [
+            e * e - 4.0 * major2 * (minor2 - oy * oy),
+            4.0 * f * e + 8.0 * major2 * oy * dy,
+            2.0 * a * e + 4.0 * f * f + 4.0 * major2 * dy * dy,
+            4.0 * a * f,
+            a * a,
+        ];
+
+        let (roots, nb_roots) = Torus::roots(&coefficients, t_min, t_max);
+        for t in &roots[..nb_roots] {
+            push.t(*t);
+        }
+    }
+
+    // Coefficients go from the constant term up.
+    fn evaluate(coefficients: &[f64], t: f64) -> f64 {
+        coefficients.iter().rev().fold(0.0, |acc, c| acc * t + c)
+    }
+
+    // The roots of a polynomial are separated by the roots of its derivative: between two of
+    // them, the polynomial is monotonic and has at most one root, found by bisection. Roots where
+    // the polynomial only touches zero are missed, they are grazing hits anyway.
+    fn roots(coefficients: &[f64], t_min: f64, t_max: f64) -> ([f64; MAX_DEGREE], usize) {
+        let mut roots = [0.0; MAX_DEGREE];
+
+        if coefficients.len() <= 2 {
+            return match coefficients {
+                [c0, c1] if *c1 != 0.0 && (t_min..=t_max).contains(&(-c0 / c1)) => {
+                    roots[0] = -c0 / c1;
+                    (roots, 1)
+                }
+                _ => (roots, 0),
+            };
+        }
+
+        let degree = coefficients.len() - 1;
+        let mut derivative = [0.0; MAX_DEGREE];
+        for power in 1..=degree {
+            derivative[power - 1] = power as f64 * coefficients[power];
+        }
+
+        let (derivative_roots, nb_derivative_roots) =
+            Torus::roots(&derivative[..degree], t_min, t_max);
+
+        let mut bounds = [0.0; MAX_DEGREE + 1];
+        bounds[0] = t_min;
+        bounds[1..=nb_derivative_roots].copy_from_slice(&derivative_roots[..nb_derivative_roots]);
+        bounds[nb_derivative_roots + 1] = t_max;
+
+        let mut nb_roots = 0;
+        for w in bounds[..nb_derivative_roots + 2].windows(2) {
+            if let Some(t) = Torus::bisect(coefficients, w[0], w[1]) {
+                roots[nb_roots] = t;
+                nb_roots += 1;
+            }
+        }
+
+        (roots, nb_roots)
+    }
+
+    fn bisect(coefficients: &[f64], mut low: f64, mut high: f64) -> Option<f64> {
+        let mut value_low = Torus::evaluate(coefficients, low);
+        let value_high = Torus::evaluate(coefficients, high);
+        if value_low * value_high > 0.0 {
+            return None;
+        }
+
+        for _ in 0..BISECTION_ITERATIONS {
+            let middle = (low + high) / 2.0;
+            let value = Torus::evaluate(coefficients, middle);
+            if value_low * value <= 0.0 {
+                high = middle;
+            } else {
+                low = middle;
+                value_low = value;
+            }
+        }
+
+        Some((low + high) / 2.0)
+    }
+
+    // Gradient of the implicit surface.
+    pub fn normal_at(&self, object_point: &Point) -> Vector {
+        let (major2, minor2) = (self.major * self.major, self.minor * self.minor);
+        let (x, y, z) = (object_point.x(), object_point.y(), object_point.z());
+        let sum = x * x + y * y + z * z;
+
+        Vector::new(
+            x * (sum - major2 - minor2),
+            y * (sum + major2 - minor2),
+            z * (sum - major2 - minor2),
+        )
+    }
+
+    pub fn bounds(&self) -> BoundingBox {
+        let radius = self.major + self.minor;
+
+        BoundingBox::new()
+            .with_min(Point::new(-radius, -self.minor, -radius))
+            .with_max(Point::new(radius, self.minor, radius))
+    }
+
+    // The angle around the y axis is uniform, but the outer side of the ring is larger than the
+    // inner one: the angle around the tube is rejected with a probability following the distance
+    // to the axis, (major + minor * cos φ) / (major + minor). A torus without a tube can't be
+    // sampled.
+    pub fn sample_surface(&self, rng: &mut impl Rng) -> Option<(Point, Vector)> {
+        if self.minor <= 0.0 {
+            return None;
+        }
+
+        let theta = 2.0 * PI * rng.gen::<f64>();
+        let phi = loop {
+            let phi = 2.0 * PI * rng.gen::<f64>();
+            let weight = (self.major + self.minor * phi.cos()).abs();
+            if rng.gen::<f64>() * (self.major + self.minor) < weight {
+                break phi;
+            }
+        };
+
+        let normal = Vector::new(phi.cos() * theta.cos(), phi.sin(), phi.cos() * theta.sin());
+        let center = Point::new(self.major * theta.cos(), 0.0, self.major * theta.sin());
+
+        Some((center + normal * self.minor, normal))
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{float::ApproxEq, rtc::Object};
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    struct Push {
+        pub xs: Vec<f64>,
+    }
+
+    impl IntersectionPusher<'_> for Push {
+        fn t(&mut self, t: f64) {
+            self.xs.push(t);
+        }
+        fn t_u_v(&mut self, _t: f64, _u: f64, _v: f64) {
+            panic!();
+        }
+        fn set_object(&mut self, _object: &'_ Object) {
+            panic!();
+        }
+    }
+
+    fn intersects(torus: &Torus, origin: Point, direction: Vector) -> Vec<f64> {
+        let mut push = Push { xs: vec![] };
+        torus.intersects(&Ray { origin, direction }, &mut push);
+        push.xs.sort_by(|lhs, rhs| lhs.partial_cmp(rhs).unwrap());
+
+        push.xs
+    }
+
+    #[test]
+    fn a_ray_crosses_both_sides_of_a_torus() {
+        let t = Torus::new(2.0, 0.5);
+
+        let xs = intersects(&t, Point::new(-5.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+        assert_eq!(xs.len(), 4);
+        for (x, expected) in xs.iter().zip([2.5, 3.5, 6.5, 7.5]) {
+            assert!(x.approx_eq(expected));
+        }
+
+        // The direction doesn't have to be normalized.
+        let xs = intersects(&t, Point::new(2.0, 5.0, 0.0), Vector::new(0.0, -2.0, 0.0));
+        assert_eq!(xs.len(), 2);
+        assert!(xs[0].approx_eq(2.25));
+        assert!(xs[1].approx_eq(2.75));
+
+        // Obliquely, through the tube only.
+        let xs = intersects(&t, Point::new(-5.0, 5.0, 2.0), Vector::new(1.0, -1.0, 0.0));
+        assert_eq!(xs.len(), 2);
+        let (p0, p1) = (
+            Point::new(-5.0, 5.0, 2.0) + Vector::new(1.0, -1.0, 0.0) * xs[0],
+            Point::new(-5.0, 5.0, 2.0) + Vector::new(1.0, -1.0, 0.0) * xs[1],
+        );
+        for p in [p0, p1] {
+            let ring = (p.x() * p.x() + p.z() * p.z()).sqrt() - 2.0;
+            assert!((ring * ring + p.y() * p.y()).approx_eq(0.25));
+        }
+    }
+
+    #[test]
+    fn a_ray_misses_a_torus() {
+        let t = Torus::new(2.0, 0.5);
+
+        // Through the hole.
+        assert!(intersects(&t, Point::new(0.0, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0)).is_empty());
+        // Above the ring.
+        assert!(intersects(&t, Point::new(-5.0, 0.6, 0.0), Vector::new(1.0, 0.0, 0.0)).is_empty());
+        // Outside of the bounding sphere.
+        assert!(intersects(&t, Point::new(-5.0, 0.0, 3.0), Vector::new(1.0, 0.0, 0.0)).is_empty());
+    }
+
+    #[test]
+    fn the_normal_of_a_torus() {
+        let t = Torus::new(2.0, 0.5);
+
+        let tests = [
+            (Point::new(2.5, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0)),
+            (Point::new(1.5, 0.0, 0.0), Vector::new(-1.0, 0.0, 0.0)),
+            (Point::new(0.0, 0.5, 2.0), Vector::new(0.0, 1.0, 0.0)),
+            (Point::new(0.0, 0.0, -2.5), Vector::new(0.0, 0.0, -1.0)),
+        ];
+
+        for (point, normal) in tests {
+            assert_eq!(t.normal_at(&point).normalize(), normal);
+        }
+    }
+
+    #[test]
+    fn the_bounds_of_a_torus() {
+        let b = Torus::new(2.0, 0.5).bounds();
+
+        assert_eq!(b.min(), Point::new(-2.5, -0.5, -2.5));
+        assert_eq!(b.max(), Point::new(2.5, 0.5, 2.5));
+    }
+
+    #[test]
+    fn sampling_the_surface_of_a_torus() {
+        let t = Torus::new(2.0, 1.0);
+        let mut rng = SmallRng::seed_from_u64(0);
+
+        assert_eq!(Torus::new(2.0, 0.0).sample_surface(&mut rng), None);
+
+        let nb_samples = 4000;
+        let mut nb_outer = 0;
+        for _ in 0..nb_samples {
+            let (point, normal) = t.sample_surface(&mut rng).unwrap();
+            let ring = (point.x() * point.x() + point.z() * point.z()).sqrt();
+
+            assert!(((ring - 2.0).powi(2) + point.y() * point.y()).approx_eq(1.0));
+            assert_eq!(normal, t.normal_at(&point).normalize());
+
+            if ring > 2.0 {
+                nb_outer += 1;
+            }
+        }
+
+        // The outer half of the ring has an area of π R + 2 r, out of 2 π R.
+        let expected = (2.0 * PI + 2.0) / (4.0 * PI);
+        assert!((nb_outer as f64 / nb_samples as f64 - expected).abs() < 0.03);
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
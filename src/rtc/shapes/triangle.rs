@@ -2,9 +2,10 @@
 
 use crate::{
     float::EPSILON,
-    primitive::{Point, Vector},
+    primitive::{Matrix, Point, Vector},
     rtc::{BoundingBox, IntersectionPusher, Ray},
 };
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -17,6 +18,8 @@ pub struct Triangle {
     e1: Vector,
     e2: Vector,
     normal: Vector,
+    // Texture coordinates of the vertices.
+    uvs: Option<[(f64, f64); 3]>,
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -34,47 +37,78 @@ impl Triangle {
             e1,
             e2,
             normal,
+            uvs: None,
         }
     }
 
+    pub fn with_uvs(mut self, uvs: [(f64, f64); 3]) -> Self {
+        self.uvs = Some(uvs);
+
+        self
+    }
+
+    // Returns t, u and v. Meshes, which don't store the edges of their faces, use it directly.
     #[allow(clippy::manual_range_contains)]
-    pub fn intersects<'a>(&self, ray: &Ray, push: &mut impl IntersectionPusher<'a>) {
-        let dir_cross_e2 = ray.direction * self.e2;
-        let det = self.e1 ^ dir_cross_e2;
+    pub fn intersection(p1: Point, e1: Vector, e2: Vector, ray: &Ray) -> Option<(f64, f64, f64)> {
+        let dir_cross_e2 = ray.direction * e2;
+        let det = e1 ^ dir_cross_e2;
 
         if det.abs() < EPSILON {
-            return;
+            return None;
         }
 
         let f = 1.0 / det;
-        let p1_to_origin = ray.origin - self.p1;
+        let p1_to_origin = ray.origin - p1;
         let u = f * (p1_to_origin ^ dir_cross_e2);
 
         if u < 0.0 || u > 1.0 {
-            return;
+            return None;
         }
 
-        let origin_cross_e1 = p1_to_origin * self.e1;
+        let origin_cross_e1 = p1_to_origin * e1;
         let v = f * (ray.direction ^ origin_cross_e1);
 
         if v < 0.0 || (u + v) > 1.0 {
-            return;
+            return None;
         }
 
-        let t = f * (self.e2 ^ origin_cross_e1);
+        Some((f * (e2 ^ origin_cross_e1), u, v))
+    }
 
+    pub fn intersects<'a>(&self, ray: &Ray, push: &mut impl IntersectionPusher<'a>) {
         // As SmoothTriangle delegates its intersection to Triangle, we need to take care
         // of pushing u and v as well here.
         // It's not very satisfying in regard to the code architecture, but it avoids code
-        // duplication or a lot of boiler plate (like returning Option<f64, f64, f64> that would be
-        // used differently by SmoothTriangle::intersects and Triangle::intersects)
-        push.t_u_v(t, u, v);
+        // duplication or a lot of boiler plate.
+        if let Some((t, u, v)) = Triangle::intersection(self.p1, self.e1, self.e2, ray) {
+            push.t_u_v(t, u, v);
+        }
     }
 
     pub fn normal_at(&self, _object_point: &Point) -> Vector {
         self.normal
     }
 
+    // The normal is transformed like any other normal rather than recomputed from the winding,
+    // so it's the same as the one of the transformed object.
+    pub fn transform(&self, transformation: &Matrix, inverse_transpose: &Matrix) -> Self {
+        let (p1, p2, p3) = (
+            *transformation * self.p1,
+            *transformation * self.p2,
+            *transformation * self.p3,
+        );
+
+        Self {
+            p1,
+            p2,
+            p3,
+            e1: p2 - p1,
+            e2: p3 - p1,
+            normal: (*inverse_transpose * self.normal).normalize(),
+            uvs: self.uvs,
+        }
+    }
+
     pub fn bounds(&self) -> BoundingBox {
         BoundingBox::new()
             .add_point(self.p1)
@@ -82,6 +116,56 @@ impl Triangle {
             .add_point(self.p3)
     }
 
+    pub fn sample_surface(&self, rng: &mut impl Rng) -> (Point, Vector) {
+        let (u, v) = Triangle::sample_u_v(rng);
+
+        (self.p1 + self.e1 * u + self.e2 * v, self.normal)
+    }
+
+    // Uniformly distributed barycentric coordinates, with the same meaning as the ones pushed
+    // by intersects().
+    pub fn sample_u_v(rng: &mut impl Rng) -> (f64, f64) {
+        let (u, v) = (rng.gen::<f64>(), rng.gen::<f64>());
+
+        if u + v > 1.0 {
+            (1.0 - u, 1.0 - v)
+        } else {
+            (u, v)
+        }
+    }
+
+    pub fn uvs(&self) -> Option<[(f64, f64); 3]> {
+        self.uvs
+    }
+
+    // Barycentric coordinates of a point of the triangle, with the same meaning as the ones
+    // pushed by intersects().
+    pub fn barycentric(&self, point: &Point) -> (f64, f64) {
+        let p1_to_point = *point - self.p1;
+        let (d11, d12, d22) = (
+            self.e1.dot(self.e1),
+            self.e1.dot(self.e2),
+            self.e2.dot(self.e2),
+        );
+        let (dp1, dp2) = (p1_to_point.dot(self.e1), p1_to_point.dot(self.e2));
+        let denominator = d11 * d22 - d12 * d12;
+
+        (
+            (d22 * dp1 - d12 * dp2) / denominator,
+            (d11 * dp2 - d12 * dp1) / denominator,
+        )
+    }
+
+    // Texture coordinates interpolated with the barycentric coordinates pushed by intersects().
+    pub fn uv_at(&self, u: f64, v: f64) -> Option<(f64, f64)> {
+        self.uvs.map(|[uv1, uv2, uv3]| {
+            (
+                uv1.0 * (1.0 - u - v) + uv2.0 * u + uv3.0 * v,
+                uv1.1 * (1.0 - u - v) + uv2.1 * u + uv3.1 * v,
+            )
+        })
+    }
+
     pub fn p1(&self) -> Point {
         self.p1
     }
@@ -100,7 +184,8 @@ impl Triangle {
 #[cfg(test)]
 pub mod tests {
     use super::*;
-    use crate::{primitive::Tuple, rtc::Object};
+    use crate::{float::ApproxEq, primitive::Tuple, rtc::Object};
+    use rand::{rngs::SmallRng, SeedableRng};
 
     struct Push {
         pub xs: Vec<f64>,
@@ -258,6 +343,32 @@ pub mod tests {
         assert_eq!(t.bounds().min(), Point::new(-3.0, -1.0, -4.0));
         assert_eq!(t.bounds().max(), Point::new(6.0, 7.0, 2.0));
     }
+
+    #[test]
+    fn sampling_the_surface_of_a_triangle() {
+        let t = Triangle::new(
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+        );
+        let mut rng = SmallRng::seed_from_u64(0);
+
+        for _ in 0..100 {
+            let (point, normal) = t.sample_surface(&mut rng);
+
+            assert_eq!(normal, t.normal);
+
+            // The point is on the triangle if a ray going through it along the normal hits it.
+            let ray = Ray {
+                origin: point + normal,
+                direction: -normal,
+            };
+            let mut push = Push { xs: vec![] };
+            t.intersects(&ray, &mut push);
+            assert_eq!(push.xs.len(), 1);
+            assert!(push.xs[0].approx_eq(1.0));
+        }
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -0,0 +1,435 @@
+/* ---------------------------------------------------------------------------------------------- */
+
+use crate::{
+    float::EPSILON,
+    primitive::{Point, Tuple, Vector},
+    rtc::{Material, Object, Shape},
+};
+use std::collections::{BTreeMap, HashMap};
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Polygonal mesh whose faces share their vertices, so the neighbours of faces and vertices are
+// known.
+#[derive(Clone, Debug)]
+pub(in crate::rtc) struct Mesh {
+    vertices: Vec<Point>,
+    // Indices of vertices, and index of the material of the face.
+    faces: Vec<(Vec<usize>, usize)>,
+    materials: Vec<Material>,
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+impl Mesh {
+    // Vertices of triangles at the same position (up to EPSILON) are welded together.
+    pub fn from_triangles(triangles: &[&Object]) -> Self {
+        let mut mesh = Mesh {
+            vertices: vec![],
+            faces: vec![],
+            materials: vec![],
+        };
+        let mut welded = HashMap::<(i64, i64, i64), usize>::new();
+
+        for triangle in triangles {
+            let faces = match triangle.shape() {
+                Shape::Triangle(t) => vec![[t.p1(), t.p2(), t.p3()]],
+                Shape::SmoothTriangle(t) => vec![[t.p1(), t.p2(), t.p3()]],
+                // Faces of indexed meshes are welded like separate triangles.
+                Shape::Mesh(m) => m
+                    .faces()
+                    .iter()
+                    .map(|face| face.map(|index| m.vertices()[index]))
+                    .collect(),
+                _ => continue,
+            };
+
+            let material = match mesh.materials.iter().position(|m| m == triangle.material()) {
+                Some(index) => index,
+                None => {
+                    mesh.materials.push(triangle.material().clone());
+                    mesh.materials.len() - 1
+                }
+            };
+
+            for points in faces {
+                let face = points
+                    .iter()
+                    .map(|p| {
+                        let p = *triangle.transformation() * *p;
+                        let key = |x: f64| (x / EPSILON).round() as i64;
+
+                        *welded
+                            .entry((key(p.x()), key(p.y()), key(p.z())))
+                            .or_insert_with(|| {
+                                mesh.vertices.push(p);
+                                mesh.vertices.len() - 1
+                            })
+                    })
+                    .collect();
+
+                mesh.faces.push((face, material));
+            }
+        }
+
+        mesh
+    }
+
+    // One level of Catmull-Clark subdivision: each face of n vertices is replaced by n quads.
+    // Boundary edges and vertices follow the rules of cubic B-splines.
+    pub fn subdivide(&self) -> Self {
+        self.refine(true)
+    }
+
+    // Same faces as subdivide, but vertices don't move and new ones are on the original faces.
+    pub fn split(&self) -> Self {
+        self.refine(false)
+    }
+
+    fn refine(&self, smooth: bool) -> Self {
+        let face_points = self
+            .faces
+            .iter()
+            .map(|(face, _)| average(face.iter().map(|&v| self.vertices[v])))
+            .collect::<Vec<_>>();
+
+        // Faces adjacent to each edge. A BTreeMap keeps the order of new vertices deterministic.
+        let mut edges = BTreeMap::<(usize, usize), Vec<usize>>::new();
+        let mut vertex_faces = vec![vec![]; self.vertices.len()];
+        for (face_index, (face, _)) in self.faces.iter().enumerate() {
+            for (i, &v) in face.iter().enumerate() {
+                let w = face[(i + 1) % face.len()];
+                edges.entry(edge_key(v, w)).or_default().push(face_index);
+                vertex_faces[v].push(face_index);
+            }
+        }
+
+        let mut vertex_edges = vec![vec![]; self.vertices.len()];
+        for &(v, w) in edges.keys() {
+            vertex_edges[v].push((v, w));
+            vertex_edges[w].push((v, w));
+        }
+
+        let midpoint =
+            |(v, w): (usize, usize)| average([self.vertices[v], self.vertices[w]].into_iter());
+
+        // New vertices are the moved original ones, then face points, then edge points.
+        let mut vertices = self
+            .vertices
+            .iter()
+            .enumerate()
+            .map(|(v, &p)| {
+                let boundary = vertex_edges[v]
+                    .iter()
+                    .filter(|edge| edges[edge].len() == 1)
+                    .collect::<Vec<_>>();
+
+                if !smooth || vertex_faces[v].is_empty() {
+                    p
+                } else if boundary.is_empty() {
+                    let n = vertex_faces[v].len() as f64;
+                    let q = average(vertex_faces[v].iter().map(|&f| face_points[f]));
+                    let r = average(vertex_edges[v].iter().map(|&edge| midpoint(edge)));
+
+                    Point::zero()
+                        + ((q - Point::zero())
+                            + 2.0 * (r - Point::zero())
+                            + (n - 3.0) * (p - Point::zero()))
+                            / n
+                } else if boundary.len() == 2 {
+                    let neighbours = boundary
+                        .iter()
+                        .map(|&&(a, b)| self.vertices[if a == v { b } else { a }] - Point::zero())
+                        .fold(Vector::zero(), |acc, n| acc + n);
+
+                    Point::zero() + (6.0 * (p - Point::zero()) + neighbours) / 8.0
+                } else {
+                    // Corners of non-manifold boundaries stay in place.
+                    p
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let face_offset = vertices.len();
+        vertices.extend(&face_points);
+
+        let mut edge_points = HashMap::<(usize, usize), usize>::new();
+        for (&edge, faces) in &edges {
+            let point = match faces[..] {
+                [f1, f2] if smooth => average(
+                    [
+                        self.vertices[edge.0],
+                        self.vertices[edge.1],
+                        face_points[f1],
+                        face_points[f2],
+                    ]
+                    .into_iter(),
+                ),
+                _ => midpoint(edge),
+            };
+
+            edge_points.insert(edge, vertices.len());
+            vertices.push(point);
+        }
+
+        let faces = self
+            .faces
+            .iter()
+            .enumerate()
+            .flat_map(|(face_index, (face, material))| {
+                let n = face.len();
+                let edge_point =
+                    |i: usize, j: usize| edge_points[&edge_key(face[i % n], face[j % n])];
+
+                (0..n)
+                    .map(|i| {
+                        (
+                            vec![
+                                face[i],
+                                edge_point(i, i + 1),
+                                face_offset + face_index,
+                                edge_point(i + n - 1, i),
+                            ],
+                            *material,
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        Mesh {
+            vertices,
+            faces,
+            materials: self.materials.clone(),
+        }
+    }
+
+    fn triangles(&self) -> Vec<([usize; 3], usize)> {
+        self.faces
+            .iter()
+            .flat_map(|(face, material)| {
+                (1..face.len() - 1).map(move |i| ([face[0], face[i], face[i + 1]], *material))
+            })
+            .collect()
+    }
+
+    // Area-weighted average of the normals of the triangles around each vertex.
+    fn vertex_normals(&self, triangles: &[([usize; 3], usize)]) -> Vec<Vector> {
+        let mut normals = vec![Vector::zero(); self.vertices.len()];
+        for ([v1, v2, v3], _) in triangles {
+            let (p1, p2, p3) = (self.vertices[*v1], self.vertices[*v2], self.vertices[*v3]);
+            // Same orientation as the normal of a Triangle.
+            let normal = (p3 - p1).cross(p2 - p1);
+            for v in [v1, v2, v3] {
+                normals[*v] = normals[*v] + normal;
+            }
+        }
+
+        normals.into_iter().map(|n| n.normalize()).collect()
+    }
+
+    // Moves each vertex along its normal, by the given offset at its position.
+    pub fn displace<F>(self, offset: F) -> Self
+    where
+        F: Fn(&Point) -> f64,
+    {
+        let normals = self.vertex_normals(&self.triangles());
+        let vertices = self
+            .vertices
+            .iter()
+            .zip(normals)
+            .map(|(p, normal)| *p + normal * offset(p))
+            .collect();
+
+        Mesh { vertices, ..self }
+    }
+
+    // Faces are split into triangles fanning from their first vertex. With smooth normals, the
+    // normal of a vertex is the area-weighted average of the normals of its triangles.
+    pub fn into_triangles(self, smooth_normals: bool) -> Vec<Object> {
+        let triangles = self.triangles();
+
+        let normals = if smooth_normals {
+            self.vertex_normals(&triangles)
+        } else {
+            vec![]
+        };
+
+        triangles
+            .into_iter()
+            .map(|([v1, v2, v3], material)| {
+                let (p1, p2, p3) = (self.vertices[v1], self.vertices[v2], self.vertices[v3]);
+                let triangle = if smooth_normals {
+                    Object::new_smooth_triangle(p1, p2, p3, normals[v1], normals[v2], normals[v3])
+                } else {
+                    Object::new_triangle(p1, p2, p3)
+                };
+
+                triangle.with_material(self.materials[material].clone())
+            })
+            .collect()
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+fn edge_key(v: usize, w: usize) -> (usize, usize) {
+    (v.min(w), v.max(w))
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+fn average(points: impl Iterator<Item = Point>) -> Point {
+    let (sum, nb) = points.fold((Vector::zero(), 0), |(sum, nb), p| {
+        (sum + (p - Point::zero()), nb + 1)
+    });
+
+    Point::zero() + sum / nb as f64
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtc::shapes::Triangle;
+
+    // A cube of 12 triangles, from -1 to 1 on each axis.
+    fn cube() -> Object {
+        let p = |x, y, z| Point::new(x, y, z);
+        let quads = [
+            [
+                p(-1., -1., -1.),
+                p(-1., 1., -1.),
+                p(1., 1., -1.),
+                p(1., -1., -1.),
+            ],
+            [
+                p(-1., -1., 1.),
+                p(1., -1., 1.),
+                p(1., 1., 1.),
+                p(-1., 1., 1.),
+            ],
+            [
+                p(-1., -1., -1.),
+                p(-1., -1., 1.),
+                p(-1., 1., 1.),
+                p(-1., 1., -1.),
+            ],
+            [
+                p(1., -1., -1.),
+                p(1., 1., -1.),
+                p(1., 1., 1.),
+                p(1., -1., 1.),
+            ],
+            [
+                p(-1., -1., -1.),
+                p(1., -1., -1.),
+                p(1., -1., 1.),
+                p(-1., -1., 1.),
+            ],
+            [
+                p(-1., 1., -1.),
+                p(-1., 1., 1.),
+                p(1., 1., 1.),
+                p(1., 1., -1.),
+            ],
+        ];
+
+        Object::new_group(
+            quads
+                .iter()
+                .flat_map(|[a, b, c, d]| {
+                    [
+                        Object::new_triangle(*a, *b, *c),
+                        Object::new_triangle(*a, *c, *d),
+                    ]
+                })
+                .collect(),
+        )
+    }
+
+    fn vertices(object: &Object) -> Vec<Point> {
+        object
+            .leaves()
+            .iter()
+            .flat_map(|leaf| match leaf.shape() {
+                Shape::Triangle(t) => vec![t.p1(), t.p2(), t.p3()],
+                Shape::SmoothTriangle(t) => vec![t.p1(), t.p2(), t.p3()],
+                _ => vec![],
+            })
+            .collect()
+    }
+
+    fn max_distance_to_center(object: &Object) -> f64 {
+        vertices(object)
+            .iter()
+            .map(|p| (*p - Point::zero()).magnitude())
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn subdividing_a_cube_multiplies_its_faces() {
+        let cube = cube();
+        assert_eq!(cube.leaves().len(), 12);
+
+        // Each triangle becomes 3 quads, each split in 2 triangles.
+        let subdivided = cube.subdivide_surface(1, false);
+        assert_eq!(subdivided.leaves().len(), 72);
+
+        // Then each quad becomes 4 quads.
+        let subdivided = cube.subdivide_surface(2, true);
+        assert_eq!(subdivided.leaves().len(), 288);
+        assert!(subdivided
+            .leaves()
+            .iter()
+            .all(|leaf| matches!(leaf.shape(), Shape::SmoothTriangle(_))));
+
+        assert_eq!(cube.subdivide_surface(0, false).leaves().len(), 12);
+    }
+
+    #[test]
+    fn subdividing_a_cube_moves_its_vertices_toward_the_limit_surface() {
+        let cube = cube();
+        let corner = 3.0_f64.sqrt();
+
+        let level_1 = max_distance_to_center(&cube.subdivide_surface(1, false));
+        let level_2 = max_distance_to_center(&cube.subdivide_surface(2, false));
+
+        assert!(level_1 < corner - 0.3);
+        assert!(level_2 < level_1);
+
+        // The surface shrinks but stays closed around the center.
+        let min_distance = vertices(&cube.subdivide_surface(2, false))
+            .iter()
+            .map(|p| (*p - Point::zero()).magnitude())
+            .fold(f64::INFINITY, f64::min);
+        assert!(min_distance > 0.5);
+    }
+
+    #[test]
+    fn subdivision_keeps_the_orientation_of_faces() {
+        let subdivided = cube().subdivide_surface(1, true);
+
+        for leaf in subdivided.leaves() {
+            if let Shape::SmoothTriangle(t) = leaf.shape() {
+                let center = Point::zero()
+                    + ((t.p1() - Point::zero()) / 3.0)
+                    + ((t.p2() - Point::zero()) / 3.0)
+                    + ((t.p3() - Point::zero()) / 3.0);
+                let outward = center - Point::zero();
+
+                // Faces of the cube are wound so their normals point inward.
+                assert!(t.n1().dot(outward) < 0.0);
+                assert!(
+                    Triangle::new(t.p1(), t.p2(), t.p3())
+                        .normal_at(&center)
+                        .dot(outward)
+                        < 0.0
+                );
+            }
+        }
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
@@ -26,6 +26,24 @@ pub fn scaling(x: f64, y: f64, z: f64) -> Matrix {
 
 /* ---------------------------------------------------------------------------------------------- */
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+// Reflection across the plane going through the origin and orthogonal to the axis.
+pub fn reflection(axis: Axis) -> Matrix {
+    match axis {
+        Axis::X => scaling(-1.0, 1.0, 1.0),
+        Axis::Y => scaling(1.0, -1.0, 1.0),
+        Axis::Z => scaling(1.0, 1.0, -1.0),
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
 pub fn rotation_x(angle: f64) -> Matrix {
     let mut res = Matrix::id();
     res[(1, 1)] = f64::cos(angle);
@@ -62,6 +80,37 @@ pub fn rotation_z(angle: f64) -> Matrix {
 
 /* ---------------------------------------------------------------------------------------------- */
 
+// Rotation around an arbitrary axis going through the origin (Rodrigues' formula). The rotation
+// is counterclockwise when looking from the tip of the axis toward the origin.
+pub fn rotation_axis(axis: Vector, angle: f64) -> Matrix {
+    let axis = axis.normalize();
+    let (x, y, z) = (axis.x(), axis.y(), axis.z());
+    let (sin, cos) = angle.sin_cos();
+    let t = 1.0 - cos;
+
+    let mut res = Matrix::id();
+    res[(0, 0)] = t * x * x + cos;
+    res[(0, 1)] = t * x * y - sin * z;
+    res[(0, 2)] = t * x * z + sin * y;
+    res[(1, 0)] = t * x * y + sin * z;
+    res[(1, 1)] = t * y * y + cos;
+    res[(1, 2)] = t * y * z - sin * x;
+    res[(2, 0)] = t * x * z - sin * y;
+    res[(2, 1)] = t * y * z + sin * x;
+    res[(2, 2)] = t * z * z + cos;
+
+    res
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Rotations around the x, then y, then z axes.
+pub fn rotation_euler(x: f64, y: f64, z: f64) -> Matrix {
+    rotation_z(z) * rotation_y(y) * rotation_x(x)
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
 pub fn shearing(xy: f64, xz: f64, yx: f64, yz: f64, zx: f64, zy: f64) -> Matrix {
     let mut res = Matrix::id();
     res[(0, 1)] = xy;
@@ -156,6 +205,34 @@ where
         self.transformation = shearing(xy, xz, yx, yz, zx, zy) * self.transformation;
         self
     }
+
+    pub fn translate_x(self, x: f64) -> Self {
+        self.translate(x, 0.0, 0.0)
+    }
+
+    pub fn translate_y(self, y: f64) -> Self {
+        self.translate(0.0, y, 0.0)
+    }
+
+    pub fn translate_z(self, z: f64) -> Self {
+        self.translate(0.0, 0.0, z)
+    }
+
+    pub fn scale_uniform(self, s: f64) -> Self {
+        self.scale(s, s, s)
+    }
+
+    pub fn rotate_x_deg(self, degrees: f64) -> Self {
+        self.rotate_x(degrees.to_radians())
+    }
+
+    pub fn rotate_y_deg(self, degrees: f64) -> Self {
+        self.rotate_y(degrees.to_radians())
+    }
+
+    pub fn rotate_z_deg(self, degrees: f64) -> Self {
+        self.rotate_z(degrees.to_radians())
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -230,6 +307,55 @@ pub trait Transform {
             x: self,
         }
     }
+
+    fn translate_x(self, x: f64) -> TransformationBuilder<Self>
+    where
+        Self: Sized,
+    {
+        self.translate(x, 0.0, 0.0)
+    }
+
+    fn translate_y(self, y: f64) -> TransformationBuilder<Self>
+    where
+        Self: Sized,
+    {
+        self.translate(0.0, y, 0.0)
+    }
+
+    fn translate_z(self, z: f64) -> TransformationBuilder<Self>
+    where
+        Self: Sized,
+    {
+        self.translate(0.0, 0.0, z)
+    }
+
+    fn scale_uniform(self, s: f64) -> TransformationBuilder<Self>
+    where
+        Self: Sized,
+    {
+        self.scale(s, s, s)
+    }
+
+    fn rotate_x_deg(self, degrees: f64) -> TransformationBuilder<Self>
+    where
+        Self: Sized,
+    {
+        self.rotate_x(degrees.to_radians())
+    }
+
+    fn rotate_y_deg(self, degrees: f64) -> TransformationBuilder<Self>
+    where
+        Self: Sized,
+    {
+        self.rotate_y(degrees.to_radians())
+    }
+
+    fn rotate_z_deg(self, degrees: f64) -> TransformationBuilder<Self>
+    where
+        Self: Sized,
+    {
+        self.rotate_z(degrees.to_radians())
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -391,6 +517,44 @@ mod tests {
         }
     }
 
+    #[test]
+    fn rotation_around_an_arbitrary_axis() {
+        for angle in [0.0, PI / 6.0, PI / 2.0, -2.0, PI] {
+            assert_eq!(
+                rotation_axis(Vector::new(2.0, 0.0, 0.0), angle),
+                rotation_x(angle)
+            );
+            assert_eq!(
+                rotation_axis(Vector::new(0.0, 1.0, 0.0), angle),
+                rotation_y(angle)
+            );
+            assert_eq!(
+                rotation_axis(Vector::new(0.0, 0.0, 1.0), angle),
+                rotation_z(angle)
+            );
+        }
+
+        // A third of a turn around the diagonal swaps the axes.
+        let r = rotation_axis(Vector::new(1.0, 1.0, 1.0), 2.0 * PI / 3.0);
+        assert_eq!(r * Vector::new(1.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0));
+        assert_eq!(r * Vector::new(0.0, 1.0, 0.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(r * Point::new(0.0, 0.0, 3.0), Point::new(3.0, 0.0, 0.0));
+
+        // Points on the axis don't move.
+        assert_eq!(r * Point::new(2.0, 2.0, 2.0), Point::new(2.0, 2.0, 2.0));
+    }
+
+    #[test]
+    fn rotation_from_euler_angles() {
+        let p = Point::new(1.0, 2.0, 3.0);
+
+        assert_eq!(
+            rotation_euler(0.3, -1.2, 2.0) * p,
+            p.rotate_x(0.3).rotate_y(-1.2).rotate_z(2.0).transform()
+        );
+        assert_eq!(rotation_euler(0.0, 0.0, 0.0), Matrix::id());
+    }
+
     #[test]
     fn shearing() {
         let p = Point::new(2.0, 3.0, 4.0);
@@ -443,6 +607,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn single_argument_helpers() {
+        let p = Point::new(1.0, 2.0, 3.0);
+
+        assert_eq!(
+            p.scale_uniform(2.0).transform(),
+            p.scale(2.0, 2.0, 2.0).transform()
+        );
+        assert_eq!(
+            p.rotate_x_deg(90.0).transform(),
+            p.rotate_x(PI / 2.0).transform()
+        );
+        assert_eq!(
+            p.translate_x(1.0)
+                .translate_y(2.0)
+                .translate_z(3.0)
+                .transform(),
+            p.translate(1.0, 2.0, 3.0).transform()
+        );
+        assert_eq!(
+            p.rotate_y_deg(45.0).rotate_z_deg(180.0).transform(),
+            p.rotate_y(PI / 4.0).rotate_z(PI).transform()
+        );
+    }
+
     #[test]
     fn identity_is_the_default_transformation_matrix() {
         let from = Point::new(0.0, 0.0, 0.0);
@@ -2,10 +2,87 @@
 
 use crate::{
     float::ApproxEq,
-    primitive::Point,
-    rtc::{Color, IntersectionState, Intersections, Light, Object, Ray},
+    primitive::{Point, Tuple, Vector},
+    rtc::{
+        without_nan_checks, Color, CubeMap, Environment, IntersectionState, Intersections, Light,
+        Object, Ray, RenderOptions, Shader, Shape, Transform,
+    },
 };
 use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    time::Instant,
+};
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// The higher, the narrower the bright spots of caustics.
+const CAUSTIC_SHARPNESS: f64 = 64.0;
+
+// Flat objects get proxies of this half thickness, so their scaling can be inverted.
+const PROXY_MIN_HALF_EXTENT: f64 = 0.01;
+
+/* ---------------------------------------------------------------------------------------------- */
+
+#[derive(Debug)]
+struct Profile {
+    // Number of intersections found with each object.
+    costs: Vec<AtomicUsize>,
+    rays: AtomicUsize,
+    // Nanoseconds elapsed since start when the first and the last rays were traced.
+    start: Instant,
+    first_ray: AtomicU64,
+    last_ray: AtomicU64,
+}
+
+impl Profile {
+    fn new(nb_objects: usize) -> Self {
+        Profile {
+            costs: (0..nb_objects).map(|_| AtomicUsize::new(0)).collect(),
+            rays: AtomicUsize::new(0),
+            start: Instant::now(),
+            first_ray: AtomicU64::new(u64::MAX),
+            last_ray: AtomicU64::new(0),
+        }
+    }
+
+    fn count_ray(&self) {
+        let now = self.start.elapsed().as_nanos() as u64;
+
+        self.rays.fetch_add(1, Ordering::Relaxed);
+        self.first_ray.fetch_min(now, Ordering::Relaxed);
+        self.last_ray.fetch_max(now, Ordering::Relaxed);
+    }
+}
+
+#[derive(Serialize)]
+struct Stats {
+    rays: usize,
+    intersections: usize,
+    bvh_depth: usize,
+    // Seconds between the first and the last traced rays.
+    render_time: f64,
+    objects: Vec<ObjectStats>,
+}
+
+#[derive(Serialize)]
+struct ObjectStats {
+    id: usize,
+    intersections: usize,
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+// Color seen by rays that miss every object, computed from the ray.
+struct Background(Box<dyn Fn(&Ray) -> Color + Sync>);
+
+impl std::fmt::Debug for Background {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Background")
+    }
+}
 
 /* ---------------------------------------------------------------------------------------------- */
 
@@ -13,7 +90,19 @@ use serde::{Deserialize, Serialize};
 pub struct World {
     objects: Vec<Object>,
     lights: Vec<Light>,
+    environment: Option<Environment>,
     recursion_limit: u8,
+    render_options: RenderOptions,
+    // Top-level hierarchy of copies of the objects, traversed instead of them when built.
+    #[serde(skip)]
+    tlas: Option<Vec<Object>>,
+    // Counters of the traced rays and of the intersections, when profiling is enabled.
+    #[serde(skip)]
+    profile: Option<Profile>,
+    #[serde(skip)]
+    shader: Option<Box<dyn Shader>>,
+    #[serde(skip)]
+    background: Option<Background>,
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -31,18 +120,245 @@ impl World {
         self
     }
 
+    pub fn with_render_options(mut self, render_options: RenderOptions) -> Self {
+        self.render_options = render_options;
+
+        self
+    }
+
+    pub fn with_shader(mut self, shader: Box<dyn Shader>) -> Self {
+        self.shader = Some(shader);
+
+        self
+    }
+
     pub fn with_objects(mut self, objects: Vec<Object>) -> Self {
         self.objects = objects;
+        self.tlas = None;
+        if self.profile.is_some() {
+            self = self.with_cost_profiling();
+        }
+
+        self
+    }
+
+    // Levels of detail are chosen from their distance to the viewpoint, usually the position of
+    // the camera, rather than from the origin of each ray. Shadows and reflections of an object
+    // then show the same level as the object itself.
+    pub fn with_lod_viewpoint(mut self, viewpoint: &Point) -> Self {
+        for object in self
+            .objects
+            .iter_mut()
+            .chain(self.tlas.iter_mut().flatten())
+        {
+            object.set_lod_viewpoint(viewpoint);
+        }
+
+        self
+    }
+
+    // Fast layout view, where each object is replaced by a cube filling its bounding box, with the
+    // same material, which is the one of its first leaf for groups and levels of detail. Unbounded
+    // objects, like planes, are kept. The shader and the background function are not kept.
+    pub fn to_bbox_proxies(&self) -> World {
+        let objects = self
+            .objects
+            .iter()
+            .map(|object| {
+                let bbox = object.bounding_box();
+                let (min, max) = (bbox.min(), bbox.max());
+                let bounded = [min.x(), min.y(), min.z(), max.x(), max.y(), max.z()]
+                    .iter()
+                    .all(|x| x.is_finite());
+
+                if bbox.is_empty() || !bounded {
+                    return object.clone();
+                }
+
+                let half_extent =
+                    |min: f64, max: f64| ((max - min) / 2.0).max(PROXY_MIN_HALF_EXTENT);
+
+                let material = object
+                    .leaves()
+                    .first()
+                    .map_or_else(|| object.material(), |leaf| leaf.material())
+                    .clone();
+
+                Object::new_cube()
+                    .with_material(material)
+                    .with_shadow(object.has_shadow())
+                    .with_light_mask(object.light_mask())
+                    .scale(
+                        half_extent(min.x(), max.x()),
+                        half_extent(min.y(), max.y()),
+                        half_extent(min.z(), max.z()),
+                    )
+                    .translate(
+                        (min.x() + max.x()) / 2.0,
+                        (min.y() + max.y()) / 2.0,
+                        (min.z() + max.z()) / 2.0,
+                    )
+                    .transform()
+            })
+            .collect();
+
+        let world = World {
+            lights: self.lights.clone(),
+            environment: self.environment.clone(),
+            recursion_limit: self.recursion_limit,
+            render_options: self.render_options,
+            ..Default::default()
+        }
+        .with_objects(objects);
+
+        if self.profile.is_some() {
+            world.with_cost_profiling()
+        } else {
+            world
+        }
+    }
+
+    // Groups the bounded objects of the world in a hierarchy of bounding boxes, so rays skip whole
+    // objects before descending into their own hierarchy. Unbounded objects are always tested.
+    // The hierarchy holds copies of the objects and must be rebuilt when they change. It's not
+    // used while profiling costs, which are counted per object.
+    pub fn build_tlas(mut self, threshold: usize) -> Self {
+        let (bounded, mut unbounded): (Vec<_>, Vec<_>) =
+            self.objects.iter().cloned().partition(|object| {
+                let bbox = object.bounding_box();
+                let (min, max) = (bbox.min(), bbox.max());
+
+                !bbox.is_empty()
+                    && [min.x(), min.y(), min.z(), max.x(), max.y(), max.z()]
+                        .iter()
+                        .all(|x| x.is_finite())
+            });
+
+        if !bounded.is_empty() {
+            unbounded.push(Object::new_group(bounded).divide(threshold));
+        }
+        self.tlas = Some(unbounded);
+
+        self
+    }
+
+    // Counting has a cost of its own, thus profiling is disabled by default.
+    pub fn with_cost_profiling(mut self) -> Self {
+        self.profile = Some(Profile::new(self.objects.len()));
 
         self
     }
 
+    // Indices of the objects along with their number of intersections since profiling was
+    // enabled, from the most expensive to the cheapest.
+    pub fn cost_profile(&self) -> Vec<(usize, usize)> {
+        let mut profile = self
+            .profile
+            .iter()
+            .flat_map(|profile| profile.costs.iter())
+            .map(|cost| cost.load(Ordering::Relaxed))
+            .enumerate()
+            .collect::<Vec<_>>();
+
+        profile.sort_by(|(lhs_id, lhs_cost), (rhs_id, rhs_cost)| {
+            rhs_cost.cmp(lhs_cost).then(lhs_id.cmp(rhs_id))
+        });
+
+        profile
+    }
+
+    // Statistics gathered since profiling was enabled, for tools tracking performance.
+    pub fn stats_json(&self) -> String {
+        let (rays, render_time) = match &self.profile {
+            Some(profile) => {
+                let (first, last) = (
+                    profile.first_ray.load(Ordering::Relaxed),
+                    profile.last_ray.load(Ordering::Relaxed),
+                );
+                (
+                    profile.rays.load(Ordering::Relaxed),
+                    last.saturating_sub(first) as f64 / 1e9,
+                )
+            }
+            None => (0, 0.0),
+        };
+        let objects = self
+            .cost_profile()
+            .into_iter()
+            .map(|(id, intersections)| ObjectStats { id, intersections })
+            .collect::<Vec<_>>();
+
+        let stats = Stats {
+            rays,
+            intersections: objects.iter().map(|object| object.intersections).sum(),
+            bvh_depth: self
+                .tlas
+                .as_ref()
+                .unwrap_or(&self.objects)
+                .iter()
+                .map(hierarchy_depth)
+                .max()
+                .unwrap_or(0),
+            render_time,
+            objects,
+        };
+
+        serde_json::to_string_pretty(&stats).unwrap()
+    }
+
+    fn intersection_state<'a>(
+        &self,
+        intersections: &Intersections<'a>,
+        index: usize,
+        ray: &Ray,
+    ) -> IntersectionState<'a> {
+        IntersectionState::new_with_epsilon(
+            intersections,
+            index,
+            ray,
+            self.render_options.epsilon(),
+        )
+    }
+
+    fn intersects(&self, ray: &Ray) -> Intersections<'_> {
+        let intersections = match (&self.profile, &self.tlas) {
+            (Some(profile), _) => {
+                profile.count_ray();
+                ray.intersects_and_count(&self.objects, Intersections::new(), &profile.costs)
+            }
+            (None, Some(tlas)) => ray.intersects(tlas, Intersections::new()),
+            (None, None) => ray.intersects(&self.objects, Intersections::new()),
+        };
+
+        if self.render_options.dedup_intersections() {
+            intersections.dedup()
+        } else {
+            intersections
+        }
+    }
+
     pub fn with_lights(mut self, lights: Vec<Light>) -> Self {
         self.lights = lights;
 
         self
     }
 
+    // Rays that miss every object see the environment, which also lights diffuse surfaces.
+    pub fn with_environment(mut self, environment: Environment) -> Self {
+        self.environment = Some(environment);
+
+        self
+    }
+
+    // Procedural background, such as stars or clouds, seen instead of the environment by rays that
+    // miss every object, including reflected and refracted ones. It's called concurrently when
+    // rendering in parallel.
+    pub fn with_background_fn(mut self, background: Box<dyn Fn(&Ray) -> Color + Sync>) -> Self {
+        self.background = Some(Background(background));
+
+        self
+    }
+
     pub fn objects(&self) -> &Vec<Object> {
         &self.objects
     }
@@ -51,50 +367,321 @@ impl World {
         &self.lights
     }
 
+    pub fn render_options(&self) -> &RenderOptions {
+        &self.render_options
+    }
+
     pub fn color_at(&self, ray: &Ray) -> Color {
-        self.color_at_impl(ray, self.recursion_limit)
+        self.finite_color(|| self.color_at_impl(ray, self.recursion_limit, 1.0))
     }
 
-    fn color_at_impl(&self, ray: &Ray, remaining_recursions: u8) -> Color {
-        let intersections = ray.intersects(&self.objects, Intersections::new());
+    // For shaders tracing secondary rays: they pass their remaining recursions minus one and stop
+    // tracing when they have none left.
+    pub fn color_at_with_depth(&self, ray: &Ray, remaining_recursions: u8) -> Color {
+        self.color_at_impl(ray, remaining_recursions, 1.0)
+    }
+
+    // Ignores reflections and refractions.
+    pub fn primary_color_at(&self, ray: &Ray) -> Color {
+        self.finite_color(|| self.color_at_impl(ray, 0, 1.0))
+    }
+
+    fn finite_color(&self, color: impl FnOnce() -> Color) -> Color {
+        let color = if self.render_options.debug_non_finite() {
+            without_nan_checks(color)
+        } else {
+            color()
+        };
+
+        if color.is_finite() {
+            color
+        } else if self.render_options.debug_non_finite() {
+            Color::magenta()
+        } else {
+            Color::black()
+        }
+    }
+
+    // Ids start at 1 and follow the order of the leaves of the world, so they only depend on how
+    // the world is built. Objects are identified by their address.
+    pub(crate) fn object_ids(&self) -> HashMap<usize, u32> {
+        self.objects
+            .iter()
+            .flat_map(Object::leaves)
+            .zip(1..)
+            .map(|(object, id)| (object as *const Object as usize, id))
+            .collect()
+    }
+
+    // Ids are given to the objects of the world, not to their copies in the TLAS.
+    pub(crate) fn object_at(&self, ray: &Ray) -> Option<&Object> {
+        ray.intersects(&self.objects, Intersections::new())
+            .hit()
+            .map(|hit| hit.object())
+    }
+
+    // Fraction of light reaching the visible surface, averaged over all lights, whatever the
+    // render options.
+    pub fn light_fraction_at(&self, ray: &Ray) -> Option<f64> {
+        let intersections = self.intersects(ray);
+        let hit_index = intersections.hit_index()?;
+        let comps = self.intersection_state(&intersections, hit_index, ray);
+
+        if self.lights.is_empty() {
+            return Some(1.0);
+        }
+
+        let sum = self
+            .lights
+            .iter()
+            .map(|light| light.intensity_at(self, &comps.over_point()))
+            .sum::<f64>();
+
+        Some(sum / self.lights.len() as f64)
+    }
+
+    // Schlick approximation of the Fresnel reflectance of the visible surface, if any.
+    pub fn reflectance_at(&self, ray: &Ray) -> Option<f64> {
+        let intersections = self.intersects(ray);
+        let hit_index = intersections.hit_index()?;
+
+        Some(
+            self.intersection_state(&intersections, hit_index, ray)
+                .schlick(),
+        )
+    }
+
+    // Distance along the ray to the visible surface, if any.
+    pub fn distance_at(&self, ray: &Ray) -> Option<f64> {
+        self.intersects(ray).hit().map(|hit| hit.t())
+    }
+
+    // Renders the world in all directions from a position, to be used as a reflection probe.
+    pub fn capture_probe(&self, position: Point, resolution: usize) -> CubeMap {
+        CubeMap::new(resolution, |direction| {
+            self.color_at(&Ray {
+                origin: position,
+                direction: *direction,
+            })
+        })
+    }
+
+    // Unlit color and normal of the visible surface, if any.
+    pub fn albedo_and_normal_at(&self, ray: &Ray) -> Option<(Color, Vector)> {
+        let intersections = self.intersects(ray);
+        let hit_index = intersections.hit_index()?;
+        let comps = self.intersection_state(&intersections, hit_index, ray);
+        let object = comps.object();
+
+        Some((
+            object
+                .material()
+                .pattern
+                .pattern_at_object(object, &comps.over_point()),
+            comps.normal_v(),
+        ))
+    }
+
+    fn color_at_impl(&self, ray: &Ray, remaining_recursions: u8, throughput: f64) -> Color {
+        let intersections = self.intersects(ray);
 
         match intersections.hit_index() {
             Some(hit_index) => {
-                let comps = IntersectionState::new(&intersections, hit_index, ray);
-                self.shade_hit(&comps, remaining_recursions)
+                let comps = self.intersection_state(&intersections, hit_index, ray);
+                match &self.shader {
+                    Some(shader) => shader.shade(self, &comps, remaining_recursions),
+                    None => self.shade_hit(&comps, remaining_recursions, throughput),
+                }
+            }
+            None => match (&self.background, &self.environment) {
+                (Some(background), _) => (background.0)(ray),
+                (None, Some(environment)) => environment.color_at(&ray.direction),
+                (None, None) => Color::black(),
+            },
+        }
+    }
+
+    // Occlusion is not taken into account: surfaces facing the environment are fully lit by it.
+    fn environment_color(&self, comps: &IntersectionState) -> Color {
+        match &self.environment {
+            Some(environment) => {
+                let material = comps.object().material();
+
+                material
+                    .pattern
+                    .pattern_at_object(comps.object(), &comps.over_point())
+                    * material.diffuse
+                    * environment.irradiance_at(&comps.normal_v())
             }
             None => Color::black(),
         }
     }
 
-    fn shade_hit(&self, comps: &IntersectionState, remaining_recursions: u8) -> Color {
-        self.lights.iter().fold(Color::black(), |acc, light| {
-            let light_intensity = light.intensity_at(self, &comps.over_point());
+    fn shade_hit(
+        &self,
+        comps: &IntersectionState,
+        remaining_recursions: u8,
+        throughput: f64,
+    ) -> Color {
+        let emissive = comps.object().material().emissive + self.environment_color(comps);
+
+        // Unlinked lights don't light the object, but it still reflects and refracts.
+        let intensities = self
+            .lights
+            .iter()
+            .enumerate()
+            .map(|(light_index, light)| {
+                comps
+                    .object()
+                    .is_lit_by(light_index)
+                    .then(|| self.light_intensity_at(light, comps))
+            })
+            .collect::<SmallVec<[_; 8]>>();
+
+        // Neither reflections nor refractions are traced for matte surfaces in the shadow of all
+        // lights.
+        let material = comps.object().material();
+        if material.reflective.approx_eq(0.0)
+            && material.transparency.approx_eq(0.0)
+            && material.clearcoat <= 0.0
+            && intensities.iter().flatten().all(|i| i.approx_eq(0.0))
+        {
+            self.shade_shadowed_hit(comps, emissive, &intensities)
+        } else {
+            self.shade_lit_hit(
+                comps,
+                emissive,
+                &intensities,
+                remaining_recursions,
+                throughput,
+            )
+        }
+    }
 
-            let surface_color = comps.object().material().lighting(
-                comps.object(),
-                light,
-                &comps.over_point(),
-                &comps.eye_v(),
-                &comps.normal_v(),
-                light_intensity,
-            );
+    // Only the ambient term of the lights is left.
+    fn shade_shadowed_hit(
+        &self,
+        comps: &IntersectionState,
+        emissive: Color,
+        intensities: &[Option<f64>],
+    ) -> Color {
+        self.lights
+            .iter()
+            .zip(intensities)
+            .filter(|(_, intensity)| intensity.is_some())
+            .fold(emissive, |acc, (light, _)| {
+                acc + comps.object().material().lighting(
+                    comps.object(),
+                    light,
+                    &comps.over_point(),
+                    &comps.eye_v(),
+                    &comps.normal_v(),
+                    0.0,
+                )
+            })
+    }
 
-            let reflected_color = self.reflected_color(comps, remaining_recursions);
-            let refracted_color = self.refracted_color(comps, remaining_recursions);
+    fn shade_lit_hit(
+        &self,
+        comps: &IntersectionState,
+        emissive: Color,
+        intensities: &[Option<f64>],
+        remaining_recursions: u8,
+        throughput: f64,
+    ) -> Color {
+        self.lights
+            .iter()
+            .zip(intensities)
+            .fold(emissive, |acc, (light, intensity)| {
+                let surface_color = match intensity {
+                    Some(intensity) => comps.object().material().lighting(
+                        comps.object(),
+                        light,
+                        &comps.over_point(),
+                        &comps.eye_v(),
+                        &comps.normal_v(),
+                        *intensity,
+                    ),
+                    None => Color::black(),
+                };
 
-            if comps.object().material().reflective > 0.0
-                && comps.object().material().transparency > 0.0
-            {
-                let reflectance = comps.schlick();
+                let surface_color =
+                    surface_color + self.clearcoat_color(comps, remaining_recursions, throughput);
 
-                acc + surface_color
-                    + reflected_color * reflectance
-                    + refracted_color * (1.0 - reflectance)
-            } else {
-                acc + surface_color + reflected_color + refracted_color
-            }
-        })
+                let reflected_color = self.reflected_color(comps, remaining_recursions, throughput);
+                let refracted_color = self.refracted_color(comps, remaining_recursions, throughput);
+
+                if comps.object().material().reflective > 0.0
+                    && comps.object().material().transparency > 0.0
+                {
+                    let reflectance = comps.schlick();
+
+                    acc + surface_color
+                        + reflected_color * reflectance
+                        + refracted_color * (1.0 - reflectance)
+                } else {
+                    acc + surface_color + reflected_color + refracted_color
+                }
+            })
+    }
+
+    fn light_intensity_at(&self, light: &Light, comps: &IntersectionState) -> f64 {
+        if !self.render_options.shadows() {
+            return 1.0;
+        }
+
+        let material = comps.object().material();
+        let light_center = World::light_center(light);
+        let light_dot_normal = (light_center - comps.over_point())
+            .normalize()
+            .dot(comps.normal_v());
+
+        let point = if light_dot_normal >= 0.0 {
+            comps.over_point()
+        } else if material.two_sided {
+            // Light coming from behind a two-sided surface goes through it, thus only what lies
+            // behind the surface casts shadows.
+            comps.under_point()
+        } else if light_dot_normal + material.subsurface > 0.0 {
+            // Past the terminator, the light wrapped by subsurface scattering would always be in
+            // the shadow of the object itself.
+            self.point_past_object(comps.object(), &comps.over_point(), &light_center)
+        } else {
+            comps.over_point()
+        };
+
+        light.intensity_at(self, &point) + self.caustic_at(light, &point)
+    }
+
+    // Point on the way to the target, just after the last intersection with the object.
+    fn point_past_object(&self, object: &Object, point: &Point, target: &Point) -> Point {
+        let v = *target - *point;
+        let ray = Ray {
+            origin: *point,
+            direction: v.normalize(),
+        };
+
+        self.intersects(&ray)
+            .iter()
+            .filter(|x| x.t() > 0.0 && x.t() < v.magnitude() && std::ptr::eq(x.object(), object))
+            .map(|x| x.t())
+            .fold(None, |acc: Option<f64>, t| {
+                Some(acc.map_or(t, |acc| acc.max(t)))
+            })
+            .map_or(*point, |t| ray.position(t + self.render_options.epsilon()))
+    }
+
+    // Weighted average of the positions of a light.
+    fn light_center(light: &Light) -> Point {
+        let total_weight = light.weights().iter().sum::<f64>();
+
+        light
+            .positions()
+            .iter()
+            .zip(light.weights())
+            .fold(Point::zero(), |acc, (position, weight)| {
+                acc + (*position - Point::zero()) * (*weight / total_weight)
+            })
     }
 
     pub fn is_shadowed(&self, light_position: &Point, point: &Point) -> bool {
@@ -107,7 +694,7 @@ impl World {
             direction,
         };
 
-        let intersections = ray.intersects(&self.objects, Intersections::new());
+        let intersections = self.intersects(&ray);
 
         if let Some(hit) = intersections.hit() {
             if hit.object().has_shadow() && hit.t() < distance {
@@ -118,45 +705,189 @@ impl World {
         false
     }
 
-    fn reflected_color(&self, comps: &IntersectionState, remaining_recursions: u8) -> Color {
-        if remaining_recursions == 0 || comps.object().material().reflective.approx_eq(0.0) {
+    // Cheap approximation of caustics: the path from the point toward the light is refracted
+    // through the transparent object blocking it. The closer the refracted path heads to the
+    // light, the more light is considered focused on the point.
+    fn caustic_at(&self, light: &Light, point: &Point) -> f64 {
+        let strength = self.render_options.caustics();
+        if strength.approx_eq(0.0) {
+            return 0.0;
+        }
+
+        let light_position = World::light_center(light);
+
+        let v = light_position - *point;
+        let ray = Ray {
+            origin: *point,
+            direction: v.normalize(),
+        };
+
+        let xs = self.intersects(&ray);
+        let entry = match xs.hit_index() {
+            Some(index) if xs[index].t() < v.magnitude() => index,
+            _ => return 0.0,
+        };
+        let object = xs[entry].object();
+        let transparency = object.material().transparency;
+        if transparency.approx_eq(0.0) {
+            return 0.0;
+        }
+
+        let comps = self.intersection_state(&xs, entry, &ray);
+        let inner_ray = match World::refracted_direction(&comps) {
+            Some(direction) => Ray {
+                origin: comps.under_point(),
+                direction,
+            },
+            None => return 0.0,
+        };
+
+        // Only paths leaving the object without going through another one are considered.
+        let xs = self.intersects(&inner_ray);
+        let exit = match xs.hit_index() {
+            Some(index) if std::ptr::eq(xs[index].object(), object) => index,
+            _ => return 0.0,
+        };
+
+        let comps = self.intersection_state(&xs, exit, &inner_ray);
+        let alignment = match World::refracted_direction(&comps) {
+            Some(direction) => direction
+                .normalize()
+                .dot((light_position - comps.over_point()).normalize()),
+            None => return 0.0,
+        };
+
+        if alignment <= 0.0 {
+            0.0
+        } else {
+            strength * transparency * alignment.powf(CAUSTIC_SHARPNESS)
+        }
+    }
+
+    // Direction of the refracted ray, or None under total internal reflection.
+    fn refracted_direction(comps: &IntersectionState) -> Option<Vector> {
+        let (n1, n2) = comps.n();
+
+        World::refracted_direction_with(comps, n1, n2)
+    }
+
+    // Same as refracted_direction(), for each RGB channel.
+    fn refracted_directions(comps: &IntersectionState) -> [Option<Vector>; 3] {
+        match comps.dispersive_n() {
+            Some((n1, n2)) => {
+                [0, 1, 2].map(|i| World::refracted_direction_with(comps, n1[i], n2[i]))
+            }
+            None => [World::refracted_direction(comps); 3],
+        }
+    }
+
+    fn refracted_direction_with(comps: &IntersectionState, n1: f64, n2: f64) -> Option<Vector> {
+        let n_ratio = n1 / n2;
+        let cos_i = comps.cos_i();
+        let sin2_t = n_ratio * n_ratio * (1.0 - cos_i * cos_i);
+
+        if sin2_t > 1.0 {
+            None
+        } else {
+            let cos_t = f64::sqrt(1.0 - sin2_t);
+
+            Some(comps.normal_v() * (n_ratio * cos_i - cos_t) - comps.eye_v() * n_ratio)
+        }
+    }
+
+    fn reflected_color(
+        &self,
+        comps: &IntersectionState,
+        remaining_recursions: u8,
+        throughput: f64,
+    ) -> Color {
+        let reflective = comps.object().material().reflective;
+        let film_color = comps.object().material().thin_film_color(comps.cos_i());
+
+        self.reflection(comps, remaining_recursions, throughput, reflective) * film_color
+    }
+
+    fn clearcoat_color(
+        &self,
+        comps: &IntersectionState,
+        remaining_recursions: u8,
+        throughput: f64,
+    ) -> Color {
+        let reflectance = comps.object().material().clearcoat_fresnel(comps.cos_i());
+
+        self.reflection(comps, remaining_recursions, throughput, reflectance)
+    }
+
+    fn reflection(
+        &self,
+        comps: &IntersectionState,
+        remaining_recursions: u8,
+        throughput: f64,
+        amount: f64,
+    ) -> Color {
+        let throughput = throughput * amount;
+
+        if remaining_recursions == 0
+            || amount.approx_eq(0.0)
+            || throughput < self.render_options.min_throughput()
+        {
             Color::black()
+        } else if let Some(probe) = &comps.object().material().reflection_probe {
+            probe.color_at(&comps.reflect_v()) * amount
         } else {
             let reflect_ray = Ray {
                 origin: comps.over_point(),
                 direction: comps.reflect_v(),
             };
 
-            let color = self.color_at_impl(&reflect_ray, remaining_recursions - 1);
-
-            color * comps.object().material().reflective
+            self.color_at_impl(&reflect_ray, remaining_recursions - 1, throughput) * amount
         }
     }
 
-    fn refracted_color(&self, comps: &IntersectionState, remaining_recursions: u8) -> Color {
-        if remaining_recursions == 0 || comps.object().material().transparency.approx_eq(0.0) {
+    fn refracted_color(
+        &self,
+        comps: &IntersectionState,
+        remaining_recursions: u8,
+        throughput: f64,
+    ) -> Color {
+        let transparency = comps.object().material().transparency;
+        let throughput = throughput * transparency;
+
+        if remaining_recursions == 0
+            || transparency.approx_eq(0.0)
+            || throughput < self.render_options.min_throughput()
+        {
             Color::black()
+        } else if comps.dispersive_n().is_some() {
+            // Each channel follows its own ray, only this channel of its color is kept.
+            let [r, g, b] = World::refracted_directions(comps).map(|direction| {
+                direction.map(|direction| {
+                    let refract_ray = Ray {
+                        origin: comps.under_point(),
+                        direction,
+                    };
+
+                    self.color_at_impl(&refract_ray, remaining_recursions - 1, throughput)
+                })
+            });
+
+            Color::new(
+                r.map_or(0.0, |c| c.r),
+                g.map_or(0.0, |c| c.g),
+                b.map_or(0.0, |c| c.b),
+            ) * transparency
         } else {
-            let (n1, n2) = comps.n();
-            let n_ratio = n1 / n2;
-            let cos_i = comps.cos_i();
-            let sin2_t = n_ratio * n_ratio * (1.0 - cos_i * cos_i);
-
-            if sin2_t > 1.0 {
-                Color::black()
-            } else {
-                let cos_t = f64::sqrt(1.0 - sin2_t);
-
-                let direction =
-                    comps.normal_v() * (n_ratio * cos_i - cos_t) - comps.eye_v() * n_ratio;
-
-                let refract_ray = Ray {
-                    origin: comps.under_point(),
-                    direction,
-                };
-
-                self.color_at_impl(&refract_ray, remaining_recursions - 1)
-                    * comps.object().material().transparency
+            match World::refracted_direction(comps) {
+                None => Color::black(),
+                Some(direction) => {
+                    let refract_ray = Ray {
+                        origin: comps.under_point(),
+                        direction,
+                    };
+
+                    self.color_at_impl(&refract_ray, remaining_recursions - 1, throughput)
+                        * transparency
+                }
             }
         }
     }
@@ -164,12 +895,36 @@ impl World {
 
 /* ---------------------------------------------------------------------------------------------- */
 
+// Number of nested groups, operands of composite shapes being part of the same level.
+fn hierarchy_depth(object: &Object) -> usize {
+    match object.shape() {
+        Shape::Group(g) => 1 + g.children().iter().map(hierarchy_depth).max().unwrap_or(0),
+        Shape::Clip(c) => hierarchy_depth(c.object()),
+        Shape::Csg(c) => hierarchy_depth(c.left()).max(hierarchy_depth(c.right())),
+        Shape::Lod(l) => l
+            .levels()
+            .iter()
+            .map(|(_, level)| hierarchy_depth(level))
+            .max()
+            .unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
 impl Default for World {
     fn default() -> Self {
         World {
             objects: vec![],
             lights: vec![],
+            environment: None,
             recursion_limit: 4,
+            render_options: RenderOptions::new(),
+            tlas: None,
+            profile: None,
+            shader: None,
+            background: None,
         }
     }
 }
@@ -179,10 +934,11 @@ impl Default for World {
 #[cfg(test)]
 pub mod tests {
     use super::*;
-    use crate::{
-        primitive::{Tuple, Vector},
-        rtc::{Intersection, Material, Pattern, Transform},
+    use crate::rtc::{
+        view_transform, Camera, Canvas, CubeFace, Intersection, Material, ParallelRendering,
+        Pattern, Shape,
     };
+    use std::f64::consts::PI;
 
     pub fn default_world() -> World {
         World {
@@ -235,7 +991,7 @@ pub mod tests {
 
         let comps =
             IntersectionState::new(&Intersections::new().with_intersections(vec![i]), 0, &ray);
-        let color = w.shade_hit(&comps, 1);
+        let color = w.shade_hit(&comps, 1, 1.0);
 
         assert_eq!(color, Color::new(0.38066, 0.47583, 0.2855));
     }
@@ -262,7 +1018,7 @@ pub mod tests {
             IntersectionState::new(&Intersections::new().with_intersections(vec![i]), 0, &ray);
 
         assert_eq!(
-            w.shade_hit(&comps, 1),
+            w.shade_hit(&comps, 1, 1.0),
             Color::new(0.90498, 0.90498, 0.90498)
         );
     }
@@ -291,7 +1047,41 @@ pub mod tests {
         let comps =
             IntersectionState::new(&Intersections::new().with_intersections(vec![i]), 0, &ray);
 
-        assert_eq!(w.shade_hit(&comps, 1), Color::new(0.1, 0.1, 0.1));
+        assert_eq!(w.shade_hit(&comps, 1, 1.0), Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn a_matte_object_in_full_shadow_only_gets_ambient_light() {
+        let material = Material::new().with_color(Color::new(0.2, 0.4, 0.6));
+        let occluder = Object::new_sphere();
+        let object = Object::new_sphere()
+            .with_material(material)
+            .translate(0.0, 0.0, 10.0)
+            .transform();
+
+        let w = World::new()
+            .with_lights(vec![
+                Light::new_point_light(Color::white(), Point::new(0.0, 0.0, -10.0)),
+                Light::new_point_light(Color::new(0.5, 0.5, 0.5), Point::new(0.0, 0.0, -20.0)),
+            ])
+            .with_objects(vec![occluder, object.clone()]);
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, 5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+        let xs = Intersections::new().with_intersections(vec![Intersection::new(4.0, &object)]);
+        let comps = IntersectionState::new(&xs, 0, &ray);
+
+        // The ambient term of both lights.
+        let ambient = Color::new(0.2, 0.4, 0.6) * 0.1 * 1.5;
+        assert_eq!(w.shade_hit(&comps, 5, 1.0), ambient);
+
+        let intensities = vec![Some(0.0), Some(0.0)];
+        assert_eq!(
+            w.shade_shadowed_hit(&comps, Color::black(), &intensities),
+            w.shade_lit_hit(&comps, Color::black(), &intensities, 5, 1.0)
+        );
     }
 
     #[test]
@@ -346,6 +1136,23 @@ pub mod tests {
         assert_eq!(w.color_at(&ray), Color::new(1.0, 1.0, 1.0));
     }
 
+    #[test]
+    fn shadows_show_the_level_of_detail_seen_from_the_viewpoint() {
+        let lod = Object::new_lod(vec![
+            (20.0, Object::new_cube()),
+            (0.0, Object::new_sphere()),
+        ]);
+        let w = World::new().with_objects(vec![lod]);
+
+        // Far from the object, the shadow ray sees the cube, but not the sphere.
+        let light_position = Point::new(0.9, 0.9, 50.0);
+        let point = Point::new(0.9, 0.9, -50.0);
+        assert!(w.is_shadowed(&light_position, &point));
+
+        let w = w.with_lod_viewpoint(&Point::new(0.0, 0.0, -5.0));
+        assert!(!w.is_shadowed(&light_position, &point));
+    }
+
     #[test]
     fn is_shadowed_tests_for_occlusion_between_two_points() {
         let w = default_world();
@@ -381,7 +1188,7 @@ pub mod tests {
         let comps =
             IntersectionState::new(&Intersections::new().with_intersections(vec![i]), 0, &ray);
 
-        assert_eq!(w.reflected_color(&comps, 1), Color::black());
+        assert_eq!(w.reflected_color(&comps, 1, 1.0), Color::black());
     }
 
     #[test]
@@ -409,7 +1216,7 @@ pub mod tests {
             IntersectionState::new(&Intersections::new().with_intersections(vec![i]), 0, &ray);
 
         assert_eq!(
-            w.reflected_color(&comps, 1),
+            w.reflected_color(&comps, 1, 1.0),
             Color::new(0.19032, 0.2379, 0.14274)
         );
     }
@@ -433,13 +1240,13 @@ pub mod tests {
             direction: Vector::new(0.0, -sqrt2 / 2.0, sqrt2 / 2.0),
         };
 
-        let i = Intersection::new(sqrt2, &object);
+        let i = Intersection::new(sqrt2, object);
 
         let comps =
             IntersectionState::new(&Intersections::new().with_intersections(vec![i]), 0, &ray);
 
         assert_eq!(
-            w.shade_hit(&comps, 1),
+            w.shade_hit(&comps, 1, 1.0),
             Color::new(0.87677, 0.92436, 0.82918)
         );
     }
@@ -474,6 +1281,47 @@ pub mod tests {
         w.color_at(&ray);
     }
 
+    #[test]
+    fn weakly_reflective_surfaces_stop_the_recursion_early() {
+        let mk_world = |render_options| {
+            World::new()
+                .with_lights(vec![Light::new_point_light(
+                    Color::white(),
+                    Point::new(0.0, 0.0, 0.0),
+                )])
+                .with_objects(vec![
+                    Object::new_plane()
+                        .with_material(Material::new().with_reflective(0.1))
+                        .translate(0.0, -1.0, 0.0)
+                        .transform(),
+                    Object::new_plane()
+                        .with_material(Material::new().with_reflective(0.1))
+                        .translate(0.0, 1.0, 0.0)
+                        .transform(),
+                ])
+                .with_recursion_limit(20)
+                .with_render_options(render_options)
+        };
+
+        let w = mk_world(RenderOptions::new());
+        let cutoff_w = mk_world(RenderOptions::new().with_min_throughput(0.001));
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, 0.0),
+            direction: Vector::new(0.0, 1.0, 1.0).normalize(),
+        };
+
+        assert_eq!(w.color_at(&ray), cutoff_w.color_at(&ray));
+
+        let xs = ray.intersects(&cutoff_w.objects, Intersections::new());
+        let comps = IntersectionState::new(&xs, xs.hit_index().unwrap(), &ray);
+
+        // A reflection that would contribute less than the threshold is not traced.
+        assert_ne!(w.reflected_color(&comps, 20, 0.005), Color::black());
+        assert_ne!(cutoff_w.reflected_color(&comps, 20, 0.1), Color::black());
+        assert_eq!(cutoff_w.reflected_color(&comps, 20, 0.005), Color::black());
+    }
+
     #[test]
     fn the_refracted_color_with_an_opaque_surface() {
         let w = default_world();
@@ -490,7 +1338,7 @@ pub mod tests {
 
         let comps = IntersectionState::new(&xs, 0, &ray);
 
-        assert_eq!(w.refracted_color(&comps, 5), Color::black());
+        assert_eq!(w.refracted_color(&comps, 5, 1.0), Color::black());
     }
 
     #[test]
@@ -517,7 +1365,7 @@ pub mod tests {
 
         let comps = IntersectionState::new(&xs, 0, &ray);
 
-        assert_eq!(w.refracted_color(&comps, 0), Color::black());
+        assert_eq!(w.refracted_color(&comps, 0, 1.0), Color::black());
     }
 
     #[test]
@@ -544,7 +1392,7 @@ pub mod tests {
 
         let comps = IntersectionState::new(&xs, 1, &ray);
 
-        assert_eq!(w.refracted_color(&comps, 5), Color::black());
+        assert_eq!(w.refracted_color(&comps, 5, 1.0), Color::black());
     }
 
     #[test]
@@ -582,20 +1430,91 @@ pub mod tests {
         let b = &w.objects[1];
 
         let xs = Intersections::new().with_intersections(vec![
-            Intersection::new(-0.9899, &a),
-            Intersection::new(-0.4899, &b),
-            Intersection::new(0.4899, &b),
-            Intersection::new(0.9899, &a),
+            Intersection::new(-0.9899, a),
+            Intersection::new(-0.4899, b),
+            Intersection::new(0.4899, b),
+            Intersection::new(0.9899, a),
         ]);
 
         let comps = IntersectionState::new(&xs, 2, &ray);
 
         assert_eq!(
-            w.refracted_color(&comps, 5),
+            w.refracted_color(&comps, 5, 1.0),
             Color::new(0.0, 0.99888, 0.04725)
         );
     }
 
+    #[test]
+    fn a_dispersive_prism_splits_white_light() {
+        let glass = Material::new()
+            .with_transparency(1.0)
+            .with_refractive_index(1.5);
+        let backdrop = Object::new_plane()
+            .rotate_x(std::f64::consts::PI / 2.0)
+            .translate(0.0, 0.0, 10.0)
+            .transform()
+            .with_material(
+                Material::new()
+                    .with_ambient(1.0)
+                    .with_diffuse(0.0)
+                    .with_specular(0.0)
+                    .with_pattern(Pattern::new_gradient(Color::white(), Color::black())),
+            );
+        let world = |material: Material| {
+            World::new()
+                .with_objects(vec![
+                    Object::new_sphere().with_material(material),
+                    backdrop.clone(),
+                ])
+                .with_lights(vec![Light::new_point_light(
+                    Color::white(),
+                    Point::new(0.0, 10.0, -10.0),
+                )])
+        };
+        let ray = Ray {
+            origin: Point::new(0.0, 0.6, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+        let directions = |w: &World| {
+            let xs = w.intersects(&ray);
+            let comps = w.intersection_state(&xs, xs.hit_index().unwrap(), &ray);
+
+            (World::refracted_directions(&comps), comps.normal_v())
+        };
+
+        let clear = world(glass.clone());
+        let ([r, g, b], _) = directions(&clear);
+        assert_eq!(r, g);
+        assert_eq!(g, b);
+
+        let w = world(glass.with_dispersion(10.0));
+        let ([r, g, b], normal_v) = directions(&w);
+        let (r, g, b) = (r.unwrap(), g.unwrap(), b.unwrap());
+        assert_ne!(r, g);
+        assert_ne!(g, b);
+        // Blue is bent the most toward the inside.
+        assert!((-normal_v).dot(b) > (-normal_v).dot(g));
+        assert!((-normal_v).dot(g) > (-normal_v).dot(r));
+
+        // The channels see different parts of the backdrop.
+        let through_sphere = |w: &World| {
+            (1..10)
+                .map(|i| {
+                    w.color_at(&Ray {
+                        origin: Point::new(0.1 * i as f64, 0.0, -5.0),
+                        direction: Vector::new(0.0, 0.0, 1.0),
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+        for color in through_sphere(&clear) {
+            assert!(color.r.approx_eq(color.b));
+        }
+        assert!(through_sphere(&w)
+            .iter()
+            .any(|color| (color.r - color.b).abs() > 0.01));
+    }
+
     #[test]
     fn shade_hit_with_a_transparent_material() {
         let mut w = default_world();
@@ -631,7 +1550,7 @@ pub mod tests {
         let comps = IntersectionState::new(&xs, 0, &ray);
 
         assert_eq!(
-            w.shade_hit(&comps, 5),
+            w.shade_hit(&comps, 5, 1.0),
             Color::new(0.93642, 0.68642, 0.68642)
         );
     }
@@ -672,10 +1591,655 @@ pub mod tests {
         let comps = IntersectionState::new(&xs, 0, &ray);
 
         assert_eq!(
-            w.shade_hit(&comps, 5),
+            w.shade_hit(&comps, 5, 1.0),
             Color::new(0.93391, 0.69643, 0.69243)
         );
     }
+
+    #[test]
+    fn objects_hit_by_more_rays_are_more_expensive() {
+        let w = World::new()
+            .with_cost_profiling()
+            .with_objects(vec![
+                Object::new_sphere().translate(3.0, 0.0, 0.0).transform(),
+                Object::new_sphere().translate(-3.0, 0.0, 0.0).transform(),
+                Object::new_sphere().translate(0.0, 10.0, 0.0).transform(),
+            ])
+            .with_lights(vec![Light::new_point_light(
+                Color::white(),
+                Point::new(0.0, 0.0, -10.0),
+            )]);
+
+        let ray_to = |x: f64| Ray {
+            origin: Point::new(x, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        w.color_at(&ray_to(3.0));
+        for _ in 0..10 {
+            w.color_at(&ray_to(-3.0));
+        }
+
+        let profile = w.cost_profile();
+        assert_eq!(
+            profile.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+            vec![1, 0, 2]
+        );
+        assert!(profile[0].1 > profile[1].1);
+        assert_eq!(profile[2].1, 0);
+
+        assert!(default_world().cost_profile().is_empty());
+    }
+
+    #[test]
+    fn exporting_render_statistics_to_json() {
+        let w = World::new()
+            .with_objects(vec![
+                Object::new_group(vec![Object::new_sphere(), Object::new_cube()]),
+                Object::new_plane().translate(0.0, -1.0, 0.0).transform(),
+            ])
+            .with_lights(vec![Light::new_point_light(
+                Color::white(),
+                Point::new(-10.0, 10.0, -10.0),
+            )])
+            .with_cost_profiling();
+        let c = Camera::new()
+            .with_size(10, 10)
+            .with_fov(std::f64::consts::PI / 3.0)
+            .with_transformation(&view_transform(
+                &Point::new(0.0, 1.5, -5.0),
+                &Point::zero(),
+                &Vector::new(0.0, 1.0, 0.0),
+            ));
+        c.render(&w, ParallelRendering::False, None).unwrap();
+
+        let stats: serde_json::Value = serde_json::from_str(&w.stats_json()).unwrap();
+
+        // A primary ray and a shadow ray per pixel at least.
+        let rays = stats["rays"].as_u64().unwrap();
+        assert!(rays >= 100);
+        assert!(stats["intersections"].as_u64().unwrap() > 0);
+        assert_eq!(stats["bvh_depth"].as_u64().unwrap(), 1);
+        assert!(stats["render_time"].as_f64().unwrap() > 0.0);
+
+        let objects = stats["objects"].as_array().unwrap();
+        assert_eq!(objects.len(), 2);
+        assert_eq!(
+            objects
+                .iter()
+                .map(|object| object["intersections"].as_u64().unwrap())
+                .sum::<u64>(),
+            stats["intersections"].as_u64().unwrap()
+        );
+
+        let stats: serde_json::Value = serde_json::from_str(&default_world().stats_json()).unwrap();
+        assert_eq!(stats["rays"].as_u64().unwrap(), 0);
+        assert!(stats["objects"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn shading_with_a_custom_shader() {
+        #[derive(Debug)]
+        struct NormalShader {}
+
+        impl Shader for NormalShader {
+            fn shade(&self, _world: &World, comps: &IntersectionState, _remaining: u8) -> Color {
+                let n = comps.normal_v();
+                Color::new(n.x() + 1.0, n.y() + 1.0, n.z() + 1.0) * 0.5
+            }
+        }
+
+        let w = default_world().with_shader(Box::new(NormalShader {}));
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        assert_eq!(w.color_at(&ray), Color::new(0.5, 0.5, 0.0));
+    }
+
+    #[test]
+    fn a_custom_shader_traces_secondary_rays_until_the_recursion_limit() {
+        // Each bounce halves the color, white is returned when there are no recursions left.
+        #[derive(Debug)]
+        struct MirrorShader {}
+
+        impl Shader for MirrorShader {
+            fn shade(&self, world: &World, comps: &IntersectionState, remaining: u8) -> Color {
+                if remaining == 0 {
+                    return Color::white();
+                }
+
+                let ray = Ray {
+                    origin: comps.over_point(),
+                    direction: comps.reflect_v(),
+                };
+                world.color_at_with_depth(&ray, remaining - 1) * 0.5
+            }
+        }
+
+        // Two facing mirrors, the ray bounces between them.
+        let w = World::new()
+            .with_objects(vec![
+                Object::new_plane().translate(0.0, -1.0, 0.0).transform(),
+                Object::new_plane().translate(0.0, 1.0, 0.0).transform(),
+            ])
+            .with_recursion_limit(3)
+            .with_shader(Box::new(MirrorShader {}));
+        let ray = Ray {
+            origin: Point::zero(),
+            direction: Vector::new(0.0, 1.0, 0.0),
+        };
+
+        assert_eq!(w.color_at(&ray), Color::new(0.125, 0.125, 0.125));
+    }
+
+    #[test]
+    fn a_glass_sphere_focuses_light_behind_it() {
+        let glass = Material::new()
+            .with_transparency(1.0)
+            .with_refractive_index(1.5);
+        let opaque = glass.clone().with_transparency(0.0);
+
+        let world = |material: Material, caustics: f64| {
+            World::new()
+                .with_objects(vec![
+                    Object::new_plane(),
+                    Object::new_sphere()
+                        .with_material(material)
+                        .translate(0.0, 3.0, 0.0)
+                        .transform(),
+                ])
+                .with_lights(vec![Light::new_point_light(
+                    Color::white(),
+                    Point::new(0.0, 10.0, 0.0),
+                )])
+                .with_render_options(RenderOptions::new().with_caustics(caustics))
+        };
+
+        // Looking at the floor under the sphere.
+        let ray_to = |x: f64| Ray {
+            origin: Point::new(0.0, 1.0, -5.0),
+            direction: (Point::new(x, 0.0, 0.0) - Point::new(0.0, 1.0, -5.0)).normalize(),
+        };
+
+        let glass_color = world(glass.clone(), 1.0).color_at(&ray_to(0.0));
+        let opaque_color = world(opaque.clone(), 1.0).color_at(&ray_to(0.0));
+        assert!(glass_color.r > opaque_color.r + 0.5);
+
+        // The bright spot is narrow.
+        let off_center = world(glass.clone(), 1.0).color_at(&ray_to(1.0));
+        assert!(off_center.r < glass_color.r);
+
+        // Off by default.
+        assert_eq!(
+            world(glass, 0.0).color_at(&ray_to(0.0)),
+            world(opaque, 0.0).color_at(&ray_to(0.0))
+        );
+    }
+
+    #[test]
+    fn a_smaller_epsilon_keeps_shadows_attached_in_tiny_scenes() {
+        // A disc thinner than the default epsilon, lying on the floor. It's built in object space
+        // as such a tiny scaling would not be invertible.
+        let thickness = 1.0e-8;
+        let world = |render_options: RenderOptions| {
+            World::new()
+                .with_objects(vec![
+                    Object::new_plane(),
+                    Object::new_cylinder(Some(0.0), Some(thickness), true),
+                ])
+                .with_lights(vec![Light::new_point_light(
+                    Color::white(),
+                    Point::new(-10.0, 10.0, 0.0),
+                )])
+                .with_render_options(render_options)
+        };
+
+        // Looking at the floor right next to the disc, in its shadow.
+        let origin = Point::new(3.0, 1.0, 0.0);
+        let ray = Ray {
+            origin,
+            direction: (Point::new(1.0 + thickness / 2.0, 0.0, 0.0) - origin).normalize(),
+        };
+        let shadowed = Color::new(0.1, 0.1, 0.1);
+
+        assert_ne!(world(RenderOptions::new()).color_at(&ray), shadowed);
+        assert_eq!(
+            world(RenderOptions::new().with_epsilon(thickness / 100.0)).color_at(&ray),
+            shadowed
+        );
+    }
+
+    #[test]
+    fn a_two_sided_surface_is_lit_from_behind() {
+        let world = |two_sided: bool, objects_below: Vec<Object>| {
+            let mut objects =
+                vec![Object::new_plane().with_material(Material::new().with_two_sided(two_sided))];
+            objects.extend(objects_below);
+
+            World::new()
+                .with_objects(objects)
+                .with_lights(vec![Light::new_point_light(
+                    Color::white(),
+                    Point::new(0.0, -10.0, 0.0),
+                )])
+        };
+
+        // Looking down at the plane, lit from below.
+        let ray = Ray {
+            origin: Point::new(0.0, 1.0, 0.0),
+            direction: Vector::new(0.0, -1.0, 0.0),
+        };
+        let shadowed = Color::new(0.1, 0.1, 0.1);
+
+        assert_eq!(world(false, vec![]).color_at(&ray), shadowed);
+        assert_eq!(
+            world(true, vec![]).color_at(&ray),
+            Color::new(1.0, 1.0, 1.0)
+        );
+
+        // An object between the plane and the light still casts a shadow.
+        let occluder = Object::new_plane().translate(0.0, -1.0, 0.0).transform();
+        assert_eq!(world(true, vec![occluder]).color_at(&ray), shadowed);
+    }
+
+    #[test]
+    fn subsurface_scattering_lights_a_sphere_past_its_terminator() {
+        let world = |material: Material, objects: Vec<Object>| {
+            let mut objects = objects;
+            objects.push(Object::new_sphere().with_material(material));
+
+            World::new()
+                .with_objects(objects)
+                .with_lights(vec![Light::new_point_light(
+                    Color::white(),
+                    Point::new(0.0, 10.0, 0.5),
+                )])
+        };
+
+        // The light is slightly behind the visible point of the sphere.
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+        let shadowed = Color::new(0.1, 0.1, 0.1);
+        let wax = Material::new().with_subsurface(0.5);
+
+        assert_eq!(world(Material::new(), vec![]).color_at(&ray), shadowed);
+        assert!(world(wax.clone(), vec![]).color_at(&ray).r > 0.2);
+
+        // Other objects still cast shadows.
+        let occluder = Object::new_plane().translate(0.0, 2.0, 0.0).transform();
+        assert_eq!(world(wax, vec![occluder]).color_at(&ray), shadowed);
+    }
+
+    #[test]
+    fn emissive_surfaces_are_seen_in_reflections_and_through_glass() {
+        let glow = Color::new(0.8, 0.4, 0.1);
+        let emissive = Object::new_sphere().with_material(
+            Material::new()
+                .with_ambient(0.0)
+                .with_diffuse(0.0)
+                .with_specular(0.0)
+                .with_emissive(glow),
+        );
+        let black = Material::new()
+            .with_color(Color::black())
+            .with_ambient(0.0)
+            .with_diffuse(0.0)
+            .with_specular(0.0);
+        let light = Light::new_point_light(Color::white(), Point::new(-10.0, 10.0, -10.0));
+
+        let world = |object: Object| {
+            World::new()
+                .with_objects(vec![emissive.clone(), object])
+                .with_lights(vec![light.clone()])
+        };
+
+        // Direct view.
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+        assert_eq!(world(Object::new_plane()).color_at(&ray), glow);
+
+        // A mirror under the sphere.
+        let mirror = Object::new_plane()
+            .with_material(black.clone().with_reflective(1.0))
+            .translate(0.0, -1.0, 0.0)
+            .transform();
+        let ray = Ray {
+            origin: Point::new(0.0, 2.0, -4.0),
+            direction: Vector::new(0.0, -1.0, 1.0).normalize(),
+        };
+        assert_eq!(world(mirror).color_at(&ray), glow);
+
+        // A glass slab in front of the sphere.
+        let slab = Object::new_cube()
+            .with_material(black.with_transparency(1.0).with_refractive_index(1.5))
+            .scale(2.0, 2.0, 0.1)
+            .translate(0.0, 0.0, -2.0)
+            .transform();
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+        assert_eq!(world(slab).color_at(&ray), glow);
+    }
+
+    #[test]
+    fn bounding_box_proxies_of_objects() {
+        let material = Material::new().with_color(Color::red());
+        let sphere = Object::new_sphere()
+            .with_material(material.clone())
+            .scale(2.0, 1.0, 1.0)
+            .translate(1.0, 2.0, 3.0)
+            .transform();
+        let triangle = Object::new_triangle(
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(0.0, 1.0, 0.0),
+        );
+        let group = Object::new_group(vec![Object::new_cube().with_material(material.clone())]);
+        let lod = Object::new_lod(vec![(0.0, group.clone())]);
+        let w = World::new()
+            .with_objects(vec![
+                sphere.clone(),
+                Object::new_plane(),
+                triangle,
+                group,
+                lod,
+            ])
+            .with_lights(default_world().lights().clone())
+            .to_bbox_proxies();
+
+        let proxy = &w.objects()[0];
+        assert_eq!(proxy.shape(), &Shape::Cube());
+        assert_eq!(proxy.material(), &material);
+        assert_eq!(proxy.bounding_box().min(), sphere.bounding_box().min());
+        assert_eq!(proxy.bounding_box().max(), sphere.bounding_box().max());
+        assert_eq!(proxy.bounding_box().min(), Point::new(-1.0, 1.0, 2.0));
+        assert_eq!(proxy.bounding_box().max(), Point::new(3.0, 3.0, 4.0));
+
+        // Planes are unbounded.
+        assert_eq!(w.objects()[1].shape(), &Shape::Plane());
+
+        // Flat objects get a thin proxy.
+        let proxy = &w.objects()[2];
+        assert_eq!(proxy.shape(), &Shape::Cube());
+        assert_eq!(
+            proxy.bounding_box().min(),
+            Point::new(0.0, 0.0, -PROXY_MIN_HALF_EXTENT)
+        );
+
+        // Groups and levels of detail get the material of their first leaf.
+        assert_eq!(w.objects()[3].material(), &material);
+        assert_eq!(w.objects()[4].material(), &material);
+
+        assert_eq!(w.lights().len(), 1);
+    }
+
+    #[test]
+    fn a_surface_under_a_bright_sky_is_lit_from_above() {
+        let mut sky = Canvas::new(32, 16);
+        for row in 0..8 {
+            for col in 0..32 {
+                sky[row][col] = Color::white();
+            }
+        }
+
+        let world = World::new()
+            .with_objects(vec![Object::new_sphere()])
+            .with_environment(Environment::new(&sky));
+        assert!(world.lights().is_empty());
+
+        let from_above = world.color_at(&Ray {
+            origin: Point::new(0.0, 5.0, 0.0),
+            direction: Vector::new(0.0, -1.0, 0.0),
+        });
+        let from_below = world.color_at(&Ray {
+            origin: Point::new(0.0, -5.0, 0.0),
+            direction: Vector::new(0.0, 1.0, 0.0),
+        });
+
+        // The default material has a diffuse of 0.9.
+        assert!((from_above.r - 0.9).abs() < 0.01);
+        assert!(from_below.r < 0.01);
+
+        // Missing rays see the sky.
+        assert_eq!(
+            world.color_at(&Ray {
+                origin: Point::new(0.0, 5.0, 0.0),
+                direction: Vector::new(0.0, 1.0, 0.0)
+            }),
+            Color::white()
+        );
+    }
+
+    #[test]
+    fn a_procedural_background_is_seen_by_missing_rays() {
+        // Blue above the horizon, brown below.
+        let world = World::new()
+            .with_objects(vec![Object::new_plane()
+                .with_material(Material::new().with_reflective(1.0).with_diffuse(0.0))
+                .translate(0.0, -1.0, 0.0)
+                .transform()])
+            .with_lights(vec![Light::new_point_light(
+                Color::white(),
+                Point::new(0.0, 10.0, 0.0),
+            )])
+            .with_background_fn(Box::new(|ray: &Ray| {
+                if ray.direction.y() > 0.0 {
+                    Color::new(0.2, 0.4, 0.9)
+                } else {
+                    Color::new(0.4, 0.3, 0.1)
+                }
+            }));
+
+        let up = world.color_at(&Ray {
+            origin: Point::zero(),
+            direction: Vector::new(0.0, 1.0, 0.0),
+        });
+        // Below the plane.
+        let down = world.color_at(&Ray {
+            origin: Point::new(0.0, -5.0, 0.0),
+            direction: Vector::new(0.0, -1.0, 0.0),
+        });
+        assert_eq!(up, Color::new(0.2, 0.4, 0.9));
+        assert_eq!(down, Color::new(0.4, 0.3, 0.1));
+
+        // The plane reflects the sky.
+        let reflected = world.color_at(&Ray {
+            origin: Point::new(0.0, 5.0, -5.0),
+            direction: Vector::new(0.0, -1.0, 1.0).normalize(),
+        });
+        assert!((reflected.b - 0.9).abs() < 0.15);
+        assert!(reflected.b > reflected.r);
+    }
+
+    #[test]
+    fn a_tlas_skips_objects_far_from_the_ray() {
+        // A grid of 10x10 shapes, each recording the rays it's tested against.
+        let shapes = || {
+            (0..100)
+                .map(|i| {
+                    Object::new_test_shape()
+                        .translate(3.0 * (i % 10) as f64, 3.0 * (i / 10) as f64, 0.0)
+                        .transform()
+                })
+                .collect::<Vec<_>>()
+        };
+        // Copies of a test shape share the ray they record.
+        let nb_tested = |shapes: &[Object]| {
+            shapes
+                .iter()
+                .filter(|object| match object.shape() {
+                    Shape::TestShape(t) => t.ray().is_some(),
+                    _ => unreachable!(),
+                })
+                .count()
+        };
+
+        // Goes through the gaps of the grid and misses all shapes.
+        let ray = Ray {
+            origin: Point::new(1.5, 1.5, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let without_tlas = shapes();
+        let w = World::new().with_objects(without_tlas.clone());
+        assert_eq!(w.color_at(&ray), Color::black());
+        assert_eq!(nb_tested(&without_tlas), 100);
+
+        let with_tlas = shapes();
+        let w = World::new().with_objects(with_tlas.clone()).build_tlas(4);
+        assert_eq!(w.color_at(&ray), Color::black());
+        assert!(nb_tested(&with_tlas) < 20);
+    }
+
+    #[test]
+    fn a_tlas_does_not_change_renderings() {
+        let w = default_world().with_objects(vec![
+            Object::new_plane().translate(0.0, -1.0, 0.0).transform(),
+            Object::new_sphere(),
+            Object::new_cube().translate(3.0, 0.0, 0.0).transform(),
+            Object::new_sphere().translate(-3.0, 0.0, 1.0).transform(),
+        ]);
+        let rays = (-10..=10)
+            .map(|i| Ray {
+                origin: Point::new(0.0, 0.5, -5.0),
+                direction: Vector::new(i as f64 / 3.0, -0.2, 1.0).normalize(),
+            })
+            .collect::<Vec<_>>();
+        let colors = rays.iter().map(|ray| w.color_at(ray)).collect::<Vec<_>>();
+
+        let w = w.build_tlas(1);
+        for (ray, color) in rays.iter().zip(colors) {
+            assert_eq!(w.color_at(ray), color);
+        }
+    }
+
+    #[test]
+    fn non_finite_colors_are_replaced() {
+        // Unless non-finite colors are shown, NaN colors are caught by debug assertions, infinite
+        // ones aren't.
+        let w = default_world().with_objects(vec![
+            Object::new_sphere().with_material(Material::new().with_ambient(f64::INFINITY))
+        ]);
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        assert_eq!(w.color_at(&ray), Color::black());
+        assert_eq!(w.primary_color_at(&ray), Color::black());
+
+        let w = w.with_render_options(RenderOptions::new().with_debug_non_finite(true));
+        assert_eq!(w.color_at(&ray), Color::magenta());
+        assert_eq!(w.primary_color_at(&ray), Color::magenta());
+
+        // Legitimate black pixels are kept.
+        let miss = Ray {
+            origin: Point::new(0.0, 5.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+        assert_eq!(w.color_at(&miss), Color::black());
+
+        let w = w.with_objects(vec![
+            Object::new_sphere().with_material(Material::new().with_ambient(f64::NAN))
+        ]);
+        assert_eq!(w.color_at(&ray), Color::magenta());
+        assert_eq!(w.primary_color_at(&ray), Color::magenta());
+    }
+
+    #[test]
+    fn lights_only_light_linked_objects() {
+        let left_light = Light::new_point_light(Color::white(), Point::new(-10.0, 0.0, -10.0));
+        let right_light = Light::new_point_light(Color::white(), Point::new(10.0, 0.0, -10.0));
+
+        // Only lit by the right light.
+        let excluded = Object::new_sphere()
+            .with_light_mask(0b10)
+            .translate(-2.0, 0.0, 0.0)
+            .transform();
+        let included = Object::new_sphere().translate(2.0, 0.0, 0.0).transform();
+
+        let w = World::new()
+            .with_objects(vec![excluded.clone(), included.clone()])
+            .with_lights(vec![left_light, right_light.clone()]);
+        // In this world, the right light is the first one.
+        let only_right = World::new()
+            .with_objects(vec![excluded.with_light_mask(u64::MAX), included])
+            .with_lights(vec![right_light]);
+
+        let ray = |x| Ray {
+            origin: Point::new(x, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        assert_eq!(w.color_at(&ray(-2.0)), only_right.color_at(&ray(-2.0)));
+        assert_ne!(w.color_at(&ray(2.0)), only_right.color_at(&ray(2.0)));
+
+        let unlinked = World::new()
+            .with_objects(vec![Object::new_sphere().with_light_mask(0)])
+            .with_lights(vec![Light::new_point_light(
+                Color::white(),
+                Point::new(0.0, 0.0, -10.0),
+            )]);
+        assert_eq!(unlinked.color_at(&ray(0.0)), Color::black());
+    }
+
+    #[test]
+    fn a_reflection_probe_sees_a_nearby_wall() {
+        // A red wall at x = 2, which isn't affected by the lighting.
+        let wall = Object::new_plane()
+            .with_material(
+                Material::new()
+                    .with_color(Color::red())
+                    .with_ambient(1.0)
+                    .with_diffuse(0.0)
+                    .with_specular(0.0),
+            )
+            .rotate_z(PI / 2.0)
+            .translate(2.0, 0.0, 0.0)
+            .transform();
+        let light = Light::new_point_light(Color::white(), Point::new(0.0, 10.0, 0.0));
+        let w = World::new()
+            .with_objects(vec![wall])
+            .with_lights(vec![light.clone()]);
+
+        let probe = w.capture_probe(Point::zero(), 8);
+        assert_eq!(probe.resolution(), 8);
+        let (positive_x, negative_x) = (
+            probe.face(CubeFace::PositiveX),
+            probe.face(CubeFace::NegativeX),
+        );
+        for row in 0..8 {
+            for col in 0..8 {
+                assert_eq!(positive_x[row][col], Color::red());
+                assert_eq!(negative_x[row][col], Color::black());
+            }
+        }
+
+        // Without the wall, a mirror using the probe still reflects it.
+        let mk_world = |material: Material| {
+            World::new()
+                .with_objects(vec![Object::new_sphere().with_material(material)])
+                .with_lights(vec![light.clone()])
+        };
+        let mirror = Material::new().with_reflective(0.5);
+        let w = mk_world(mirror.clone());
+        let probed_w = mk_world(mirror.with_reflection_probe(probe));
+
+        // The ray is reflected toward +x.
+        let ray = Ray {
+            origin: Point::new(2.0_f64.sqrt() / 2.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+        let difference = probed_w.color_at(&ray) - w.color_at(&ray);
+        assert_eq!(difference, Color::new(0.5, 0.0, 0.0));
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */